@@ -1,13 +1,12 @@
 #![allow(unused_assignments, unused_variables, unreachable_patterns)]
 
-use std::u8;
-use derive_more::From;
-use ntex_bytes::{BufMut, Bytes, BytesMut, ByteString};
-use uuid::Uuid;
-
 use super::*;
-use crate::error::AmqpParseError;
 use crate::codec::{self, decode_format_code, decode_list_header, Decode, DecodeFormatted, Encode};
+use crate::error::AmqpParseError;
+use derive_more::From;
+use ntex_bytes::{BufMut, ByteString, Bytes, BytesMut};
+use std::u8;
+use uuid::Uuid;
 
 #[derive(Clone, Debug, PartialEq, From)]
 pub enum Frame {
@@ -20,6 +19,7 @@ pub enum Frame {
     Detach(Detach),
     End(End),
     Close(Close),
+    Unknown(Box<UnknownFrame>),
     Empty,
 }
 
@@ -35,6 +35,7 @@ impl Frame {
             Frame::Detach(_) => "Detach",
             Frame::End(_) => "End",
             Frame::Close(_) => "Close",
+            Frame::Unknown(_) => "Unknown",
             Frame::Empty => "Empty",
         }
     }
@@ -97,7 +98,8 @@ impl Decode for Frame {
                 Descriptor::Symbol(ref a) if a.as_str() == "amqp:close:list" => {
                     decode_close_inner(input).map(|(i, r)| (i, Frame::Close(r)))
                 }
-                _ => Err(AmqpParseError::InvalidDescriptor(descriptor)),
+                _ => UnknownFrame::decode_inner(descriptor, input)
+                    .map(|(i, r)| (i, Frame::Unknown(Box::new(r)))),
             }
         }
     }
@@ -105,30 +107,38 @@ impl Decode for Frame {
 
 impl Encode for Frame {
     fn encoded_size(&self) -> usize {
+        self.encoded_size_with(false)
+    }
+    fn encode(&self, buf: &mut BytesMut) {
+        self.encode_with(buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
         match *self {
-            Frame::Open(ref v) => encoded_size_open_inner(v),
-            Frame::Begin(ref v) => encoded_size_begin_inner(v),
-            Frame::Attach(ref v) => encoded_size_attach_inner(v),
-            Frame::Flow(ref v) => encoded_size_flow_inner(v),
-            Frame::Transfer(ref v) => encoded_size_transfer_inner(v),
-            Frame::Disposition(ref v) => encoded_size_disposition_inner(v),
-            Frame::Detach(ref v) => encoded_size_detach_inner(v),
-            Frame::End(ref v) => encoded_size_end_inner(v),
-            Frame::Close(ref v) => encoded_size_close_inner(v),
+            Frame::Open(ref v) => encoded_size_open_inner(v, compact),
+            Frame::Begin(ref v) => encoded_size_begin_inner(v, compact),
+            Frame::Attach(ref v) => encoded_size_attach_inner(v, compact),
+            Frame::Flow(ref v) => encoded_size_flow_inner(v, compact),
+            Frame::Transfer(ref v) => encoded_size_transfer_inner(v, compact),
+            Frame::Disposition(ref v) => encoded_size_disposition_inner(v, compact),
+            Frame::Detach(ref v) => encoded_size_detach_inner(v, compact),
+            Frame::End(ref v) => encoded_size_end_inner(v, compact),
+            Frame::Close(ref v) => encoded_size_close_inner(v, compact),
+            Frame::Unknown(ref v) => v.encoded_size(),
             Frame::Empty => 0,
         }
     }
-    fn encode(&self, buf: &mut BytesMut) {
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
         match *self {
-            Frame::Open(ref v) => encode_open_inner(v, buf),
-            Frame::Begin(ref v) => encode_begin_inner(v, buf),
-            Frame::Attach(ref v) => encode_attach_inner(v, buf),
-            Frame::Flow(ref v) => encode_flow_inner(v, buf),
-            Frame::Transfer(ref v) => encode_transfer_inner(v, buf),
-            Frame::Disposition(ref v) => encode_disposition_inner(v, buf),
-            Frame::Detach(ref v) => encode_detach_inner(v, buf),
-            Frame::End(ref v) => encode_end_inner(v, buf),
-            Frame::Close(ref v) => encode_close_inner(v, buf),
+            Frame::Open(ref v) => encode_open_inner(v, buf, compact),
+            Frame::Begin(ref v) => encode_begin_inner(v, buf, compact),
+            Frame::Attach(ref v) => encode_attach_inner(v, buf, compact),
+            Frame::Flow(ref v) => encode_flow_inner(v, buf, compact),
+            Frame::Transfer(ref v) => encode_transfer_inner(v, buf, compact),
+            Frame::Disposition(ref v) => encode_disposition_inner(v, buf, compact),
+            Frame::Detach(ref v) => encode_detach_inner(v, buf, compact),
+            Frame::End(ref v) => encode_end_inner(v, buf, compact),
+            Frame::Close(ref v) => encode_close_inner(v, buf, compact),
+            Frame::Unknown(ref v) => v.encode(buf),
             Frame::Empty => (),
         }
     }
@@ -161,16 +171,22 @@ impl DecodeFormatted for {{provide.name}} {
 
 impl Encode for {{provide.name}} {
     fn encoded_size(&self) -> usize {
+        self.encoded_size_with(false)
+    }
+    fn encode(&self, buf: &mut BytesMut) {
+        self.encode_with(buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
         match *self {
             {{#each provide.options as |option|}}
-            {{provide.name}}::{{option.ty}}(ref v) => encoded_size_{{snake option.ty}}_inner(v),
+            {{provide.name}}::{{option.ty}}(ref v) => encoded_size_{{snake option.ty}}_inner(v, compact),
             {{/each}}
         }
     }
-    fn encode(&self, buf: &mut BytesMut) {
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
         match *self {
             {{#each provide.options as |option|}}
-            {{provide.name}}::{{option.ty}}(ref v) => encode_{{snake option.ty}}_inner(v, buf),
+            {{provide.name}}::{{option.ty}}(ref v) => encode_{{snake option.ty}}_inner(v, buf, compact),
             {{/each}}
         }
     }
@@ -269,13 +285,13 @@ type {{dr.name}} = {{dr.ty}};
 fn decode_{{snake dr.name}}_inner(input: &[u8]) -> Result<(&[u8], {{dr.name}}), AmqpParseError> {
     {{dr.name}}::decode(input)
 }
-fn encoded_size_{{snake dr.name}}_inner(dr: &{{dr.name}}) -> usize {
+fn encoded_size_{{snake dr.name}}_inner(dr: &{{dr.name}}, compact: bool) -> usize {
     // descriptor size + actual size
-    3 + dr.encoded_size()
+    3 + dr.encoded_size_with(compact)
 }
-fn encode_{{snake dr.name}}_inner(dr: &{{dr.name}}, buf: &mut BytesMut) {
+fn encode_{{snake dr.name}}_inner(dr: &{{dr.name}}, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong({{dr.descriptor.code}}).encode(buf);
-    dr.encode(buf);
+    dr.encode_with(buf, compact);
 }
 {{/each}}
 
@@ -400,9 +416,9 @@ fn decode_{{snake list.name}}_inner(input: &[u8]) -> Result<(&[u8], {{list.name}
     }))
 }
 
-fn encoded_size_{{snake list.name}}_inner(list: &{{list.name}}) -> usize {
+fn encoded_size_{{snake list.name}}_inner(list: &{{list.name}}, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
-    let content_size = 0 {{#each list.fields as |field|}} + list.{{field.name}}.encoded_size(){{/each}};
+    let content_size = 0 {{#each list.fields as |field|}} + list.{{field.name}}.encoded_size_with(compact){{/each}};
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize { 12 } else { 6 })
         + content_size
@@ -411,10 +427,10 @@ fn encoded_size_{{snake list.name}}_inner(list: &{{list.name}}) -> usize {
     + list.body.as_ref().map(|b| b.len()).unwrap_or(0)
     {{/if}}
 }
-fn encode_{{snake list.name}}_inner(list: &{{list.name}}, buf: &mut BytesMut) {
+fn encode_{{snake list.name}}_inner(list: &{{list.name}}, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong({{list.descriptor.code}}).encode(buf);
     #[allow(clippy::identity_op)]
-    let content_size = 0 {{#each list.fields as |field|}} + list.{{field.name}}.encoded_size(){{/each}};
+    let content_size = 0 {{#each list.fields as |field|}} + list.{{field.name}}.encoded_size_with(compact){{/each}};
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -426,7 +442,7 @@ fn encode_{{snake list.name}}_inner(list: &{{list.name}}, buf: &mut BytesMut) {
         buf.put_u8({{list.name}}::FIELD_COUNT as u8);
     }
     {{#each list.fields as |field|}}
-    list.{{field.name}}.encode(buf);
+    list.{{field.name}}.encode_with(buf, compact);
     {{/each}}
     {{#if list.transfer}}
     if let Some(ref body) = list.body {
@@ -452,8 +468,12 @@ impl DecodeFormatted for {{list.name}} {
 }
 
 impl Encode for {{list.name}} {
-    fn encoded_size(&self) -> usize { encoded_size_{{snake list.name}}_inner(self) }
+    fn encoded_size(&self) -> usize { encoded_size_{{snake list.name}}_inner(self, false) }
+
+    fn encode(&self, buf: &mut BytesMut) { encode_{{snake list.name}}_inner(self, buf, false) }
+
+    fn encoded_size_with(&self, compact: bool) -> usize { encoded_size_{{snake list.name}}_inner(self, compact) }
 
-    fn encode(&self, buf: &mut BytesMut) { encode_{{snake list.name}}_inner(self, buf) }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) { encode_{{snake list.name}}_inner(self, buf, compact) }
 }
 {{/each}}
@@ -200,7 +200,8 @@ impl Definitions {
         let mut enums = vec![];
         let mut lists = vec![];
         let mut described_restricted = vec![];
-        let mut provide_map: HashMap<String, Vec<ProvidesItem>> = HashMap::new();
+        // keep order of specification, so generated code is stable
+        let mut provide_map: Vec<(String, Vec<ProvidesItem>)> = vec![];
         for t in types.into_iter() {
             match t {
                 _Type::Alias(ref a) if a.source != "map" => {
@@ -266,22 +267,24 @@ impl Definitions {
     }
 
     fn register_provides(
-        map: &mut HashMap<String, Vec<ProvidesItem>>,
+        map: &mut Vec<(String, Vec<ProvidesItem>)>,
         name: &str,
         descriptor: Option<Descriptor>,
         provides: &Vec<String>,
     ) {
         for p in provides.iter() {
-            map.entry(p.clone())
-                .or_insert_with(|| vec![])
-                .push(ProvidesItem {
-                    ty: name.to_string(),
-                    descriptor: descriptor.clone().unwrap_or_else(|| Descriptor {
-                        name: String::new(),
-                        domain: 0,
-                        code: 0,
-                    }),
-                });
+            let item = ProvidesItem {
+                ty: name.to_string(),
+                descriptor: descriptor.clone().unwrap_or_else(|| Descriptor {
+                    name: String::new(),
+                    domain: 0,
+                    code: 0,
+                }),
+            };
+            match map.iter_mut().find(|(k, _)| k == p) {
+                Some((_, items)) => items.push(item),
+                None => map.push((p.clone(), vec![item])),
+            }
         }
     }
 }
@@ -0,0 +1,546 @@
+//! Generators for property-based tests
+//!
+//! Available with `testing` feature. Codec types implement
+//! `arbitrary::Arbitrary`, values could be generated from fuzzer input
+//! or with `check()` that derives input from seed, test runner could
+//! report failing seed for reproduction.
+//!
+//! ```rust,ignore
+//! use ntex_amqp_codec::arbitrary::{check, roundtrip_frame};
+//! use ntex_amqp_codec::AmqpFrame;
+//!
+//! check(seed, 1000, |frame: AmqpFrame| {
+//!     assert_eq!(roundtrip_frame(&frame).unwrap(), frame);
+//! });
+//! ```
+use std::{convert::TryFrom, fmt};
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use chrono::{TimeZone, Utc};
+use ntex_bytes::{ByteString, Bytes, BytesMut};
+use ntex_codec::{Decoder, Encoder};
+use ordered_float::OrderedFloat;
+use uuid::Uuid;
+
+use crate::codec::{Decode, Encode};
+use crate::error::{AmqpCodecError, AmqpParseError};
+use crate::framing::AmqpFrame;
+use crate::io::AmqpCodec;
+use crate::message::Message;
+use crate::protocol::*;
+use crate::types::{Descriptor, List, Multiple, Str, Symbol, Variant, VariantMap};
+
+/// Max depth of nested variant containers
+const MAX_DEPTH: usize = 2;
+
+/// Max size of generated collections
+const MAX_LEN: usize = 8;
+
+/// Size of input generated from seed
+const INPUT_SIZE: usize = 1024;
+
+/// Encode and decode value
+pub fn roundtrip<T: Encode + Decode>(value: &T) -> Result<T, AmqpParseError> {
+    let mut buf = BytesMut::with_capacity(value.encoded_size());
+    value.encode(&mut buf);
+    let (rest, decoded) = T::decode(&buf)?;
+    if rest.is_empty() {
+        Ok(decoded)
+    } else {
+        Err(AmqpParseError::InvalidSize)
+    }
+}
+
+/// Encode and decode frame with `AmqpCodec`
+pub fn roundtrip_frame(frame: &AmqpFrame) -> Result<AmqpFrame, AmqpCodecError> {
+    let codec = AmqpCodec::<AmqpFrame>::new();
+    let mut buf = BytesMut::new();
+    codec.encode(frame.clone(), &mut buf)?;
+    let decoded = codec
+        .decode(&mut buf)?
+        .ok_or(AmqpCodecError::UnparsedBytesLeft)?;
+    if buf.is_empty() {
+        Ok(decoded)
+    } else {
+        Err(AmqpCodecError::UnparsedBytesLeft)
+    }
+}
+
+/// Run `f` for `count` values generated from seed
+///
+/// Panics with failing seed if `f` panics.
+pub fn check<T, F>(seed: u64, count: usize, f: F)
+where
+    T: for<'a> Arbitrary<'a> + fmt::Debug,
+    F: Fn(T),
+{
+    for idx in 0..count {
+        let seed = seed.wrapping_add(idx as u64);
+        let data = input(seed);
+        let value = T::arbitrary(&mut Unstructured::new(&data))
+            .unwrap_or_else(|err| panic!("Cannot generate value, seed: {}, {}", seed, err));
+        let desc = format!("{:?}", value);
+        if std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(value))).is_err() {
+            panic!("Property failed, seed: {}, value: {}", seed, desc);
+        }
+    }
+}
+
+/// Generator input for seed
+fn input(seed: u64) -> Vec<u8> {
+    // splitmix64
+    let mut state = seed ^ 0x9e37_79b9_7f4a_7c15;
+    (0..INPUT_SIZE / 8)
+        .flat_map(|_| {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            (z ^ (z >> 31)).to_le_bytes().to_vec()
+        })
+        .collect()
+}
+
+/// Random collection length
+fn len(u: &mut Unstructured<'_>) -> Result<usize> {
+    u.int_in_range(0..=MAX_LEN)
+}
+
+/// Random integer, prefers small values, they use compact encodings
+fn int<'a, T>(u: &mut Unstructured<'a>) -> Result<T>
+where
+    T: Arbitrary<'a> + TryFrom<u8>,
+{
+    if u.arbitrary()? {
+        if let Ok(val) = T::try_from(u.arbitrary::<u8>()?) {
+            return Ok(val);
+        }
+    }
+    u.arbitrary()
+}
+
+fn option<'a, T, F>(u: &mut Unstructured<'a>, f: F) -> Result<Option<T>>
+where
+    F: FnOnce(&mut Unstructured<'a>) -> Result<T>,
+{
+    if u.arbitrary()? {
+        Ok(Some(f(u)?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn bytes(u: &mut Unstructured<'_>) -> Result<Bytes> {
+    let len = len(u)? * 4;
+    Ok(Bytes::copy_from_slice(u.bytes(len)?))
+}
+
+fn byte_string(u: &mut Unstructured<'_>) -> Result<ByteString> {
+    const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789-_./:";
+    let s = (0..len(u)? * 2)
+        .map(|_| u.choose(CHARS).map(|c| *c as char))
+        .collect::<Result<String>>()?;
+    Ok(ByteString::from(s))
+}
+
+fn uuid(u: &mut Unstructured<'_>) -> Result<Uuid> {
+    Ok(Uuid::from_bytes(u.arbitrary()?))
+}
+
+fn fields(u: &mut Unstructured<'_>) -> Result<Fields> {
+    (0..len(u)?)
+        .map(|_| Ok((u.arbitrary()?, u.arbitrary()?)))
+        .collect()
+}
+
+fn variant(u: &mut Unstructured<'_>, depth: usize) -> Result<Variant> {
+    let kinds = if depth < MAX_DEPTH { 21 } else { 18 };
+    Ok(match u.choose_index(kinds)? {
+        0 => Variant::Null,
+        1 => Variant::Boolean(u.arbitrary()?),
+        2 => Variant::Ubyte(int(u)?),
+        3 => Variant::Ushort(int(u)?),
+        4 => Variant::Uint(int(u)?),
+        5 => Variant::Ulong(int(u)?),
+        6 => Variant::Byte(int(u)?),
+        7 => Variant::Short(int(u)?),
+        8 => Variant::Int(int(u)?),
+        9 => Variant::Long(int(u)?),
+        10 => Variant::Float(OrderedFloat(f32::from_bits(u.arbitrary()?))),
+        11 => Variant::Double(OrderedFloat(f64::from_bits(u.arbitrary()?))),
+        12 => Variant::Char(std::char::from_u32(u.int_in_range(0..=0xd7ff)?).unwrap_or('a')),
+        13 => {
+            let ms = u.int_in_range(0..=4_102_444_799_999)?;
+            Variant::Timestamp(Utc.timestamp_millis_opt(ms).unwrap())
+        }
+        14 => Variant::Uuid(uuid(u)?),
+        15 => Variant::Binary(bytes(u)?),
+        16 => Variant::String(u.arbitrary()?),
+        17 => Variant::Symbol(u.arbitrary()?),
+        18 => Variant::List(List(
+            (0..len(u)?)
+                .map(|_| variant(u, depth + 1))
+                .collect::<Result<_>>()?,
+        )),
+        // map keys are scalars, `VariantMap` is not hashable
+        19 => Variant::Map(VariantMap::new(
+            (0..len(u)?)
+                .map(|_| Ok((variant(u, MAX_DEPTH)?, variant(u, depth + 1)?)))
+                .collect::<Result<_>>()?,
+        )),
+        _ => Variant::Described((Descriptor::Ulong(int(u)?), Box::new(variant(u, depth + 1)?))),
+    })
+}
+
+impl<'a, T: Arbitrary<'a>> Arbitrary<'a> for Multiple<T> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let len = len(u)? + 1;
+        Ok(Multiple(
+            (0..len).map(|_| u.arbitrary()).collect::<Result<_>>()?,
+        ))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Str {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Str::ByteStr(byte_string(u)?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Symbol {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Symbol(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Variant {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        variant(u, 0)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Error {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let condition = match u.choose_index(4)? {
+            0 => AmqpError::InternalError.into(),
+            1 => AmqpError::NotFound.into(),
+            2 => LinkError::DetachForced.into(),
+            _ => ErrorCondition::Custom(Symbol::from(format!("x:{}", u.int_in_range(0..=99u8)?))),
+        };
+        Ok(Error {
+            condition,
+            description: option(u, byte_string)?,
+            info: option(u, fields)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Role {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(if u.arbitrary()? {
+            Role::Sender
+        } else {
+            Role::Receiver
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for DeliveryState {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.choose_index(7)? {
+            0 => DeliveryState::Received(Received {
+                section_number: int(u)?,
+                section_offset: int(u)?,
+            }),
+            1 => DeliveryState::Accepted(Accepted {}),
+            2 => DeliveryState::Rejected(Rejected {
+                error: u.arbitrary()?,
+            }),
+            3 => DeliveryState::Released(Released {}),
+            4 => DeliveryState::Declared(Declared { txn_id: bytes(u)? }),
+            5 => DeliveryState::TransactionalState(TransactionalState {
+                txn_id: bytes(u)?,
+                outcome: u.arbitrary()?,
+            }),
+            _ => DeliveryState::Modified(Modified {
+                delivery_failed: u.arbitrary()?,
+                undeliverable_here: u.arbitrary()?,
+                message_annotations: option(u, fields)?,
+            }),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Outcome {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.choose_index(5)? {
+            0 => Outcome::Accepted(Accepted {}),
+            1 => Outcome::Rejected(Rejected {
+                error: u.arbitrary()?,
+            }),
+            2 => Outcome::Released(Released {}),
+            3 => Outcome::Declared(Declared { txn_id: bytes(u)? }),
+            _ => Outcome::Modified(Modified {
+                delivery_failed: u.arbitrary()?,
+                undeliverable_here: u.arbitrary()?,
+                message_annotations: option(u, fields)?,
+            }),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Open {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Open {
+            container_id: byte_string(u)?,
+            hostname: option(u, byte_string)?,
+            max_frame_size: int(u)?,
+            channel_max: int(u)?,
+            idle_time_out: u.arbitrary()?,
+            outgoing_locales: None,
+            incoming_locales: None,
+            offered_capabilities: u.arbitrary()?,
+            desired_capabilities: u.arbitrary()?,
+            properties: option(u, fields)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Begin {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Begin {
+            remote_channel: u.arbitrary()?,
+            next_outgoing_id: int(u)?,
+            incoming_window: int(u)?,
+            outgoing_window: int(u)?,
+            handle_max: int(u)?,
+            offered_capabilities: u.arbitrary()?,
+            desired_capabilities: u.arbitrary()?,
+            properties: option(u, fields)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Attach {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let source = option(u, |u| {
+            Ok(Source {
+                address: option(u, byte_string)?,
+                durable: TerminusDurability::None,
+                expiry_policy: TerminusExpiryPolicy::SessionEnd,
+                timeout: 0,
+                dynamic: u.arbitrary()?,
+                dynamic_node_properties: None,
+                distribution_mode: None,
+                filter: None,
+                default_outcome: None,
+                outcomes: None,
+                capabilities: u.arbitrary()?,
+            })
+        })?;
+        let target = option(u, |u| {
+            Ok(Target {
+                address: option(u, byte_string)?,
+                durable: TerminusDurability::None,
+                expiry_policy: TerminusExpiryPolicy::SessionEnd,
+                timeout: 0,
+                dynamic: u.arbitrary()?,
+                dynamic_node_properties: None,
+                capabilities: u.arbitrary()?,
+            })
+        })?;
+        Ok(Attach {
+            name: byte_string(u)?,
+            handle: int(u)?,
+            role: u.arbitrary()?,
+            snd_settle_mode: SenderSettleMode::Mixed,
+            rcv_settle_mode: ReceiverSettleMode::First,
+            source,
+            target,
+            unsettled: None,
+            incomplete_unsettled: u.arbitrary()?,
+            initial_delivery_count: u.arbitrary()?,
+            max_message_size: u.arbitrary()?,
+            offered_capabilities: u.arbitrary()?,
+            desired_capabilities: u.arbitrary()?,
+            properties: option(u, fields)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Flow {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Flow {
+            next_incoming_id: u.arbitrary()?,
+            incoming_window: int(u)?,
+            next_outgoing_id: int(u)?,
+            outgoing_window: int(u)?,
+            handle: u.arbitrary()?,
+            delivery_count: u.arbitrary()?,
+            link_credit: u.arbitrary()?,
+            available: u.arbitrary()?,
+            drain: u.arbitrary()?,
+            echo: u.arbitrary()?,
+            properties: option(u, fields)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Transfer {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        // payload is decoded as raw data
+        let body = bytes(u)?;
+        Ok(Transfer {
+            handle: int(u)?,
+            delivery_id: u.arbitrary()?,
+            delivery_tag: option(u, bytes)?,
+            message_format: u.arbitrary()?,
+            settled: u.arbitrary()?,
+            more: u.arbitrary()?,
+            rcv_settle_mode: None,
+            state: u.arbitrary()?,
+            resume: u.arbitrary()?,
+            aborted: u.arbitrary()?,
+            batchable: u.arbitrary()?,
+            body: if body.is_empty() {
+                None
+            } else {
+                Some(TransferBody::Data(body))
+            },
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Disposition {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Disposition {
+            role: u.arbitrary()?,
+            first: int(u)?,
+            last: u.arbitrary()?,
+            settled: u.arbitrary()?,
+            state: u.arbitrary()?,
+            batchable: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Detach {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Detach {
+            handle: int(u)?,
+            closed: u.arbitrary()?,
+            error: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for End {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(End {
+            error: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Close {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Close {
+            error: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Frame {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.choose_index(10)? {
+            0 => Frame::Open(u.arbitrary()?),
+            1 => Frame::Begin(u.arbitrary()?),
+            2 => Frame::Attach(u.arbitrary()?),
+            3 => Frame::Flow(u.arbitrary()?),
+            4 => Frame::Transfer(u.arbitrary()?),
+            5 => Frame::Disposition(u.arbitrary()?),
+            6 => Frame::Detach(u.arbitrary()?),
+            7 => Frame::End(u.arbitrary()?),
+            8 => Frame::Close(u.arbitrary()?),
+            _ => Frame::Empty,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for AmqpFrame {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(AmqpFrame::new(int(u)?, u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Header {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Header {
+            durable: u.arbitrary()?,
+            priority: int(u)?,
+            ttl: u.arbitrary()?,
+            first_acquirer: u.arbitrary()?,
+            delivery_count: int(u)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Message {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut msg = Message::default();
+        if let Some(hdr) = u.arbitrary()? {
+            msg.set_header(hdr);
+        }
+        if u.arbitrary()? {
+            let id = match u.choose_index(3)? {
+                0 => MessageId::Ulong(int(u)?),
+                1 => MessageId::Uuid(uuid(u)?),
+                _ => MessageId::String(byte_string(u)?),
+            };
+            let subject = option(u, byte_string)?;
+            msg.set_properties(|props| {
+                props.message_id = Some(id.clone());
+                props.subject = subject.clone();
+            });
+        }
+        for _ in 0..len(u)? {
+            msg.set_app_property(byte_string(u)?, u.arbitrary::<Variant>()?);
+        }
+        for _ in 0..u.choose_index(3)? {
+            msg.add_message_annotation(u.arbitrary::<Symbol>()?, u.arbitrary::<Variant>()?);
+        }
+        for _ in 0..u.choose_index(3)? {
+            let data = bytes(u)?;
+            msg.set_body(|body| body.data.push(data));
+        }
+        Ok(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variant_roundtrip() {
+        check(0, 1000, |value: Variant| {
+            assert_eq!(roundtrip(&value).unwrap(), value);
+        });
+    }
+
+    #[test]
+    fn test_frame_roundtrip() {
+        check(0, 1000, |frame: AmqpFrame| {
+            assert_eq!(roundtrip_frame(&frame).unwrap(), frame);
+        });
+    }
+
+    #[test]
+    fn test_message_roundtrip() {
+        check(0, 1000, |msg: Message| {
+            let decoded = roundtrip(&msg).unwrap();
+            assert_eq!(decoded.encoded_size(), msg.encoded_size());
+            assert_eq!(decoded, msg);
+        });
+    }
+}
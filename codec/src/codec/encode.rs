@@ -308,6 +308,10 @@ impl Encode for Bytes {
 
 impl ArrayEncode for Bytes {
     const ARRAY_FORMAT_CODE: u8 = codec::FORMATCODE_BINARY32;
+    const ARRAY_FORMAT_CODE8: Option<u8> = Some(codec::FORMATCODE_BINARY8);
+    fn array_value(&self) -> &[u8] {
+        self.as_ref()
+    }
     fn array_encoded_size(&self) -> usize {
         4 + self.len()
     }
@@ -338,6 +342,10 @@ impl Encode for ByteString {
 }
 impl ArrayEncode for ByteString {
     const ARRAY_FORMAT_CODE: u8 = codec::FORMATCODE_STRING32;
+    const ARRAY_FORMAT_CODE8: Option<u8> = Some(codec::FORMATCODE_STRING8);
+    fn array_value(&self) -> &[u8] {
+        self.as_bytes()
+    }
     fn array_encoded_size(&self) -> usize {
         4 + self.len()
     }
@@ -369,6 +377,10 @@ impl Encode for str {
 
 impl ArrayEncode for str {
     const ARRAY_FORMAT_CODE: u8 = codec::FORMATCODE_STRING32;
+    const ARRAY_FORMAT_CODE8: Option<u8> = Some(codec::FORMATCODE_STRING8);
+    fn array_value(&self) -> &[u8] {
+        self.as_bytes()
+    }
     fn array_encoded_size(&self) -> usize {
         4 + self.len()
     }
@@ -420,6 +432,10 @@ impl Encode for Symbol {
 
 impl ArrayEncode for Symbol {
     const ARRAY_FORMAT_CODE: u8 = codec::FORMATCODE_SYMBOL32;
+    const ARRAY_FORMAT_CODE8: Option<u8> = Some(codec::FORMATCODE_SYMBOL8);
+    fn array_value(&self) -> &[u8] {
+        self.as_bytes()
+    }
     fn array_encoded_size(&self) -> usize {
         4 + self.len()
     }
@@ -451,21 +467,31 @@ impl Encode for StaticSymbol {
 
 fn map_encoded_size<K: Hash + Eq + Encode, V: Encode, S: BuildHasher>(
     map: &HashMap<K, V, S>,
+    compact: bool,
 ) -> usize {
-    map.iter()
-        .fold(0, |r, (k, v)| r + k.encoded_size() + v.encoded_size())
+    map.iter().fold(0, |r, (k, v)| {
+        r + k.encoded_size_with(compact) + v.encoded_size_with(compact)
+    })
 }
 impl<K: Eq + Hash + Encode, V: Encode, S: BuildHasher> Encode for HashMap<K, V, S> {
     fn encoded_size(&self) -> usize {
-        let size = map_encoded_size(self);
+        self.encoded_size_with(false)
+    }
+
+    fn encode(&self, buf: &mut BytesMut) {
+        self.encode_with(buf, false)
+    }
+
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        let size = map_encoded_size(self, compact);
         // f:1 + s:4 + c:4 vs f:1 + s:1 + c:1
         let preamble = if size + 1 > u8::MAX as usize { 9 } else { 3 };
         preamble + size
     }
 
-    fn encode(&self, buf: &mut BytesMut) {
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
         let count = self.len() * 2; // key-value pair accounts for two items in count
-        let size = map_encoded_size(self);
+        let size = map_encoded_size(self, compact);
         if size + 1 > u8::MAX as usize {
             buf.put_u8(codec::FORMATCODE_MAP32);
             buf.put_u32((size + 4) as u32); // +4 for 4 byte count that follows
@@ -477,8 +503,8 @@ impl<K: Eq + Hash + Encode, V: Encode, S: BuildHasher> Encode for HashMap<K, V,
         }
 
         for (k, v) in self {
-            k.encode(buf);
-            v.encode(buf);
+            k.encode_with(buf, compact);
+            v.encode_with(buf, compact);
         }
     }
 }
@@ -486,11 +512,11 @@ impl<K: Eq + Hash + Encode, V: Encode, S: BuildHasher> Encode for HashMap<K, V,
 impl<K: Eq + Hash + Encode, V: Encode> ArrayEncode for HashMap<K, V> {
     const ARRAY_FORMAT_CODE: u8 = codec::FORMATCODE_MAP32;
     fn array_encoded_size(&self) -> usize {
-        8 + map_encoded_size(self)
+        8 + map_encoded_size(self, false)
     }
     fn array_encode(&self, buf: &mut BytesMut) {
         let count = self.len() * 2;
-        let size = map_encoded_size(self) + 4;
+        let size = map_encoded_size(self, false) + 4;
         buf.put_u32(size as u32);
         buf.put_u32(count as u32);
 
@@ -503,22 +529,28 @@ impl<K: Eq + Hash + Encode, V: Encode> ArrayEncode for HashMap<K, V> {
 
 impl Encode for VecSymbolMap {
     fn encoded_size(&self) -> usize {
-        let size = self
-            .0
-            .iter()
-            .fold(0, |r, (k, v)| r + k.encoded_size() + v.encoded_size());
+        self.encoded_size_with(false)
+    }
+
+    fn encode(&self, buf: &mut BytesMut) {
+        self.encode_with(buf, false)
+    }
+
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        let size = self.0.iter().fold(0, |r, (k, v)| {
+            r + k.encoded_size_with(compact) + v.encoded_size_with(compact)
+        });
 
         // f:1 + s:4 + c:4 vs f:1 + s:1 + c:1
         let preamble = if size + 1 > u8::MAX as usize { 9 } else { 3 };
         preamble + size
     }
 
-    fn encode(&self, buf: &mut BytesMut) {
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
         let count = self.len() * 2; // key-value pair accounts for two items in count
-        let size = self
-            .0
-            .iter()
-            .fold(0, |r, (k, v)| r + k.encoded_size() + v.encoded_size());
+        let size = self.0.iter().fold(0, |r, (k, v)| {
+            r + k.encoded_size_with(compact) + v.encoded_size_with(compact)
+        });
 
         if size + 1 > u8::MAX as usize {
             buf.put_u8(codec::FORMATCODE_MAP32);
@@ -531,30 +563,36 @@ impl Encode for VecSymbolMap {
         }
 
         for (k, v) in self.iter() {
-            k.encode(buf);
-            v.encode(buf);
+            k.encode_with(buf, compact);
+            v.encode_with(buf, compact);
         }
     }
 }
 
 impl Encode for VecStringMap {
     fn encoded_size(&self) -> usize {
-        let size = self
-            .0
-            .iter()
-            .fold(0, |r, (k, v)| r + k.encoded_size() + v.encoded_size());
+        self.encoded_size_with(false)
+    }
+
+    fn encode(&self, buf: &mut BytesMut) {
+        self.encode_with(buf, false)
+    }
+
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        let size = self.0.iter().fold(0, |r, (k, v)| {
+            r + k.encoded_size_with(compact) + v.encoded_size_with(compact)
+        });
 
         // f:1 + s:4 + c:4 vs f:1 + s:1 + c:1
         let preamble = if size + 1 > u8::MAX as usize { 9 } else { 3 };
         preamble + size
     }
 
-    fn encode(&self, buf: &mut BytesMut) {
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
         let count = self.len() * 2; // key-value pair accounts for two items in count
-        let size = self
-            .0
-            .iter()
-            .fold(0, |r, (k, v)| r + k.encoded_size() + v.encoded_size());
+        let size = self.0.iter().fold(0, |r, (k, v)| {
+            r + k.encoded_size_with(compact) + v.encoded_size_with(compact)
+        });
 
         if size + 1 > u8::MAX as usize {
             buf.put_u8(codec::FORMATCODE_MAP32);
@@ -567,19 +605,44 @@ impl Encode for VecStringMap {
         }
 
         for (k, v) in self.iter() {
-            k.encode(buf);
-            v.encode(buf);
+            k.encode_with(buf, compact);
+            v.encode_with(buf, compact);
         }
     }
 }
 
-fn array_encoded_size<T: ArrayEncode>(vec: &[T]) -> usize {
-    vec.iter().fold(0, |r, i| r + i.array_encoded_size())
+/// Compact element constructor, if all elements fit
+fn array_format_code8<T: ArrayEncode>(vec: &[T], compact: bool) -> Option<u8> {
+    if compact
+        && vec
+            .iter()
+            .all(|i| i.array_value().len() <= u8::MAX as usize)
+    {
+        T::ARRAY_FORMAT_CODE8
+    } else {
+        None
+    }
+}
+
+fn array_encoded_size<T: ArrayEncode>(vec: &[T], compact: bool) -> usize {
+    if array_format_code8(vec, compact).is_some() {
+        vec.iter().fold(0, |r, i| r + 1 + i.array_value().len())
+    } else {
+        vec.iter().fold(0, |r, i| r + i.array_encoded_size())
+    }
 }
 
 impl<T: ArrayEncode> Encode for Vec<T> {
     fn encoded_size(&self) -> usize {
-        let content_size = array_encoded_size(self);
+        self.encoded_size_with(false)
+    }
+
+    fn encode(&self, buf: &mut BytesMut) {
+        self.encode_with(buf, false)
+    }
+
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        let content_size = array_encoded_size(self, compact);
         // format_code + size + count + item constructor -- todo: support described ctor?
         (if content_size + 1 > u8::MAX as usize {
             10
@@ -589,8 +652,8 @@ impl<T: ArrayEncode> Encode for Vec<T> {
             + content_size
     }
 
-    fn encode(&self, buf: &mut BytesMut) {
-        let size = array_encoded_size(self);
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        let size = array_encoded_size(self, compact);
         if size + 1 > u8::MAX as usize {
             buf.put_u8(codec::FORMATCODE_ARRAY32);
             buf.put_u32((size + 5) as u32); // +4 for 4 byte count and 1 byte item ctor that follow
@@ -600,42 +663,70 @@ impl<T: ArrayEncode> Encode for Vec<T> {
             buf.put_u8((size + 2) as u8); // +1 for 1 byte count and 1 byte item ctor that follow
             buf.put_u8(self.len() as u8);
         }
-        buf.put_u8(T::ARRAY_FORMAT_CODE);
-        for i in self {
-            i.array_encode(buf);
+        if let Some(code) = array_format_code8(self, compact) {
+            buf.put_u8(code);
+            for i in self {
+                let value = i.array_value();
+                buf.put_u8(value.len() as u8);
+                buf.put_slice(value);
+            }
+        } else {
+            buf.put_u8(T::ARRAY_FORMAT_CODE);
+            for i in self {
+                i.array_encode(buf);
+            }
         }
     }
 }
 
 impl<T: Encode + ArrayEncode> Encode for Multiple<T> {
     fn encoded_size(&self) -> usize {
+        self.encoded_size_with(false)
+    }
+
+    fn encode(&self, buf: &mut BytesMut) {
+        self.encode_with(buf, false)
+    }
+
+    fn encoded_size_with(&self, compact: bool) -> usize {
         let count = self.len();
         if count == 1 {
             // special case: single item is encoded without array encoding
-            self.0[0].encoded_size()
+            self.0[0].encoded_size_with(compact)
         } else {
-            self.0.encoded_size()
+            self.0.encoded_size_with(compact)
         }
     }
 
-    fn encode(&self, buf: &mut BytesMut) {
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
         let count = self.0.len();
         if count == 1 {
             // special case: single item is encoded without array encoding
-            self.0[0].encode(buf)
+            self.0[0].encode_with(buf, compact)
         } else {
-            self.0.encode(buf)
+            self.0.encode_with(buf, compact)
         }
     }
 }
 
-fn list_encoded_size(vec: &List) -> usize {
-    vec.iter().fold(0, |r, i| r + i.encoded_size())
+fn list_encoded_size(vec: &List, compact: bool) -> usize {
+    vec.iter().fold(0, |r, i| r + i.encoded_size_with(compact))
 }
 
 impl Encode for List {
     fn encoded_size(&self) -> usize {
-        let content_size = list_encoded_size(self);
+        self.encoded_size_with(false)
+    }
+
+    fn encode(&self, buf: &mut BytesMut) {
+        self.encode_with(buf, false)
+    }
+
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        if self.is_empty() && compact {
+            return 1;
+        }
+        let content_size = list_encoded_size(self, compact);
         // format_code + size + count
         (if content_size + 1 > u8::MAX as usize {
             9
@@ -644,25 +735,38 @@ impl Encode for List {
         }) + content_size
     }
 
-    fn encode(&self, buf: &mut BytesMut) {
-        let size = list_encoded_size(self);
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        if self.is_empty() && compact {
+            buf.put_u8(codec::FORMATCODE_LIST0);
+            return;
+        }
+        let size = list_encoded_size(self, compact);
         if size + 1 > u8::MAX as usize {
-            buf.put_u8(codec::FORMATCODE_ARRAY32);
+            buf.put_u8(codec::FORMATCODE_LIST32);
             buf.put_u32((size + 4) as u32); // +4 for 4 byte count that follow
             buf.put_u32(self.len() as u32);
         } else {
-            buf.put_u8(codec::FORMATCODE_ARRAY8);
+            buf.put_u8(codec::FORMATCODE_LIST8);
             buf.put_u8((size + 1) as u8); // +1 for 1 byte count that follow
             buf.put_u8(self.len() as u8);
         }
         for i in self.iter() {
-            i.encode(buf);
+            i.encode_with(buf, compact);
         }
     }
 }
 
 impl Encode for Variant {
     fn encoded_size(&self) -> usize {
+        self.encoded_size_with(false)
+    }
+
+    /// Encodes `Variant` into provided `BytesMut`
+    fn encode(&self, buf: &mut BytesMut) {
+        self.encode_with(buf, false)
+    }
+
+    fn encoded_size_with(&self, compact: bool) -> usize {
         match *self {
             Variant::Null => 1,
             Variant::Boolean(b) => b.encoded_size(),
@@ -683,14 +787,13 @@ impl Encode for Variant {
             Variant::String(ref s) => s.encoded_size(),
             Variant::Symbol(ref s) => s.encoded_size(),
             Variant::StaticSymbol(ref s) => s.encoded_size(),
-            Variant::List(ref l) => l.encoded_size(),
-            Variant::Map(ref m) => m.map.encoded_size(),
-            Variant::Described(ref dv) => dv.0.encoded_size() + dv.1.encoded_size(),
+            Variant::List(ref l) => l.encoded_size_with(compact),
+            Variant::Map(ref m) => m.map.encoded_size_with(compact),
+            Variant::Described(ref dv) => dv.0.encoded_size() + dv.1.encoded_size_with(compact),
         }
     }
 
-    /// Encodes `Variant` into provided `BytesMut`
-    fn encode(&self, buf: &mut BytesMut) {
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
         match *self {
             Variant::Null => encode_null(buf),
             Variant::Boolean(b) => b.encode(buf),
@@ -711,11 +814,11 @@ impl Encode for Variant {
             Variant::String(ref s) => s.encode(buf),
             Variant::Symbol(ref s) => s.encode(buf),
             Variant::StaticSymbol(ref s) => s.encode(buf),
-            Variant::List(ref l) => l.encode(buf),
-            Variant::Map(ref m) => m.map.encode(buf),
+            Variant::List(ref l) => l.encode_with(buf, compact),
+            Variant::Map(ref m) => m.map.encode_with(buf, compact),
             Variant::Described(ref dv) => {
                 dv.0.encode(buf);
-                dv.1.encode(buf);
+                dv.1.encode_with(buf, compact);
             }
         }
     }
@@ -723,12 +826,20 @@ impl Encode for Variant {
 
 impl<T: Encode> Encode for Option<T> {
     fn encoded_size(&self) -> usize {
-        self.as_ref().map_or(1, |v| v.encoded_size())
+        self.encoded_size_with(false)
     }
 
     fn encode(&self, buf: &mut BytesMut) {
+        self.encode_with(buf, false)
+    }
+
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        self.as_ref().map_or(1, |v| v.encoded_size_with(compact))
+    }
+
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
         match *self {
-            Some(ref e) => e.encode(buf),
+            Some(ref e) => e.encode_with(buf, compact),
             None => encode_null(buf),
         }
     }
@@ -755,30 +866,46 @@ const WORD_LEN: usize = 4;
 
 impl Encode for AmqpFrame {
     fn encoded_size(&self) -> usize {
-        framing::HEADER_LEN + self.performative().encoded_size()
+        self.encoded_size_with(false)
     }
 
     fn encode(&self, buf: &mut BytesMut) {
+        self.encode_with(buf, false)
+    }
+
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        framing::HEADER_LEN + self.performative().encoded_size_with(compact)
+    }
+
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
         let doff: u8 = (framing::HEADER_LEN / WORD_LEN) as u8;
-        buf.put_u32(self.encoded_size() as u32);
+        buf.put_u32(self.encoded_size_with(compact) as u32);
         buf.put_u8(doff);
         buf.put_u8(framing::FRAME_TYPE_AMQP);
         buf.put_u16(self.channel_id());
-        self.performative().encode(buf);
+        self.performative().encode_with(buf, compact);
     }
 }
 
 impl Encode for SaslFrame {
     fn encoded_size(&self) -> usize {
-        framing::HEADER_LEN + self.body.encoded_size()
+        self.encoded_size_with(false)
     }
 
     fn encode(&self, buf: &mut BytesMut) {
+        self.encode_with(buf, false)
+    }
+
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        framing::HEADER_LEN + self.body.encoded_size_with(compact)
+    }
+
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
         let doff: u8 = (framing::HEADER_LEN / WORD_LEN) as u8;
-        buf.put_u32(self.encoded_size() as u32);
+        buf.put_u32(self.encoded_size_with(compact) as u32);
         buf.put_u8(doff);
         buf.put_u8(framing::FRAME_TYPE_SASL);
         buf.put_u16(0);
-        self.body.encode(buf);
+        self.body.encode_with(buf, compact);
     }
 }
@@ -21,14 +21,40 @@ pub trait Encode {
     fn encoded_size(&self) -> usize;
 
     fn encode(&self, buf: &mut BytesMut);
+
+    /// Encoded size with compact encoding preference
+    ///
+    /// In compact mode empty lists are encoded as `list0` and arrays of
+    /// strings, symbols and binaries use 8-bit element constructors if all
+    /// elements fit. Scalars, strings and lists are always encoded with
+    /// smallest fitting format. Containers pass preference to nested values,
+    /// size and encoding of a value must be computed with the same preference.
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        let _ = compact;
+        self.encoded_size()
+    }
+
+    /// Encode with compact encoding preference, see `encoded_size_with()`
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        let _ = compact;
+        self.encode(buf)
+    }
 }
 
 pub trait ArrayEncode {
     const ARRAY_FORMAT_CODE: u8;
 
+    /// Element constructor of compact encoding, variable width types only
+    const ARRAY_FORMAT_CODE8: Option<u8> = None;
+
     fn array_encoded_size(&self) -> usize;
 
     fn array_encode(&self, buf: &mut BytesMut);
+
+    /// Value of variable width type, used by compact encoding
+    fn array_value(&self) -> &[u8] {
+        &[]
+    }
 }
 
 pub trait Decode
@@ -109,7 +135,10 @@ mod tests {
     use crate::codec::{Decode, Encode};
     use crate::error::AmqpCodecError;
     use crate::framing::{AmqpFrame, SaslFrame};
-    use crate::protocol::SaslFrameBody;
+    use crate::protocol::{
+        Accepted, Declared, DeliveryState, Frame, Outcome, SaslFrameBody, TransactionalState,
+    };
+    use crate::types::{Descriptor, List};
 
     #[test]
     fn test_sasl_mechanisms() -> Result<(), AmqpCodecError> {
@@ -147,4 +176,74 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_unknown_performative() -> Result<(), AmqpCodecError> {
+        let data = b"\x02\0\0\x01\0S\x70\x52\x05payload";
+
+        let (remainder, frame) = AmqpFrame::decode(data.as_ref())?;
+        assert!(remainder.is_empty());
+        assert_eq!(frame.channel_id(), 1);
+        match frame.performative() {
+            Frame::Unknown(frm) => {
+                assert_eq!(frm.descriptor, Descriptor::Ulong(0x70));
+                assert_eq!(frm.payload, Bytes::from_static(b"payload"));
+            }
+            _ => panic!("error"),
+        }
+
+        let mut buf = BytesMut::new();
+        buf.reserve(frame.encoded_size());
+        frame.encode(&mut buf);
+        let _ = buf.split_to(4);
+        assert_eq!(Bytes::from_static(data), buf.freeze());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_encoding() -> Result<(), AmqpCodecError> {
+        let data = b"\x02\x01\0\0\0S@\xc02\x01\xe0/\x04\xb3\0\0\0\x07MSSBCBS\0\0\0\x05PLAIN\0\0\0\tANONYMOUS\0\0\0\x08EXTERNAL";
+        let (_, frame) = SaslFrame::decode(data.as_ref())?;
+
+        let mut buf = BytesMut::with_capacity(frame.encoded_size_with(true));
+        frame.encode_with(&mut buf, true);
+        assert_eq!(buf.len(), frame.encoded_size_with(true));
+        assert_eq!(buf.len(), data.len() + 4 - 12);
+        assert_eq!(&buf[17..19], b"\xa3\x07");
+        let (remainder, decoded) = SaslFrame::decode(&buf[4..])?;
+        assert!(remainder.is_empty());
+        assert_eq!(decoded, frame);
+
+        let list = List(Vec::new());
+        let mut buf = BytesMut::new();
+        list.encode_with(&mut buf, true);
+        assert_eq!(&buf[..], b"\x45");
+        assert_eq!(List::decode(&buf)?.1, list);
+        buf.clear();
+        list.encode(&mut buf);
+        assert_eq!(&buf[..], b"\xc0\x01\x00");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_transactional_state() -> Result<(), AmqpCodecError> {
+        let state = DeliveryState::TransactionalState(TransactionalState {
+            txn_id: Bytes::from_static(b"txn1"),
+            outcome: Some(Outcome::Accepted(Accepted {})),
+        });
+        let mut buf = BytesMut::with_capacity(state.encoded_size());
+        state.encode(&mut buf);
+        assert_eq!(buf.len(), state.encoded_size());
+        assert_eq!(DeliveryState::decode(&buf)?.1, state);
+
+        let state = DeliveryState::Declared(Declared {
+            txn_id: Bytes::from_static(b"txn1"),
+        });
+        buf.clear();
+        state.encode(&mut buf);
+        assert_eq!(DeliveryState::decode(&buf)?.1, state);
+        Ok(())
+    }
 }
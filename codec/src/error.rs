@@ -61,3 +61,13 @@ impl From<()> for Error {
         }
     }
 }
+
+impl From<AmqpParseError> for Error {
+    fn from(err: AmqpParseError) -> Error {
+        Error {
+            condition: AmqpError::DecodeError.into(),
+            description: Some(format!("{}", err).into()),
+            info: None,
+        }
+    }
+}
@@ -1,7 +1,9 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::{cell::Cell, marker::PhantomData};
 
 use byteorder::{BigEndian, ByteOrder};
-use ntex_bytes::{Buf, BufMut, BytesMut};
+use ntex_bytes::{Buf, BufMut, Bytes, BytesMut};
 use ntex_codec::{Decoder, Encoder};
 
 use super::error::{AmqpCodecError, ProtocolIdError};
@@ -16,6 +18,8 @@ const SIZE_HIGH_WM: usize = 32768;
 pub struct AmqpCodec<T: Decode + Encode> {
     state: Cell<DecodeState>,
     max_size: usize,
+    compact: bool,
+    metrics: Option<CodecMetrics>,
     phantom: PhantomData<T>,
 }
 
@@ -36,6 +40,8 @@ impl<T: Decode + Encode> AmqpCodec<T> {
         AmqpCodec {
             state: Cell::new(DecodeState::FrameHeader),
             max_size: 0,
+            compact: false,
+            metrics: None,
             phantom: PhantomData,
         }
     }
@@ -56,6 +62,27 @@ impl<T: Decode + Encode> AmqpCodec<T> {
     pub fn set_max_size(&mut self, size: usize) {
         self.max_size = size;
     }
+
+    /// Prefer compact encodings for outbound frames, see `Encode::encoded_size_with()`
+    ///
+    /// By default compact encoding is disabled
+    pub fn compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
+    /// Record decode errors to metrics.
+    pub fn metrics(mut self, metrics: CodecMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    fn record(&self, err: AmqpCodecError, frame: &[u8]) -> AmqpCodecError {
+        if let Some(ref metrics) = self.metrics {
+            metrics.record(&err, frame);
+        }
+        err
+    }
 }
 
 impl<T: Decode + Encode> Decoder for AmqpCodec<T> {
@@ -74,7 +101,7 @@ impl<T: Decode + Encode> Decoder for AmqpCodec<T> {
                     // read frame size
                     let size = BigEndian::read_u32(src.as_ref()) as usize;
                     if self.max_size != 0 && size > self.max_size {
-                        return Err(AmqpCodecError::MaxSizeExceeded);
+                        return Err(self.record(AmqpCodecError::MaxSizeExceeded, &src[..len]));
                     }
                     self.state.set(DecodeState::Frame(size - 4));
                     src.advance(4);
@@ -93,10 +120,11 @@ impl<T: Decode + Encode> Decoder for AmqpCodec<T> {
                     }
 
                     let frame_buf = src.split_to(size);
-                    let (remainder, frame) = T::decode(frame_buf.as_ref())?;
+                    let (remainder, frame) = T::decode(frame_buf.as_ref())
+                        .map_err(|err| self.record(err.into(), &frame_buf))?;
                     if !remainder.is_empty() {
                         // todo: could it really happen?
-                        return Err(AmqpCodecError::UnparsedBytesLeft);
+                        return Err(self.record(AmqpCodecError::UnparsedBytesLeft, &frame_buf));
                     }
                     self.state.set(DecodeState::FrameHeader);
                     return Ok(Some(frame));
@@ -111,20 +139,103 @@ impl<T: Decode + Encode + ::std::fmt::Debug> Encoder for AmqpCodec<T> {
     type Error = AmqpCodecError;
 
     fn encode(&self, item: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        let size = item.encoded_size();
+        let size = item.encoded_size_with(self.compact);
         let need = std::cmp::max(SIZE_LOW_WM, size);
         if dst.remaining_mut() < need {
             dst.reserve(std::cmp::max(need, SIZE_HIGH_WM));
         }
 
         let len = dst.len();
-        item.encode(dst);
+        item.encode_with(dst, self.compact);
         debug_assert!(dst.len() - len == size);
 
         Ok(())
     }
 }
 
+/// Codec error metrics
+///
+/// Counts malformed frames and captures bytes of the first offending
+/// frame, up to capture limit. Metrics are shared between clones,
+/// metrics could be sent to other threads.
+#[derive(Clone, Debug, Default)]
+pub struct CodecMetrics(Arc<MetricsInner>);
+
+#[derive(Debug, Default)]
+struct MetricsInner {
+    capture: usize,
+    parse_errors: AtomicUsize,
+    oversized: AtomicUsize,
+    unparsed: AtomicUsize,
+    errors: Mutex<MetricsErrors>,
+}
+
+#[derive(Debug, Default)]
+struct MetricsErrors {
+    last_error: Option<AmqpCodecError>,
+    first_malformed: Option<Bytes>,
+}
+
+impl CodecMetrics {
+    /// Create metrics, capture up to `capture` bytes of malformed frame
+    pub fn new(capture: usize) -> Self {
+        CodecMetrics(Arc::new(MetricsInner {
+            capture,
+            ..Default::default()
+        }))
+    }
+
+    /// Total number of codec errors
+    pub fn errors(&self) -> usize {
+        self.parse_errors() + self.oversized_frames() + self.unparsed_frames()
+    }
+
+    /// Number of frames that could not be decoded
+    pub fn parse_errors(&self) -> usize {
+        self.0.parse_errors.load(Ordering::Relaxed)
+    }
+
+    /// Number of frames that exceed max frame size
+    pub fn oversized_frames(&self) -> usize {
+        self.0.oversized.load(Ordering::Relaxed)
+    }
+
+    /// Number of frames with unparsed trailing bytes
+    pub fn unparsed_frames(&self) -> usize {
+        self.0.unparsed.load(Ordering::Relaxed)
+    }
+
+    /// Last codec error
+    pub fn last_error(&self) -> Option<AmqpCodecError> {
+        self.captured().last_error.clone()
+    }
+
+    /// Captured bytes of the first malformed frame
+    pub fn first_malformed(&self) -> Option<Bytes> {
+        self.captured().first_malformed.clone()
+    }
+
+    fn captured(&self) -> MutexGuard<'_, MetricsErrors> {
+        self.0.errors.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    pub(crate) fn record(&self, err: &AmqpCodecError, frame: &[u8]) {
+        let counter = match err {
+            AmqpCodecError::ParseError(_) => &self.0.parse_errors,
+            AmqpCodecError::MaxSizeExceeded => &self.0.oversized,
+            AmqpCodecError::UnparsedBytesLeft => &self.0.unparsed,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        let mut errors = self.captured();
+        if errors.first_malformed.is_none() && self.0.capture > 0 {
+            let len = std::cmp::min(frame.len(), self.0.capture);
+            errors.first_malformed = Some(Bytes::copy_from_slice(&frame[..len]));
+        }
+        errors.last_error = Some(err.clone());
+    }
+}
+
 const PROTOCOL_HEADER_LEN: usize = 8;
 const PROTOCOL_HEADER_PREFIX: &[u8] = b"AMQP";
 const PROTOCOL_VERSION: &[u8] = &[1, 0, 0];
@@ -170,3 +281,46 @@ impl Encoder for ProtocolIdCodec {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AmqpFrame;
+
+    #[test]
+    fn test_codec_is_send() {
+        fn is_send<T: Send>() {}
+        is_send::<AmqpCodec<AmqpFrame>>();
+        is_send::<CodecMetrics>();
+    }
+
+    #[test]
+    fn test_metrics() {
+        let metrics = CodecMetrics::new(4);
+        let codec = AmqpCodec::<AmqpFrame>::new().metrics(metrics.clone());
+
+        let mut buf = BytesMut::from(&[0, 0, 0, 12, 2, 0, 0, 0, 0xff, 0xff, 0xff, 0xff][..]);
+        assert!(codec.decode(&mut buf).is_err());
+        assert_eq!(metrics.errors(), 1);
+        assert_eq!(metrics.parse_errors(), 1);
+        assert_eq!(
+            metrics.first_malformed(),
+            Some(Bytes::from_static(&[2, 0, 0, 0]))
+        );
+
+        let codec = AmqpCodec::<AmqpFrame>::new()
+            .max_size(8)
+            .metrics(metrics.clone());
+        let mut buf = BytesMut::from(&[0, 0, 0, 64, 2, 0, 0, 0][..]);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(AmqpCodecError::MaxSizeExceeded)
+        ));
+        assert_eq!(metrics.errors(), 2);
+        assert_eq!(metrics.oversized_frames(), 1);
+        assert_eq!(
+            metrics.first_malformed(),
+            Some(Bytes::from_static(&[2, 0, 0, 0]))
+        );
+    }
+}
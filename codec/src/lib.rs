@@ -5,18 +5,22 @@ extern crate derive_more;
 
 #[macro_use]
 mod codec;
+#[cfg(any(test, feature = "testing"))]
+pub mod arbitrary;
 mod error;
 mod framing;
 mod io;
 mod message;
+mod pretty;
 pub mod protocol;
 pub mod types;
 
 pub use self::codec::{Decode, Encode};
 pub use self::error::{AmqpCodecError, AmqpParseError, ProtocolIdError};
 pub use self::framing::{AmqpFrame, SaslFrame};
-pub use self::io::{AmqpCodec, ProtocolIdCodec};
+pub use self::io::{AmqpCodec, CodecMetrics, ProtocolIdCodec};
 pub use self::message::{Message, MessageBody};
+pub use self::pretty::{pretty, Pretty, PrettyFmt};
 
 /// A `HashMap` using a ahash::RandomState hasher.
 type HashMap<K, V> = std::collections::HashMap<K, V, ahash::RandomState>;
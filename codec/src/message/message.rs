@@ -52,6 +52,39 @@ impl Message {
         self
     }
 
+    /// Number of unsuccessful previous delivery attempts
+    pub fn delivery_count(&self) -> u32 {
+        self.header.as_ref().map_or(0, |h| h.delivery_count)
+    }
+
+    /// Check if message has not been acquired by any other link
+    pub fn first_acquirer(&self) -> bool {
+        self.header
+            .as_ref()
+            .map(|h| h.first_acquirer)
+            .unwrap_or(false)
+    }
+
+    /// Prepare message for redelivery
+    ///
+    /// Clears `first-acquirer` flag, `delivery-count` is incremented
+    /// only if previous delivery attempt has failed.
+    pub fn redelivered(&mut self, delivery_failed: bool) -> &mut Self {
+        let header = self.header.get_or_insert(Header {
+            durable: false,
+            priority: 4,
+            ttl: None,
+            first_acquirer: false,
+            delivery_count: 0,
+        });
+        header.first_acquirer = false;
+        if delivery_failed {
+            header.delivery_count = header.delivery_count.saturating_add(1);
+        }
+        self.size.set(0);
+        self
+    }
+
     /// Message properties
     pub fn properties(&self) -> Option<&Properties> {
         self.properties.as_ref()
@@ -93,7 +126,7 @@ impl Message {
         if let Some(ref props) = self.application_properties {
             props
                 .iter()
-                .find_map(|item| if &item.0 == key { Some(&item.1) } else { None })
+                .find_map(|item| if item.0 == key { Some(&item.1) } else { None })
         } else {
             None
         }
@@ -121,7 +154,7 @@ impl Message {
         if let Some(ref props) = self.message_annotations {
             props
                 .iter()
-                .find_map(|item| if &item.0 == key { Some(&item.1) } else { None })
+                .find_map(|item| if item.0 == key { Some(&item.1) } else { None })
         } else {
             None
         }
@@ -151,9 +184,38 @@ impl Message {
 
     /// Mut reference to delivery annotations
     pub fn delivery_annotations_mut(&mut self) -> Option<&mut VecSymbolMap> {
+        self.size.set(0);
         self.delivery_annotations.as_mut()
     }
 
+    /// Get delivery annotation
+    pub fn delivery_annotation(&self, key: &str) -> Option<&Variant> {
+        if let Some(ref props) = self.delivery_annotations {
+            props
+                .iter()
+                .find_map(|item| if item.0 == key { Some(&item.1) } else { None })
+        } else {
+            None
+        }
+    }
+
+    /// Add delivery annotation
+    pub fn add_delivery_annotation<K, V>(&mut self, key: K, value: V) -> &mut Self
+    where
+        K: Into<Symbol>,
+        V: Into<Variant>,
+    {
+        if let Some(ref mut props) = self.delivery_annotations {
+            props.push((key.into(), value.into()));
+        } else {
+            let mut props = VecSymbolMap::default();
+            props.push((key.into(), value.into()));
+            self.delivery_annotations = Some(props);
+        }
+        self.size.set(0);
+        self
+    }
+
     /// Call closure with message reference
     pub fn update<F>(self, f: F) -> Self
     where
@@ -360,6 +422,23 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_delivery_annotations() -> Result<(), AmqpCodecError> {
+        let mut msg = Message::with_body(Bytes::from_static(b"data"));
+        msg.add_message_annotation("msg", 1);
+        msg.add_delivery_annotation("route", 2);
+
+        let mut buf = BytesMut::with_capacity(msg.encoded_size());
+        msg.encode(&mut buf);
+        assert_eq!(buf.len(), msg.encoded_size());
+
+        let msg2 = Message::decode(&buf)?.1;
+        assert_eq!(msg2.delivery_annotation("route"), Some(&Variant::from(2)));
+        assert_eq!(msg2.delivery_annotation("msg"), None);
+        assert_eq!(msg2.message_annotation("msg"), Some(&Variant::from(1)));
+        Ok(())
+    }
+
     #[test]
     fn test_header() -> Result<(), AmqpCodecError> {
         let hdr = Header {
@@ -380,6 +459,33 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_redelivered() -> Result<(), AmqpCodecError> {
+        let mut msg = Message::with_body(Bytes::from_static(b"data"));
+        assert_eq!(msg.delivery_count(), 0);
+        assert!(!msg.first_acquirer());
+
+        let mut buf = BytesMut::with_capacity(msg.encoded_size());
+        msg.encode(&mut buf);
+
+        msg.redelivered(false);
+        assert_eq!(msg.delivery_count(), 0);
+        msg.header.as_mut().unwrap().first_acquirer = true;
+        msg.redelivered(true);
+        assert_eq!(msg.delivery_count(), 1);
+        assert!(!msg.first_acquirer());
+
+        let mut buf2 = BytesMut::with_capacity(msg.encoded_size());
+        msg.encode(&mut buf2);
+        assert_eq!(buf2.len(), msg.encoded_size());
+        assert!(buf2.len() > buf.len());
+
+        let msg2 = Message::decode(&buf2)?.1;
+        assert_eq!(msg2.delivery_count(), 1);
+        assert!(!msg2.first_acquirer());
+        Ok(())
+    }
+
     #[test]
     fn test_data() -> Result<(), AmqpCodecError> {
         let data = Bytes::from_static(b"test data");
@@ -0,0 +1,506 @@
+//! Human-readable formatting for performatives and amqp values
+use std::{fmt, hash::BuildHasher};
+
+use chrono::{DateTime, Utc};
+use ntex_bytes::{ByteString, Bytes};
+use uuid::Uuid;
+
+use crate::framing::AmqpFrame;
+use crate::protocol::*;
+use crate::types::{Descriptor, List, Multiple, StaticSymbol, Str, Symbol, Variant};
+
+/// Max number of binary bytes printed
+const MAX_BINARY: usize = 32;
+
+/// Types that could be formatted in human-readable form
+pub trait Pretty {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+
+    /// Empty values are omitted from performatives
+    fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+/// Display adapter for `Pretty` types
+pub struct PrettyFmt<'a, T: ?Sized>(&'a T);
+
+/// Format value in human-readable form
+///
+/// ```rust
+/// use ntex_amqp_codec::{pretty, types::Variant};
+///
+/// assert_eq!(pretty(&Variant::from("test")).to_string(), "\"test\"");
+/// ```
+pub fn pretty<T: Pretty + ?Sized>(value: &T) -> PrettyFmt<'_, T> {
+    PrettyFmt(value)
+}
+
+impl<'a, T: Pretty + ?Sized> fmt::Display for PrettyFmt<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_pretty(f)
+    }
+}
+
+impl<'a, T: Pretty + ?Sized> fmt::Debug for PrettyFmt<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_pretty(f)
+    }
+}
+
+macro_rules! pretty_display {
+    ($($t:ty),*) => {
+        $(
+            impl Pretty for $t {
+                fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    fmt::Display::fmt(self, f)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! pretty_debug {
+    ($($t:ty),*) => {
+        $(
+            impl Pretty for $t {
+                fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    fmt::Debug::fmt(self, f)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! pretty_struct {
+    ($($t:ident { $($field:ident),* }),* $(,)?) => {
+        $(
+            impl Pretty for $t {
+                #[allow(unused_mut)]
+                fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str(stringify!($t))?;
+                    let mut first = true;
+                    $(
+                        if !Pretty::is_empty(&self.$field) {
+                            f.write_str(if first { " { " } else { ", " })?;
+                            first = false;
+                            write!(f, "{}: {}", stringify!($field), pretty(&self.$field))?;
+                        }
+                    )*
+                    if first {
+                        Ok(())
+                    } else {
+                        f.write_str(" }")
+                    }
+                }
+            }
+        )*
+    };
+}
+
+pretty_display!(
+    bool,
+    u8,
+    u16,
+    u32,
+    u64,
+    i8,
+    i16,
+    i32,
+    i64,
+    char,
+    Uuid,
+    DateTime<Utc>
+);
+pretty_debug!(
+    Role,
+    SenderSettleMode,
+    ReceiverSettleMode,
+    TerminusDurability,
+    TerminusExpiryPolicy,
+    ErrorCondition
+);
+
+pretty_struct!(
+    Open {
+        container_id,
+        hostname,
+        max_frame_size,
+        channel_max,
+        idle_time_out,
+        outgoing_locales,
+        incoming_locales,
+        offered_capabilities,
+        desired_capabilities,
+        properties
+    },
+    Begin {
+        remote_channel,
+        next_outgoing_id,
+        incoming_window,
+        outgoing_window,
+        handle_max,
+        offered_capabilities,
+        desired_capabilities,
+        properties
+    },
+    Attach {
+        name,
+        handle,
+        role,
+        snd_settle_mode,
+        rcv_settle_mode,
+        source,
+        target,
+        unsettled,
+        incomplete_unsettled,
+        initial_delivery_count,
+        max_message_size,
+        offered_capabilities,
+        desired_capabilities,
+        properties
+    },
+    Flow {
+        next_incoming_id,
+        incoming_window,
+        next_outgoing_id,
+        outgoing_window,
+        handle,
+        delivery_count,
+        link_credit,
+        available,
+        drain,
+        echo,
+        properties
+    },
+    Transfer {
+        handle,
+        delivery_id,
+        delivery_tag,
+        message_format,
+        settled,
+        more,
+        rcv_settle_mode,
+        state,
+        resume,
+        aborted,
+        batchable,
+        body
+    },
+    Disposition {
+        role,
+        first,
+        last,
+        settled,
+        state,
+        batchable
+    },
+    Detach {
+        handle,
+        closed,
+        error
+    },
+    End { error },
+    Close { error },
+    Source {
+        address,
+        durable,
+        expiry_policy,
+        timeout,
+        dynamic,
+        dynamic_node_properties,
+        distribution_mode,
+        filter,
+        default_outcome,
+        outcomes,
+        capabilities
+    },
+    Target {
+        address,
+        durable,
+        expiry_policy,
+        timeout,
+        dynamic,
+        dynamic_node_properties,
+        capabilities
+    },
+    Error {
+        condition,
+        description,
+        info
+    },
+    Received {
+        section_number,
+        section_offset
+    },
+    Accepted {},
+    Rejected { error },
+    Released {},
+    Modified {
+        delivery_failed,
+        undeliverable_here,
+        message_annotations
+    },
+    Declared { txn_id },
+    TransactionalState { txn_id, outcome },
+);
+
+impl<T: Pretty> Pretty for Option<T> {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Some(v) => v.fmt_pretty(f),
+            None => f.write_str("null"),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_none()
+    }
+}
+
+impl<T: Pretty + ?Sized> Pretty for Box<T> {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt_pretty(f)
+    }
+}
+
+impl<T: Pretty> Pretty for Multiple<T> {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_seq(f, self.iter())
+    }
+}
+
+impl Pretty for List {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_seq(f, self.0.iter())
+    }
+}
+
+impl<K: Pretty, V: Pretty, S: BuildHasher> Pretty for std::collections::HashMap<K, V, S> {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("{")?;
+        for (idx, (k, v)) in self.iter().enumerate() {
+            if idx != 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}: {}", pretty(k), pretty(v))?;
+        }
+        f.write_str("}")
+    }
+}
+
+impl Pretty for ByteString {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", &**self)
+    }
+}
+
+impl Pretty for Str {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl Pretty for Symbol {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, ":{}", self.as_str())
+    }
+}
+
+impl Pretty for StaticSymbol {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, ":{}", self.0)
+    }
+}
+
+impl Pretty for Bytes {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("0x")?;
+        for b in self.iter().take(MAX_BINARY) {
+            write!(f, "{:02x}", b)?;
+        }
+        if self.len() > MAX_BINARY {
+            write!(f, "...({} bytes)", self.len())?;
+        }
+        Ok(())
+    }
+}
+
+impl Pretty for Descriptor {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Descriptor::Ulong(code) => write!(f, "0x{:016x}", code),
+            Descriptor::Symbol(sym) => sym.fmt_pretty(f),
+        }
+    }
+}
+
+impl Pretty for Variant {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Variant::Null => f.write_str("null"),
+            Variant::Boolean(v) => v.fmt_pretty(f),
+            Variant::Ubyte(v) => write!(f, "{}u8", v),
+            Variant::Ushort(v) => write!(f, "{}u16", v),
+            Variant::Uint(v) => write!(f, "{}u32", v),
+            Variant::Ulong(v) => write!(f, "{}u64", v),
+            Variant::Byte(v) => write!(f, "{}i8", v),
+            Variant::Short(v) => write!(f, "{}i16", v),
+            Variant::Int(v) => v.fmt_pretty(f),
+            Variant::Long(v) => write!(f, "{}i64", v),
+            Variant::Float(v) => write!(f, "{}f32", v),
+            Variant::Double(v) => write!(f, "{}f64", v),
+            Variant::Char(v) => write!(f, "{:?}", v),
+            Variant::Timestamp(v) => v.fmt_pretty(f),
+            Variant::Uuid(v) => v.fmt_pretty(f),
+            Variant::Binary(v) => v.fmt_pretty(f),
+            Variant::String(v) => v.fmt_pretty(f),
+            Variant::Symbol(v) => v.fmt_pretty(f),
+            Variant::StaticSymbol(v) => v.fmt_pretty(f),
+            Variant::List(v) => v.fmt_pretty(f),
+            Variant::Map(v) => v.map.fmt_pretty(f),
+            Variant::Described((descriptor, v)) => {
+                write!(f, "{}({})", pretty(descriptor), pretty(v))
+            }
+        }
+    }
+}
+
+impl Pretty for DistributionMode {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DistributionMode::Move => f.write_str("move"),
+            DistributionMode::Copy => f.write_str("copy"),
+            DistributionMode::Custom(sym) => sym.fmt_pretty(f),
+        }
+    }
+}
+
+impl Pretty for DeliveryState {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeliveryState::Received(v) => v.fmt_pretty(f),
+            DeliveryState::Accepted(v) => v.fmt_pretty(f),
+            DeliveryState::Rejected(v) => v.fmt_pretty(f),
+            DeliveryState::Released(v) => v.fmt_pretty(f),
+            DeliveryState::Modified(v) => v.fmt_pretty(f),
+            DeliveryState::Declared(v) => v.fmt_pretty(f),
+            DeliveryState::TransactionalState(v) => v.fmt_pretty(f),
+        }
+    }
+}
+
+impl Pretty for Outcome {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Accepted(v) => v.fmt_pretty(f),
+            Outcome::Rejected(v) => v.fmt_pretty(f),
+            Outcome::Released(v) => v.fmt_pretty(f),
+            Outcome::Modified(v) => v.fmt_pretty(f),
+            Outcome::Declared(v) => v.fmt_pretty(f),
+        }
+    }
+}
+
+impl Pretty for TransferBody {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferBody::Data(data) => write!(f, "Data({} bytes)", data.len()),
+            TransferBody::Message(msg) => write!(f, "Message({:?})", msg),
+        }
+    }
+}
+
+impl Pretty for Frame {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Frame::Open(frm) => frm.fmt_pretty(f),
+            Frame::Begin(frm) => frm.fmt_pretty(f),
+            Frame::Attach(frm) => frm.fmt_pretty(f),
+            Frame::Flow(frm) => frm.fmt_pretty(f),
+            Frame::Transfer(frm) => frm.fmt_pretty(f),
+            Frame::Disposition(frm) => frm.fmt_pretty(f),
+            Frame::Detach(frm) => frm.fmt_pretty(f),
+            Frame::End(frm) => frm.fmt_pretty(f),
+            Frame::Close(frm) => frm.fmt_pretty(f),
+            Frame::Unknown(frm) => write!(f, "{:?}", frm),
+            Frame::Empty => f.write_str("Empty"),
+        }
+    }
+}
+
+impl Pretty for AmqpFrame {
+    fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.channel_id(), pretty(self.performative()))
+    }
+}
+
+fn fmt_seq<'a, T, I>(f: &mut fmt::Formatter<'_>, iter: I) -> fmt::Result
+where
+    T: Pretty + 'a,
+    I: Iterator<Item = &'a T>,
+{
+    f.write_str("[")?;
+    for (idx, item) in iter.enumerate() {
+        if idx != 0 {
+            f.write_str(", ")?;
+        }
+        item.fmt_pretty(f)?;
+    }
+    f.write_str("]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variant() {
+        let val = Variant::List(List(vec![
+            Variant::Null,
+            Variant::Ubyte(1),
+            Variant::Symbol(Symbol::from_static("sym")),
+            Variant::Binary(Bytes::from_static(b"\x01\x02")),
+            Variant::Described((
+                Descriptor::Ulong(0x70),
+                Box::new(Variant::String(Str::from_static("str"))),
+            )),
+        ]));
+        assert_eq!(
+            pretty(&val).to_string(),
+            "[null, 1u8, :sym, 0x0102, 0x0000000000000070(\"str\")]"
+        );
+
+        let data = Bytes::from(vec![0u8; MAX_BINARY + 1]);
+        assert!(pretty(&data).to_string().ends_with("...(33 bytes)"));
+    }
+
+    #[test]
+    fn test_frame() {
+        let frame = AmqpFrame::new(
+            1,
+            Frame::Detach(Detach {
+                handle: 2,
+                closed: true,
+                error: None,
+            }),
+        );
+        assert_eq!(
+            pretty(&frame).to_string(),
+            "[1] Detach { handle: 2, closed: true }"
+        );
+
+        let frame = Frame::Disposition(Disposition {
+            role: Role::Receiver,
+            first: 1,
+            last: Some(5),
+            settled: true,
+            state: Some(DeliveryState::Accepted(Accepted {})),
+            batchable: false,
+        });
+        assert_eq!(
+            pretty(&frame).to_string(),
+            "Disposition { role: Receiver, first: 1, last: 5, settled: true, state: Accepted, batchable: false }"
+        );
+    }
+}
@@ -17,6 +17,7 @@ pub enum Frame {
     Detach(Detach),
     End(End),
     Close(Close),
+    Unknown(Box<UnknownFrame>),
     Empty,
 }
 impl Frame {
@@ -31,6 +32,7 @@ impl Frame {
             Frame::Detach(_) => "Detach",
             Frame::End(_) => "End",
             Frame::Close(_) => "Close",
+            Frame::Unknown(_) => "Unknown",
             Frame::Empty => "Empty",
         }
     }
@@ -92,42 +94,124 @@ impl Decode for Frame {
                 Descriptor::Symbol(ref a) if a.as_str() == "amqp:close:list" => {
                     decode_close_inner(input).map(|(i, r)| (i, Frame::Close(r)))
                 }
-                _ => Err(AmqpParseError::InvalidDescriptor(descriptor)),
+                _ => UnknownFrame::decode_inner(descriptor, input)
+                    .map(|(i, r)| (i, Frame::Unknown(Box::new(r)))),
             }
         }
     }
 }
 impl Encode for Frame {
     fn encoded_size(&self) -> usize {
+        self.encoded_size_with(false)
+    }
+    fn encode(&self, buf: &mut BytesMut) {
+        self.encode_with(buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
         match *self {
-            Frame::Open(ref v) => encoded_size_open_inner(v),
-            Frame::Begin(ref v) => encoded_size_begin_inner(v),
-            Frame::Attach(ref v) => encoded_size_attach_inner(v),
-            Frame::Flow(ref v) => encoded_size_flow_inner(v),
-            Frame::Transfer(ref v) => encoded_size_transfer_inner(v),
-            Frame::Disposition(ref v) => encoded_size_disposition_inner(v),
-            Frame::Detach(ref v) => encoded_size_detach_inner(v),
-            Frame::End(ref v) => encoded_size_end_inner(v),
-            Frame::Close(ref v) => encoded_size_close_inner(v),
+            Frame::Open(ref v) => encoded_size_open_inner(v, compact),
+            Frame::Begin(ref v) => encoded_size_begin_inner(v, compact),
+            Frame::Attach(ref v) => encoded_size_attach_inner(v, compact),
+            Frame::Flow(ref v) => encoded_size_flow_inner(v, compact),
+            Frame::Transfer(ref v) => encoded_size_transfer_inner(v, compact),
+            Frame::Disposition(ref v) => encoded_size_disposition_inner(v, compact),
+            Frame::Detach(ref v) => encoded_size_detach_inner(v, compact),
+            Frame::End(ref v) => encoded_size_end_inner(v, compact),
+            Frame::Close(ref v) => encoded_size_close_inner(v, compact),
+            Frame::Unknown(ref v) => v.encoded_size(),
             Frame::Empty => 0,
         }
     }
-    fn encode(&self, buf: &mut BytesMut) {
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
         match *self {
-            Frame::Open(ref v) => encode_open_inner(v, buf),
-            Frame::Begin(ref v) => encode_begin_inner(v, buf),
-            Frame::Attach(ref v) => encode_attach_inner(v, buf),
-            Frame::Flow(ref v) => encode_flow_inner(v, buf),
-            Frame::Transfer(ref v) => encode_transfer_inner(v, buf),
-            Frame::Disposition(ref v) => encode_disposition_inner(v, buf),
-            Frame::Detach(ref v) => encode_detach_inner(v, buf),
-            Frame::End(ref v) => encode_end_inner(v, buf),
-            Frame::Close(ref v) => encode_close_inner(v, buf),
+            Frame::Open(ref v) => encode_open_inner(v, buf, compact),
+            Frame::Begin(ref v) => encode_begin_inner(v, buf, compact),
+            Frame::Attach(ref v) => encode_attach_inner(v, buf, compact),
+            Frame::Flow(ref v) => encode_flow_inner(v, buf, compact),
+            Frame::Transfer(ref v) => encode_transfer_inner(v, buf, compact),
+            Frame::Disposition(ref v) => encode_disposition_inner(v, buf, compact),
+            Frame::Detach(ref v) => encode_detach_inner(v, buf, compact),
+            Frame::End(ref v) => encode_end_inner(v, buf, compact),
+            Frame::Close(ref v) => encode_close_inner(v, buf, compact),
+            Frame::Unknown(ref v) => v.encode(buf),
             Frame::Empty => (),
         }
     }
 }
 #[derive(Clone, Debug, PartialEq)]
+pub enum SaslFrameBody {
+    SaslMechanisms(SaslMechanisms),
+    SaslInit(SaslInit),
+    SaslChallenge(SaslChallenge),
+    SaslResponse(SaslResponse),
+    SaslOutcome(SaslOutcome),
+}
+impl DecodeFormatted for SaslFrameBody {
+    fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
+        validate_code!(fmt, codec::FORMATCODE_DESCRIBED);
+        let (input, descriptor) = Descriptor::decode(input)?;
+        match descriptor {
+            Descriptor::Ulong(64) => decode_sasl_mechanisms_inner(input)
+                .map(|(i, r)| (i, SaslFrameBody::SaslMechanisms(r))),
+            Descriptor::Ulong(65) => {
+                decode_sasl_init_inner(input).map(|(i, r)| (i, SaslFrameBody::SaslInit(r)))
+            }
+            Descriptor::Ulong(66) => decode_sasl_challenge_inner(input)
+                .map(|(i, r)| (i, SaslFrameBody::SaslChallenge(r))),
+            Descriptor::Ulong(67) => {
+                decode_sasl_response_inner(input).map(|(i, r)| (i, SaslFrameBody::SaslResponse(r)))
+            }
+            Descriptor::Ulong(68) => {
+                decode_sasl_outcome_inner(input).map(|(i, r)| (i, SaslFrameBody::SaslOutcome(r)))
+            }
+            Descriptor::Symbol(ref a) if a.as_str() == "amqp:sasl-mechanisms:list" => {
+                decode_sasl_mechanisms_inner(input)
+                    .map(|(i, r)| (i, SaslFrameBody::SaslMechanisms(r)))
+            }
+            Descriptor::Symbol(ref a) if a.as_str() == "amqp:sasl-init:list" => {
+                decode_sasl_init_inner(input).map(|(i, r)| (i, SaslFrameBody::SaslInit(r)))
+            }
+            Descriptor::Symbol(ref a) if a.as_str() == "amqp:sasl-challenge:list" => {
+                decode_sasl_challenge_inner(input)
+                    .map(|(i, r)| (i, SaslFrameBody::SaslChallenge(r)))
+            }
+            Descriptor::Symbol(ref a) if a.as_str() == "amqp:sasl-response:list" => {
+                decode_sasl_response_inner(input).map(|(i, r)| (i, SaslFrameBody::SaslResponse(r)))
+            }
+            Descriptor::Symbol(ref a) if a.as_str() == "amqp:sasl-outcome:list" => {
+                decode_sasl_outcome_inner(input).map(|(i, r)| (i, SaslFrameBody::SaslOutcome(r)))
+            }
+            _ => Err(AmqpParseError::InvalidDescriptor(descriptor)),
+        }
+    }
+}
+impl Encode for SaslFrameBody {
+    fn encoded_size(&self) -> usize {
+        self.encoded_size_with(false)
+    }
+    fn encode(&self, buf: &mut BytesMut) {
+        self.encode_with(buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        match *self {
+            SaslFrameBody::SaslMechanisms(ref v) => encoded_size_sasl_mechanisms_inner(v, compact),
+            SaslFrameBody::SaslInit(ref v) => encoded_size_sasl_init_inner(v, compact),
+            SaslFrameBody::SaslChallenge(ref v) => encoded_size_sasl_challenge_inner(v, compact),
+            SaslFrameBody::SaslResponse(ref v) => encoded_size_sasl_response_inner(v, compact),
+            SaslFrameBody::SaslOutcome(ref v) => encoded_size_sasl_outcome_inner(v, compact),
+        }
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        match *self {
+            SaslFrameBody::SaslMechanisms(ref v) => encode_sasl_mechanisms_inner(v, buf, compact),
+            SaslFrameBody::SaslInit(ref v) => encode_sasl_init_inner(v, buf, compact),
+            SaslFrameBody::SaslChallenge(ref v) => encode_sasl_challenge_inner(v, buf, compact),
+            SaslFrameBody::SaslResponse(ref v) => encode_sasl_response_inner(v, buf, compact),
+            SaslFrameBody::SaslOutcome(ref v) => encode_sasl_outcome_inner(v, buf, compact),
+        }
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
 pub enum Section {
     Header(Header),
     DeliveryAnnotations(DeliveryAnnotations),
@@ -202,29 +286,45 @@ impl DecodeFormatted for Section {
 }
 impl Encode for Section {
     fn encoded_size(&self) -> usize {
+        self.encoded_size_with(false)
+    }
+    fn encode(&self, buf: &mut BytesMut) {
+        self.encode_with(buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
         match *self {
-            Section::Header(ref v) => encoded_size_header_inner(v),
-            Section::DeliveryAnnotations(ref v) => encoded_size_delivery_annotations_inner(v),
-            Section::MessageAnnotations(ref v) => encoded_size_message_annotations_inner(v),
-            Section::ApplicationProperties(ref v) => encoded_size_application_properties_inner(v),
-            Section::Data(ref v) => encoded_size_data_inner(v),
-            Section::AmqpSequence(ref v) => encoded_size_amqp_sequence_inner(v),
-            Section::AmqpValue(ref v) => encoded_size_amqp_value_inner(v),
-            Section::Footer(ref v) => encoded_size_footer_inner(v),
-            Section::Properties(ref v) => encoded_size_properties_inner(v),
+            Section::Header(ref v) => encoded_size_header_inner(v, compact),
+            Section::DeliveryAnnotations(ref v) => {
+                encoded_size_delivery_annotations_inner(v, compact)
+            }
+            Section::MessageAnnotations(ref v) => {
+                encoded_size_message_annotations_inner(v, compact)
+            }
+            Section::ApplicationProperties(ref v) => {
+                encoded_size_application_properties_inner(v, compact)
+            }
+            Section::Data(ref v) => encoded_size_data_inner(v, compact),
+            Section::AmqpSequence(ref v) => encoded_size_amqp_sequence_inner(v, compact),
+            Section::AmqpValue(ref v) => encoded_size_amqp_value_inner(v, compact),
+            Section::Footer(ref v) => encoded_size_footer_inner(v, compact),
+            Section::Properties(ref v) => encoded_size_properties_inner(v, compact),
         }
     }
-    fn encode(&self, buf: &mut BytesMut) {
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
         match *self {
-            Section::Header(ref v) => encode_header_inner(v, buf),
-            Section::DeliveryAnnotations(ref v) => encode_delivery_annotations_inner(v, buf),
-            Section::MessageAnnotations(ref v) => encode_message_annotations_inner(v, buf),
-            Section::ApplicationProperties(ref v) => encode_application_properties_inner(v, buf),
-            Section::Data(ref v) => encode_data_inner(v, buf),
-            Section::AmqpSequence(ref v) => encode_amqp_sequence_inner(v, buf),
-            Section::AmqpValue(ref v) => encode_amqp_value_inner(v, buf),
-            Section::Footer(ref v) => encode_footer_inner(v, buf),
-            Section::Properties(ref v) => encode_properties_inner(v, buf),
+            Section::Header(ref v) => encode_header_inner(v, buf, compact),
+            Section::DeliveryAnnotations(ref v) => {
+                encode_delivery_annotations_inner(v, buf, compact)
+            }
+            Section::MessageAnnotations(ref v) => encode_message_annotations_inner(v, buf, compact),
+            Section::ApplicationProperties(ref v) => {
+                encode_application_properties_inner(v, buf, compact)
+            }
+            Section::Data(ref v) => encode_data_inner(v, buf, compact),
+            Section::AmqpSequence(ref v) => encode_amqp_sequence_inner(v, buf, compact),
+            Section::AmqpValue(ref v) => encode_amqp_value_inner(v, buf, compact),
+            Section::Footer(ref v) => encode_footer_inner(v, buf, compact),
+            Section::Properties(ref v) => encode_properties_inner(v, buf, compact),
         }
     }
 }
@@ -235,6 +335,8 @@ pub enum DeliveryState {
     Rejected(Rejected),
     Released(Released),
     Modified(Modified),
+    Declared(Declared),
+    TransactionalState(TransactionalState),
 }
 impl DecodeFormatted for DeliveryState {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -256,6 +358,11 @@ impl DecodeFormatted for DeliveryState {
             Descriptor::Ulong(39) => {
                 decode_modified_inner(input).map(|(i, r)| (i, DeliveryState::Modified(r)))
             }
+            Descriptor::Ulong(51) => {
+                decode_declared_inner(input).map(|(i, r)| (i, DeliveryState::Declared(r)))
+            }
+            Descriptor::Ulong(52) => decode_transactional_state_inner(input)
+                .map(|(i, r)| (i, DeliveryState::TransactionalState(r))),
             Descriptor::Symbol(ref a) if a.as_str() == "amqp:received:list" => {
                 decode_received_inner(input).map(|(i, r)| (i, DeliveryState::Received(r)))
             }
@@ -271,27 +378,48 @@ impl DecodeFormatted for DeliveryState {
             Descriptor::Symbol(ref a) if a.as_str() == "amqp:modified:list" => {
                 decode_modified_inner(input).map(|(i, r)| (i, DeliveryState::Modified(r)))
             }
+            Descriptor::Symbol(ref a) if a.as_str() == "amqp:declared:list" => {
+                decode_declared_inner(input).map(|(i, r)| (i, DeliveryState::Declared(r)))
+            }
+            Descriptor::Symbol(ref a) if a.as_str() == "amqp:transactional-state:list" => {
+                decode_transactional_state_inner(input)
+                    .map(|(i, r)| (i, DeliveryState::TransactionalState(r)))
+            }
             _ => Err(AmqpParseError::InvalidDescriptor(descriptor)),
         }
     }
 }
 impl Encode for DeliveryState {
     fn encoded_size(&self) -> usize {
+        self.encoded_size_with(false)
+    }
+    fn encode(&self, buf: &mut BytesMut) {
+        self.encode_with(buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
         match *self {
-            DeliveryState::Received(ref v) => encoded_size_received_inner(v),
-            DeliveryState::Accepted(ref v) => encoded_size_accepted_inner(v),
-            DeliveryState::Rejected(ref v) => encoded_size_rejected_inner(v),
-            DeliveryState::Released(ref v) => encoded_size_released_inner(v),
-            DeliveryState::Modified(ref v) => encoded_size_modified_inner(v),
+            DeliveryState::Received(ref v) => encoded_size_received_inner(v, compact),
+            DeliveryState::Accepted(ref v) => encoded_size_accepted_inner(v, compact),
+            DeliveryState::Rejected(ref v) => encoded_size_rejected_inner(v, compact),
+            DeliveryState::Released(ref v) => encoded_size_released_inner(v, compact),
+            DeliveryState::Modified(ref v) => encoded_size_modified_inner(v, compact),
+            DeliveryState::Declared(ref v) => encoded_size_declared_inner(v, compact),
+            DeliveryState::TransactionalState(ref v) => {
+                encoded_size_transactional_state_inner(v, compact)
+            }
         }
     }
-    fn encode(&self, buf: &mut BytesMut) {
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
         match *self {
-            DeliveryState::Received(ref v) => encode_received_inner(v, buf),
-            DeliveryState::Accepted(ref v) => encode_accepted_inner(v, buf),
-            DeliveryState::Rejected(ref v) => encode_rejected_inner(v, buf),
-            DeliveryState::Released(ref v) => encode_released_inner(v, buf),
-            DeliveryState::Modified(ref v) => encode_modified_inner(v, buf),
+            DeliveryState::Received(ref v) => encode_received_inner(v, buf, compact),
+            DeliveryState::Accepted(ref v) => encode_accepted_inner(v, buf, compact),
+            DeliveryState::Rejected(ref v) => encode_rejected_inner(v, buf, compact),
+            DeliveryState::Released(ref v) => encode_released_inner(v, buf, compact),
+            DeliveryState::Modified(ref v) => encode_modified_inner(v, buf, compact),
+            DeliveryState::Declared(ref v) => encode_declared_inner(v, buf, compact),
+            DeliveryState::TransactionalState(ref v) => {
+                encode_transactional_state_inner(v, buf, compact)
+            }
         }
     }
 }
@@ -301,6 +429,7 @@ pub enum Outcome {
     Rejected(Rejected),
     Released(Released),
     Modified(Modified),
+    Declared(Declared),
 }
 impl DecodeFormatted for Outcome {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -319,6 +448,9 @@ impl DecodeFormatted for Outcome {
             Descriptor::Ulong(39) => {
                 decode_modified_inner(input).map(|(i, r)| (i, Outcome::Modified(r)))
             }
+            Descriptor::Ulong(51) => {
+                decode_declared_inner(input).map(|(i, r)| (i, Outcome::Declared(r)))
+            }
             Descriptor::Symbol(ref a) if a.as_str() == "amqp:accepted:list" => {
                 decode_accepted_inner(input).map(|(i, r)| (i, Outcome::Accepted(r)))
             }
@@ -331,92 +463,36 @@ impl DecodeFormatted for Outcome {
             Descriptor::Symbol(ref a) if a.as_str() == "amqp:modified:list" => {
                 decode_modified_inner(input).map(|(i, r)| (i, Outcome::Modified(r)))
             }
+            Descriptor::Symbol(ref a) if a.as_str() == "amqp:declared:list" => {
+                decode_declared_inner(input).map(|(i, r)| (i, Outcome::Declared(r)))
+            }
             _ => Err(AmqpParseError::InvalidDescriptor(descriptor)),
         }
     }
 }
 impl Encode for Outcome {
     fn encoded_size(&self) -> usize {
-        match *self {
-            Outcome::Accepted(ref v) => encoded_size_accepted_inner(v),
-            Outcome::Rejected(ref v) => encoded_size_rejected_inner(v),
-            Outcome::Released(ref v) => encoded_size_released_inner(v),
-            Outcome::Modified(ref v) => encoded_size_modified_inner(v),
-        }
+        self.encoded_size_with(false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        match *self {
-            Outcome::Accepted(ref v) => encode_accepted_inner(v, buf),
-            Outcome::Rejected(ref v) => encode_rejected_inner(v, buf),
-            Outcome::Released(ref v) => encode_released_inner(v, buf),
-            Outcome::Modified(ref v) => encode_modified_inner(v, buf),
-        }
-    }
-}
-#[derive(Clone, Debug, PartialEq)]
-pub enum SaslFrameBody {
-    SaslMechanisms(SaslMechanisms),
-    SaslInit(SaslInit),
-    SaslChallenge(SaslChallenge),
-    SaslResponse(SaslResponse),
-    SaslOutcome(SaslOutcome),
-}
-impl DecodeFormatted for SaslFrameBody {
-    fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
-        validate_code!(fmt, codec::FORMATCODE_DESCRIBED);
-        let (input, descriptor) = Descriptor::decode(input)?;
-        match descriptor {
-            Descriptor::Ulong(64) => decode_sasl_mechanisms_inner(input)
-                .map(|(i, r)| (i, SaslFrameBody::SaslMechanisms(r))),
-            Descriptor::Ulong(65) => {
-                decode_sasl_init_inner(input).map(|(i, r)| (i, SaslFrameBody::SaslInit(r)))
-            }
-            Descriptor::Ulong(66) => decode_sasl_challenge_inner(input)
-                .map(|(i, r)| (i, SaslFrameBody::SaslChallenge(r))),
-            Descriptor::Ulong(67) => {
-                decode_sasl_response_inner(input).map(|(i, r)| (i, SaslFrameBody::SaslResponse(r)))
-            }
-            Descriptor::Ulong(68) => {
-                decode_sasl_outcome_inner(input).map(|(i, r)| (i, SaslFrameBody::SaslOutcome(r)))
-            }
-            Descriptor::Symbol(ref a) if a.as_str() == "amqp:sasl-mechanisms:list" => {
-                decode_sasl_mechanisms_inner(input)
-                    .map(|(i, r)| (i, SaslFrameBody::SaslMechanisms(r)))
-            }
-            Descriptor::Symbol(ref a) if a.as_str() == "amqp:sasl-init:list" => {
-                decode_sasl_init_inner(input).map(|(i, r)| (i, SaslFrameBody::SaslInit(r)))
-            }
-            Descriptor::Symbol(ref a) if a.as_str() == "amqp:sasl-challenge:list" => {
-                decode_sasl_challenge_inner(input)
-                    .map(|(i, r)| (i, SaslFrameBody::SaslChallenge(r)))
-            }
-            Descriptor::Symbol(ref a) if a.as_str() == "amqp:sasl-response:list" => {
-                decode_sasl_response_inner(input).map(|(i, r)| (i, SaslFrameBody::SaslResponse(r)))
-            }
-            Descriptor::Symbol(ref a) if a.as_str() == "amqp:sasl-outcome:list" => {
-                decode_sasl_outcome_inner(input).map(|(i, r)| (i, SaslFrameBody::SaslOutcome(r)))
-            }
-            _ => Err(AmqpParseError::InvalidDescriptor(descriptor)),
-        }
+        self.encode_with(buf, false)
     }
-}
-impl Encode for SaslFrameBody {
-    fn encoded_size(&self) -> usize {
+    fn encoded_size_with(&self, compact: bool) -> usize {
         match *self {
-            SaslFrameBody::SaslMechanisms(ref v) => encoded_size_sasl_mechanisms_inner(v),
-            SaslFrameBody::SaslInit(ref v) => encoded_size_sasl_init_inner(v),
-            SaslFrameBody::SaslChallenge(ref v) => encoded_size_sasl_challenge_inner(v),
-            SaslFrameBody::SaslResponse(ref v) => encoded_size_sasl_response_inner(v),
-            SaslFrameBody::SaslOutcome(ref v) => encoded_size_sasl_outcome_inner(v),
+            Outcome::Accepted(ref v) => encoded_size_accepted_inner(v, compact),
+            Outcome::Rejected(ref v) => encoded_size_rejected_inner(v, compact),
+            Outcome::Released(ref v) => encoded_size_released_inner(v, compact),
+            Outcome::Modified(ref v) => encoded_size_modified_inner(v, compact),
+            Outcome::Declared(ref v) => encoded_size_declared_inner(v, compact),
         }
     }
-    fn encode(&self, buf: &mut BytesMut) {
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
         match *self {
-            SaslFrameBody::SaslMechanisms(ref v) => encode_sasl_mechanisms_inner(v, buf),
-            SaslFrameBody::SaslInit(ref v) => encode_sasl_init_inner(v, buf),
-            SaslFrameBody::SaslChallenge(ref v) => encode_sasl_challenge_inner(v, buf),
-            SaslFrameBody::SaslResponse(ref v) => encode_sasl_response_inner(v, buf),
-            SaslFrameBody::SaslOutcome(ref v) => encode_sasl_outcome_inner(v, buf),
+            Outcome::Accepted(ref v) => encode_accepted_inner(v, buf, compact),
+            Outcome::Rejected(ref v) => encode_rejected_inner(v, buf, compact),
+            Outcome::Released(ref v) => encode_released_inner(v, buf, compact),
+            Outcome::Modified(ref v) => encode_modified_inner(v, buf, compact),
+            Outcome::Declared(ref v) => encode_declared_inner(v, buf, compact),
         }
     }
 }
@@ -979,13 +1055,13 @@ fn decode_delivery_annotations_inner(
 ) -> Result<(&[u8], DeliveryAnnotations), AmqpParseError> {
     DeliveryAnnotations::decode(input)
 }
-fn encoded_size_delivery_annotations_inner(dr: &DeliveryAnnotations) -> usize {
+fn encoded_size_delivery_annotations_inner(dr: &DeliveryAnnotations, compact: bool) -> usize {
     // descriptor size + actual size
-    3 + dr.encoded_size()
+    3 + dr.encoded_size_with(compact)
 }
-fn encode_delivery_annotations_inner(dr: &DeliveryAnnotations, buf: &mut BytesMut) {
+fn encode_delivery_annotations_inner(dr: &DeliveryAnnotations, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(113).encode(buf);
-    dr.encode(buf);
+    dr.encode_with(buf, compact);
 }
 type MessageAnnotations = VecSymbolMap;
 fn decode_message_annotations_inner(
@@ -993,13 +1069,13 @@ fn decode_message_annotations_inner(
 ) -> Result<(&[u8], MessageAnnotations), AmqpParseError> {
     MessageAnnotations::decode(input)
 }
-fn encoded_size_message_annotations_inner(dr: &MessageAnnotations) -> usize {
+fn encoded_size_message_annotations_inner(dr: &MessageAnnotations, compact: bool) -> usize {
     // descriptor size + actual size
-    3 + dr.encoded_size()
+    3 + dr.encoded_size_with(compact)
 }
-fn encode_message_annotations_inner(dr: &MessageAnnotations, buf: &mut BytesMut) {
+fn encode_message_annotations_inner(dr: &MessageAnnotations, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(114).encode(buf);
-    dr.encode(buf);
+    dr.encode_with(buf, compact);
 }
 type ApplicationProperties = VecStringMap;
 fn decode_application_properties_inner(
@@ -1007,61 +1083,65 @@ fn decode_application_properties_inner(
 ) -> Result<(&[u8], ApplicationProperties), AmqpParseError> {
     ApplicationProperties::decode(input)
 }
-fn encoded_size_application_properties_inner(dr: &ApplicationProperties) -> usize {
+fn encoded_size_application_properties_inner(dr: &ApplicationProperties, compact: bool) -> usize {
     // descriptor size + actual size
-    3 + dr.encoded_size()
+    3 + dr.encoded_size_with(compact)
 }
-fn encode_application_properties_inner(dr: &ApplicationProperties, buf: &mut BytesMut) {
+fn encode_application_properties_inner(
+    dr: &ApplicationProperties,
+    buf: &mut BytesMut,
+    compact: bool,
+) {
     Descriptor::Ulong(116).encode(buf);
-    dr.encode(buf);
+    dr.encode_with(buf, compact);
 }
 type Data = Bytes;
 fn decode_data_inner(input: &[u8]) -> Result<(&[u8], Data), AmqpParseError> {
     Data::decode(input)
 }
-fn encoded_size_data_inner(dr: &Data) -> usize {
+fn encoded_size_data_inner(dr: &Data, compact: bool) -> usize {
     // descriptor size + actual size
-    3 + dr.encoded_size()
+    3 + dr.encoded_size_with(compact)
 }
-fn encode_data_inner(dr: &Data, buf: &mut BytesMut) {
+fn encode_data_inner(dr: &Data, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(117).encode(buf);
-    dr.encode(buf);
+    dr.encode_with(buf, compact);
 }
 type AmqpSequence = List;
 fn decode_amqp_sequence_inner(input: &[u8]) -> Result<(&[u8], AmqpSequence), AmqpParseError> {
     AmqpSequence::decode(input)
 }
-fn encoded_size_amqp_sequence_inner(dr: &AmqpSequence) -> usize {
+fn encoded_size_amqp_sequence_inner(dr: &AmqpSequence, compact: bool) -> usize {
     // descriptor size + actual size
-    3 + dr.encoded_size()
+    3 + dr.encoded_size_with(compact)
 }
-fn encode_amqp_sequence_inner(dr: &AmqpSequence, buf: &mut BytesMut) {
+fn encode_amqp_sequence_inner(dr: &AmqpSequence, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(118).encode(buf);
-    dr.encode(buf);
+    dr.encode_with(buf, compact);
 }
 type AmqpValue = Variant;
 fn decode_amqp_value_inner(input: &[u8]) -> Result<(&[u8], AmqpValue), AmqpParseError> {
     AmqpValue::decode(input)
 }
-fn encoded_size_amqp_value_inner(dr: &AmqpValue) -> usize {
+fn encoded_size_amqp_value_inner(dr: &AmqpValue, compact: bool) -> usize {
     // descriptor size + actual size
-    3 + dr.encoded_size()
+    3 + dr.encoded_size_with(compact)
 }
-fn encode_amqp_value_inner(dr: &AmqpValue, buf: &mut BytesMut) {
+fn encode_amqp_value_inner(dr: &AmqpValue, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(119).encode(buf);
-    dr.encode(buf);
+    dr.encode_with(buf, compact);
 }
 type Footer = Annotations;
 fn decode_footer_inner(input: &[u8]) -> Result<(&[u8], Footer), AmqpParseError> {
     Footer::decode(input)
 }
-fn encoded_size_footer_inner(dr: &Footer) -> usize {
+fn encoded_size_footer_inner(dr: &Footer, compact: bool) -> usize {
     // descriptor size + actual size
-    3 + dr.encoded_size()
+    3 + dr.encoded_size_with(compact)
 }
-fn encode_footer_inner(dr: &Footer, buf: &mut BytesMut) {
+fn encode_footer_inner(dr: &Footer, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(120).encode(buf);
-    dr.encode(buf);
+    dr.encode_with(buf, compact);
 }
 #[derive(Clone, Debug, PartialEq)]
 pub struct Error {
@@ -1126,12 +1206,12 @@ fn decode_error_inner(input: &[u8]) -> Result<(&[u8], Error), AmqpParseError> {
         },
     ))
 }
-fn encoded_size_error_inner(list: &Error) -> usize {
+fn encoded_size_error_inner(list: &Error, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.condition.encoded_size()
-        + list.description.encoded_size()
-        + list.info.encoded_size();
+        + list.condition.encoded_size_with(compact)
+        + list.description.encoded_size_with(compact)
+        + list.info.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -1139,13 +1219,13 @@ fn encoded_size_error_inner(list: &Error) -> usize {
         6
     }) + content_size
 }
-fn encode_error_inner(list: &Error, buf: &mut BytesMut) {
+fn encode_error_inner(list: &Error, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(29).encode(buf);
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.condition.encoded_size()
-        + list.description.encoded_size()
-        + list.info.encoded_size();
+        + list.condition.encoded_size_with(compact)
+        + list.description.encoded_size_with(compact)
+        + list.info.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -1155,9 +1235,9 @@ fn encode_error_inner(list: &Error, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(Error::FIELD_COUNT as u8);
     }
-    list.condition.encode(buf);
-    list.description.encode(buf);
-    list.info.encode(buf);
+    list.condition.encode_with(buf, compact);
+    list.description.encode_with(buf, compact);
+    list.info.encode_with(buf, compact);
 }
 impl DecodeFormatted for Error {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -1176,10 +1256,16 @@ impl DecodeFormatted for Error {
 }
 impl Encode for Error {
     fn encoded_size(&self) -> usize {
-        encoded_size_error_inner(self)
+        encoded_size_error_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_error_inner(self, buf)
+        encode_error_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_error_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_error_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -1343,19 +1429,19 @@ fn decode_open_inner(input: &[u8]) -> Result<(&[u8], Open), AmqpParseError> {
         },
     ))
 }
-fn encoded_size_open_inner(list: &Open) -> usize {
+fn encoded_size_open_inner(list: &Open, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.container_id.encoded_size()
-        + list.hostname.encoded_size()
-        + list.max_frame_size.encoded_size()
-        + list.channel_max.encoded_size()
-        + list.idle_time_out.encoded_size()
-        + list.outgoing_locales.encoded_size()
-        + list.incoming_locales.encoded_size()
-        + list.offered_capabilities.encoded_size()
-        + list.desired_capabilities.encoded_size()
-        + list.properties.encoded_size();
+        + list.container_id.encoded_size_with(compact)
+        + list.hostname.encoded_size_with(compact)
+        + list.max_frame_size.encoded_size_with(compact)
+        + list.channel_max.encoded_size_with(compact)
+        + list.idle_time_out.encoded_size_with(compact)
+        + list.outgoing_locales.encoded_size_with(compact)
+        + list.incoming_locales.encoded_size_with(compact)
+        + list.offered_capabilities.encoded_size_with(compact)
+        + list.desired_capabilities.encoded_size_with(compact)
+        + list.properties.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -1363,20 +1449,20 @@ fn encoded_size_open_inner(list: &Open) -> usize {
         6
     }) + content_size
 }
-fn encode_open_inner(list: &Open, buf: &mut BytesMut) {
+fn encode_open_inner(list: &Open, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(16).encode(buf);
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.container_id.encoded_size()
-        + list.hostname.encoded_size()
-        + list.max_frame_size.encoded_size()
-        + list.channel_max.encoded_size()
-        + list.idle_time_out.encoded_size()
-        + list.outgoing_locales.encoded_size()
-        + list.incoming_locales.encoded_size()
-        + list.offered_capabilities.encoded_size()
-        + list.desired_capabilities.encoded_size()
-        + list.properties.encoded_size();
+        + list.container_id.encoded_size_with(compact)
+        + list.hostname.encoded_size_with(compact)
+        + list.max_frame_size.encoded_size_with(compact)
+        + list.channel_max.encoded_size_with(compact)
+        + list.idle_time_out.encoded_size_with(compact)
+        + list.outgoing_locales.encoded_size_with(compact)
+        + list.incoming_locales.encoded_size_with(compact)
+        + list.offered_capabilities.encoded_size_with(compact)
+        + list.desired_capabilities.encoded_size_with(compact)
+        + list.properties.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -1386,16 +1472,16 @@ fn encode_open_inner(list: &Open, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(Open::FIELD_COUNT as u8);
     }
-    list.container_id.encode(buf);
-    list.hostname.encode(buf);
-    list.max_frame_size.encode(buf);
-    list.channel_max.encode(buf);
-    list.idle_time_out.encode(buf);
-    list.outgoing_locales.encode(buf);
-    list.incoming_locales.encode(buf);
-    list.offered_capabilities.encode(buf);
-    list.desired_capabilities.encode(buf);
-    list.properties.encode(buf);
+    list.container_id.encode_with(buf, compact);
+    list.hostname.encode_with(buf, compact);
+    list.max_frame_size.encode_with(buf, compact);
+    list.channel_max.encode_with(buf, compact);
+    list.idle_time_out.encode_with(buf, compact);
+    list.outgoing_locales.encode_with(buf, compact);
+    list.incoming_locales.encode_with(buf, compact);
+    list.offered_capabilities.encode_with(buf, compact);
+    list.desired_capabilities.encode_with(buf, compact);
+    list.properties.encode_with(buf, compact);
 }
 impl DecodeFormatted for Open {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -1414,10 +1500,16 @@ impl DecodeFormatted for Open {
 }
 impl Encode for Open {
     fn encoded_size(&self) -> usize {
-        encoded_size_open_inner(self)
+        encoded_size_open_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_open_inner(self, buf)
+        encode_open_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_open_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_open_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -1553,17 +1645,17 @@ fn decode_begin_inner(input: &[u8]) -> Result<(&[u8], Begin), AmqpParseError> {
         },
     ))
 }
-fn encoded_size_begin_inner(list: &Begin) -> usize {
+fn encoded_size_begin_inner(list: &Begin, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.remote_channel.encoded_size()
-        + list.next_outgoing_id.encoded_size()
-        + list.incoming_window.encoded_size()
-        + list.outgoing_window.encoded_size()
-        + list.handle_max.encoded_size()
-        + list.offered_capabilities.encoded_size()
-        + list.desired_capabilities.encoded_size()
-        + list.properties.encoded_size();
+        + list.remote_channel.encoded_size_with(compact)
+        + list.next_outgoing_id.encoded_size_with(compact)
+        + list.incoming_window.encoded_size_with(compact)
+        + list.outgoing_window.encoded_size_with(compact)
+        + list.handle_max.encoded_size_with(compact)
+        + list.offered_capabilities.encoded_size_with(compact)
+        + list.desired_capabilities.encoded_size_with(compact)
+        + list.properties.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -1571,18 +1663,18 @@ fn encoded_size_begin_inner(list: &Begin) -> usize {
         6
     }) + content_size
 }
-fn encode_begin_inner(list: &Begin, buf: &mut BytesMut) {
+fn encode_begin_inner(list: &Begin, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(17).encode(buf);
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.remote_channel.encoded_size()
-        + list.next_outgoing_id.encoded_size()
-        + list.incoming_window.encoded_size()
-        + list.outgoing_window.encoded_size()
-        + list.handle_max.encoded_size()
-        + list.offered_capabilities.encoded_size()
-        + list.desired_capabilities.encoded_size()
-        + list.properties.encoded_size();
+        + list.remote_channel.encoded_size_with(compact)
+        + list.next_outgoing_id.encoded_size_with(compact)
+        + list.incoming_window.encoded_size_with(compact)
+        + list.outgoing_window.encoded_size_with(compact)
+        + list.handle_max.encoded_size_with(compact)
+        + list.offered_capabilities.encoded_size_with(compact)
+        + list.desired_capabilities.encoded_size_with(compact)
+        + list.properties.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -1592,14 +1684,14 @@ fn encode_begin_inner(list: &Begin, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(Begin::FIELD_COUNT as u8);
     }
-    list.remote_channel.encode(buf);
-    list.next_outgoing_id.encode(buf);
-    list.incoming_window.encode(buf);
-    list.outgoing_window.encode(buf);
-    list.handle_max.encode(buf);
-    list.offered_capabilities.encode(buf);
-    list.desired_capabilities.encode(buf);
-    list.properties.encode(buf);
+    list.remote_channel.encode_with(buf, compact);
+    list.next_outgoing_id.encode_with(buf, compact);
+    list.incoming_window.encode_with(buf, compact);
+    list.outgoing_window.encode_with(buf, compact);
+    list.handle_max.encode_with(buf, compact);
+    list.offered_capabilities.encode_with(buf, compact);
+    list.desired_capabilities.encode_with(buf, compact);
+    list.properties.encode_with(buf, compact);
 }
 impl DecodeFormatted for Begin {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -1618,10 +1710,16 @@ impl DecodeFormatted for Begin {
 }
 impl Encode for Begin {
     fn encoded_size(&self) -> usize {
-        encoded_size_begin_inner(self)
+        encoded_size_begin_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_begin_inner(self, buf)
+        encode_begin_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_begin_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_begin_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -1841,23 +1939,23 @@ fn decode_attach_inner(input: &[u8]) -> Result<(&[u8], Attach), AmqpParseError>
         },
     ))
 }
-fn encoded_size_attach_inner(list: &Attach) -> usize {
+fn encoded_size_attach_inner(list: &Attach, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.name.encoded_size()
-        + list.handle.encoded_size()
-        + list.role.encoded_size()
-        + list.snd_settle_mode.encoded_size()
-        + list.rcv_settle_mode.encoded_size()
-        + list.source.encoded_size()
-        + list.target.encoded_size()
-        + list.unsettled.encoded_size()
-        + list.incomplete_unsettled.encoded_size()
-        + list.initial_delivery_count.encoded_size()
-        + list.max_message_size.encoded_size()
-        + list.offered_capabilities.encoded_size()
-        + list.desired_capabilities.encoded_size()
-        + list.properties.encoded_size();
+        + list.name.encoded_size_with(compact)
+        + list.handle.encoded_size_with(compact)
+        + list.role.encoded_size_with(compact)
+        + list.snd_settle_mode.encoded_size_with(compact)
+        + list.rcv_settle_mode.encoded_size_with(compact)
+        + list.source.encoded_size_with(compact)
+        + list.target.encoded_size_with(compact)
+        + list.unsettled.encoded_size_with(compact)
+        + list.incomplete_unsettled.encoded_size_with(compact)
+        + list.initial_delivery_count.encoded_size_with(compact)
+        + list.max_message_size.encoded_size_with(compact)
+        + list.offered_capabilities.encoded_size_with(compact)
+        + list.desired_capabilities.encoded_size_with(compact)
+        + list.properties.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -1865,24 +1963,24 @@ fn encoded_size_attach_inner(list: &Attach) -> usize {
         6
     }) + content_size
 }
-fn encode_attach_inner(list: &Attach, buf: &mut BytesMut) {
+fn encode_attach_inner(list: &Attach, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(18).encode(buf);
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.name.encoded_size()
-        + list.handle.encoded_size()
-        + list.role.encoded_size()
-        + list.snd_settle_mode.encoded_size()
-        + list.rcv_settle_mode.encoded_size()
-        + list.source.encoded_size()
-        + list.target.encoded_size()
-        + list.unsettled.encoded_size()
-        + list.incomplete_unsettled.encoded_size()
-        + list.initial_delivery_count.encoded_size()
-        + list.max_message_size.encoded_size()
-        + list.offered_capabilities.encoded_size()
-        + list.desired_capabilities.encoded_size()
-        + list.properties.encoded_size();
+        + list.name.encoded_size_with(compact)
+        + list.handle.encoded_size_with(compact)
+        + list.role.encoded_size_with(compact)
+        + list.snd_settle_mode.encoded_size_with(compact)
+        + list.rcv_settle_mode.encoded_size_with(compact)
+        + list.source.encoded_size_with(compact)
+        + list.target.encoded_size_with(compact)
+        + list.unsettled.encoded_size_with(compact)
+        + list.incomplete_unsettled.encoded_size_with(compact)
+        + list.initial_delivery_count.encoded_size_with(compact)
+        + list.max_message_size.encoded_size_with(compact)
+        + list.offered_capabilities.encoded_size_with(compact)
+        + list.desired_capabilities.encoded_size_with(compact)
+        + list.properties.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -1892,20 +1990,20 @@ fn encode_attach_inner(list: &Attach, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(Attach::FIELD_COUNT as u8);
     }
-    list.name.encode(buf);
-    list.handle.encode(buf);
-    list.role.encode(buf);
-    list.snd_settle_mode.encode(buf);
-    list.rcv_settle_mode.encode(buf);
-    list.source.encode(buf);
-    list.target.encode(buf);
-    list.unsettled.encode(buf);
-    list.incomplete_unsettled.encode(buf);
-    list.initial_delivery_count.encode(buf);
-    list.max_message_size.encode(buf);
-    list.offered_capabilities.encode(buf);
-    list.desired_capabilities.encode(buf);
-    list.properties.encode(buf);
+    list.name.encode_with(buf, compact);
+    list.handle.encode_with(buf, compact);
+    list.role.encode_with(buf, compact);
+    list.snd_settle_mode.encode_with(buf, compact);
+    list.rcv_settle_mode.encode_with(buf, compact);
+    list.source.encode_with(buf, compact);
+    list.target.encode_with(buf, compact);
+    list.unsettled.encode_with(buf, compact);
+    list.incomplete_unsettled.encode_with(buf, compact);
+    list.initial_delivery_count.encode_with(buf, compact);
+    list.max_message_size.encode_with(buf, compact);
+    list.offered_capabilities.encode_with(buf, compact);
+    list.desired_capabilities.encode_with(buf, compact);
+    list.properties.encode_with(buf, compact);
 }
 impl DecodeFormatted for Attach {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -1924,10 +2022,16 @@ impl DecodeFormatted for Attach {
 }
 impl Encode for Attach {
     fn encoded_size(&self) -> usize {
-        encoded_size_attach_inner(self)
+        encoded_size_attach_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_attach_inner(self, buf)
+        encode_attach_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_attach_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_attach_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -2105,20 +2209,20 @@ fn decode_flow_inner(input: &[u8]) -> Result<(&[u8], Flow), AmqpParseError> {
         },
     ))
 }
-fn encoded_size_flow_inner(list: &Flow) -> usize {
+fn encoded_size_flow_inner(list: &Flow, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.next_incoming_id.encoded_size()
-        + list.incoming_window.encoded_size()
-        + list.next_outgoing_id.encoded_size()
-        + list.outgoing_window.encoded_size()
-        + list.handle.encoded_size()
-        + list.delivery_count.encoded_size()
-        + list.link_credit.encoded_size()
-        + list.available.encoded_size()
-        + list.drain.encoded_size()
-        + list.echo.encoded_size()
-        + list.properties.encoded_size();
+        + list.next_incoming_id.encoded_size_with(compact)
+        + list.incoming_window.encoded_size_with(compact)
+        + list.next_outgoing_id.encoded_size_with(compact)
+        + list.outgoing_window.encoded_size_with(compact)
+        + list.handle.encoded_size_with(compact)
+        + list.delivery_count.encoded_size_with(compact)
+        + list.link_credit.encoded_size_with(compact)
+        + list.available.encoded_size_with(compact)
+        + list.drain.encoded_size_with(compact)
+        + list.echo.encoded_size_with(compact)
+        + list.properties.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -2126,21 +2230,21 @@ fn encoded_size_flow_inner(list: &Flow) -> usize {
         6
     }) + content_size
 }
-fn encode_flow_inner(list: &Flow, buf: &mut BytesMut) {
+fn encode_flow_inner(list: &Flow, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(19).encode(buf);
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.next_incoming_id.encoded_size()
-        + list.incoming_window.encoded_size()
-        + list.next_outgoing_id.encoded_size()
-        + list.outgoing_window.encoded_size()
-        + list.handle.encoded_size()
-        + list.delivery_count.encoded_size()
-        + list.link_credit.encoded_size()
-        + list.available.encoded_size()
-        + list.drain.encoded_size()
-        + list.echo.encoded_size()
-        + list.properties.encoded_size();
+        + list.next_incoming_id.encoded_size_with(compact)
+        + list.incoming_window.encoded_size_with(compact)
+        + list.next_outgoing_id.encoded_size_with(compact)
+        + list.outgoing_window.encoded_size_with(compact)
+        + list.handle.encoded_size_with(compact)
+        + list.delivery_count.encoded_size_with(compact)
+        + list.link_credit.encoded_size_with(compact)
+        + list.available.encoded_size_with(compact)
+        + list.drain.encoded_size_with(compact)
+        + list.echo.encoded_size_with(compact)
+        + list.properties.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -2150,17 +2254,17 @@ fn encode_flow_inner(list: &Flow, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(Flow::FIELD_COUNT as u8);
     }
-    list.next_incoming_id.encode(buf);
-    list.incoming_window.encode(buf);
-    list.next_outgoing_id.encode(buf);
-    list.outgoing_window.encode(buf);
-    list.handle.encode(buf);
-    list.delivery_count.encode(buf);
-    list.link_credit.encode(buf);
-    list.available.encode(buf);
-    list.drain.encode(buf);
-    list.echo.encode(buf);
-    list.properties.encode(buf);
+    list.next_incoming_id.encode_with(buf, compact);
+    list.incoming_window.encode_with(buf, compact);
+    list.next_outgoing_id.encode_with(buf, compact);
+    list.outgoing_window.encode_with(buf, compact);
+    list.handle.encode_with(buf, compact);
+    list.delivery_count.encode_with(buf, compact);
+    list.link_credit.encode_with(buf, compact);
+    list.available.encode_with(buf, compact);
+    list.drain.encode_with(buf, compact);
+    list.echo.encode_with(buf, compact);
+    list.properties.encode_with(buf, compact);
 }
 impl DecodeFormatted for Flow {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -2179,10 +2283,16 @@ impl DecodeFormatted for Flow {
 }
 impl Encode for Flow {
     fn encoded_size(&self) -> usize {
-        encoded_size_flow_inner(self)
+        encoded_size_flow_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_flow_inner(self, buf)
+        encode_flow_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_flow_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_flow_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -2372,20 +2482,20 @@ fn decode_transfer_inner(input: &[u8]) -> Result<(&[u8], Transfer), AmqpParseErr
         },
     ))
 }
-fn encoded_size_transfer_inner(list: &Transfer) -> usize {
+fn encoded_size_transfer_inner(list: &Transfer, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.handle.encoded_size()
-        + list.delivery_id.encoded_size()
-        + list.delivery_tag.encoded_size()
-        + list.message_format.encoded_size()
-        + list.settled.encoded_size()
-        + list.more.encoded_size()
-        + list.rcv_settle_mode.encoded_size()
-        + list.state.encoded_size()
-        + list.resume.encoded_size()
-        + list.aborted.encoded_size()
-        + list.batchable.encoded_size();
+        + list.handle.encoded_size_with(compact)
+        + list.delivery_id.encoded_size_with(compact)
+        + list.delivery_tag.encoded_size_with(compact)
+        + list.message_format.encoded_size_with(compact)
+        + list.settled.encoded_size_with(compact)
+        + list.more.encoded_size_with(compact)
+        + list.rcv_settle_mode.encoded_size_with(compact)
+        + list.state.encoded_size_with(compact)
+        + list.resume.encoded_size_with(compact)
+        + list.aborted.encoded_size_with(compact)
+        + list.batchable.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -2394,21 +2504,21 @@ fn encoded_size_transfer_inner(list: &Transfer) -> usize {
     }) + content_size
         + list.body.as_ref().map(|b| b.len()).unwrap_or(0)
 }
-fn encode_transfer_inner(list: &Transfer, buf: &mut BytesMut) {
+fn encode_transfer_inner(list: &Transfer, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(20).encode(buf);
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.handle.encoded_size()
-        + list.delivery_id.encoded_size()
-        + list.delivery_tag.encoded_size()
-        + list.message_format.encoded_size()
-        + list.settled.encoded_size()
-        + list.more.encoded_size()
-        + list.rcv_settle_mode.encoded_size()
-        + list.state.encoded_size()
-        + list.resume.encoded_size()
-        + list.aborted.encoded_size()
-        + list.batchable.encoded_size();
+        + list.handle.encoded_size_with(compact)
+        + list.delivery_id.encoded_size_with(compact)
+        + list.delivery_tag.encoded_size_with(compact)
+        + list.message_format.encoded_size_with(compact)
+        + list.settled.encoded_size_with(compact)
+        + list.more.encoded_size_with(compact)
+        + list.rcv_settle_mode.encoded_size_with(compact)
+        + list.state.encoded_size_with(compact)
+        + list.resume.encoded_size_with(compact)
+        + list.aborted.encoded_size_with(compact)
+        + list.batchable.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -2418,17 +2528,17 @@ fn encode_transfer_inner(list: &Transfer, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(Transfer::FIELD_COUNT as u8);
     }
-    list.handle.encode(buf);
-    list.delivery_id.encode(buf);
-    list.delivery_tag.encode(buf);
-    list.message_format.encode(buf);
-    list.settled.encode(buf);
-    list.more.encode(buf);
-    list.rcv_settle_mode.encode(buf);
-    list.state.encode(buf);
-    list.resume.encode(buf);
-    list.aborted.encode(buf);
-    list.batchable.encode(buf);
+    list.handle.encode_with(buf, compact);
+    list.delivery_id.encode_with(buf, compact);
+    list.delivery_tag.encode_with(buf, compact);
+    list.message_format.encode_with(buf, compact);
+    list.settled.encode_with(buf, compact);
+    list.more.encode_with(buf, compact);
+    list.rcv_settle_mode.encode_with(buf, compact);
+    list.state.encode_with(buf, compact);
+    list.resume.encode_with(buf, compact);
+    list.aborted.encode_with(buf, compact);
+    list.batchable.encode_with(buf, compact);
     if let Some(ref body) = list.body {
         body.encode(buf)
     }
@@ -2450,10 +2560,16 @@ impl DecodeFormatted for Transfer {
 }
 impl Encode for Transfer {
     fn encoded_size(&self) -> usize {
-        encoded_size_transfer_inner(self)
+        encoded_size_transfer_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_transfer_inner(self, buf)
+        encode_transfer_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_transfer_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_transfer_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -2561,15 +2677,15 @@ fn decode_disposition_inner(input: &[u8]) -> Result<(&[u8], Disposition), AmqpPa
         },
     ))
 }
-fn encoded_size_disposition_inner(list: &Disposition) -> usize {
+fn encoded_size_disposition_inner(list: &Disposition, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.role.encoded_size()
-        + list.first.encoded_size()
-        + list.last.encoded_size()
-        + list.settled.encoded_size()
-        + list.state.encoded_size()
-        + list.batchable.encoded_size();
+        + list.role.encoded_size_with(compact)
+        + list.first.encoded_size_with(compact)
+        + list.last.encoded_size_with(compact)
+        + list.settled.encoded_size_with(compact)
+        + list.state.encoded_size_with(compact)
+        + list.batchable.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -2577,16 +2693,16 @@ fn encoded_size_disposition_inner(list: &Disposition) -> usize {
         6
     }) + content_size
 }
-fn encode_disposition_inner(list: &Disposition, buf: &mut BytesMut) {
+fn encode_disposition_inner(list: &Disposition, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(21).encode(buf);
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.role.encoded_size()
-        + list.first.encoded_size()
-        + list.last.encoded_size()
-        + list.settled.encoded_size()
-        + list.state.encoded_size()
-        + list.batchable.encoded_size();
+        + list.role.encoded_size_with(compact)
+        + list.first.encoded_size_with(compact)
+        + list.last.encoded_size_with(compact)
+        + list.settled.encoded_size_with(compact)
+        + list.state.encoded_size_with(compact)
+        + list.batchable.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -2596,12 +2712,12 @@ fn encode_disposition_inner(list: &Disposition, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(Disposition::FIELD_COUNT as u8);
     }
-    list.role.encode(buf);
-    list.first.encode(buf);
-    list.last.encode(buf);
-    list.settled.encode(buf);
-    list.state.encode(buf);
-    list.batchable.encode(buf);
+    list.role.encode_with(buf, compact);
+    list.first.encode_with(buf, compact);
+    list.last.encode_with(buf, compact);
+    list.settled.encode_with(buf, compact);
+    list.state.encode_with(buf, compact);
+    list.batchable.encode_with(buf, compact);
 }
 impl DecodeFormatted for Disposition {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -2620,10 +2736,16 @@ impl DecodeFormatted for Disposition {
 }
 impl Encode for Disposition {
     fn encoded_size(&self) -> usize {
-        encoded_size_disposition_inner(self)
+        encoded_size_disposition_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_disposition_inner(self, buf)
+        encode_disposition_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_disposition_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_disposition_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -2689,10 +2811,12 @@ fn decode_detach_inner(input: &[u8]) -> Result<(&[u8], Detach), AmqpParseError>
         },
     ))
 }
-fn encoded_size_detach_inner(list: &Detach) -> usize {
+fn encoded_size_detach_inner(list: &Detach, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
-    let content_size =
-        0 + list.handle.encoded_size() + list.closed.encoded_size() + list.error.encoded_size();
+    let content_size = 0
+        + list.handle.encoded_size_with(compact)
+        + list.closed.encoded_size_with(compact)
+        + list.error.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -2700,11 +2824,13 @@ fn encoded_size_detach_inner(list: &Detach) -> usize {
         6
     }) + content_size
 }
-fn encode_detach_inner(list: &Detach, buf: &mut BytesMut) {
+fn encode_detach_inner(list: &Detach, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(22).encode(buf);
     #[allow(clippy::identity_op)]
-    let content_size =
-        0 + list.handle.encoded_size() + list.closed.encoded_size() + list.error.encoded_size();
+    let content_size = 0
+        + list.handle.encoded_size_with(compact)
+        + list.closed.encoded_size_with(compact)
+        + list.error.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -2714,9 +2840,9 @@ fn encode_detach_inner(list: &Detach, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(Detach::FIELD_COUNT as u8);
     }
-    list.handle.encode(buf);
-    list.closed.encode(buf);
-    list.error.encode(buf);
+    list.handle.encode_with(buf, compact);
+    list.closed.encode_with(buf, compact);
+    list.error.encode_with(buf, compact);
 }
 impl DecodeFormatted for Detach {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -2735,10 +2861,16 @@ impl DecodeFormatted for Detach {
 }
 impl Encode for Detach {
     fn encoded_size(&self) -> usize {
-        encoded_size_detach_inner(self)
+        encoded_size_detach_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_detach_inner(self, buf)
+        encode_detach_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_detach_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_detach_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -2771,9 +2903,9 @@ fn decode_end_inner(input: &[u8]) -> Result<(&[u8], End), AmqpParseError> {
     }
     Ok((remainder, End { error }))
 }
-fn encoded_size_end_inner(list: &End) -> usize {
+fn encoded_size_end_inner(list: &End, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
-    let content_size = 0 + list.error.encoded_size();
+    let content_size = 0 + list.error.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -2781,10 +2913,10 @@ fn encoded_size_end_inner(list: &End) -> usize {
         6
     }) + content_size
 }
-fn encode_end_inner(list: &End, buf: &mut BytesMut) {
+fn encode_end_inner(list: &End, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(23).encode(buf);
     #[allow(clippy::identity_op)]
-    let content_size = 0 + list.error.encoded_size();
+    let content_size = 0 + list.error.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -2794,7 +2926,7 @@ fn encode_end_inner(list: &End, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(End::FIELD_COUNT as u8);
     }
-    list.error.encode(buf);
+    list.error.encode_with(buf, compact);
 }
 impl DecodeFormatted for End {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -2813,10 +2945,16 @@ impl DecodeFormatted for End {
 }
 impl Encode for End {
     fn encoded_size(&self) -> usize {
-        encoded_size_end_inner(self)
+        encoded_size_end_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_end_inner(self, buf)
+        encode_end_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_end_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_end_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -2849,9 +2987,9 @@ fn decode_close_inner(input: &[u8]) -> Result<(&[u8], Close), AmqpParseError> {
     }
     Ok((remainder, Close { error }))
 }
-fn encoded_size_close_inner(list: &Close) -> usize {
+fn encoded_size_close_inner(list: &Close, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
-    let content_size = 0 + list.error.encoded_size();
+    let content_size = 0 + list.error.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -2859,10 +2997,10 @@ fn encoded_size_close_inner(list: &Close) -> usize {
         6
     }) + content_size
 }
-fn encode_close_inner(list: &Close, buf: &mut BytesMut) {
+fn encode_close_inner(list: &Close, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(24).encode(buf);
     #[allow(clippy::identity_op)]
-    let content_size = 0 + list.error.encoded_size();
+    let content_size = 0 + list.error.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -2872,7 +3010,7 @@ fn encode_close_inner(list: &Close, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(Close::FIELD_COUNT as u8);
     }
-    list.error.encode(buf);
+    list.error.encode_with(buf, compact);
 }
 impl DecodeFormatted for Close {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -2891,10 +3029,16 @@ impl DecodeFormatted for Close {
 }
 impl Encode for Close {
     fn encoded_size(&self) -> usize {
-        encoded_size_close_inner(self)
+        encoded_size_close_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_close_inner(self, buf)
+        encode_close_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_close_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_close_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -2934,9 +3078,9 @@ fn decode_sasl_mechanisms_inner(input: &[u8]) -> Result<(&[u8], SaslMechanisms),
         },
     ))
 }
-fn encoded_size_sasl_mechanisms_inner(list: &SaslMechanisms) -> usize {
+fn encoded_size_sasl_mechanisms_inner(list: &SaslMechanisms, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
-    let content_size = 0 + list.sasl_server_mechanisms.encoded_size();
+    let content_size = 0 + list.sasl_server_mechanisms.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -2944,10 +3088,10 @@ fn encoded_size_sasl_mechanisms_inner(list: &SaslMechanisms) -> usize {
         6
     }) + content_size
 }
-fn encode_sasl_mechanisms_inner(list: &SaslMechanisms, buf: &mut BytesMut) {
+fn encode_sasl_mechanisms_inner(list: &SaslMechanisms, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(64).encode(buf);
     #[allow(clippy::identity_op)]
-    let content_size = 0 + list.sasl_server_mechanisms.encoded_size();
+    let content_size = 0 + list.sasl_server_mechanisms.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -2957,7 +3101,7 @@ fn encode_sasl_mechanisms_inner(list: &SaslMechanisms, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(SaslMechanisms::FIELD_COUNT as u8);
     }
-    list.sasl_server_mechanisms.encode(buf);
+    list.sasl_server_mechanisms.encode_with(buf, compact);
 }
 impl DecodeFormatted for SaslMechanisms {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -2976,10 +3120,16 @@ impl DecodeFormatted for SaslMechanisms {
 }
 impl Encode for SaslMechanisms {
     fn encoded_size(&self) -> usize {
-        encoded_size_sasl_mechanisms_inner(self)
+        encoded_size_sasl_mechanisms_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_sasl_mechanisms_inner(self, buf)
+        encode_sasl_mechanisms_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_sasl_mechanisms_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_sasl_mechanisms_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -3045,12 +3195,12 @@ fn decode_sasl_init_inner(input: &[u8]) -> Result<(&[u8], SaslInit), AmqpParseEr
         },
     ))
 }
-fn encoded_size_sasl_init_inner(list: &SaslInit) -> usize {
+fn encoded_size_sasl_init_inner(list: &SaslInit, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.mechanism.encoded_size()
-        + list.initial_response.encoded_size()
-        + list.hostname.encoded_size();
+        + list.mechanism.encoded_size_with(compact)
+        + list.initial_response.encoded_size_with(compact)
+        + list.hostname.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -3058,13 +3208,13 @@ fn encoded_size_sasl_init_inner(list: &SaslInit) -> usize {
         6
     }) + content_size
 }
-fn encode_sasl_init_inner(list: &SaslInit, buf: &mut BytesMut) {
+fn encode_sasl_init_inner(list: &SaslInit, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(65).encode(buf);
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.mechanism.encoded_size()
-        + list.initial_response.encoded_size()
-        + list.hostname.encoded_size();
+        + list.mechanism.encoded_size_with(compact)
+        + list.initial_response.encoded_size_with(compact)
+        + list.hostname.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -3074,9 +3224,9 @@ fn encode_sasl_init_inner(list: &SaslInit, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(SaslInit::FIELD_COUNT as u8);
     }
-    list.mechanism.encode(buf);
-    list.initial_response.encode(buf);
-    list.hostname.encode(buf);
+    list.mechanism.encode_with(buf, compact);
+    list.initial_response.encode_with(buf, compact);
+    list.hostname.encode_with(buf, compact);
 }
 impl DecodeFormatted for SaslInit {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -3095,10 +3245,16 @@ impl DecodeFormatted for SaslInit {
 }
 impl Encode for SaslInit {
     fn encoded_size(&self) -> usize {
-        encoded_size_sasl_init_inner(self)
+        encoded_size_sasl_init_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_sasl_init_inner(self, buf)
+        encode_sasl_init_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_sasl_init_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_sasl_init_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -3131,9 +3287,9 @@ fn decode_sasl_challenge_inner(input: &[u8]) -> Result<(&[u8], SaslChallenge), A
     }
     Ok((remainder, SaslChallenge { challenge }))
 }
-fn encoded_size_sasl_challenge_inner(list: &SaslChallenge) -> usize {
+fn encoded_size_sasl_challenge_inner(list: &SaslChallenge, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
-    let content_size = 0 + list.challenge.encoded_size();
+    let content_size = 0 + list.challenge.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -3141,10 +3297,10 @@ fn encoded_size_sasl_challenge_inner(list: &SaslChallenge) -> usize {
         6
     }) + content_size
 }
-fn encode_sasl_challenge_inner(list: &SaslChallenge, buf: &mut BytesMut) {
+fn encode_sasl_challenge_inner(list: &SaslChallenge, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(66).encode(buf);
     #[allow(clippy::identity_op)]
-    let content_size = 0 + list.challenge.encoded_size();
+    let content_size = 0 + list.challenge.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -3154,7 +3310,7 @@ fn encode_sasl_challenge_inner(list: &SaslChallenge, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(SaslChallenge::FIELD_COUNT as u8);
     }
-    list.challenge.encode(buf);
+    list.challenge.encode_with(buf, compact);
 }
 impl DecodeFormatted for SaslChallenge {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -3173,10 +3329,16 @@ impl DecodeFormatted for SaslChallenge {
 }
 impl Encode for SaslChallenge {
     fn encoded_size(&self) -> usize {
-        encoded_size_sasl_challenge_inner(self)
+        encoded_size_sasl_challenge_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_sasl_challenge_inner(self, buf)
+        encode_sasl_challenge_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_sasl_challenge_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_sasl_challenge_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -3209,9 +3371,9 @@ fn decode_sasl_response_inner(input: &[u8]) -> Result<(&[u8], SaslResponse), Amq
     }
     Ok((remainder, SaslResponse { response }))
 }
-fn encoded_size_sasl_response_inner(list: &SaslResponse) -> usize {
+fn encoded_size_sasl_response_inner(list: &SaslResponse, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
-    let content_size = 0 + list.response.encoded_size();
+    let content_size = 0 + list.response.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -3219,10 +3381,10 @@ fn encoded_size_sasl_response_inner(list: &SaslResponse) -> usize {
         6
     }) + content_size
 }
-fn encode_sasl_response_inner(list: &SaslResponse, buf: &mut BytesMut) {
+fn encode_sasl_response_inner(list: &SaslResponse, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(67).encode(buf);
     #[allow(clippy::identity_op)]
-    let content_size = 0 + list.response.encoded_size();
+    let content_size = 0 + list.response.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -3232,7 +3394,7 @@ fn encode_sasl_response_inner(list: &SaslResponse, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(SaslResponse::FIELD_COUNT as u8);
     }
-    list.response.encode(buf);
+    list.response.encode_with(buf, compact);
 }
 impl DecodeFormatted for SaslResponse {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -3251,10 +3413,16 @@ impl DecodeFormatted for SaslResponse {
 }
 impl Encode for SaslResponse {
     fn encoded_size(&self) -> usize {
-        encoded_size_sasl_response_inner(self)
+        encoded_size_sasl_response_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_sasl_response_inner(self, buf)
+        encode_sasl_response_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_sasl_response_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_sasl_response_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -3306,9 +3474,10 @@ fn decode_sasl_outcome_inner(input: &[u8]) -> Result<(&[u8], SaslOutcome), AmqpP
         },
     ))
 }
-fn encoded_size_sasl_outcome_inner(list: &SaslOutcome) -> usize {
+fn encoded_size_sasl_outcome_inner(list: &SaslOutcome, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
-    let content_size = 0 + list.code.encoded_size() + list.additional_data.encoded_size();
+    let content_size =
+        0 + list.code.encoded_size_with(compact) + list.additional_data.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -3316,10 +3485,11 @@ fn encoded_size_sasl_outcome_inner(list: &SaslOutcome) -> usize {
         6
     }) + content_size
 }
-fn encode_sasl_outcome_inner(list: &SaslOutcome, buf: &mut BytesMut) {
+fn encode_sasl_outcome_inner(list: &SaslOutcome, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(68).encode(buf);
     #[allow(clippy::identity_op)]
-    let content_size = 0 + list.code.encoded_size() + list.additional_data.encoded_size();
+    let content_size =
+        0 + list.code.encoded_size_with(compact) + list.additional_data.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -3329,8 +3499,8 @@ fn encode_sasl_outcome_inner(list: &SaslOutcome, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(SaslOutcome::FIELD_COUNT as u8);
     }
-    list.code.encode(buf);
-    list.additional_data.encode(buf);
+    list.code.encode_with(buf, compact);
+    list.additional_data.encode_with(buf, compact);
 }
 impl DecodeFormatted for SaslOutcome {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -3349,10 +3519,16 @@ impl DecodeFormatted for SaslOutcome {
 }
 impl Encode for SaslOutcome {
     fn encoded_size(&self) -> usize {
-        encoded_size_sasl_outcome_inner(self)
+        encoded_size_sasl_outcome_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_sasl_outcome_inner(self, buf)
+        encode_sasl_outcome_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_sasl_outcome_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_sasl_outcome_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -3530,20 +3706,20 @@ fn decode_source_inner(input: &[u8]) -> Result<(&[u8], Source), AmqpParseError>
         },
     ))
 }
-fn encoded_size_source_inner(list: &Source) -> usize {
+fn encoded_size_source_inner(list: &Source, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.address.encoded_size()
-        + list.durable.encoded_size()
-        + list.expiry_policy.encoded_size()
-        + list.timeout.encoded_size()
-        + list.dynamic.encoded_size()
-        + list.dynamic_node_properties.encoded_size()
-        + list.distribution_mode.encoded_size()
-        + list.filter.encoded_size()
-        + list.default_outcome.encoded_size()
-        + list.outcomes.encoded_size()
-        + list.capabilities.encoded_size();
+        + list.address.encoded_size_with(compact)
+        + list.durable.encoded_size_with(compact)
+        + list.expiry_policy.encoded_size_with(compact)
+        + list.timeout.encoded_size_with(compact)
+        + list.dynamic.encoded_size_with(compact)
+        + list.dynamic_node_properties.encoded_size_with(compact)
+        + list.distribution_mode.encoded_size_with(compact)
+        + list.filter.encoded_size_with(compact)
+        + list.default_outcome.encoded_size_with(compact)
+        + list.outcomes.encoded_size_with(compact)
+        + list.capabilities.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -3551,21 +3727,21 @@ fn encoded_size_source_inner(list: &Source) -> usize {
         6
     }) + content_size
 }
-fn encode_source_inner(list: &Source, buf: &mut BytesMut) {
+fn encode_source_inner(list: &Source, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(40).encode(buf);
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.address.encoded_size()
-        + list.durable.encoded_size()
-        + list.expiry_policy.encoded_size()
-        + list.timeout.encoded_size()
-        + list.dynamic.encoded_size()
-        + list.dynamic_node_properties.encoded_size()
-        + list.distribution_mode.encoded_size()
-        + list.filter.encoded_size()
-        + list.default_outcome.encoded_size()
-        + list.outcomes.encoded_size()
-        + list.capabilities.encoded_size();
+        + list.address.encoded_size_with(compact)
+        + list.durable.encoded_size_with(compact)
+        + list.expiry_policy.encoded_size_with(compact)
+        + list.timeout.encoded_size_with(compact)
+        + list.dynamic.encoded_size_with(compact)
+        + list.dynamic_node_properties.encoded_size_with(compact)
+        + list.distribution_mode.encoded_size_with(compact)
+        + list.filter.encoded_size_with(compact)
+        + list.default_outcome.encoded_size_with(compact)
+        + list.outcomes.encoded_size_with(compact)
+        + list.capabilities.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -3575,17 +3751,17 @@ fn encode_source_inner(list: &Source, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(Source::FIELD_COUNT as u8);
     }
-    list.address.encode(buf);
-    list.durable.encode(buf);
-    list.expiry_policy.encode(buf);
-    list.timeout.encode(buf);
-    list.dynamic.encode(buf);
-    list.dynamic_node_properties.encode(buf);
-    list.distribution_mode.encode(buf);
-    list.filter.encode(buf);
-    list.default_outcome.encode(buf);
-    list.outcomes.encode(buf);
-    list.capabilities.encode(buf);
+    list.address.encode_with(buf, compact);
+    list.durable.encode_with(buf, compact);
+    list.expiry_policy.encode_with(buf, compact);
+    list.timeout.encode_with(buf, compact);
+    list.dynamic.encode_with(buf, compact);
+    list.dynamic_node_properties.encode_with(buf, compact);
+    list.distribution_mode.encode_with(buf, compact);
+    list.filter.encode_with(buf, compact);
+    list.default_outcome.encode_with(buf, compact);
+    list.outcomes.encode_with(buf, compact);
+    list.capabilities.encode_with(buf, compact);
 }
 impl DecodeFormatted for Source {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -3604,10 +3780,16 @@ impl DecodeFormatted for Source {
 }
 impl Encode for Source {
     fn encoded_size(&self) -> usize {
-        encoded_size_source_inner(self)
+        encoded_size_source_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_source_inner(self, buf)
+        encode_source_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_source_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_source_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -3729,16 +3911,16 @@ fn decode_target_inner(input: &[u8]) -> Result<(&[u8], Target), AmqpParseError>
         },
     ))
 }
-fn encoded_size_target_inner(list: &Target) -> usize {
+fn encoded_size_target_inner(list: &Target, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.address.encoded_size()
-        + list.durable.encoded_size()
-        + list.expiry_policy.encoded_size()
-        + list.timeout.encoded_size()
-        + list.dynamic.encoded_size()
-        + list.dynamic_node_properties.encoded_size()
-        + list.capabilities.encoded_size();
+        + list.address.encoded_size_with(compact)
+        + list.durable.encoded_size_with(compact)
+        + list.expiry_policy.encoded_size_with(compact)
+        + list.timeout.encoded_size_with(compact)
+        + list.dynamic.encoded_size_with(compact)
+        + list.dynamic_node_properties.encoded_size_with(compact)
+        + list.capabilities.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -3746,17 +3928,17 @@ fn encoded_size_target_inner(list: &Target) -> usize {
         6
     }) + content_size
 }
-fn encode_target_inner(list: &Target, buf: &mut BytesMut) {
+fn encode_target_inner(list: &Target, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(41).encode(buf);
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.address.encoded_size()
-        + list.durable.encoded_size()
-        + list.expiry_policy.encoded_size()
-        + list.timeout.encoded_size()
-        + list.dynamic.encoded_size()
-        + list.dynamic_node_properties.encoded_size()
-        + list.capabilities.encoded_size();
+        + list.address.encoded_size_with(compact)
+        + list.durable.encoded_size_with(compact)
+        + list.expiry_policy.encoded_size_with(compact)
+        + list.timeout.encoded_size_with(compact)
+        + list.dynamic.encoded_size_with(compact)
+        + list.dynamic_node_properties.encoded_size_with(compact)
+        + list.capabilities.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -3766,13 +3948,13 @@ fn encode_target_inner(list: &Target, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(Target::FIELD_COUNT as u8);
     }
-    list.address.encode(buf);
-    list.durable.encode(buf);
-    list.expiry_policy.encode(buf);
-    list.timeout.encode(buf);
-    list.dynamic.encode(buf);
-    list.dynamic_node_properties.encode(buf);
-    list.capabilities.encode(buf);
+    list.address.encode_with(buf, compact);
+    list.durable.encode_with(buf, compact);
+    list.expiry_policy.encode_with(buf, compact);
+    list.timeout.encode_with(buf, compact);
+    list.dynamic.encode_with(buf, compact);
+    list.dynamic_node_properties.encode_with(buf, compact);
+    list.capabilities.encode_with(buf, compact);
 }
 impl DecodeFormatted for Target {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -3791,10 +3973,16 @@ impl DecodeFormatted for Target {
 }
 impl Encode for Target {
     fn encoded_size(&self) -> usize {
-        encoded_size_target_inner(self)
+        encoded_size_target_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_target_inner(self, buf)
+        encode_target_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_target_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_target_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -3888,14 +4076,14 @@ fn decode_header_inner(input: &[u8]) -> Result<(&[u8], Header), AmqpParseError>
         },
     ))
 }
-fn encoded_size_header_inner(list: &Header) -> usize {
+fn encoded_size_header_inner(list: &Header, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.durable.encoded_size()
-        + list.priority.encoded_size()
-        + list.ttl.encoded_size()
-        + list.first_acquirer.encoded_size()
-        + list.delivery_count.encoded_size();
+        + list.durable.encoded_size_with(compact)
+        + list.priority.encoded_size_with(compact)
+        + list.ttl.encoded_size_with(compact)
+        + list.first_acquirer.encoded_size_with(compact)
+        + list.delivery_count.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -3903,15 +4091,15 @@ fn encoded_size_header_inner(list: &Header) -> usize {
         6
     }) + content_size
 }
-fn encode_header_inner(list: &Header, buf: &mut BytesMut) {
+fn encode_header_inner(list: &Header, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(112).encode(buf);
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.durable.encoded_size()
-        + list.priority.encoded_size()
-        + list.ttl.encoded_size()
-        + list.first_acquirer.encoded_size()
-        + list.delivery_count.encoded_size();
+        + list.durable.encoded_size_with(compact)
+        + list.priority.encoded_size_with(compact)
+        + list.ttl.encoded_size_with(compact)
+        + list.first_acquirer.encoded_size_with(compact)
+        + list.delivery_count.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -3921,11 +4109,11 @@ fn encode_header_inner(list: &Header, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(Header::FIELD_COUNT as u8);
     }
-    list.durable.encode(buf);
-    list.priority.encode(buf);
-    list.ttl.encode(buf);
-    list.first_acquirer.encode(buf);
-    list.delivery_count.encode(buf);
+    list.durable.encode_with(buf, compact);
+    list.priority.encode_with(buf, compact);
+    list.ttl.encode_with(buf, compact);
+    list.first_acquirer.encode_with(buf, compact);
+    list.delivery_count.encode_with(buf, compact);
 }
 impl DecodeFormatted for Header {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -3944,10 +4132,16 @@ impl DecodeFormatted for Header {
 }
 impl Encode for Header {
     fn encoded_size(&self) -> usize {
-        encoded_size_header_inner(self)
+        encoded_size_header_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_header_inner(self, buf)
+        encode_header_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_header_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_header_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -4153,22 +4347,22 @@ fn decode_properties_inner(input: &[u8]) -> Result<(&[u8], Properties), AmqpPars
         },
     ))
 }
-fn encoded_size_properties_inner(list: &Properties) -> usize {
+fn encoded_size_properties_inner(list: &Properties, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.message_id.encoded_size()
-        + list.user_id.encoded_size()
-        + list.to.encoded_size()
-        + list.subject.encoded_size()
-        + list.reply_to.encoded_size()
-        + list.correlation_id.encoded_size()
-        + list.content_type.encoded_size()
-        + list.content_encoding.encoded_size()
-        + list.absolute_expiry_time.encoded_size()
-        + list.creation_time.encoded_size()
-        + list.group_id.encoded_size()
-        + list.group_sequence.encoded_size()
-        + list.reply_to_group_id.encoded_size();
+        + list.message_id.encoded_size_with(compact)
+        + list.user_id.encoded_size_with(compact)
+        + list.to.encoded_size_with(compact)
+        + list.subject.encoded_size_with(compact)
+        + list.reply_to.encoded_size_with(compact)
+        + list.correlation_id.encoded_size_with(compact)
+        + list.content_type.encoded_size_with(compact)
+        + list.content_encoding.encoded_size_with(compact)
+        + list.absolute_expiry_time.encoded_size_with(compact)
+        + list.creation_time.encoded_size_with(compact)
+        + list.group_id.encoded_size_with(compact)
+        + list.group_sequence.encoded_size_with(compact)
+        + list.reply_to_group_id.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -4176,23 +4370,23 @@ fn encoded_size_properties_inner(list: &Properties) -> usize {
         6
     }) + content_size
 }
-fn encode_properties_inner(list: &Properties, buf: &mut BytesMut) {
+fn encode_properties_inner(list: &Properties, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(115).encode(buf);
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.message_id.encoded_size()
-        + list.user_id.encoded_size()
-        + list.to.encoded_size()
-        + list.subject.encoded_size()
-        + list.reply_to.encoded_size()
-        + list.correlation_id.encoded_size()
-        + list.content_type.encoded_size()
-        + list.content_encoding.encoded_size()
-        + list.absolute_expiry_time.encoded_size()
-        + list.creation_time.encoded_size()
-        + list.group_id.encoded_size()
-        + list.group_sequence.encoded_size()
-        + list.reply_to_group_id.encoded_size();
+        + list.message_id.encoded_size_with(compact)
+        + list.user_id.encoded_size_with(compact)
+        + list.to.encoded_size_with(compact)
+        + list.subject.encoded_size_with(compact)
+        + list.reply_to.encoded_size_with(compact)
+        + list.correlation_id.encoded_size_with(compact)
+        + list.content_type.encoded_size_with(compact)
+        + list.content_encoding.encoded_size_with(compact)
+        + list.absolute_expiry_time.encoded_size_with(compact)
+        + list.creation_time.encoded_size_with(compact)
+        + list.group_id.encoded_size_with(compact)
+        + list.group_sequence.encoded_size_with(compact)
+        + list.reply_to_group_id.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -4202,19 +4396,19 @@ fn encode_properties_inner(list: &Properties, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(Properties::FIELD_COUNT as u8);
     }
-    list.message_id.encode(buf);
-    list.user_id.encode(buf);
-    list.to.encode(buf);
-    list.subject.encode(buf);
-    list.reply_to.encode(buf);
-    list.correlation_id.encode(buf);
-    list.content_type.encode(buf);
-    list.content_encoding.encode(buf);
-    list.absolute_expiry_time.encode(buf);
-    list.creation_time.encode(buf);
-    list.group_id.encode(buf);
-    list.group_sequence.encode(buf);
-    list.reply_to_group_id.encode(buf);
+    list.message_id.encode_with(buf, compact);
+    list.user_id.encode_with(buf, compact);
+    list.to.encode_with(buf, compact);
+    list.subject.encode_with(buf, compact);
+    list.reply_to.encode_with(buf, compact);
+    list.correlation_id.encode_with(buf, compact);
+    list.content_type.encode_with(buf, compact);
+    list.content_encoding.encode_with(buf, compact);
+    list.absolute_expiry_time.encode_with(buf, compact);
+    list.creation_time.encode_with(buf, compact);
+    list.group_id.encode_with(buf, compact);
+    list.group_sequence.encode_with(buf, compact);
+    list.reply_to_group_id.encode_with(buf, compact);
 }
 impl DecodeFormatted for Properties {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -4233,10 +4427,16 @@ impl DecodeFormatted for Properties {
 }
 impl Encode for Properties {
     fn encoded_size(&self) -> usize {
-        encoded_size_properties_inner(self)
+        encoded_size_properties_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_properties_inner(self, buf)
+        encode_properties_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_properties_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_properties_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -4288,9 +4488,11 @@ fn decode_received_inner(input: &[u8]) -> Result<(&[u8], Received), AmqpParseErr
         },
     ))
 }
-fn encoded_size_received_inner(list: &Received) -> usize {
+fn encoded_size_received_inner(list: &Received, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
-    let content_size = 0 + list.section_number.encoded_size() + list.section_offset.encoded_size();
+    let content_size = 0
+        + list.section_number.encoded_size_with(compact)
+        + list.section_offset.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -4298,10 +4500,12 @@ fn encoded_size_received_inner(list: &Received) -> usize {
         6
     }) + content_size
 }
-fn encode_received_inner(list: &Received, buf: &mut BytesMut) {
+fn encode_received_inner(list: &Received, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(35).encode(buf);
     #[allow(clippy::identity_op)]
-    let content_size = 0 + list.section_number.encoded_size() + list.section_offset.encoded_size();
+    let content_size = 0
+        + list.section_number.encoded_size_with(compact)
+        + list.section_offset.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -4311,8 +4515,8 @@ fn encode_received_inner(list: &Received, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(Received::FIELD_COUNT as u8);
     }
-    list.section_number.encode(buf);
-    list.section_offset.encode(buf);
+    list.section_number.encode_with(buf, compact);
+    list.section_offset.encode_with(buf, compact);
 }
 impl DecodeFormatted for Received {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -4331,10 +4535,16 @@ impl DecodeFormatted for Received {
 }
 impl Encode for Received {
     fn encoded_size(&self) -> usize {
-        encoded_size_received_inner(self)
+        encoded_size_received_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_received_inner(self, buf)
+        encode_received_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_received_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_received_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -4352,7 +4562,7 @@ fn decode_accepted_inner(input: &[u8]) -> Result<(&[u8], Accepted), AmqpParseErr
     let mut remainder = &input[size..];
     Ok((remainder, Accepted {}))
 }
-fn encoded_size_accepted_inner(list: &Accepted) -> usize {
+fn encoded_size_accepted_inner(list: &Accepted, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
     let content_size = 0;
     // header: 0x00 0x53 <descriptor code> format_code size count
@@ -4362,7 +4572,7 @@ fn encoded_size_accepted_inner(list: &Accepted) -> usize {
         6
     }) + content_size
 }
-fn encode_accepted_inner(list: &Accepted, buf: &mut BytesMut) {
+fn encode_accepted_inner(list: &Accepted, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(36).encode(buf);
     #[allow(clippy::identity_op)]
     let content_size = 0;
@@ -4393,10 +4603,16 @@ impl DecodeFormatted for Accepted {
 }
 impl Encode for Accepted {
     fn encoded_size(&self) -> usize {
-        encoded_size_accepted_inner(self)
+        encoded_size_accepted_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_accepted_inner(self, buf)
+        encode_accepted_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_accepted_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_accepted_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -4429,9 +4645,9 @@ fn decode_rejected_inner(input: &[u8]) -> Result<(&[u8], Rejected), AmqpParseErr
     }
     Ok((remainder, Rejected { error }))
 }
-fn encoded_size_rejected_inner(list: &Rejected) -> usize {
+fn encoded_size_rejected_inner(list: &Rejected, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
-    let content_size = 0 + list.error.encoded_size();
+    let content_size = 0 + list.error.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -4439,10 +4655,10 @@ fn encoded_size_rejected_inner(list: &Rejected) -> usize {
         6
     }) + content_size
 }
-fn encode_rejected_inner(list: &Rejected, buf: &mut BytesMut) {
+fn encode_rejected_inner(list: &Rejected, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(37).encode(buf);
     #[allow(clippy::identity_op)]
-    let content_size = 0 + list.error.encoded_size();
+    let content_size = 0 + list.error.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -4452,7 +4668,7 @@ fn encode_rejected_inner(list: &Rejected, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(Rejected::FIELD_COUNT as u8);
     }
-    list.error.encode(buf);
+    list.error.encode_with(buf, compact);
 }
 impl DecodeFormatted for Rejected {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -4471,10 +4687,16 @@ impl DecodeFormatted for Rejected {
 }
 impl Encode for Rejected {
     fn encoded_size(&self) -> usize {
-        encoded_size_rejected_inner(self)
+        encoded_size_rejected_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_rejected_inner(self, buf)
+        encode_rejected_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_rejected_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_rejected_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -4492,7 +4714,7 @@ fn decode_released_inner(input: &[u8]) -> Result<(&[u8], Released), AmqpParseErr
     let mut remainder = &input[size..];
     Ok((remainder, Released {}))
 }
-fn encoded_size_released_inner(list: &Released) -> usize {
+fn encoded_size_released_inner(list: &Released, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
     let content_size = 0;
     // header: 0x00 0x53 <descriptor code> format_code size count
@@ -4502,7 +4724,7 @@ fn encoded_size_released_inner(list: &Released) -> usize {
         6
     }) + content_size
 }
-fn encode_released_inner(list: &Released, buf: &mut BytesMut) {
+fn encode_released_inner(list: &Released, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(38).encode(buf);
     #[allow(clippy::identity_op)]
     let content_size = 0;
@@ -4533,10 +4755,16 @@ impl DecodeFormatted for Released {
 }
 impl Encode for Released {
     fn encoded_size(&self) -> usize {
-        encoded_size_released_inner(self)
+        encoded_size_released_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_released_inner(self, buf)
+        encode_released_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_released_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_released_inner(self, buf, compact)
     }
 }
 #[derive(Clone, Debug, PartialEq)]
@@ -4602,12 +4830,12 @@ fn decode_modified_inner(input: &[u8]) -> Result<(&[u8], Modified), AmqpParseErr
         },
     ))
 }
-fn encoded_size_modified_inner(list: &Modified) -> usize {
+fn encoded_size_modified_inner(list: &Modified, compact: bool) -> usize {
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.delivery_failed.encoded_size()
-        + list.undeliverable_here.encoded_size()
-        + list.message_annotations.encoded_size();
+        + list.delivery_failed.encoded_size_with(compact)
+        + list.undeliverable_here.encoded_size_with(compact)
+        + list.message_annotations.encoded_size_with(compact);
     // header: 0x00 0x53 <descriptor code> format_code size count
     (if content_size + 1 > u8::MAX as usize {
         12
@@ -4615,13 +4843,13 @@ fn encoded_size_modified_inner(list: &Modified) -> usize {
         6
     }) + content_size
 }
-fn encode_modified_inner(list: &Modified, buf: &mut BytesMut) {
+fn encode_modified_inner(list: &Modified, buf: &mut BytesMut, compact: bool) {
     Descriptor::Ulong(39).encode(buf);
     #[allow(clippy::identity_op)]
     let content_size = 0
-        + list.delivery_failed.encoded_size()
-        + list.undeliverable_here.encoded_size()
-        + list.message_annotations.encoded_size();
+        + list.delivery_failed.encoded_size_with(compact)
+        + list.undeliverable_here.encoded_size_with(compact)
+        + list.message_annotations.encoded_size_with(compact);
     if content_size + 1 > u8::MAX as usize {
         buf.put_u8(codec::FORMATCODE_LIST32);
         buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
@@ -4631,9 +4859,9 @@ fn encode_modified_inner(list: &Modified, buf: &mut BytesMut) {
         buf.put_u8((content_size + 1) as u8);
         buf.put_u8(Modified::FIELD_COUNT as u8);
     }
-    list.delivery_failed.encode(buf);
-    list.undeliverable_here.encode(buf);
-    list.message_annotations.encode(buf);
+    list.delivery_failed.encode_with(buf, compact);
+    list.undeliverable_here.encode_with(buf, compact);
+    list.message_annotations.encode_with(buf, compact);
 }
 impl DecodeFormatted for Modified {
     fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
@@ -4652,9 +4880,201 @@ impl DecodeFormatted for Modified {
 }
 impl Encode for Modified {
     fn encoded_size(&self) -> usize {
-        encoded_size_modified_inner(self)
+        encoded_size_modified_inner(self, false)
     }
     fn encode(&self, buf: &mut BytesMut) {
-        encode_modified_inner(self, buf)
+        encode_modified_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_modified_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_modified_inner(self, buf, compact)
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct Declared {
+    pub txn_id: Bytes,
+}
+impl Declared {
+    pub fn txn_id(&self) -> &Bytes {
+        &self.txn_id
+    }
+    #[allow(clippy::identity_op)]
+    const FIELD_COUNT: usize = 0 + 1;
+}
+#[allow(unused_mut)]
+fn decode_declared_inner(input: &[u8]) -> Result<(&[u8], Declared), AmqpParseError> {
+    let (input, format) = decode_format_code(input)?;
+    let (input, header) = decode_list_header(input, format)?;
+    let size = header.size as usize;
+    decode_check_len!(input, size);
+    let (mut input, mut remainder) = input.split_at(size);
+    let mut count = header.count;
+    let txn_id: Bytes;
+    if count > 0 {
+        let (in1, decoded) = Bytes::decode(input)?;
+        txn_id = decoded;
+        input = in1;
+        count -= 1;
+    } else {
+        return Err(AmqpParseError::RequiredFieldOmitted("txn_id"));
+    }
+    Ok((remainder, Declared { txn_id }))
+}
+fn encoded_size_declared_inner(list: &Declared, compact: bool) -> usize {
+    #[allow(clippy::identity_op)]
+    let content_size = 0 + list.txn_id.encoded_size_with(compact);
+    // header: 0x00 0x53 <descriptor code> format_code size count
+    (if content_size + 1 > u8::MAX as usize {
+        12
+    } else {
+        6
+    }) + content_size
+}
+fn encode_declared_inner(list: &Declared, buf: &mut BytesMut, compact: bool) {
+    Descriptor::Ulong(51).encode(buf);
+    #[allow(clippy::identity_op)]
+    let content_size = 0 + list.txn_id.encoded_size_with(compact);
+    if content_size + 1 > u8::MAX as usize {
+        buf.put_u8(codec::FORMATCODE_LIST32);
+        buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
+        buf.put_u32(Declared::FIELD_COUNT as u32);
+    } else {
+        buf.put_u8(codec::FORMATCODE_LIST8);
+        buf.put_u8((content_size + 1) as u8);
+        buf.put_u8(Declared::FIELD_COUNT as u8);
+    }
+    list.txn_id.encode_with(buf, compact);
+}
+impl DecodeFormatted for Declared {
+    fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
+        validate_code!(fmt, codec::FORMATCODE_DESCRIBED);
+        let (input, descriptor) = Descriptor::decode(input)?;
+        let is_match = match descriptor {
+            Descriptor::Ulong(val) => val == 51,
+            Descriptor::Symbol(ref sym) => sym.as_bytes() == b"amqp:declared:list",
+        };
+        if !is_match {
+            Err(AmqpParseError::InvalidDescriptor(descriptor))
+        } else {
+            decode_declared_inner(input)
+        }
+    }
+}
+impl Encode for Declared {
+    fn encoded_size(&self) -> usize {
+        encoded_size_declared_inner(self, false)
+    }
+    fn encode(&self, buf: &mut BytesMut) {
+        encode_declared_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_declared_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_declared_inner(self, buf, compact)
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransactionalState {
+    pub txn_id: Bytes,
+    pub outcome: Option<Outcome>,
+}
+impl TransactionalState {
+    pub fn txn_id(&self) -> &Bytes {
+        &self.txn_id
+    }
+    pub fn outcome(&self) -> Option<&Outcome> {
+        self.outcome.as_ref()
+    }
+    #[allow(clippy::identity_op)]
+    const FIELD_COUNT: usize = 0 + 1 + 1;
+}
+#[allow(unused_mut)]
+fn decode_transactional_state_inner(
+    input: &[u8],
+) -> Result<(&[u8], TransactionalState), AmqpParseError> {
+    let (input, format) = decode_format_code(input)?;
+    let (input, header) = decode_list_header(input, format)?;
+    let size = header.size as usize;
+    decode_check_len!(input, size);
+    let (mut input, mut remainder) = input.split_at(size);
+    let mut count = header.count;
+    let txn_id: Bytes;
+    if count > 0 {
+        let (in1, decoded) = Bytes::decode(input)?;
+        txn_id = decoded;
+        input = in1;
+        count -= 1;
+    } else {
+        return Err(AmqpParseError::RequiredFieldOmitted("txn_id"));
+    }
+    let outcome: Option<Outcome>;
+    if count > 0 {
+        let decoded = Option::<Outcome>::decode(input)?;
+        input = decoded.0;
+        outcome = decoded.1;
+        count -= 1;
+    } else {
+        outcome = None;
+    }
+    Ok((remainder, TransactionalState { txn_id, outcome }))
+}
+fn encoded_size_transactional_state_inner(list: &TransactionalState, compact: bool) -> usize {
+    #[allow(clippy::identity_op)]
+    let content_size =
+        0 + list.txn_id.encoded_size_with(compact) + list.outcome.encoded_size_with(compact);
+    // header: 0x00 0x53 <descriptor code> format_code size count
+    (if content_size + 1 > u8::MAX as usize {
+        12
+    } else {
+        6
+    }) + content_size
+}
+fn encode_transactional_state_inner(list: &TransactionalState, buf: &mut BytesMut, compact: bool) {
+    Descriptor::Ulong(52).encode(buf);
+    #[allow(clippy::identity_op)]
+    let content_size =
+        0 + list.txn_id.encoded_size_with(compact) + list.outcome.encoded_size_with(compact);
+    if content_size + 1 > u8::MAX as usize {
+        buf.put_u8(codec::FORMATCODE_LIST32);
+        buf.put_u32((content_size + 4) as u32); // +4 for 4 byte count
+        buf.put_u32(TransactionalState::FIELD_COUNT as u32);
+    } else {
+        buf.put_u8(codec::FORMATCODE_LIST8);
+        buf.put_u8((content_size + 1) as u8);
+        buf.put_u8(TransactionalState::FIELD_COUNT as u8);
+    }
+    list.txn_id.encode_with(buf, compact);
+    list.outcome.encode_with(buf, compact);
+}
+impl DecodeFormatted for TransactionalState {
+    fn decode_with_format(input: &[u8], fmt: u8) -> Result<(&[u8], Self), AmqpParseError> {
+        validate_code!(fmt, codec::FORMATCODE_DESCRIBED);
+        let (input, descriptor) = Descriptor::decode(input)?;
+        let is_match = match descriptor {
+            Descriptor::Ulong(val) => val == 52,
+            Descriptor::Symbol(ref sym) => sym.as_bytes() == b"amqp:transactional-state:list",
+        };
+        if !is_match {
+            Err(AmqpParseError::InvalidDescriptor(descriptor))
+        } else {
+            decode_transactional_state_inner(input)
+        }
+    }
+}
+impl Encode for TransactionalState {
+    fn encoded_size(&self) -> usize {
+        encoded_size_transactional_state_inner(self, false)
+    }
+    fn encode(&self, buf: &mut BytesMut) {
+        encode_transactional_state_inner(self, buf, false)
+    }
+    fn encoded_size_with(&self, compact: bool) -> usize {
+        encoded_size_transactional_state_inner(self, compact)
+    }
+    fn encode_with(&self, buf: &mut BytesMut, compact: bool) {
+        encode_transactional_state_inner(self, buf, compact)
     }
 }
@@ -5,7 +5,7 @@ use derive_more::From;
 use ntex_bytes::{BufMut, ByteString, Bytes, BytesMut};
 use uuid::Uuid;
 
-use super::codec::{self, DecodeFormatted, Encode};
+use super::codec::{self, Decode, DecodeFormatted, Encode};
 use super::error::AmqpParseError;
 use super::message::Message;
 use super::types::*;
@@ -53,6 +53,47 @@ pub type Annotations = HashMap<Symbol, Variant>;
 mod definitions;
 pub use self::definitions::*;
 
+/// Frame with unrecognized performative descriptor
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownFrame {
+    /// Performative descriptor
+    pub descriptor: Descriptor,
+    /// Performative value
+    pub value: Variant,
+    /// Frame payload that follows performative
+    pub payload: Bytes,
+}
+
+impl UnknownFrame {
+    pub(crate) fn decode_inner(
+        descriptor: Descriptor,
+        input: &[u8],
+    ) -> Result<(&[u8], Self), AmqpParseError> {
+        let (input, value) = Variant::decode(input)?;
+        let payload = Bytes::copy_from_slice(input);
+        Ok((
+            &input[input.len()..],
+            UnknownFrame {
+                descriptor,
+                value,
+                payload,
+            },
+        ))
+    }
+}
+
+impl Encode for UnknownFrame {
+    fn encoded_size(&self) -> usize {
+        self.descriptor.encoded_size() + self.value.encoded_size() + self.payload.len()
+    }
+
+    fn encode(&self, buf: &mut BytesMut) {
+        self.descriptor.encode(buf);
+        self.value.encode(buf);
+        buf.put_slice(&self.payload);
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Clone, From, Display)]
 pub enum MessageId {
     #[display(fmt = "{}", _0)]
@@ -237,6 +278,14 @@ pub enum TransferBody {
     Message(Box<Message>),
 }
 
+impl Transfer {
+    #[inline]
+    /// Check if delivery is settled by sender
+    pub fn is_settled(&self) -> bool {
+        self.settled == Some(true)
+    }
+}
+
 impl TransferBody {
     #[inline]
     pub fn len(&self) -> usize {
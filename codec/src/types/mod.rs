@@ -2,9 +2,11 @@ use std::{borrow, fmt, hash, ops, str};
 
 use ntex_bytes::ByteString;
 
+mod registry;
 mod symbol;
 mod variant;
 
+pub use self::registry::DescribedRegistry;
 pub use self::symbol::{StaticSymbol, Symbol};
 pub use self::variant::{Variant, VariantMap, VecStringMap, VecSymbolMap};
 
@@ -188,6 +190,12 @@ impl PartialEq<str> for Str {
     }
 }
 
+impl PartialEq<&str> for Str {
+    fn eq(&self, other: &&str) -> bool {
+        *self == **other
+    }
+}
+
 impl fmt::Debug for Str {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -0,0 +1,137 @@
+use std::{any::Any, fmt, sync::Arc};
+
+use crate::error::AmqpParseError;
+use crate::types::{Descriptor, Symbol, Variant};
+use crate::HashMap;
+
+type DecodeFn = Arc<dyn Fn(&Variant) -> Result<Box<dyn Any + Send>, AmqpParseError> + Send + Sync>;
+
+/// Registry of described type decoders
+///
+/// Codec decodes unknown described types as `Variant::Described`.
+/// Registry maps descriptor codes and symbolic names to decode functions,
+/// so extensions (broker specific filters, custom sections, etc) could be
+/// converted to typed values.
+#[derive(Clone, Default)]
+pub struct DescribedRegistry {
+    codes: HashMap<u64, DecodeFn>,
+    names: HashMap<Symbol, DecodeFn>,
+}
+
+impl DescribedRegistry {
+    /// Create empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register decoder for described type
+    ///
+    /// Decoder is registered for both descriptor code and symbolic name,
+    /// decode function receives described value.
+    pub fn register<T, F>(mut self, code: u64, name: &str, f: F) -> Self
+    where
+        T: Any + Send,
+        F: Fn(&Variant) -> Result<T, AmqpParseError> + Send + Sync + 'static,
+    {
+        let f: DecodeFn = Arc::new(move |v| f(v).map(|v| Box::new(v) as Box<dyn Any + Send>));
+        self.codes.insert(code, f.clone());
+        self.names.insert(Symbol::from_slice(name), f);
+        self
+    }
+
+    /// Check if registry has decoder for descriptor
+    pub fn contains(&self, descriptor: &Descriptor) -> bool {
+        self.get(descriptor).is_some()
+    }
+
+    /// Decode described value
+    ///
+    /// Returns `None` if value is not described or descriptor is not registered.
+    pub fn decode(&self, value: &Variant) -> Option<Result<Box<dyn Any + Send>, AmqpParseError>> {
+        if let Variant::Described((descriptor, value)) = value {
+            self.get(descriptor).map(|f| f(value))
+        } else {
+            None
+        }
+    }
+
+    /// Decode described value to specific type
+    ///
+    /// Returns `None` if value is not described or descriptor is not registered.
+    pub fn decode_as<T: Any>(&self, value: &Variant) -> Option<Result<T, AmqpParseError>> {
+        self.decode(value).map(|res| {
+            res.and_then(|v| {
+                v.downcast::<T>()
+                    .map(|v| *v)
+                    .map_err(|_| AmqpParseError::UnexpectedType(std::any::type_name::<T>()))
+            })
+        })
+    }
+
+    fn get(&self, descriptor: &Descriptor) -> Option<&DecodeFn> {
+        match descriptor {
+            Descriptor::Ulong(code) => self.codes.get(code),
+            Descriptor::Symbol(name) => self.names.get(name.as_str()),
+        }
+    }
+}
+
+impl fmt::Debug for DescribedRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DescribedRegistry")
+            .field("codes", &self.codes.keys().collect::<Vec<_>>())
+            .field("names", &self.names.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex_bytes::{Bytes, BytesMut};
+
+    use super::*;
+    use crate::codec::{Decode, Encode};
+
+    #[derive(Debug, PartialEq)]
+    struct Selector(String);
+
+    fn selector(v: &Variant) -> Result<Selector, AmqpParseError> {
+        v.as_str()
+            .map(|s| Selector(s.to_string()))
+            .ok_or(AmqpParseError::UnexpectedType("string"))
+    }
+
+    #[test]
+    fn test_registry() {
+        let registry = DescribedRegistry::new().register(
+            0x0000_468c_0000_0004,
+            "apache.org:selector-filter:string",
+            selector,
+        );
+
+        let value = Variant::Described((
+            Descriptor::Symbol("apache.org:selector-filter:string".into()),
+            Box::new(Variant::from("color = 'red'")),
+        ));
+        let mut buf = BytesMut::with_capacity(value.encoded_size());
+        value.encode(&mut buf);
+        let (_, decoded) = Variant::decode(&Bytes::from(buf)).unwrap();
+
+        assert!(registry.contains(&Descriptor::Ulong(0x0000_468c_0000_0004)));
+        assert_eq!(
+            registry.decode_as::<Selector>(&decoded).unwrap().unwrap(),
+            Selector("color = 'red'".to_string())
+        );
+        assert!(registry.decode_as::<u32>(&decoded).unwrap().is_err());
+
+        let value = Variant::Described((
+            Descriptor::Ulong(0x0000_468c_0000_0004),
+            Box::new(Variant::Uint(1)),
+        ));
+        assert!(registry.decode(&value).unwrap().is_err());
+
+        let value = Variant::Described((Descriptor::Ulong(1), Box::new(Variant::Null)));
+        assert!(registry.decode(&value).is_none());
+        assert!(registry.decode(&Variant::Null).is_none());
+    }
+}
@@ -69,6 +69,24 @@ impl PartialEq<str> for Symbol {
     }
 }
 
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == **other
+    }
+}
+
+impl PartialEq<ByteString> for Symbol {
+    fn eq(&self, other: &ByteString) -> bool {
+        self.0 == *other.as_ref()
+    }
+}
+
+impl PartialEq<Symbol> for str {
+    fn eq(&self, other: &Symbol) -> bool {
+        other.0 == *self
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Display)]
 pub struct StaticSymbol(pub &'static str);
 
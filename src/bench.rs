@@ -0,0 +1,247 @@
+//! Throughput baseline tools
+//!
+//! `sink()` is a link service that accepts every link and settles all
+//! deliveries as accepted. `LoadGenerator` opens sender links on a session
+//! and publishes messages of configured size and rate.
+use std::{future::Future, time::Duration, time::Instant};
+
+use ntex::channel::mpsc;
+use ntex::rt::time::sleep;
+use ntex::service::{fn_factory_with_config, fn_service, ServiceFactory};
+use ntex::util::{next, ByteString, Bytes, Ready};
+
+use crate::codec::protocol::{Error, SenderSettleMode};
+use crate::error::AmqpProtocolError;
+use crate::types::{Link, Outcome};
+use crate::{SenderLink, Session, State};
+
+/// Link service that accepts and discards all deliveries
+///
+/// Links are attached with `credit` and credit is replenished
+/// once half of it is consumed.
+pub fn sink<S: 'static>(
+    credit: u32,
+) -> impl ServiceFactory<
+    Config = State<S>,
+    Request = Link<S>,
+    Response = (),
+    Error = Error,
+    InitError = std::convert::Infallible,
+> {
+    let credit = std::cmp::max(credit, 2);
+    fn_factory_with_config(move |_: State<S>| {
+        Ready::Ok(fn_service(move |link: Link<S>| async move {
+            let mut rcv = link.receiver().clone();
+            rcv.open();
+            rcv.set_link_credit(credit);
+
+            let mut received = 0;
+            while let Some(Ok(transfer)) = next(&mut rcv).await {
+                if !transfer.settled.unwrap_or(false) {
+                    rcv.settle(&transfer, Outcome::Accepted);
+                }
+                if !transfer.more {
+                    received += 1;
+                    if received == credit / 2 {
+                        received = 0;
+                        rcv.add_credit(credit / 2);
+                    }
+                }
+            }
+            Ok::<_, Error>(())
+        }))
+    })
+}
+
+/// Load generator
+///
+/// Each link publishes messages sequentially with up to `window`
+/// unsettled deliveries in flight. Pre-settled deliveries are counted
+/// as accepted once sent.
+#[derive(Debug, Clone)]
+pub struct LoadGenerator {
+    address: ByteString,
+    message_size: usize,
+    rate: Option<u32>,
+    window: usize,
+    links: usize,
+    messages: usize,
+    duration: Option<Duration>,
+    settle_mode: Option<SenderSettleMode>,
+}
+
+/// Load generation results
+#[derive(Debug, Default, Clone)]
+pub struct LoadReport {
+    /// Number of sent messages
+    pub sent: usize,
+    /// Number of accepted messages
+    pub accepted: usize,
+    /// Number of messages with non accepted outcome
+    pub failed: usize,
+    /// Total run time
+    pub elapsed: Duration,
+}
+
+impl LoadReport {
+    /// Accepted messages per second
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.accepted as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    fn add(&mut self, outcome: Outcome) {
+        if outcome.is_accepted() || matches!(outcome, Outcome::Settled) {
+            self.accepted += 1;
+        } else {
+            self.failed += 1;
+        }
+    }
+}
+
+impl LoadGenerator {
+    /// Create load generator for target address
+    ///
+    /// By default one link publishes 10000 messages of 256 bytes
+    /// with window of 64 deliveries and without rate limit.
+    pub fn new<T: Into<ByteString>>(address: T) -> Self {
+        LoadGenerator {
+            address: address.into(),
+            message_size: 256,
+            rate: None,
+            window: 64,
+            links: 1,
+            messages: 10_000,
+            duration: None,
+            settle_mode: None,
+        }
+    }
+
+    /// Set message body size in bytes
+    pub fn message_size(mut self, size: usize) -> Self {
+        self.message_size = size;
+        self
+    }
+
+    /// Set max number of messages per second for each link
+    pub fn rate(mut self, rate: u32) -> Self {
+        self.rate = if rate == 0 { None } else { Some(rate) };
+        self
+    }
+
+    /// Set max number of unsettled deliveries for each link
+    ///
+    /// Window of `1` waits for outcome of every delivery.
+    pub fn window(mut self, window: usize) -> Self {
+        self.window = std::cmp::max(window, 1);
+        self
+    }
+
+    /// Set number of sender links
+    pub fn links(mut self, links: usize) -> Self {
+        self.links = std::cmp::max(links, 1);
+        self
+    }
+
+    /// Set number of messages for each link
+    pub fn messages(mut self, messages: usize) -> Self {
+        self.messages = messages;
+        self
+    }
+
+    /// Stop publishing after duration
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Set settlement mode of sender links
+    ///
+    /// `SenderSettleMode::Settled` publishes pre-settled deliveries.
+    /// By default links use connection's settlement modes.
+    pub fn settle_mode(mut self, mode: SenderSettleMode) -> Self {
+        self.settle_mode = Some(mode);
+        self
+    }
+
+    /// Run load on session
+    pub async fn run(&self, session: &mut Session) -> Result<LoadReport, AmqpProtocolError> {
+        let mut links = Vec::with_capacity(self.links);
+        for idx in 0..self.links {
+            let name = ByteString::from(format!("load-{}-{}", self.address, idx));
+            let mut builder = session.build_sender_link(name, self.address.clone());
+            if let Some(mode) = self.settle_mode {
+                builder = builder.with_frame(|frame| frame.snd_settle_mode = mode);
+            }
+            let link = builder.open().await?;
+            links.push(link);
+        }
+
+        let start = Instant::now();
+        let body = Bytes::from(vec![0u8; self.message_size]);
+        let (tx, mut rx) = mpsc::channel();
+        for link in links {
+            let tx = tx.clone();
+            let fut = self.publish(link, body.clone(), start);
+            ntex::rt::spawn(async move {
+                let _ = tx.send(fut.await);
+            });
+        }
+        drop(tx);
+
+        let mut report = LoadReport::default();
+        while let Some(res) = next(&mut rx).await {
+            let res = res?;
+            report.sent += res.sent;
+            report.accepted += res.accepted;
+            report.failed += res.failed;
+        }
+        report.elapsed = start.elapsed();
+        Ok(report)
+    }
+
+    fn publish(
+        &self,
+        link: SenderLink,
+        body: Bytes,
+        start: Instant,
+    ) -> impl Future<Output = Result<LoadReport, AmqpProtocolError>> {
+        let cfg = self.clone();
+        async move {
+            let interval = cfg.rate.map(|rate| Duration::from_secs(1) / rate);
+            let mut report = LoadReport::default();
+            let mut inflight = Vec::with_capacity(cfg.window);
+
+            while report.sent < cfg.messages {
+                if matches!(cfg.duration, Some(d) if start.elapsed() >= d) {
+                    break;
+                }
+                inflight.push(link.send(body.clone()));
+                report.sent += 1;
+
+                if inflight.len() >= cfg.window {
+                    for fut in inflight.drain(..) {
+                        report.add(fut.await?);
+                    }
+                }
+                if let Some(interval) = interval {
+                    let next = interval * report.sent as u32;
+                    let elapsed = start.elapsed();
+                    if next > elapsed {
+                        sleep(next - elapsed).await;
+                    }
+                }
+            }
+            for fut in inflight.drain(..) {
+                report.add(fut.await?);
+            }
+            let _ = link.close().await;
+            report.elapsed = start.elapsed();
+            Ok(report)
+        }
+    }
+}
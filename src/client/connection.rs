@@ -1,11 +1,13 @@
-use ntex::codec::{AsyncRead, AsyncWrite};
+use std::fmt;
+
+use ntex::codec::{AsyncRead, AsyncWrite, Framed};
 use ntex::framed::{Dispatcher as IoDispatcher, State as IoState, Timer};
-use ntex::service::{fn_service, Service};
+use ntex::service::{fn_service, IntoService, Service};
 use ntex::util::Ready;
 
 use crate::codec::{AmqpCodec, AmqpFrame};
-use crate::error::{DispatcherError, LinkError};
-use crate::{dispatcher::Dispatcher, Configuration, Connection, State};
+use crate::error::{DispatcherError, Error, LinkError};
+use crate::{dispatcher::Dispatcher, types::Link, Configuration, Connection, ControlFrame, State};
 
 /// Mqtt client
 pub struct Client<Io, St = ()> {
@@ -34,6 +36,7 @@ where
         timer: Timer,
     ) -> Self {
         Client {
+            st: State::new((), &connection),
             io,
             state,
             codec,
@@ -41,7 +44,6 @@ where
             keepalive,
             remote_config,
             timer,
-            st: State::new(()),
         }
     }
 }
@@ -57,10 +59,30 @@ where
         self.connection.clone()
     }
 
+    #[inline]
+    /// Get remote connection configuration
+    pub fn remote_config(&self) -> &Configuration {
+        &self.remote_config
+    }
+
+    /// Take over connection's framed transport.
+    ///
+    /// Returns framed transport and remote configuration received
+    /// in `Open` frame. Framed transport implements `Sink` and `Stream`
+    /// for amqp frames, buffered data is preserved. Connection sink
+    /// becomes unusable, all frames must be handled by the caller.
+    pub fn into_framed_parts(self) -> (Framed<Io, AmqpCodec<AmqpFrame>>, Configuration) {
+        (
+            self.state.into_framed(self.io, self.codec),
+            self.remote_config,
+        )
+    }
+
     #[inline]
     /// Set connection state
     pub fn state<T: 'static>(self, st: T) -> Client<Io, T> {
         Client {
+            st: State::new(st, &self.connection),
             io: self.io,
             state: self.state,
             codec: self.codec,
@@ -68,7 +90,6 @@ where
             keepalive: self.keepalive,
             remote_config: self.remote_config,
             timer: self.timer,
-            st: State::new(st),
         }
     }
 
@@ -93,4 +114,38 @@ where
             })
             .await
     }
+
+    /// Run client with provided link and control services.
+    ///
+    /// Sessions and links initiated by the peer are not rejected, remote
+    /// `Begin` is reported to control service with `SessionBegin` control
+    /// frame, control service error ends the session. Links attached by
+    /// the peer on any session are passed to link service.
+    pub async fn start<F, S, C, Ctl>(self, service: F, control: C) -> Result<(), DispatcherError>
+    where
+        F: IntoService<S>,
+        S: Service<Request = Link<St>, Response = ()> + 'static,
+        S::Error: fmt::Debug + 'static,
+        C: IntoService<Ctl>,
+        Ctl: Service<Request = ControlFrame, Response = ()> + 'static,
+        Ctl::Error: fmt::Debug + 'static,
+        Error: From<S::Error> + From<Ctl::Error>,
+    {
+        let dispatcher = Dispatcher::new(
+            self.st,
+            self.connection,
+            service.into_service(),
+            control.into_service(),
+            self.remote_config.timeout_remote_secs(),
+        )
+        .map(|_| Option::<AmqpFrame>::None);
+
+        IoDispatcher::new(self.io, self.codec, self.state, dispatcher, self.timer)
+            .keepalive_timeout(if self.keepalive != 0 {
+                self.keepalive + 5
+            } else {
+                0
+            })
+            .await
+    }
 }
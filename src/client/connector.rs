@@ -1,22 +1,34 @@
-use std::{future::Future, marker::PhantomData, time::Duration};
+use std::task::{Context, Poll};
+use std::{future::Future, marker::PhantomData, pin::Pin, rc::Rc, time::Duration};
 
 use ntex::codec::{AsyncRead, AsyncWrite};
 use ntex::connect::{self, Address, Connect};
 use ntex::framed::{State, Timer};
 use ntex::rt::time::delay_for;
-use ntex::service::Service;
-use ntex::util::{select, ByteString, Either};
+use ntex::service::{Service, ServiceFactory};
+use ntex::util::{select, ByteString, Either, Ready};
 
 #[cfg(feature = "openssl")]
-use ntex::connect::openssl::{OpensslConnector, SslConnector};
+use ntex::connect::openssl::SslConnector;
+
+#[cfg(feature = "openssl")]
+use super::pinning::{PinnedConnector, SpkiPins};
+#[cfg(feature = "openssl")]
+use super::tls::OpensslConnector;
 
 #[cfg(feature = "rustls")]
-use ntex::connect::rustls::{ClientConfig, RustlsConnector};
+use super::tls::RustlsConnector;
+#[cfg(feature = "rustls")]
+use ntex::connect::rustls::ClientConfig;
 
-use crate::codec::protocol::{Frame, Milliseconds, ProtocolId, SaslCode, SaslFrameBody, SaslInit};
-use crate::codec::{types::Symbol, AmqpCodec, AmqpFrame, ProtocolIdCodec, SaslFrame};
-use crate::{error::ProtocolIdError, Configuration, Connection};
+use crate::codec::protocol::Milliseconds;
+use crate::negotiate::{open_connection, sasl_client};
+use crate::retry::{retry, RetryPolicy, RetryableError};
+use crate::{Configuration, Connection, SocketOptions};
 
+use super::failover::{EndpointTier, Failover};
+use super::resolver::{Resolver, ResolverConnector};
+use super::tls::TlsTimeouts;
 use super::{connection::Client, error::ConnectError, SaslAuth};
 
 /// Amqp client connector
@@ -24,10 +36,17 @@ pub struct Connector<A, T> {
     connector: T,
     config: Configuration,
     handshake_timeout: u16,
+    connect_timeout: u16,
+    tls_handshake_timeout: u16,
+    tls: Option<TlsTimeouts>,
+    timeouts: Timeouts,
     disconnect_timeout: u16,
     lw: u16,
     read_hw: u16,
     write_hw: u16,
+    socket_options: Option<SocketOptions>,
+    retry: Option<Rc<dyn RetryPolicy>>,
+    pub(super) reconnect: Option<Rc<dyn RetryPolicy>>,
     timer: Timer,
     _t: PhantomData<A>,
 }
@@ -39,11 +58,18 @@ impl<A> Connector<A, ()> {
         Connector {
             connector: connect::Connector::default(),
             handshake_timeout: 0,
+            connect_timeout: 0,
+            tls_handshake_timeout: 0,
+            tls: None,
+            timeouts: Timeouts::default(),
             disconnect_timeout: 3,
             lw: 1024,
             read_hw: 8 * 1024,
             write_hw: 8 * 1024,
             config: Configuration::default(),
+            socket_options: None,
+            retry: None,
+            reconnect: None,
             timer: Timer::with(Duration::from_secs(1)),
             _t: PhantomData,
         }
@@ -95,6 +121,139 @@ where
         self
     }
 
+    /// Set container id
+    ///
+    /// By default random container id is generated for each connection
+    pub fn container_id(&mut self, id: &str) -> &mut Self {
+        self.config.container_id(id);
+        self
+    }
+
+    /// Set human-readable connection name
+    ///
+    /// Name is sent to the peer as `connection-name` connection property
+    pub fn connection_name(&mut self, name: &str) -> &mut Self {
+        self.config.connection_name(name);
+        self
+    }
+
+    /// Enable session incoming-window auto-tuning
+    ///
+    /// By default incoming window is not limited
+    pub fn session_window(&mut self, min: u32, max: u32) -> &mut Self {
+        self.config.session_window(min, max);
+        self
+    }
+
+    /// Set max number of outgoing transfers queued per sender link
+    ///
+    /// By default queue is not limited
+    pub fn max_pending_transfers(&mut self, num: usize) -> &mut Self {
+        self.config.max_pending_transfers(num);
+        self
+    }
+
+    /// Set max number of incoming transfers queued per receiver link
+    ///
+    /// By default queue is not limited
+    pub fn max_queued_transfers(&mut self, num: usize) -> &mut Self {
+        self.config.max_queued_transfers(num);
+        self
+    }
+
+    /// Set settlement timeout for deliveries
+    ///
+    /// By default timeout is not set
+    pub fn settlement_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.config.settlement_timeout(timeout);
+        self
+    }
+
+    /// Set credit starvation threshold for sender links
+    ///
+    /// By default starvation is not reported
+    pub fn starvation_threshold(&mut self, threshold: Duration) -> &mut Self {
+        self.config.starvation_threshold(threshold);
+        self
+    }
+
+    /// Batch outgoing dispositions, see `Configuration::disposition_batch()`
+    pub fn disposition_batch(&mut self, window: Duration, max: usize) -> &mut Self {
+        self.config.disposition_batch(window, max);
+        self
+    }
+
+    /// Set attach timeout for locally opened links
+    ///
+    /// By default timeout is not set
+    pub fn attach_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.config.attach_timeout(timeout);
+        self
+    }
+
+    /// Schedule queued transfers of sessions in round-robin order,
+    /// see `Configuration::session_quantum()`
+    pub fn session_quantum(&mut self, quantum: usize) -> &mut Self {
+        self.config.session_quantum(quantum);
+        self
+    }
+
+    /// Set number of bytes captured from first malformed frame.
+    ///
+    /// By default 64 bytes are captured, `0` disables capture.
+    pub fn malformed_capture(&mut self, size: usize) -> &mut Self {
+        self.config.malformed_capture(size);
+        self
+    }
+
+    /// Prefer compact encodings, see `Configuration::compact_encoding()`
+    pub fn compact_encoding(&mut self, val: bool) -> &mut Self {
+        self.config.compact_encoding(val);
+        self
+    }
+
+    /// Open each link in its own session, see `Configuration::session_per_link()`
+    pub fn session_per_link(&mut self, val: bool) -> &mut Self {
+        self.config.session_per_link(val);
+        self
+    }
+
+    /// Pass session flows to control service, see `Configuration::session_flow_events()`
+    pub fn session_flow_events(&mut self, val: bool) -> &mut Self {
+        self.config.session_flow_events(val);
+        self
+    }
+
+    /// Resume sender links, see `Configuration::link_resume()`
+    pub fn link_resume(&mut self, val: bool) -> &mut Self {
+        self.config.link_resume(val);
+        self
+    }
+
+    /// Use scram sasl mechanisms, see `Configuration::sasl_scram()`
+    pub fn sasl_scram(&mut self, val: bool) -> &mut Self {
+        self.config.sasl_scram(val);
+        self
+    }
+
+    /// Use at-most-once delivery preset, see `Configuration::at_most_once()`
+    pub fn at_most_once(&mut self, prefetch: u32) -> &mut Self {
+        self.config.at_most_once(prefetch);
+        self
+    }
+
+    /// Use at-least-once delivery preset, see `Configuration::at_least_once()`
+    pub fn at_least_once(&mut self, prefetch: u32) -> &mut Self {
+        self.config.at_least_once(prefetch);
+        self
+    }
+
+    /// Use exactly-once delivery preset, see `Configuration::exactly_once()`
+    pub fn exactly_once(&mut self, prefetch: u32) -> &mut Self {
+        self.config.exactly_once(prefetch);
+        self
+    }
+
     /// Set handshake timeout in milliseconds.
     ///
     /// Handshake includes `connect` packet and response `connect-ack`.
@@ -104,6 +263,53 @@ where
         self
     }
 
+    /// Set connect timeout in milliseconds.
+    ///
+    /// Connect timeout covers connector service call. For tls connectors
+    /// it covers tcp connect only, see `tls_handshake_timeout()`.
+    /// By default connect timeout is disabled.
+    pub fn connect_timeout(mut self, timeout: u16) -> Self {
+        self.connect_timeout = timeout;
+        if let Some(ref tls) = self.tls {
+            tls.set_connect(timeout);
+        }
+        self
+    }
+
+    /// Set tls handshake timeout in milliseconds.
+    ///
+    /// Tls handshake timeout applies to `openssl()`, `openssl_pinned()`
+    /// and `rustls()` connectors, connect fails with
+    /// `ConnectError::TlsHandshakeTimeout`.
+    /// By default tls handshake timeout is disabled.
+    pub fn tls_handshake_timeout(mut self, timeout: u16) -> Self {
+        self.tls_handshake_timeout = timeout;
+        if let Some(ref tls) = self.tls {
+            tls.set_handshake(timeout);
+        }
+        self
+    }
+
+    /// Set sasl exchange timeout in milliseconds.
+    ///
+    /// Sasl exchange includes sasl protocol header negotiation and
+    /// sasl frames exchange up to `sasl-outcome`.
+    /// By default sasl timeout is disabled.
+    pub fn sasl_timeout(mut self, timeout: u16) -> Self {
+        self.timeouts.sasl = timeout;
+        self
+    }
+
+    /// Set open exchange timeout in milliseconds.
+    ///
+    /// Open exchange includes amqp protocol header negotiation and
+    /// `open` frames exchange.
+    /// By default open timeout is disabled.
+    pub fn open_timeout(mut self, timeout: u16) -> Self {
+        self.timeouts.open = timeout;
+        self
+    }
+
     /// Set client connection disconnect timeout in milliseconds.
     ///
     /// Defines a timeout for disconnect connection. If a disconnect procedure does not complete
@@ -133,6 +339,37 @@ where
         self
     }
 
+    /// Set tcp socket options
+    ///
+    /// Options are applied only if io object is `TcpStream`
+    pub fn socket_options(mut self, options: SocketOptions) -> Self {
+        self.socket_options = Some(options);
+        self
+    }
+
+    /// Set retry policy
+    ///
+    /// Policy is used by `connect_with_retry()` and `connect_sasl_with_retry()`,
+    /// and for link attach and send operations of established connection.
+    /// By default operations are not retried.
+    pub fn retry_policy<P: RetryPolicy + 'static>(mut self, policy: P) -> Self {
+        self.retry = Some(Rc::new(policy));
+        self
+    }
+
+    /// Set reconnect policy
+    ///
+    /// Policy is used by reconnecting clients, see `connect_reconnecting()`.
+    /// Policy's backoff is applied between reconnect attempts, client
+    /// is closed once attempts are exhausted or error is not retryable.
+    /// Retry policy applies to initial connect only, each reconnect
+    /// attempt is a single connect. By default client reconnects every
+    /// second until it is closed.
+    pub fn reconnect_policy<P: RetryPolicy + 'static>(mut self, policy: P) -> Self {
+        self.reconnect = Some(Rc::new(policy));
+        self
+    }
+
     #[inline]
     #[doc(hidden)]
     #[deprecated(since = "0.4.3")]
@@ -167,47 +404,75 @@ where
             connector,
             config: self.config,
             handshake_timeout: self.handshake_timeout,
+            connect_timeout: self.connect_timeout,
+            tls_handshake_timeout: self.tls_handshake_timeout,
+            tls: None,
+            timeouts: self.timeouts,
             disconnect_timeout: self.disconnect_timeout,
             lw: self.lw,
             read_hw: self.read_hw,
             write_hw: self.write_hw,
+            socket_options: self.socket_options,
+            retry: self.retry,
+            reconnect: self.reconnect,
             timer: self.timer,
             _t: PhantomData,
         }
     }
 
+    /// Use tcp connector with custom dns resolver
+    ///
+    /// Resolved addresses are raced, see `ResolverConnector`.
+    pub fn resolver<R>(self, resolver: R) -> Connector<A, ResolverConnector<A>>
+    where
+        R: Resolver + 'static,
+    {
+        self.connector(ResolverConnector::new(resolver))
+    }
+
     #[cfg(feature = "openssl")]
     /// Use openssl connector
     pub fn openssl(self, connector: SslConnector) -> Connector<A, OpensslConnector<A>> {
-        Connector {
-            config: self.config,
-            connector: OpensslConnector::new(connector),
-            handshake_timeout: self.handshake_timeout,
-            disconnect_timeout: self.disconnect_timeout,
-            lw: self.lw,
-            read_hw: self.read_hw,
-            write_hw: self.write_hw,
-            timer: self.timer,
-            _t: PhantomData,
-        }
+        let connector = OpensslConnector::new(connector);
+        let tls = connector.timeouts().clone();
+        self.tls_connector(connector, tls)
+    }
+
+    #[cfg(feature = "openssl")]
+    /// Use openssl connector with SPKI pin verification
+    ///
+    /// Connection fails with `ConnectError::PinMismatch` if verified
+    /// certificate chain does not match any of `pins`.
+    pub fn openssl_pinned(
+        self,
+        connector: SslConnector,
+        pins: SpkiPins,
+    ) -> Connector<A, PinnedConnector<A>> {
+        let connector = PinnedConnector::new(connector, pins);
+        let tls = connector.timeouts().clone();
+        self.tls_connector(connector, tls)
     }
 
     #[cfg(feature = "rustls")]
     /// Use rustls connector
     pub fn rustls(self, config: ClientConfig) -> Connector<A, RustlsConnector<A>> {
-        use std::sync::Arc;
+        let connector = RustlsConnector::new(std::sync::Arc::new(config));
+        let tls = connector.timeouts().clone();
+        self.tls_connector(connector, tls)
+    }
 
-        Connector {
-            config: self.config,
-            connector: RustlsConnector::new(Arc::new(config)),
-            handshake_timeout: self.handshake_timeout,
-            disconnect_timeout: self.disconnect_timeout,
-            lw: self.lw,
-            read_hw: self.read_hw,
-            write_hw: self.write_hw,
-            timer: self.timer,
-            _t: PhantomData,
-        }
+    #[cfg(any(feature = "openssl", feature = "rustls"))]
+    /// Use tls connector, tcp connect and tls handshake are bounded by connector
+    fn tls_connector<U>(self, connector: U, tls: TlsTimeouts) -> Connector<A, U>
+    where
+        U: Service<Request = Connect<A>, Error = connect::ConnectError>,
+        U::Response: AsyncRead + AsyncWrite + Unpin + 'static,
+    {
+        tls.set_connect(self.connect_timeout);
+        tls.set_handshake(self.tls_handshake_timeout);
+        let mut connector = self.connector(connector);
+        connector.tls = Some(tls);
+        connector
     }
 
     /// Connect to amqp server
@@ -231,6 +496,90 @@ where
         }
     }
 
+    /// Connect to amqp server, retry according to retry policy
+    pub async fn connect_with_retry(&self, address: A) -> Result<Client<T::Response>, ConnectError>
+    where
+        A: Clone,
+    {
+        retry(
+            self.retry.clone(),
+            |err| RetryableError::Connect(err),
+            || self.connect(address.clone()),
+        )
+        .await
+    }
+
+    /// Connect to first available endpoint.
+    ///
+    /// Healthy primary endpoints are tried first, then healthy secondary
+    /// endpoints, unhealthy endpoints are tried last. Returns client and
+    /// tier of connected endpoint, error of last attempt is returned if
+    /// all endpoints fail.
+    pub async fn connect_failover(
+        &self,
+        failover: &Failover<A>,
+    ) -> Result<(Client<T::Response>, EndpointTier), ConnectError>
+    where
+        A: Clone,
+    {
+        self.connect_tiers(failover, EndpointTier::Secondary, None)
+            .await
+            .and_then(|res| res.ok_or(ConnectError::Disconnected))
+    }
+
+    /// Try to fail back to primary region.
+    ///
+    /// Returns new client if one of primary endpoints is available. Caller
+    /// is responsible for moving work to the new connection, durable links
+    /// should be re-attached in the order they were attached originally.
+    /// Reconnecting client fails back automatically, see
+    /// `connect_failover_reconnecting()`.
+    pub async fn fail_back(
+        &self,
+        failover: &Failover<A>,
+    ) -> Result<Option<Client<T::Response>>, ConnectError>
+    where
+        A: Clone,
+    {
+        self.connect_tiers(failover, EndpointTier::Primary, None)
+            .await
+            .map(|res| res.map(|(client, _)| client))
+    }
+
+    pub(super) async fn connect_tiers(
+        &self,
+        failover: &Failover<A>,
+        max_tier: EndpointTier,
+        auth: Option<SaslAuth>,
+    ) -> Result<Option<(Client<T::Response>, EndpointTier)>, ConnectError>
+    where
+        A: Clone,
+    {
+        let mut last_err = None;
+        for (address, tier, idx) in failover.candidates(max_tier) {
+            let res = if let Some(ref auth) = auth {
+                self.connect_sasl(address.clone(), auth.clone()).await
+            } else {
+                self.connect(address.clone()).await
+            };
+            match res {
+                Ok(client) => {
+                    failover.succeeded(idx);
+                    return Ok(Some((client, tier)));
+                }
+                Err(err) => {
+                    log::trace!("Failed to connect to {:?} endpoint: {:?}", tier, err);
+                    failover.failed(idx);
+                    last_err = Some(err);
+                }
+            }
+        }
+        match last_err {
+            Some(err) if max_tier == EndpointTier::Secondary => Err(err),
+            _ => Ok(None),
+        }
+    }
+
     /// Negotiate amqp protocol over opened socket
     pub fn negotiate<Io>(&self, io: Io) -> impl Future<Output = Result<Client<Io>, ConnectError>>
     where
@@ -245,15 +594,37 @@ where
             self.disconnect_timeout,
         );
 
-        _connect_plain(io, state, self.config.clone(), self.timer.clone())
+        _connect_plain(
+            io,
+            state,
+            self.config.clone(),
+            self.timeouts,
+            self.timer.clone(),
+            self.retry.clone(),
+        )
+    }
+
+    /// Connect timeout of connector service call, tls connectors bound
+    /// tcp connect and tls handshake themselves
+    fn transport_timeout(&self) -> u16 {
+        if self.tls.is_some() {
+            0
+        } else {
+            self.connect_timeout
+        }
     }
 
     fn _connect(
         &self,
         address: A,
     ) -> impl Future<Output = Result<Client<T::Response>, ConnectError>> {
-        let fut = self.connector.call(Connect::new(address));
+        let fut = with_timeout(
+            self.transport_timeout(),
+            ConnectError::ConnectTimeout,
+            self.connector.call(Connect::new(address)),
+        );
         let config = self.config.clone();
+        let timeouts = self.timeouts;
         let timer = self.timer.clone();
         let state = State::with_params(
             self.read_hw,
@@ -262,11 +633,17 @@ where
             self.disconnect_timeout,
         );
 
+        let socket_options = self.socket_options.clone();
+        let retry = self.retry.clone();
+
         async move {
             trace!("Negotiation client protocol id: Amqp");
 
             let io = fut.await?;
-            _connect_plain(io, state, config, timer).await
+            if let Some(options) = socket_options {
+                options.apply(&io);
+            }
+            _connect_plain(io, state, config, timeouts, timer, retry).await
         }
     }
 
@@ -292,6 +669,23 @@ where
         }
     }
 
+    /// Connect to amqp server with sasl auth, retry according to retry policy
+    pub async fn connect_sasl_with_retry(
+        &self,
+        addr: A,
+        auth: SaslAuth,
+    ) -> Result<Client<T::Response>, ConnectError>
+    where
+        A: Clone,
+    {
+        retry(
+            self.retry.clone(),
+            |err| RetryableError::Connect(err),
+            || self.connect_sasl(addr.clone(), auth.clone()),
+        )
+        .await
+    }
+
     /// Negotiate amqp sasl protocol over opened socket
     pub fn negotiate_sasl<Io>(
         &self,
@@ -312,7 +706,15 @@ where
             self.disconnect_timeout,
         );
 
-        _connect_sasl(io, state, auth, config, timer)
+        _connect_sasl(
+            io,
+            state,
+            auth,
+            config,
+            self.timeouts,
+            timer,
+            self.retry.clone(),
+        )
     }
 
     fn _connect_sasl(
@@ -320,8 +722,13 @@ where
         addr: A,
         auth: SaslAuth,
     ) -> impl Future<Output = Result<Client<T::Response>, ConnectError>> {
-        let fut = self.connector.call(Connect::new(addr));
+        let fut = with_timeout(
+            self.transport_timeout(),
+            ConnectError::ConnectTimeout,
+            self.connector.call(Connect::new(addr)),
+        );
         let config = self.config.clone();
+        let timeouts = self.timeouts;
         let timer = self.timer.clone();
         let state = State::with_params(
             self.read_hw,
@@ -330,151 +737,191 @@ where
             self.disconnect_timeout,
         );
 
-        async move { _connect_sasl(fut.await?, state, auth, config, timer).await }
+        let socket_options = self.socket_options.clone();
+        let retry = self.retry.clone();
+
+        async move {
+            let io = fut.await?;
+            if let Some(options) = socket_options {
+                options.apply(&io);
+            }
+            _connect_sasl(io, state, auth, config, timeouts, timer, retry).await
+        }
     }
 }
 
-async fn _connect_sasl<T>(
-    mut io: T,
-    state: State,
-    auth: SaslAuth,
-    config: Configuration,
-    timer: Timer,
-) -> Result<Client<T>, ConnectError>
-where
-    T: AsyncRead + AsyncWrite + Unpin + 'static,
-{
-    trace!("Negotiation client protocol id: AmqpSasl");
+impl<A, T: Clone> Clone for Connector<A, T> {
+    fn clone(&self) -> Self {
+        Connector {
+            connector: self.connector.clone(),
+            config: self.config.clone(),
+            handshake_timeout: self.handshake_timeout,
+            connect_timeout: self.connect_timeout,
+            tls_handshake_timeout: self.tls_handshake_timeout,
+            tls: self.tls.clone(),
+            timeouts: self.timeouts,
+            disconnect_timeout: self.disconnect_timeout,
+            lw: self.lw,
+            read_hw: self.read_hw,
+            write_hw: self.write_hw,
+            socket_options: self.socket_options.clone(),
+            retry: self.retry.clone(),
+            reconnect: self.reconnect.clone(),
+            timer: self.timer.clone(),
+            _t: PhantomData,
+        }
+    }
+}
 
-    state
-        .send(&mut io, &ProtocolIdCodec, ProtocolId::AmqpSasl)
-        .await?;
+/// Connect request for connector service
+#[derive(Debug, Clone)]
+pub struct ConnectRequest<A> {
+    address: A,
+    auth: Option<SaslAuth>,
+}
 
-    let proto = state
-        .next(&mut io, &ProtocolIdCodec)
-        .await
-        .map_err(ConnectError::from)
-        .and_then(|res| {
-            res.ok_or_else(|| {
-                log::trace!("Amqp server is disconnected during handshake");
-                ConnectError::Disconnected
-            })
-        })?;
-    if proto != ProtocolId::AmqpSasl {
-        return Err(ConnectError::from(ProtocolIdError::Unexpected {
-            exp: ProtocolId::AmqpSasl,
-            got: proto,
-        }));
+impl<A> ConnectRequest<A> {
+    /// Create request for plain amqp connection
+    pub fn new(address: A) -> Self {
+        ConnectRequest {
+            address,
+            auth: None,
+        }
     }
 
-    let codec = AmqpCodec::<SaslFrame>::new();
-
-    // processing sasl-mechanisms
-    let _ = state
-        .next(&mut io, &codec)
-        .await
-        .map_err(ConnectError::from)
-        .and_then(|res| res.ok_or(ConnectError::Disconnected))?;
+    /// Use sasl authentication
+    pub fn sasl(mut self, auth: SaslAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
 
-    let initial_response =
-        SaslInit::prepare_response(&auth.authz_id, &auth.authn_id, &auth.password);
+    /// Remote address
+    pub fn address(&self) -> &A {
+        &self.address
+    }
+}
 
-    let sasl_init = SaslInit {
-        hostname: config.hostname.clone(),
-        mechanism: Symbol::from("PLAIN"),
-        initial_response: Some(initial_response),
-    };
+impl<A: Address> From<A> for ConnectRequest<A> {
+    fn from(address: A) -> Self {
+        ConnectRequest::new(address)
+    }
+}
 
-    state.send(&mut io, &codec, sasl_init.into()).await?;
+impl<A, T> Service for Connector<A, T>
+where
+    A: Address,
+    T: Service<Request = Connect<A>, Error = connect::ConnectError> + 'static,
+    T::Response: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    type Request = ConnectRequest<A>;
+    type Response = Client<T::Response>;
+    type Error = ConnectError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
 
-    // processing sasl-outcome
-    let sasl_frame = state
-        .next(&mut io, &codec)
-        .await
-        .map_err(ConnectError::from)
-        .and_then(|res| res.ok_or(ConnectError::Disconnected))?;
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.connector.poll_ready(cx).map_err(ConnectError::from)
+    }
 
-    if let SaslFrame {
-        body: SaslFrameBody::SaslOutcome(outcome),
-    } = sasl_frame
-    {
-        if outcome.code() != SaslCode::Ok {
-            return Err(ConnectError::Sasl(outcome.code()));
+    fn call(&self, req: ConnectRequest<A>) -> Self::Future {
+        if let Some(auth) = req.auth {
+            Box::pin(self.connect_sasl(req.address, auth))
+        } else {
+            Box::pin(self.connect(req.address))
         }
+    }
+}
+
+impl<A, T> ServiceFactory for Connector<A, T>
+where
+    A: Address,
+    T: Service<Request = Connect<A>, Error = connect::ConnectError> + Clone + 'static,
+    T::Response: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    type Config = ();
+    type Request = ConnectRequest<A>;
+    type Response = Client<T::Response>;
+    type Error = ConnectError;
+    type Service = Connector<A, T>;
+    type InitError = ();
+    type Future = Ready<Self::Service, Self::InitError>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        Ready::Ok(self.clone())
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+struct Timeouts {
+    sasl: u16,
+    open: u16,
+}
+
+async fn with_timeout<F, R, E>(timeout: u16, err: ConnectError, fut: F) -> Result<R, ConnectError>
+where
+    F: Future<Output = Result<R, E>>,
+    ConnectError: From<E>,
+{
+    if timeout == 0 {
+        fut.await.map_err(From::from)
     } else {
-        return Err(ConnectError::Disconnected);
+        match select(delay_for(Duration::from_millis(timeout as u64)), fut).await {
+            Either::Left(_) => Err(err),
+            Either::Right(res) => res.map_err(From::from),
+        }
     }
+}
 
-    _connect_plain(io, state, config, timer).await
+async fn _connect_sasl<T>(
+    mut io: T,
+    state: State,
+    auth: SaslAuth,
+    config: Configuration,
+    timeouts: Timeouts,
+    timer: Timer,
+    retry: Option<Rc<dyn RetryPolicy>>,
+) -> Result<Client<T>, ConnectError>
+where
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    with_timeout(
+        timeouts.sasl,
+        ConnectError::SaslTimeout,
+        sasl_client(&mut io, &state, &auth, &config),
+    )
+    .await?;
+
+    _connect_plain(io, state, config, timeouts, timer, retry).await
 }
 
 async fn _connect_plain<T>(
     mut io: T,
     state: State,
     config: Configuration,
+    timeouts: Timeouts,
     timer: Timer,
+    retry: Option<Rc<dyn RetryPolicy>>,
 ) -> Result<Client<T>, ConnectError>
 where
     T: AsyncRead + AsyncWrite + Unpin + 'static,
 {
-    trace!("Negotiation client protocol id: Amqp");
-
-    state
-        .send(&mut io, &ProtocolIdCodec, ProtocolId::Amqp)
-        .await?;
-
-    let proto = state
-        .next(&mut io, &ProtocolIdCodec)
-        .await
-        .map_err(ConnectError::from)
-        .and_then(|res| {
-            res.ok_or_else(|| {
-                log::trace!("Amqp server is disconnected during handshake");
-                ConnectError::Disconnected
-            })
-        })?;
-
-    if proto != ProtocolId::Amqp {
-        return Err(ConnectError::from(ProtocolIdError::Unexpected {
-            exp: ProtocolId::Amqp,
-            got: proto,
-        }));
-    }
-
-    let open = config.to_open();
-    let codec = AmqpCodec::<AmqpFrame>::new().max_size(config.max_frame_size as usize);
-
-    trace!("Open client amqp connection: {:?}", open);
-    state
-        .send(&mut io, &codec, AmqpFrame::new(0, Frame::Open(open)))
-        .await?;
-
-    let frame = state
-        .next(&mut io, &codec)
-        .await
-        .map_err(ConnectError::from)
-        .and_then(|res| {
-            res.ok_or_else(|| {
-                log::trace!("Amqp server is disconnected during handshake");
-                ConnectError::Disconnected
-            })
-        })?;
-
-    if let Frame::Open(open) = frame.performative() {
-        trace!("Open confirmed: {:?}", open);
-        let remote_config = open.into();
-        let connection = Connection::new(state.clone(), &config, &remote_config);
-        let client = Client::new(
-            io,
-            state,
-            codec,
-            connection,
-            config.timeout_secs() as u16,
-            remote_config,
-            timer,
-        );
-        Ok(client)
-    } else {
-        Err(ConnectError::ExpectOpenFrame(Box::new(frame)))
-    }
+    let (codec, remote_config) = with_timeout(
+        timeouts.open,
+        ConnectError::OpenTimeout,
+        open_connection(&mut io, &state, &config),
+    )
+    .await?;
+
+    let connection = Connection::new(state.clone(), &config, &remote_config);
+    let codec = codec.metrics(connection.codec_metrics());
+    connection.0.get_mut().retry_policy = retry;
+    Ok(Client::new(
+        io,
+        state,
+        codec,
+        connection,
+        config.timeout_secs() as u16,
+        remote_config,
+        timer,
+    ))
 }
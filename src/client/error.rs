@@ -1,7 +1,9 @@
-use ntex::util::Either;
+use ntex::util::{Bytes, Either};
 
 use crate::codec::{protocol, AmqpCodecError, AmqpFrame, ProtocolIdError};
 
+use super::tls::TlsTimeout;
+
 /// Errors which can occur when attempting to handle amqp client connection.
 #[derive(Debug, Display, From)]
 pub enum ConnectError {
@@ -11,6 +13,18 @@ pub enum ConnectError {
     /// Handshake timeout
     #[display(fmt = "Handshake timeout")]
     HandshakeTimeout,
+    /// Connect timeout
+    #[display(fmt = "Connect timeout")]
+    ConnectTimeout,
+    /// Tls handshake timeout
+    #[display(fmt = "Tls handshake timeout")]
+    TlsHandshakeTimeout,
+    /// Sasl exchange timeout
+    #[display(fmt = "Sasl exchange timeout")]
+    SaslTimeout,
+    /// Open exchange timeout
+    #[display(fmt = "Open exchange timeout")]
+    OpenTimeout,
     /// Protocol negotiation error
     #[display(fmt = "Peer disconnected")]
     ProtocolNegotiation(ProtocolIdError),
@@ -18,20 +32,59 @@ pub enum ConnectError {
     /// Expected open frame
     #[display(fmt = "Expect open frame, got: {:?}", _0)]
     ExpectOpenFrame(Box<AmqpFrame>),
-    /// Peer disconnected
+    /// Sasl authentication failed, credentials are rejected
+    #[from(ignore)]
+    #[display(fmt = "Sasl authentication failed")]
+    SaslAuth(Option<Bytes>),
+    /// Sasl authentication failed due to server side error
+    #[from(ignore)]
+    #[display(fmt = "Sasl system error: {:?}", _0)]
+    SaslSystem(protocol::SaslCode, Option<Bytes>),
+    /// Sasl error code
+    ///
+    /// Connector reports sasl failures with `SaslAuth` and `SaslSystem`
     #[display(fmt = "Sasl error code: {:?}", _0)]
     Sasl(protocol::SaslCode),
+    /// Scram exchange failed, server messages are malformed
+    /// or server signature does not match
+    #[from(ignore)]
+    #[display(fmt = "Scram error: {}", _0)]
+    Scram(&'static str),
+    /// Peer disconnected
     #[display(fmt = "Peer disconnected")]
     Disconnected,
     /// Connect error
+    #[from(ignore)]
     #[display(fmt = "Connect error: {}", _0)]
     Connect(ntex::connect::ConnectError),
+    /// Peer certificate chain does not match pinned keys
+    #[display(fmt = "Certificate pin mismatch")]
+    PinMismatch,
     /// Unexpected io error
     Io(std::io::Error),
 }
 
 impl std::error::Error for ConnectError {}
 
+impl From<ntex::connect::ConnectError> for ConnectError {
+    fn from(err: ntex::connect::ConnectError) -> Self {
+        match TlsTimeout::from_error(&err) {
+            Some(TlsTimeout::Connect) => return ConnectError::ConnectTimeout,
+            Some(TlsTimeout::Handshake) => return ConnectError::TlsHandshakeTimeout,
+            None => (),
+        }
+        #[cfg(feature = "openssl")]
+        {
+            if let ntex::connect::ConnectError::Io(ref e) = err {
+                if e.get_ref().map(|e| e.is::<super::PinMismatch>()) == Some(true) {
+                    return ConnectError::PinMismatch;
+                }
+            }
+        }
+        ConnectError::Connect(err)
+    }
+}
+
 impl From<Either<AmqpCodecError, std::io::Error>> for ConnectError {
     fn from(err: Either<AmqpCodecError, std::io::Error>) -> Self {
         match err {
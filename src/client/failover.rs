@@ -0,0 +1,129 @@
+use std::{cell::Cell, time::Duration, time::Instant};
+
+/// Endpoint tier
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EndpointTier {
+    /// Endpoint in primary region
+    Primary,
+    /// Endpoint in secondary region
+    Secondary,
+}
+
+struct Endpoint<A> {
+    address: A,
+    tier: EndpointTier,
+    unhealthy_until: Cell<Option<Instant>>,
+}
+
+/// Tiered list of endpoints for failover
+///
+/// Endpoints are tried in order, primary endpoints first. Endpoint that
+/// failed to connect is considered unhealthy for `retry_after` period
+/// and is tried after healthy endpoints only.
+pub struct Failover<A> {
+    endpoints: Vec<Endpoint<A>>,
+    retry_after: Duration,
+    fail_back_interval: Duration,
+}
+
+impl<A> Default for Failover<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A> Failover<A> {
+    /// Create empty endpoints list
+    pub fn new() -> Self {
+        Failover {
+            endpoints: Vec::new(),
+            retry_after: Duration::from_secs(30),
+            fail_back_interval: Duration::from_secs(30),
+        }
+    }
+
+    /// Add primary region endpoint
+    pub fn primary(mut self, address: A) -> Self {
+        self.endpoints.push(Endpoint {
+            address,
+            tier: EndpointTier::Primary,
+            unhealthy_until: Cell::new(None),
+        });
+        self
+    }
+
+    /// Add secondary region endpoint
+    pub fn secondary(mut self, address: A) -> Self {
+        self.endpoints.push(Endpoint {
+            address,
+            tier: EndpointTier::Secondary,
+            unhealthy_until: Cell::new(None),
+        });
+        self
+    }
+
+    /// Set period endpoint is considered unhealthy after failure.
+    ///
+    /// By default period is 30 seconds.
+    pub fn retry_after(mut self, period: Duration) -> Self {
+        self.retry_after = period;
+        self
+    }
+
+    /// Set interval of primary region checks.
+    ///
+    /// Reconnecting client that is connected to secondary region checks
+    /// primary endpoints every `interval` and fails back to primary region
+    /// once one of them is available, see `Connector::connect_failover_reconnecting()`.
+    /// By default interval is 30 seconds.
+    pub fn fail_back_interval(mut self, interval: Duration) -> Self {
+        self.fail_back_interval = interval;
+        self
+    }
+
+    pub(super) fn get_fail_back_interval(&self) -> Duration {
+        self.fail_back_interval
+    }
+
+    /// Check if endpoints list is empty
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    /// Endpoints in connect order for specified tiers
+    pub(super) fn candidates(&self, max_tier: EndpointTier) -> Vec<(&A, EndpointTier, usize)> {
+        let now = Instant::now();
+        let mut healthy = Vec::new();
+        let mut unhealthy = Vec::new();
+
+        for tier in [EndpointTier::Primary, EndpointTier::Secondary]
+            .iter()
+            .copied()
+        {
+            if tier > max_tier {
+                break;
+            }
+            for (idx, ep) in self.endpoints.iter().enumerate() {
+                if ep.tier != tier {
+                    continue;
+                }
+                match ep.unhealthy_until.get() {
+                    Some(until) if until > now => unhealthy.push((&ep.address, tier, idx)),
+                    _ => healthy.push((&ep.address, tier, idx)),
+                }
+            }
+        }
+        healthy.extend(unhealthy);
+        healthy
+    }
+
+    pub(super) fn failed(&self, idx: usize) {
+        self.endpoints[idx]
+            .unhealthy_until
+            .set(Some(Instant::now() + self.retry_after));
+    }
+
+    pub(super) fn succeeded(&self, idx: usize) {
+        self.endpoints[idx].unhealthy_until.set(None);
+    }
+}
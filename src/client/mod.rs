@@ -3,12 +3,32 @@ use ntex::util::ByteString;
 mod connection;
 mod connector;
 mod error;
+mod failover;
+#[cfg(feature = "openssl")]
+mod pinning;
+mod reconnect;
+mod resolver;
+mod subscription;
+mod tls;
 
 pub use self::connection::Client;
-pub use self::connector::Connector;
+pub use self::connector::{ConnectRequest, Connector};
 pub use self::error::ConnectError;
+pub use self::failover::{EndpointTier, Failover};
+#[cfg(feature = "openssl")]
+pub use self::pinning::{spki_pin, PinMismatch, PinnedConnector, SpkiPins};
+pub use self::reconnect::{
+    Credentials, CredentialsProvider, ReconnectEvent, ReconnectingClient, RefreshingClient,
+};
+pub use self::resolver::{Resolver, ResolverConnector, StaticResolver};
+pub use self::subscription::{CreditPolicy, SubscriptionManager};
 
-#[derive(Debug)]
+#[cfg(feature = "openssl")]
+pub use self::tls::OpensslConnector;
+#[cfg(feature = "rustls")]
+pub use self::tls::RustlsConnector;
+
+#[derive(Debug, Clone)]
 /// Sasl authentication parameters
 pub struct SaslAuth {
     pub authz_id: ByteString,
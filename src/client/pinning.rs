@@ -0,0 +1,231 @@
+use std::task::{Context, Poll};
+use std::{cell::RefCell, collections::HashSet, fmt, future::Future, io, pin::Pin, rc::Rc};
+
+use ntex::connect::openssl::{SslConnector, SslStream};
+use ntex::connect::{Address, Connect, ConnectError};
+use ntex::rt::net::TcpStream;
+use ntex::service::Service;
+use open_ssl::{sha::sha256, ssl::SslRef, stack::StackRef, x509::X509Ref, x509::X509};
+
+use super::tls::{OpensslConnector, TlsTimeouts};
+
+/// Set of allowed SPKI pins
+///
+/// Pin is sha256 digest of DER encoded SubjectPublicKeyInfo of a certificate.
+/// Handshake succeeds if any certificate of verified chain matches a pin,
+/// certificates that are presented by peer but are not part of the
+/// verified chain are ignored.
+/// Set is shared between clones, pins could be rotated at runtime and
+/// new pins apply to subsequent connections. Empty set rejects all peers.
+#[derive(Clone, Default)]
+pub struct SpkiPins(Rc<RefCell<HashSet<[u8; 32]>>>);
+
+impl SpkiPins {
+    /// Create empty pin set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add pin
+    pub fn add(&self, pin: [u8; 32]) -> &Self {
+        self.0.borrow_mut().insert(pin);
+        self
+    }
+
+    /// Add pin of certificate's public key
+    pub fn add_cert(&self, cert: &X509Ref) -> Result<&Self, io::Error> {
+        Ok(self.add(spki_pin(cert)?))
+    }
+
+    /// Remove pin
+    pub fn remove(&self, pin: &[u8; 32]) -> bool {
+        self.0.borrow_mut().remove(pin)
+    }
+
+    /// Replace all pins
+    pub fn replace<I: IntoIterator<Item = [u8; 32]>>(&self, pins: I) {
+        *self.0.borrow_mut() = pins.into_iter().collect();
+    }
+
+    /// Check if pin is in the set
+    pub fn contains(&self, pin: &[u8; 32]) -> bool {
+        self.0.borrow().contains(pin)
+    }
+
+    /// Number of pins
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    /// Check if pin set is empty
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+
+    fn verify(&self, ssl: &SslRef) -> bool {
+        ssl.verified_chain()
+            .map(|chain| self.matches(chain))
+            .unwrap_or(false)
+    }
+
+    /// Check if any certificate of the chain matches a pin
+    fn matches(&self, chain: &StackRef<X509>) -> bool {
+        let pins = self.0.borrow();
+        chain.iter().any(|cert| match spki_pin(cert) {
+            Ok(pin) => pins.contains(&pin),
+            Err(_) => false,
+        })
+    }
+}
+
+impl fmt::Debug for SpkiPins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpkiPins")
+            .field("pins", &self.len())
+            .finish()
+    }
+}
+
+/// Calculate SPKI pin of certificate
+pub fn spki_pin(cert: &X509Ref) -> Result<[u8; 32], io::Error> {
+    let der = cert
+        .public_key()
+        .and_then(|key| key.public_key_to_der())
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    Ok(sha256(&der))
+}
+
+/// Presented certificate chain does not match any pin
+#[derive(Debug, Copy, Clone)]
+pub struct PinMismatch;
+
+impl fmt::Display for PinMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Certificate chain does not match pinned keys")
+    }
+}
+
+impl std::error::Error for PinMismatch {}
+
+/// Openssl connector with SPKI pin verification
+///
+/// Pins are checked after tls handshake, connection fails
+/// with `ConnectError::PinMismatch` if chain does not match. Ssl connector
+/// must verify peer, without verified chain all peers are rejected.
+pub struct PinnedConnector<A> {
+    connector: OpensslConnector<A>,
+    pins: SpkiPins,
+}
+
+impl<A> PinnedConnector<A> {
+    /// Create pinned connector
+    pub fn new(connector: SslConnector, pins: SpkiPins) -> Self {
+        PinnedConnector {
+            connector: OpensslConnector::new(connector),
+            pins,
+        }
+    }
+
+    pub(super) fn timeouts(&self) -> &TlsTimeouts {
+        self.connector.timeouts()
+    }
+}
+
+impl<A> Clone for PinnedConnector<A> {
+    fn clone(&self) -> Self {
+        PinnedConnector {
+            connector: self.connector.clone(),
+            pins: self.pins.clone(),
+        }
+    }
+}
+
+impl<A: Address + 'static> Service for PinnedConnector<A> {
+    type Request = Connect<A>;
+    type Response = SslStream<TcpStream>;
+    type Error = ConnectError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    #[inline]
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, req: Connect<A>) -> Self::Future {
+        let fut = self.connector.connect(req);
+        let pins = self.pins.clone();
+
+        Box::pin(async move {
+            let io = fut.await?;
+            if pins.verify(io.ssl()) {
+                Ok(io)
+            } else {
+                trace!("Peer certificate chain does not match pinned keys");
+                Err(io::Error::new(io::ErrorKind::InvalidData, PinMismatch).into())
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use open_ssl::{asn1::Asn1Time, hash::MessageDigest, pkey::PKey, rsa::Rsa};
+    use open_ssl::{stack::Stack, x509::X509Name};
+
+    use super::*;
+
+    fn cert(cn: &str) -> X509 {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509Name::builder().unwrap();
+        name.append_entry_by_text("CN", cn).unwrap();
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+        builder.build()
+    }
+
+    fn chain(certs: &[&X509]) -> Stack<X509> {
+        let mut chain = Stack::new().unwrap();
+        for cert in certs {
+            chain.push((*cert).clone()).unwrap();
+        }
+        chain
+    }
+
+    #[test]
+    fn test_pin_match() {
+        let leaf = cert("leaf");
+        let ca = cert("ca");
+        let pins = SpkiPins::new();
+        pins.add_cert(&ca).unwrap();
+
+        assert!(pins.matches(&chain(&[&leaf, &ca])));
+        assert!(pins.matches(&chain(&[&ca])));
+    }
+
+    #[test]
+    fn test_pin_mismatch() {
+        let leaf = cert("leaf");
+        let ca = cert("ca");
+        let pins = SpkiPins::new();
+        assert!(!pins.matches(&chain(&[&leaf, &ca])));
+
+        pins.add_cert(&cert("other")).unwrap();
+        assert!(!pins.matches(&chain(&[&leaf, &ca])));
+
+        pins.replace(vec![spki_pin(&leaf).unwrap()]);
+        assert!(pins.matches(&chain(&[&leaf, &ca])));
+        assert!(pins.remove(&spki_pin(&leaf).unwrap()));
+        assert!(!pins.matches(&chain(&[&leaf, &ca])));
+    }
+}
@@ -0,0 +1,663 @@
+use std::{cell::RefCell, cmp, future::Future, pin::Pin, rc::Rc, time::Duration, time::Instant};
+
+use ntex::channel::{condition::Condition, mpsc};
+use ntex::codec::{AsyncRead, AsyncWrite};
+use ntex::connect::{self, Address, Connect};
+use ntex::rt::time::delay_for;
+use ntex::service::Service;
+use ntex::util::{select, ByteString, Either, Ready};
+
+use crate::error::AmqpProtocolError;
+use crate::retry::{RetryPolicy, RetryableError};
+use crate::{Connection, ReceiverLink, SenderLink, Session, ShutdownReport, ShutdownTimeouts};
+
+use super::{ConnectError, Connector, EndpointTier, Failover, SaslAuth, SubscriptionManager};
+
+/// Sasl credentials with expiry time
+///
+/// Bearer tokens are passed as sasl password.
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    /// Sasl auth parameters
+    pub auth: SaslAuth,
+    /// Credentials expiry time
+    pub expires: Option<Instant>,
+}
+
+/// Credentials provider
+///
+/// Provider is implemented for closures
+/// `Fn() -> impl Future<Output = Result<Credentials, ConnectError>>`.
+pub trait CredentialsProvider {
+    /// Get fresh credentials
+    fn credentials(&self) -> Pin<Box<dyn Future<Output = Result<Credentials, ConnectError>>>>;
+}
+
+impl<F, R> CredentialsProvider for F
+where
+    F: Fn() -> R,
+    R: Future<Output = Result<Credentials, ConnectError>> + 'static,
+{
+    fn credentials(&self) -> Pin<Box<dyn Future<Output = Result<Credentials, ConnectError>>>> {
+        Box::pin((*self)())
+    }
+}
+
+/// Reconnecting client event
+#[derive(Debug)]
+pub enum ReconnectEvent {
+    /// Connection is lost, reconnect is in progress
+    Disconnected(Option<AmqpProtocolError>),
+    /// Connection is re-established after number of failed attempts
+    Reconnected(usize),
+    /// Connection is replaced before credentials expiry
+    Refreshed,
+    /// Connection is moved back to primary region endpoint, previous
+    /// connection is closed once its in-flight deliveries are settled
+    FailedBack,
+    /// Receiver link opened with `open_receiver_link()` is reopened
+    /// on new connection, previous link is closed
+    ReceiverReattached(ReceiverLink),
+    /// Link opened with `open_sender_link()` or `open_receiver_link()`
+    /// cannot be reattached to new connection, contains link name
+    LinkLost(ByteString),
+    /// Reconnect attempts are exhausted, client is closed
+    Failed(ConnectError),
+}
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+const SEND_ATTEMPTS: usize = 10;
+
+/// Client connection that is re-established automatically
+///
+/// If connection is lost, client reconnects according to connector's
+/// reconnect policy, sasl exchange and open exchange are performed for
+/// each connection. Sender links opened with `open_sender_link()` are
+/// reattached to the new connection, existing handles keep working,
+/// deliveries that failed due to connection loss are re-sent on
+/// reattached links. Receiver links opened with `open_receiver_link()`
+/// are reopened and delivered with `ReconnectEvent::ReceiverReattached`.
+/// Sessions and links opened directly on `sink()` or `session()` are
+/// not restored, use `events()` to reopen them after reconnect.
+///
+/// With expiring credentials, new connection is opened `refresh_before`
+/// credentials expiry and previous connection is closed once its
+/// in-flight deliveries are settled.
+///
+/// Client connected with failover endpoints reconnects to first available
+/// endpoint. While connected to secondary region, client checks primary
+/// endpoints and moves to primary region once one of them is available.
+/// Links are reattached in the order they were opened.
+#[derive(Clone)]
+pub struct ReconnectingClient(Rc<RefCell<Inner>>);
+
+/// Client connection that is re-established with fresh credentials
+pub type RefreshingClient = ReconnectingClient;
+
+struct Inner {
+    sink: Connection,
+    session: Session,
+    tier: EndpointTier,
+    links: Vec<TrackedLink>,
+    subscriptions: Option<SubscriptionManager>,
+    events: Option<mpsc::Sender<ReconnectEvent>>,
+    reconnects: usize,
+    stopped: bool,
+    stop: Condition,
+}
+
+/// Link that is reattached after reconnect, in open order
+#[derive(Clone)]
+enum TrackedLink {
+    Sender(ByteString, ByteString, SenderLink),
+    Receiver(ByteString, ByteString),
+}
+
+/// Reconnect target
+enum Target<A> {
+    Address(A),
+    Failover(Failover<A>),
+}
+
+impl ReconnectingClient {
+    /// Current connection
+    pub fn sink(&self) -> Connection {
+        self.0.borrow().sink.clone()
+    }
+
+    /// Session of current connection
+    pub fn session(&self) -> Session {
+        self.0.borrow().session.clone()
+    }
+
+    /// Endpoint tier of current connection
+    ///
+    /// Client connected to single address is always in primary tier.
+    pub fn tier(&self) -> EndpointTier {
+        self.0.borrow().tier
+    }
+
+    /// Number of times connection was re-established
+    pub fn reconnects(&self) -> usize {
+        self.0.borrow().reconnects
+    }
+
+    /// Check if client is closed
+    pub fn is_closed(&self) -> bool {
+        self.0.borrow().stopped
+    }
+
+    /// Get stream of reconnect events
+    ///
+    /// Only one stream is active, new stream replaces previous one.
+    pub fn events(&self) -> mpsc::Receiver<ReconnectEvent> {
+        let (tx, rx) = mpsc::channel();
+        if let Some(tx) = self.0.borrow_mut().events.replace(tx) {
+            tx.close();
+        }
+        rx
+    }
+
+    /// Open sender link on current session
+    ///
+    /// Link is opened in its own session if session-per-link is enabled.
+    /// Link is reattached after each reconnect.
+    pub async fn open_sender_link<T, U>(
+        &self,
+        name: T,
+        address: U,
+    ) -> Result<SenderLink, AmqpProtocolError>
+    where
+        T: Into<ByteString>,
+        U: Into<ByteString>,
+    {
+        let name = name.into();
+        let address = address.into();
+        let session = self.session();
+        let link = self
+            .sink()
+            .link_session(&session)
+            .await?
+            .build_sender_link(name.clone(), address.clone())
+            .open()
+            .await?;
+        self.0
+            .borrow_mut()
+            .links
+            .push(TrackedLink::Sender(name, address, link.clone()));
+        Ok(link)
+    }
+
+    /// Open receiver link on current session
+    ///
+    /// Link is opened in its own session if session-per-link is enabled.
+    /// Link is reopened after each reconnect, new link is delivered
+    /// with `ReconnectEvent::ReceiverReattached` event.
+    pub async fn open_receiver_link<T, U>(
+        &self,
+        name: T,
+        address: U,
+    ) -> Result<ReceiverLink, AmqpProtocolError>
+    where
+        T: Into<ByteString>,
+        U: Into<ByteString>,
+    {
+        let name = name.into();
+        let address = address.into();
+        let session = self.session();
+        let link = self
+            .sink()
+            .link_session(&session)
+            .await?
+            .build_receiver_link(name.clone(), address.clone())
+            .open()
+            .await?;
+        self.0
+            .borrow_mut()
+            .links
+            .push(TrackedLink::Receiver(name, address));
+        Ok(link)
+    }
+
+    /// Attach subscriptions to current session and after each reconnect
+    pub async fn subscriptions(
+        &self,
+        manager: SubscriptionManager,
+    ) -> Result<(), AmqpProtocolError> {
+        self.0.borrow_mut().subscriptions = Some(manager.clone());
+        manager.attach(&self.session()).await
+    }
+
+    /// Stop reconnecting and close connection
+    pub fn close(&self) -> impl Future<Output = Result<(), AmqpProtocolError>> {
+        let mut inner = self.0.borrow_mut();
+        inner.stopped = true;
+        inner.stop.notify();
+        inner.sink.close()
+    }
+
+    /// Stop reconnecting and shutdown connection
+    ///
+    /// See `Connection::shutdown()`.
+    pub fn shutdown(&self, timeouts: ShutdownTimeouts) -> impl Future<Output = ShutdownReport> {
+        let mut inner = self.0.borrow_mut();
+        inner.stopped = true;
+        inner.stop.notify();
+        inner.sink.shutdown(timeouts)
+    }
+
+    fn notify(&self, event: ReconnectEvent) {
+        let mut inner = self.0.borrow_mut();
+        if let Some(ref tx) = inner.events {
+            if tx.send(event).is_err() {
+                inner.events = None;
+            }
+        }
+    }
+}
+
+impl<A, T> Connector<A, T>
+where
+    A: Address + Clone,
+    T: Service<Request = Connect<A>, Error = connect::ConnectError> + Clone + 'static,
+    T::Response: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    /// Connect to amqp server, reconnect if connection is lost
+    pub async fn connect_reconnecting(
+        &self,
+        address: A,
+    ) -> Result<ReconnectingClient, ConnectError> {
+        self.reconnecting(Target::Address(address), None, Duration::from_secs(0))
+            .await
+    }
+
+    /// Connect to amqp server with sasl auth, reconnect if connection is lost
+    pub async fn connect_sasl_reconnecting(
+        &self,
+        address: A,
+        auth: SaslAuth,
+    ) -> Result<ReconnectingClient, ConnectError> {
+        self.reconnecting(
+            Target::Address(address),
+            Some(static_credentials(auth)),
+            Duration::from_secs(0),
+        )
+        .await
+    }
+
+    /// Connect to first available failover endpoint, reconnect if connection is lost
+    ///
+    /// While connected to secondary region, primary endpoints are checked
+    /// every `Failover::fail_back_interval()`, see `ReconnectingClient`.
+    pub async fn connect_failover_reconnecting(
+        &self,
+        failover: Failover<A>,
+    ) -> Result<ReconnectingClient, ConnectError> {
+        self.reconnecting(Target::Failover(failover), None, Duration::from_secs(0))
+            .await
+    }
+
+    /// Connect to first available failover endpoint with sasl auth,
+    /// reconnect if connection is lost
+    pub async fn connect_sasl_failover_reconnecting(
+        &self,
+        failover: Failover<A>,
+        auth: SaslAuth,
+    ) -> Result<ReconnectingClient, ConnectError> {
+        self.reconnecting(
+            Target::Failover(failover),
+            Some(static_credentials(auth)),
+            Duration::from_secs(0),
+        )
+        .await
+    }
+
+    /// Connect with expiring credentials
+    ///
+    /// Credentials are requested from `provider` for each connection,
+    /// connection is refreshed `refresh_before` credentials expiry.
+    /// Sasl exchange is retried according to retry policy.
+    pub async fn connect_refreshing<P>(
+        &self,
+        address: A,
+        provider: P,
+        refresh_before: Duration,
+    ) -> Result<ReconnectingClient, ConnectError>
+    where
+        P: CredentialsProvider + 'static,
+    {
+        self.reconnecting(
+            Target::Address(address),
+            Some(Rc::new(provider)),
+            refresh_before,
+        )
+        .await
+    }
+
+    async fn reconnecting(
+        &self,
+        target: Target<A>,
+        provider: Option<Rc<dyn CredentialsProvider>>,
+        refresh_before: Duration,
+    ) -> Result<ReconnectingClient, ConnectError> {
+        let (sink, session, expires, tier) = self
+            .connect_with(&target, &provider, EndpointTier::Secondary, true)
+            .await?
+            .ok_or(ConnectError::Disconnected)?;
+        let client = ReconnectingClient(Rc::new(RefCell::new(Inner {
+            sink,
+            session,
+            tier,
+            links: Vec::new(),
+            subscriptions: None,
+            events: None,
+            reconnects: 0,
+            stopped: false,
+            stop: Condition::new(),
+        })));
+
+        ntex::rt::spawn(reconnect(
+            self.clone(),
+            target,
+            provider,
+            client.clone(),
+            refresh_before,
+            expires,
+        ));
+        Ok(client)
+    }
+
+    /// Connect to target, failover endpoints are limited to `max_tier`
+    ///
+    /// Returns `None` if none of failover endpoints is available
+    /// and `max_tier` is primary tier.
+    async fn connect_with(
+        &self,
+        target: &Target<A>,
+        provider: &Option<Rc<dyn CredentialsProvider>>,
+        max_tier: EndpointTier,
+        with_retry: bool,
+    ) -> Result<Option<(Connection, Session, Option<Instant>, EndpointTier)>, ConnectError> {
+        let credentials = if let Some(ref provider) = provider {
+            Some(provider.credentials().await?)
+        } else {
+            None
+        };
+        let expires = credentials.as_ref().and_then(|c| c.expires);
+        let auth = credentials.map(|c| c.auth);
+
+        let (client, tier) = match target {
+            Target::Address(ref address) => {
+                let client = match (auth, with_retry) {
+                    (Some(auth), true) => {
+                        self.connect_sasl_with_retry(address.clone(), auth).await?
+                    }
+                    (Some(auth), false) => self.connect_sasl(address.clone(), auth).await?,
+                    (None, true) => self.connect_with_retry(address.clone()).await?,
+                    (None, false) => self.connect(address.clone()).await?,
+                };
+                (client, EndpointTier::Primary)
+            }
+            Target::Failover(ref failover) => {
+                match self.connect_tiers(failover, max_tier, auth).await? {
+                    Some(res) => res,
+                    None => return Ok(None),
+                }
+            }
+        };
+
+        let sink = client.sink();
+        let policy = sink.0.get_ref().retry_policy.clone();
+        sink.0.get_mut().retry_policy = Some(Rc::new(ReconnectRetry(policy)));
+        ntex::rt::spawn(async move {
+            let _ = client.start_default().await;
+        });
+
+        match sink.open_session().await {
+            Ok(session) => Ok(Some((sink, session, expires, tier))),
+            Err(err) => {
+                trace!("Cannot open session: {:?}", err);
+                sink.force_close();
+                Err(ConnectError::Disconnected)
+            }
+        }
+    }
+}
+
+/// Sasl credentials without expiry
+fn static_credentials(auth: SaslAuth) -> Rc<dyn CredentialsProvider> {
+    Rc::new(move || {
+        Ready::Ok(Credentials {
+            auth: auth.clone(),
+            expires: None,
+        })
+    })
+}
+
+/// Reason of connection replacement
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Replace {
+    Closed,
+    Refresh,
+    FailBack,
+}
+
+async fn reconnect<A, T>(
+    connector: Connector<A, T>,
+    target: Target<A>,
+    provider: Option<Rc<dyn CredentialsProvider>>,
+    client: ReconnectingClient,
+    refresh_before: Duration,
+    mut expires: Option<Instant>,
+) where
+    A: Address + Clone,
+    T: Service<Request = Connect<A>, Error = connect::ConnectError> + Clone + 'static,
+    T::Response: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    loop {
+        let sink = client.sink();
+        let stop = client.0.borrow().stop.wait();
+        let refresh_at = expires.map(|exp| exp.checked_sub(refresh_before).unwrap_or(exp));
+        let fail_back_at = match target {
+            Target::Failover(ref failover) if client.tier() == EndpointTier::Secondary => {
+                Some(Instant::now() + failover.get_fail_back_interval())
+            }
+            _ => None,
+        };
+        let wake_at = match (refresh_at, fail_back_at) {
+            (Some(refresh), Some(fail_back)) if fail_back < refresh => {
+                Some((fail_back, Replace::FailBack))
+            }
+            (Some(refresh), _) => Some((refresh, Replace::Refresh)),
+            (None, Some(fail_back)) => Some((fail_back, Replace::FailBack)),
+            (None, None) => None,
+        };
+
+        let replace = if let Some((at, replace)) = wake_at {
+            let delay = at.saturating_duration_since(Instant::now());
+            match select(stop, select(delay_for(delay), sink.closed())).await {
+                Either::Left(_) => return,
+                Either::Right(Either::Left(_)) => replace,
+                Either::Right(Either::Right(_)) => Replace::Closed,
+            }
+        } else {
+            match select(stop, sink.closed()).await {
+                Either::Left(_) => return,
+                Either::Right(_) => Replace::Closed,
+            }
+        };
+        if client.is_closed() {
+            return;
+        }
+
+        let mut attempts = 0;
+        let (new_sink, session, new_expires, tier) = match replace {
+            Replace::FailBack => {
+                trace!("Checking primary region endpoints");
+                match connector
+                    .connect_with(&target, &provider, EndpointTier::Primary, false)
+                    .await
+                {
+                    Ok(Some(res)) => res,
+                    Ok(None) | Err(_) => continue,
+                }
+            }
+            Replace::Closed | Replace::Refresh => {
+                if replace == Replace::Closed {
+                    trace!("Connection is closed, reconnecting: {:?}", sink.get_error());
+                    client.notify(ReconnectEvent::Disconnected(sink.get_error()));
+                } else {
+                    trace!("Credentials expire soon, refreshing connection");
+                }
+
+                // reconnect attempts are controlled by reconnect policy only,
+                // retry policy applies to initial connect
+                loop {
+                    let err = match connector
+                        .connect_with(&target, &provider, EndpointTier::Secondary, false)
+                        .await
+                    {
+                        Ok(Some(res)) => break res,
+                        Ok(None) => ConnectError::Disconnected,
+                        Err(err) => err,
+                    };
+                    trace!("Cannot re-establish connection: {:?}", err);
+                    attempts += 1;
+
+                    let delay = if let Some(ref policy) = connector.reconnect {
+                        if attempts >= policy.max_attempts()
+                            || !policy.is_retryable(&RetryableError::Connect(&err))
+                        {
+                            trace!("Reconnect attempts are exhausted");
+                            client.notify(ReconnectEvent::Failed(err));
+                            client.0.borrow_mut().stopped = true;
+                            return;
+                        }
+                        policy.backoff(attempts)
+                    } else {
+                        RECONNECT_DELAY
+                    };
+                    delay_for(delay).await;
+                    if client.is_closed() {
+                        return;
+                    }
+                }
+            }
+        };
+        expires = new_expires;
+        // resumed links keep using the same state store
+        new_sink.0.get_mut().link_store = sink.0.get_ref().link_store.clone();
+
+        // links are reattached in the order they were opened
+        let links = client.0.borrow().links.clone();
+        let mut previous = Vec::with_capacity(links.len());
+        for link in links {
+            match link {
+                TrackedLink::Sender(name, addr, link) => {
+                    let opened = match new_sink.link_session(&session).await {
+                        Ok(mut session) => session.build_sender_link(name, addr).open().await,
+                        Err(err) => Err(err),
+                    };
+                    match opened {
+                        Ok(new_link) => {
+                            previous.push(SenderLink::new(link.inner().clone()));
+                            link.reattach(new_link);
+                        }
+                        Err(err) => {
+                            trace!("Cannot reattach sender link {:?}: {:?}", link, err);
+                            client.notify(ReconnectEvent::LinkLost(link.name().clone()));
+                        }
+                    }
+                }
+                TrackedLink::Receiver(name, addr) => {
+                    let opened = match new_sink.link_session(&session).await {
+                        Ok(mut session) => {
+                            session.build_receiver_link(name.clone(), addr).open().await
+                        }
+                        Err(err) => Err(err),
+                    };
+                    match opened {
+                        Ok(link) => client.notify(ReconnectEvent::ReceiverReattached(link)),
+                        Err(err) => {
+                            trace!("Cannot reopen receiver link {:?}: {:?}", name, err);
+                            client.notify(ReconnectEvent::LinkLost(name));
+                        }
+                    }
+                }
+            }
+        }
+
+        let subscriptions = {
+            let mut inner = client.0.borrow_mut();
+            inner.sink = new_sink;
+            inner.session = session.clone();
+            inner.tier = tier;
+            inner.reconnects += 1;
+            inner.subscriptions.clone()
+        };
+        if let Some(manager) = subscriptions {
+            if let Err(err) = manager.attach(&session).await {
+                trace!("Cannot attach subscriptions: {:?}", err);
+            }
+        }
+
+        match replace {
+            Replace::Closed => client.notify(ReconnectEvent::Reconnected(attempts)),
+            Replace::Refresh => {
+                client.notify(ReconnectEvent::Refreshed);
+                ntex::rt::spawn(drain(sink, previous));
+            }
+            Replace::FailBack => {
+                client.notify(ReconnectEvent::FailedBack);
+                ntex::rt::spawn(drain(sink, previous));
+            }
+        }
+    }
+}
+
+/// Close previous connection once in-flight deliveries are settled
+async fn drain(sink: Connection, links: Vec<SenderLink>) {
+    let start = Instant::now();
+    while links.iter().any(|link| link.inflight() > 0) && start.elapsed() < DRAIN_TIMEOUT {
+        if sink.get_error().is_some() {
+            return;
+        }
+        delay_for(Duration::from_millis(50)).await;
+    }
+    let _ = sink.close().await;
+}
+
+/// Retry policy that re-sends deliveries lost with connection
+///
+/// Other errors are checked with connector's policy.
+struct ReconnectRetry(Option<Rc<dyn RetryPolicy>>);
+
+impl RetryPolicy for ReconnectRetry {
+    fn max_attempts(&self) -> usize {
+        cmp::max(
+            self.0.as_ref().map(|p| p.max_attempts()).unwrap_or(1),
+            SEND_ATTEMPTS,
+        )
+    }
+
+    fn backoff(&self, attempt: usize) -> Duration {
+        if let Some(ref policy) = self.0 {
+            policy.backoff(attempt)
+        } else {
+            let factor = 1u32 << cmp::min(attempt.saturating_sub(1), 16) as u32;
+            cmp::min(Duration::from_millis(100) * factor, RECONNECT_DELAY)
+        }
+    }
+
+    fn is_retryable(&self, err: &RetryableError<'_>) -> bool {
+        match err {
+            RetryableError::Send(AmqpProtocolError::Disconnected) => true,
+            _ => self
+                .0
+                .as_ref()
+                .map(|p| p.is_retryable(err))
+                .unwrap_or(false),
+        }
+    }
+}
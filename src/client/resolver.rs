@@ -0,0 +1,225 @@
+use std::task::{Context, Poll};
+use std::{
+    future::Future, io, marker::PhantomData, net::IpAddr, net::SocketAddr, pin::Pin, rc::Rc,
+    time::Duration,
+};
+
+use ntex::channel::mpsc;
+use ntex::connect::{Address, Connect, ConnectError};
+use ntex::rt::{net::TcpStream, time::delay_for};
+use ntex::service::Service;
+use ntex::util::{next, select, Either, HashMap};
+
+/// Async dns resolver
+///
+/// Resolver is implemented for closures
+/// `Fn(&str, u16) -> impl Future<Output = io::Result<Vec<SocketAddr>>>`.
+pub trait Resolver {
+    /// Resolve host name to socket addresses
+    fn lookup(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> Pin<Box<dyn Future<Output = io::Result<Vec<SocketAddr>>>>>;
+}
+
+impl<F, R> Resolver for F
+where
+    F: Fn(&str, u16) -> R,
+    R: Future<Output = io::Result<Vec<SocketAddr>>> + 'static,
+{
+    fn lookup(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> Pin<Box<dyn Future<Output = io::Result<Vec<SocketAddr>>>>> {
+        Box::pin((*self)(host, port))
+    }
+}
+
+/// Resolver with static host map
+///
+/// Hosts that are not in the map are resolved with system resolver
+/// on blocking thread pool.
+#[derive(Debug, Clone, Default)]
+pub struct StaticResolver {
+    hosts: HashMap<String, Vec<IpAddr>>,
+}
+
+impl StaticResolver {
+    /// Create empty host map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add host addresses
+    pub fn host<T: Into<String>>(mut self, host: T, addrs: Vec<IpAddr>) -> Self {
+        self.hosts.insert(host.into(), addrs);
+        self
+    }
+}
+
+impl Resolver for StaticResolver {
+    fn lookup(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> Pin<Box<dyn Future<Output = io::Result<Vec<SocketAddr>>>>> {
+        if let Some(addrs) = self.hosts.get(host) {
+            let addrs = addrs.iter().map(|ip| SocketAddr::new(*ip, port)).collect();
+            return Box::pin(async move { Ok(addrs) });
+        }
+
+        let host = format!("{}:{}", host, port);
+        Box::pin(async move {
+            ntex::rt::task::spawn_blocking(move || {
+                std::net::ToSocketAddrs::to_socket_addrs(&host).map(|addrs| addrs.collect())
+            })
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+        })
+    }
+}
+
+/// Tcp connector with custom dns resolver
+///
+/// Connector races resolved addresses, address families are interleaved
+/// and next connection attempt starts if previous attempt is not complete
+/// within attempt delay (RFC 8305). First established connection is used.
+pub struct ResolverConnector<A> {
+    resolver: Rc<dyn Resolver>,
+    attempt_delay: Duration,
+    _t: PhantomData<A>,
+}
+
+impl<A> ResolverConnector<A> {
+    /// Create connector, default attempt delay is 250 millis
+    pub fn new<R: Resolver + 'static>(resolver: R) -> Self {
+        ResolverConnector {
+            resolver: Rc::new(resolver),
+            attempt_delay: Duration::from_millis(250),
+            _t: PhantomData,
+        }
+    }
+
+    /// Set delay between connection attempts
+    pub fn attempt_delay(mut self, delay: Duration) -> Self {
+        self.attempt_delay = delay;
+        self
+    }
+}
+
+impl<A> Clone for ResolverConnector<A> {
+    fn clone(&self) -> Self {
+        ResolverConnector {
+            resolver: self.resolver.clone(),
+            attempt_delay: self.attempt_delay,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<A: Address> Service for ResolverConnector<A> {
+    type Request = Connect<A>;
+    type Response = TcpStream;
+    type Error = ConnectError;
+    type Future = Pin<Box<dyn Future<Output = Result<TcpStream, ConnectError>>>>;
+
+    #[inline]
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, req: Connect<A>) -> Self::Future {
+        let mut addrs: Vec<_> = req.addrs().collect();
+        let lookup = if !addrs.is_empty() {
+            None
+        } else if let Ok(addr) = req.host().parse::<SocketAddr>() {
+            addrs.push(addr);
+            None
+        } else if let Ok(ip) = req.host().parse::<IpAddr>() {
+            addrs.push(SocketAddr::new(ip, req.port()));
+            None
+        } else {
+            let (host, port) = split_host(req.host(), req.port());
+            trace!("Resolving host {:?}", host);
+            Some(self.resolver.lookup(host, port))
+        };
+        let delay = self.attempt_delay;
+
+        Box::pin(async move {
+            if let Some(lookup) = lookup {
+                addrs = lookup.await.map_err(ConnectError::Resolver)?;
+            }
+            race(interleave(addrs), delay).await
+        })
+    }
+}
+
+fn split_host(host: &str, port: u16) -> (&str, u16) {
+    if let Some(idx) = host.rfind(':') {
+        if let Ok(p) = host[idx + 1..].parse() {
+            return (&host[..idx], p);
+        }
+    }
+    (host, port)
+}
+
+/// Alternate address families, starting with family of first address
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_v6 = addrs.first().map(|a| a.is_ipv6()).unwrap_or(false);
+    let (mut primary, mut secondary): (Vec<_>, Vec<_>) =
+        addrs.into_iter().partition(|a| a.is_ipv6() == first_v6);
+    primary.reverse();
+    secondary.reverse();
+
+    let mut result = Vec::with_capacity(primary.len() + secondary.len());
+    loop {
+        match (primary.pop(), secondary.pop()) {
+            (None, None) => return result,
+            (a, b) => result.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+async fn race(addrs: Vec<SocketAddr>, delay: Duration) -> Result<TcpStream, ConnectError> {
+    let (tx, mut rx) = mpsc::channel();
+    let mut addrs = addrs.into_iter();
+    let mut pending = 0;
+    let mut last_err = None;
+
+    loop {
+        if let Some(addr) = addrs.next() {
+            trace!("Connecting to {:?}", addr);
+            let tx = tx.clone();
+            pending += 1;
+            ntex::rt::spawn(async move {
+                let _ = tx.send(TcpStream::connect(addr).await);
+            });
+        } else if pending == 0 {
+            return Err(last_err.map_or(ConnectError::NoRecords, ConnectError::Io));
+        }
+
+        let res = if addrs.len() > 0 {
+            match select(delay_for(delay), next(&mut rx)).await {
+                Either::Left(_) => continue,
+                Either::Right(res) => res,
+            }
+        } else {
+            next(&mut rx).await
+        };
+
+        match res {
+            Some(Ok(stream)) => {
+                let _ = stream.set_nodelay(true);
+                return Ok(stream);
+            }
+            Some(Err(err)) => {
+                trace!("Connection attempt failed: {:?}", err);
+                pending -= 1;
+                last_err = Some(err);
+            }
+            None => return Err(ConnectError::Unresolved),
+        }
+    }
+}
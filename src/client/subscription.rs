@@ -0,0 +1,268 @@
+use std::{cell::RefCell, fmt, future::Future, pin::Pin, rc::Rc};
+
+use ntex::util::{next, ByteString, HashMap};
+
+use crate::codec::protocol::{Error, Transfer};
+use crate::error::AmqpProtocolError;
+use crate::types::Outcome;
+use crate::{ReceiverLink, Session};
+
+type Handler = Rc<dyn Fn(Transfer) -> Pin<Box<dyn Future<Output = Result<Outcome, Error>>>>>;
+
+/// Credit policy of subscription
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CreditPolicy {
+    /// Up to `n` deliveries are handled concurrently,
+    /// credit is granted as handlers complete
+    Concurrent(u32),
+    /// Deliveries are handled one by one, credit `n` is
+    /// granted again once previous credit is consumed
+    Batch(u32),
+}
+
+/// Receiver subscriptions
+///
+/// Manager keeps set of subscriptions, each subscription is a receiver
+/// link with handler and credit policy. `attach()` attaches all
+/// subscriptions to a session, application calls it after connect and
+/// after each reconnect. Subscriptions could be added and removed at
+/// runtime, changes are applied to attached session immediately.
+/// If session-per-link is enabled, each subscription is attached in
+/// its own session.
+#[derive(Clone, Default)]
+pub struct SubscriptionManager(Rc<RefCell<Inner>>);
+
+#[derive(Default)]
+struct Inner {
+    session: Option<Session>,
+    subscriptions: HashMap<ByteString, Subscription>,
+}
+
+struct Subscription {
+    handler: Handler,
+    credit: CreditPolicy,
+    link: Option<ReceiverLink>,
+    // dedicated session, see `Configuration::session_per_link()`
+    session: Option<Session>,
+}
+
+impl Subscription {
+    async fn close(self) -> Result<(), AmqpProtocolError> {
+        let result = if let Some(link) = self.link {
+            link.close().await
+        } else {
+            Ok(())
+        };
+        if let Some(session) = self.session {
+            session.end();
+        }
+        result
+    }
+}
+
+impl SubscriptionManager {
+    /// Create empty subscriptions set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add subscription for address
+    ///
+    /// Subscription replaces existing subscription for the same address.
+    /// If manager is attached, receiver link is attached immediately.
+    pub async fn subscribe<T, F, R, E>(
+        &self,
+        address: T,
+        credit: CreditPolicy,
+        f: F,
+    ) -> Result<(), AmqpProtocolError>
+    where
+        T: Into<ByteString>,
+        F: Fn(Transfer) -> R + 'static,
+        R: Future<Output = Result<Outcome, E>> + 'static,
+        Error: From<E>,
+    {
+        let address = address.into();
+        let handler: Handler = Rc::new(move |tr| {
+            let fut = f(tr);
+            Box::pin(async move { fut.await.map_err(Error::from) })
+        });
+        let sub = Subscription {
+            handler,
+            credit,
+            link: None,
+            session: None,
+        };
+        let prev = self
+            .0
+            .borrow_mut()
+            .subscriptions
+            .insert(address.clone(), sub);
+        if let Some(prev) = prev {
+            let _ = prev.close().await;
+        }
+
+        let session = self.0.borrow().session.clone();
+        if let Some(session) = session {
+            self.attach_one(session, address).await
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Remove subscription and detach its receiver link
+    pub async fn unsubscribe(&self, address: &str) -> Result<(), AmqpProtocolError> {
+        let sub = self.0.borrow_mut().subscriptions.remove(address);
+        if let Some(sub) = sub {
+            sub.close().await
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Addresses of subscriptions
+    pub fn addresses(&self) -> Vec<ByteString> {
+        self.0.borrow().subscriptions.keys().cloned().collect()
+    }
+
+    /// Attach all subscriptions to session
+    ///
+    /// Links of previous session are dropped. Returns first attach error,
+    /// remaining subscriptions are attached regardless.
+    pub async fn attach(&self, session: &Session) -> Result<(), AmqpProtocolError> {
+        let addresses = {
+            let mut inner = self.0.borrow_mut();
+            inner.session = Some(session.clone());
+            for sub in inner.subscriptions.values_mut() {
+                sub.link = None;
+                sub.session = None;
+            }
+            inner.subscriptions.keys().cloned().collect::<Vec<_>>()
+        };
+
+        let mut result = Ok(());
+        for address in addresses {
+            if let Err(err) = self.attach_one(session.clone(), address).await {
+                log::trace!("Cannot attach subscription: {:?}", err);
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+        }
+        result
+    }
+
+    /// Detach all subscriptions, subscriptions are kept
+    pub async fn detach(&self) {
+        let subs: Vec<_> = {
+            let mut inner = self.0.borrow_mut();
+            inner.session = None;
+            inner
+                .subscriptions
+                .values_mut()
+                .map(|sub| Subscription {
+                    handler: sub.handler.clone(),
+                    credit: sub.credit,
+                    link: sub.link.take(),
+                    session: sub.session.take(),
+                })
+                .collect()
+        };
+        for sub in subs {
+            let _ = sub.close().await;
+        }
+    }
+
+    async fn attach_one(
+        &self,
+        session: Session,
+        address: ByteString,
+    ) -> Result<(), AmqpProtocolError> {
+        let (handler, credit) = match self.0.borrow().subscriptions.get(&address) {
+            Some(sub) => (sub.handler.clone(), sub.credit),
+            None => return Ok(()),
+        };
+
+        let mut link_session = session.connection().link_session(&session).await?;
+        let dedicated = if link_session.channel() != session.channel() {
+            Some(link_session.clone())
+        } else {
+            None
+        };
+
+        let name = ByteString::from(format!("subscription-{}", address));
+        let link = match link_session
+            .build_receiver_link(name, address.clone())
+            .open()
+            .await
+        {
+            Ok(link) => link,
+            Err(err) => {
+                if let Some(session) = dedicated {
+                    session.end();
+                }
+                return Err(err);
+            }
+        };
+
+        // subscription could be removed or replaced during attach
+        match self.0.borrow_mut().subscriptions.get_mut(&address) {
+            Some(sub) if Rc::ptr_eq(&sub.handler, &handler) => {
+                sub.link = Some(link.clone());
+                sub.session = dedicated;
+            }
+            _ => {
+                let sub = Subscription {
+                    handler,
+                    credit,
+                    link: Some(link),
+                    session: dedicated,
+                };
+                ntex::rt::spawn(async move {
+                    let _ = sub.close().await;
+                });
+                return Ok(());
+            }
+        }
+
+        trace!("Subscription for {:?} is attached", address);
+        ntex::rt::spawn(run(link, handler, credit));
+        Ok(())
+    }
+}
+
+async fn run(link: ReceiverLink, handler: Handler, credit: CreditPolicy) {
+    match credit {
+        CreditPolicy::Concurrent(concurrency) => {
+            let _ = link.start(concurrency, move |tr| (*handler)(tr)).await;
+        }
+        CreditPolicy::Batch(credit) => {
+            let credit = std::cmp::max(credit, 1);
+            let mut link = link;
+            link.set_link_credit(credit);
+
+            while let Some(Ok(transfer)) = next(&mut link).await {
+                let settled = transfer.settled.unwrap_or(false);
+                let delivery = transfer.clone();
+                let outcome = match (*handler)(transfer).await {
+                    Ok(outcome) => outcome,
+                    Err(err) => Outcome::Rejected { error: Some(err) },
+                };
+                if !settled {
+                    link.settle(&delivery, outcome);
+                }
+                if link.credit() == 0 {
+                    link.set_link_credit(credit);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SubscriptionManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubscriptionManager")
+            .field("addresses", &self.addresses())
+            .finish()
+    }
+}
@@ -0,0 +1,275 @@
+use std::{cell::Cell, error::Error, fmt, rc::Rc};
+
+use ntex::connect::ConnectError;
+
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+use std::{future::Future, io, pin::Pin, task::Context, task::Poll, time::Duration};
+
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+use ntex::connect::{self, Address, Connect};
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+use ntex::{rt::net::TcpStream, rt::time::delay_for, service::Service, util::select, util::Either};
+
+#[cfg(feature = "openssl")]
+use ntex::connect::openssl::{SslConnector, SslStream};
+
+#[cfg(feature = "rustls")]
+use ntex::connect::rustls::{ClientConfig, TlsStream};
+
+/// Tcp connect and tls handshake timeouts of tls connector
+///
+/// Timeouts are shared with `Connector`, timeouts could be
+/// changed after tls connector is configured.
+#[derive(Clone, Default)]
+pub(super) struct TlsTimeouts(Rc<Timeouts>);
+
+#[derive(Default)]
+struct Timeouts {
+    connect: Cell<u16>,
+    handshake: Cell<u16>,
+}
+
+impl TlsTimeouts {
+    pub(super) fn set_connect(&self, timeout: u16) {
+        self.0.connect.set(timeout);
+    }
+
+    pub(super) fn set_handshake(&self, timeout: u16) {
+        self.0.handshake.set(timeout);
+    }
+}
+
+/// Tls connector timeout, converted to `client::ConnectError`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(super) enum TlsTimeout {
+    Connect,
+    Handshake,
+}
+
+impl fmt::Display for TlsTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlsTimeout::Connect => f.write_str("Connect timeout"),
+            TlsTimeout::Handshake => f.write_str("Tls handshake timeout"),
+        }
+    }
+}
+
+impl Error for TlsTimeout {}
+
+impl TlsTimeout {
+    /// Check if connect error is caused by tls connector timeout
+    pub(super) fn from_error(err: &ConnectError) -> Option<TlsTimeout> {
+        if let ConnectError::Io(ref e) = err {
+            e.get_ref()
+                .and_then(|e| e.downcast_ref::<TlsTimeout>())
+                .copied()
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+async fn with_timeout<F, R>(timeout: u16, kind: TlsTimeout, fut: F) -> Result<R, ConnectError>
+where
+    F: Future<Output = Result<R, ConnectError>>,
+{
+    if timeout == 0 {
+        fut.await
+    } else {
+        match select(delay_for(Duration::from_millis(timeout as u64)), fut).await {
+            Either::Left(_) => Err(io::Error::new(io::ErrorKind::TimedOut, kind).into()),
+            Either::Right(res) => res,
+        }
+    }
+}
+
+#[cfg(feature = "openssl")]
+/// Openssl connector
+///
+/// Tcp connect is bounded by `Connector::connect_timeout()`, tls handshake
+/// is bounded by `Connector::tls_handshake_timeout()`.
+pub struct OpensslConnector<A> {
+    connector: connect::Connector<A>,
+    openssl: SslConnector,
+    timeouts: TlsTimeouts,
+}
+
+#[cfg(feature = "openssl")]
+impl<A> OpensslConnector<A> {
+    /// Create openssl connector
+    pub fn new(connector: SslConnector) -> Self {
+        OpensslConnector {
+            connector: connect::Connector::default(),
+            openssl: connector,
+            timeouts: TlsTimeouts::default(),
+        }
+    }
+
+    pub(super) fn timeouts(&self) -> &TlsTimeouts {
+        &self.timeouts
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl<A: Address + 'static> OpensslConnector<A> {
+    /// Resolve and connect to remote host
+    pub fn connect(
+        &self,
+        req: Connect<A>,
+    ) -> impl Future<Output = Result<SslStream<TcpStream>, ConnectError>> {
+        let host = req.host().to_string();
+        let conn = self.connector.call(req);
+        let openssl = self.openssl.clone();
+        let timeouts = self.timeouts.0.clone();
+
+        async move {
+            let io = with_timeout(timeouts.connect.get(), TlsTimeout::Connect, conn).await?;
+            trace!("Tls handshake start for: {:?}", host);
+
+            let handshake = async move {
+                let config = openssl
+                    .configure()
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                let ssl = config
+                    .into_ssl(&host)
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                let mut io =
+                    SslStream::new(ssl, io).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                match Pin::new(&mut io).connect().await {
+                    Ok(_) => {
+                        trace!("Tls handshake success: {:?}", host);
+                        Ok(io)
+                    }
+                    Err(e) => {
+                        trace!("Tls handshake error: {:?}", e);
+                        Err(io::Error::new(io::ErrorKind::Other, format!("{}", e)).into())
+                    }
+                }
+            };
+            with_timeout(timeouts.handshake.get(), TlsTimeout::Handshake, handshake).await
+        }
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl<A> Clone for OpensslConnector<A> {
+    fn clone(&self) -> Self {
+        OpensslConnector {
+            connector: self.connector.clone(),
+            openssl: self.openssl.clone(),
+            timeouts: self.timeouts.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl<A: Address + 'static> Service for OpensslConnector<A> {
+    type Request = Connect<A>;
+    type Response = SslStream<TcpStream>;
+    type Error = ConnectError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    #[inline]
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, req: Connect<A>) -> Self::Future {
+        Box::pin(self.connect(req))
+    }
+}
+
+#[cfg(feature = "rustls")]
+/// Rustls connector
+///
+/// Tcp connect is bounded by `Connector::connect_timeout()`, tls handshake
+/// is bounded by `Connector::tls_handshake_timeout()`.
+pub struct RustlsConnector<A> {
+    connector: connect::Connector<A>,
+    config: std::sync::Arc<ClientConfig>,
+    timeouts: TlsTimeouts,
+}
+
+#[cfg(feature = "rustls")]
+impl<A> RustlsConnector<A> {
+    /// Create rustls connector
+    pub fn new(config: std::sync::Arc<ClientConfig>) -> Self {
+        RustlsConnector {
+            connector: connect::Connector::default(),
+            config,
+            timeouts: TlsTimeouts::default(),
+        }
+    }
+
+    pub(super) fn timeouts(&self) -> &TlsTimeouts {
+        &self.timeouts
+    }
+}
+
+#[cfg(feature = "rustls")]
+impl<A: Address + 'static> RustlsConnector<A> {
+    /// Resolve and connect to remote host
+    pub fn connect(
+        &self,
+        req: Connect<A>,
+    ) -> impl Future<Output = Result<TlsStream<TcpStream>, ConnectError>> {
+        let host = req.host().split(':').next().unwrap().to_owned();
+        let conn = self.connector.call(req);
+        let config = self.config.clone();
+        let timeouts = self.timeouts.0.clone();
+
+        async move {
+            let io = with_timeout(timeouts.connect.get(), TlsTimeout::Connect, conn).await?;
+            trace!("Tls handshake start for: {:?}", host);
+
+            let handshake = async move {
+                let host = webpki::DNSNameRef::try_from_ascii_str(&host)
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{}", e)))?;
+                match tokio_rustls::TlsConnector::from(config)
+                    .connect(host, io)
+                    .await
+                {
+                    Ok(io) => {
+                        trace!("Tls handshake success: {:?}", host);
+                        Ok(io)
+                    }
+                    Err(e) => {
+                        trace!("Tls handshake error: {:?}", e);
+                        Err(io::Error::new(io::ErrorKind::Other, format!("{}", e)).into())
+                    }
+                }
+            };
+            with_timeout(timeouts.handshake.get(), TlsTimeout::Handshake, handshake).await
+        }
+    }
+}
+
+#[cfg(feature = "rustls")]
+impl<A> Clone for RustlsConnector<A> {
+    fn clone(&self) -> Self {
+        RustlsConnector {
+            connector: self.connector.clone(),
+            config: self.config.clone(),
+            timeouts: self.timeouts.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "rustls")]
+impl<A: Address + 'static> Service for RustlsConnector<A> {
+    type Request = Connect<A>;
+    type Response = TlsStream<TcpStream>;
+    type Error = ConnectError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    #[inline]
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, req: Connect<A>) -> Self::Future {
+        Box::pin(self.connect(req))
+    }
+}
@@ -1,15 +1,27 @@
+use std::collections::VecDeque;
 use std::future::Future;
 
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
 use ntex::channel::{condition::Condition, condition::Waiter, oneshot};
 use ntex::framed::State;
-use ntex::util::{HashMap, Ready};
+use ntex::rt::time::sleep;
+use ntex::util::{select, ByteString, Either, HashMap, Ready};
 
 use crate::cell::Cell;
-use crate::codec::protocol::{Begin, Close, End, Error, Frame};
-use crate::codec::{AmqpCodec, AmqpCodecError, AmqpFrame};
+use crate::codec::protocol::{
+    Begin, Close, End, Error, Frame, ReceiverSettleMode, SenderSettleMode,
+};
+use crate::codec::{AmqpCodec, AmqpCodecError, AmqpFrame, CodecMetrics};
 use crate::error::AmqpProtocolError;
+use crate::hooks::{ContainerRegistry, DeliveryQuota, LinkRegistry};
+use crate::interceptor::Interceptors;
+use crate::retry::RetryPolicy;
 use crate::session::{Session, SessionInner};
-use crate::Configuration;
+use crate::shutdown::{self, ShutdownReport, ShutdownTimeouts};
+use crate::store::{LinkStateStore, MemoryLinkStateStore};
+use crate::{Configuration, Spawner};
 
 #[derive(Clone)]
 pub struct Connection(pub(crate) Cell<ConnectionInner>);
@@ -24,6 +36,31 @@ pub(crate) struct ConnectionInner {
     pub(crate) error: Option<AmqpProtocolError>,
     channel_max: usize,
     pub(crate) max_frame_size: usize,
+    pub(crate) session_window: Option<(u32, u32)>,
+    pub(crate) max_pending_transfers: Option<usize>,
+    pub(crate) max_queued_transfers: Option<usize>,
+    pub(crate) snd_settle_mode: SenderSettleMode,
+    pub(crate) rcv_settle_mode: ReceiverSettleMode,
+    pub(crate) auto_accept: bool,
+    pub(crate) prefetch: u32,
+    pub(crate) settlement_timeout: Option<Duration>,
+    pub(crate) starvation_threshold: Option<Duration>,
+    pub(crate) disposition_batch: Option<(Duration, usize)>,
+    pub(crate) attach_timeout: Option<Duration>,
+    pub(crate) session_quantum: Option<usize>,
+    pub(crate) session_per_link: bool,
+    pub(crate) session_flow_events: bool,
+    pub(crate) link_store: Option<Rc<dyn LinkStateStore>>,
+    pub(crate) ready_sessions: VecDeque<usize>,
+    pub(crate) remote_container_id: ByteString,
+    pub(crate) link_names: Option<Rc<dyn LinkRegistry>>,
+    pub(crate) container_ids: Option<Rc<dyn ContainerRegistry>>,
+    pub(crate) retry_policy: Option<Rc<dyn RetryPolicy>>,
+    pub(crate) interceptors: Interceptors,
+    pub(crate) identity: Option<ByteString>,
+    pub(crate) quota: Option<Box<dyn DeliveryQuota>>,
+    pub(crate) codec_metrics: CodecMetrics,
+    last_frame: Instant,
 }
 
 pub(crate) enum ChannelState {
@@ -55,7 +92,7 @@ impl Connection {
     ) -> Connection {
         Connection(Cell::new(ConnectionInner {
             state,
-            codec: AmqpCodec::new(),
+            codec: AmqpCodec::new().compact(local_config.compact_encoding),
             st: ConnectionState::Normal,
             sessions: slab::Slab::with_capacity(8),
             sessions_map: HashMap::default(),
@@ -63,9 +100,92 @@ impl Connection {
             on_close: Condition::new(),
             channel_max: local_config.channel_max,
             max_frame_size: remote_config.max_frame_size as usize,
+            session_window: local_config.session_window,
+            max_pending_transfers: local_config.max_pending_transfers,
+            max_queued_transfers: local_config.max_queued_transfers,
+            snd_settle_mode: local_config.snd_settle_mode,
+            rcv_settle_mode: local_config.rcv_settle_mode,
+            auto_accept: local_config.auto_accept,
+            prefetch: local_config.prefetch,
+            settlement_timeout: local_config.settlement_timeout,
+            starvation_threshold: local_config.starvation_threshold,
+            disposition_batch: local_config.disposition_batch,
+            attach_timeout: local_config.attach_timeout,
+            session_quantum: local_config.session_quantum,
+            session_per_link: local_config.session_per_link,
+            session_flow_events: local_config.session_flow_events,
+            link_store: if local_config.link_resume {
+                Some(Rc::new(MemoryLinkStateStore::new()))
+            } else {
+                None
+            },
+            ready_sessions: VecDeque::new(),
+            remote_container_id: remote_config.container_id.clone().unwrap_or_default(),
+            link_names: None,
+            container_ids: None,
+            retry_policy: None,
+            interceptors: Interceptors::default(),
+            identity: None,
+            quota: None,
+            codec_metrics: CodecMetrics::new(local_config.malformed_capture),
+            last_frame: Instant::now(),
         }))
     }
 
+    /// Time of last frame received from the peer
+    ///
+    /// Includes empty frames that peer sends as heartbeats.
+    pub fn last_frame_received(&self) -> Instant {
+        self.0.get_ref().last_frame
+    }
+
+    /// Register staleness callback.
+    ///
+    /// Callback is called with time since last received frame if peer does
+    /// not send any frame within `threshold`. Threshold is usually set below
+    /// local idle timeout, so application could reconnect before connection
+    /// gets closed. Callback is called again only after peer sends new frame
+    /// and becomes stale again.
+    pub fn on_stale<F>(&self, threshold: Duration, f: F)
+    where
+        F: Fn(Duration) + 'static,
+    {
+        let cell = self.0.clone();
+        let threshold = std::cmp::max(threshold, Duration::from_millis(1));
+
+        ntex::rt::spawn(async move {
+            let mut notified = None;
+            loop {
+                let inner = cell.get_ref();
+                if inner.error.is_some() {
+                    break;
+                }
+                let last = inner.last_frame;
+                let elapsed = last.elapsed();
+                let delay = if elapsed < threshold {
+                    threshold - elapsed
+                } else {
+                    if notified != Some(last) {
+                        log::trace!("Connection is stale for {:?}", elapsed);
+                        notified = Some(last);
+                        f(elapsed);
+                    }
+                    threshold
+                };
+                if let Either::Right(_) = select(sleep(delay), inner.on_close.wait()).await {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Codec error metrics of the connection
+    ///
+    /// Metrics count malformed frames received from peer.
+    pub fn codec_metrics(&self) -> CodecMetrics {
+        self.0.get_ref().codec_metrics.clone()
+    }
+
     #[inline]
     /// Force close connection
     pub fn force_close(&self) {
@@ -84,11 +204,47 @@ impl Connection {
         inner.error.is_none()
     }
 
+    /// Task spawner bound to connection lifetime
+    ///
+    /// Tasks spawned with spawner are cancelled on connection close.
+    pub fn spawner(&self) -> Spawner {
+        Spawner(self.0.clone())
+    }
+
     /// Get waiter for on_close event
     pub fn on_close(&self) -> Waiter {
         self.0.get_ref().on_close.wait()
     }
 
+    /// Set message interceptors for all links of this connection
+    ///
+    /// Connection interceptors run before link interceptors.
+    pub fn set_interceptors(&self, interceptors: Interceptors) {
+        self.0.get_mut().interceptors = interceptors;
+    }
+
+    /// Wait for connection close.
+    ///
+    /// Returned future resolves with close reason, `AmqpProtocolError::Closed`
+    /// for connection closed by peer or `AmqpProtocolError::Disconnected`
+    /// for dropped connection. Resolves immediately if connection is
+    /// already closed.
+    pub fn closed(&self) -> impl Future<Output = Option<AmqpProtocolError>> {
+        let cell = self.0.clone();
+        let waiter = if cell.get_ref().error.is_some() {
+            None
+        } else {
+            Some(cell.get_ref().on_close.wait())
+        };
+
+        async move {
+            if let Some(waiter) = waiter {
+                waiter.await;
+            }
+            cell.get_ref().error.clone()
+        }
+    }
+
     /// Get connection error
     pub fn get_error(&self) -> Option<AmqpProtocolError> {
         self.0.get_ref().error.clone()
@@ -96,10 +252,25 @@ impl Connection {
 
     /// Gracefully close connection
     pub fn close(&self) -> impl Future<Output = Result<(), AmqpProtocolError>> {
+        for (_, channel) in self.0.get_ref().sessions.iter() {
+            if let ChannelState::Established(ref ses) = channel {
+                ses.get_mut().flush_dispositions();
+            }
+        }
         self.0.get_ref().state.close();
         Ready::Ok(())
     }
 
+    /// Ordered connection shutdown
+    ///
+    /// Detaches all links with `closed` flag, ends all sessions and
+    /// closes connection. Each phase waits for peer's confirmations
+    /// within phase timeout, connection is dropped if close is not
+    /// confirmed in time. Returns report of completed phases.
+    pub fn shutdown(&self, timeouts: ShutdownTimeouts) -> impl Future<Output = ShutdownReport> {
+        shutdown::shutdown(self.clone(), timeouts)
+    }
+
     // TODO: implement
     /// Close connection with error
     pub fn close_with_error<E>(
@@ -139,7 +310,7 @@ impl Connection {
                     let begin = Begin {
                         remote_channel: None,
                         next_outgoing_id: 1,
-                        incoming_window: std::u32::MAX,
+                        incoming_window: inner.initial_window(),
                         outgoing_window: std::u32::MAX,
                         handle_max: std::u32::MAX,
                         offered_capabilities: None,
@@ -154,6 +325,27 @@ impl Connection {
         }
     }
 
+    /// Session for a new link
+    ///
+    /// Opens new session if session-per-link is enabled, see
+    /// `Configuration::session_per_link()`, otherwise returns `session`.
+    pub fn link_session(
+        &self,
+        session: &Session,
+    ) -> impl Future<Output = Result<Session, AmqpProtocolError>> {
+        let per_link = self.0.get_ref().session_per_link;
+        let open = self.open_session();
+        let session = session.clone();
+
+        async move {
+            if per_link {
+                open.await
+            } else {
+                Ok(session)
+            }
+        }
+    }
+
     /// Get session by remote id. This method panics if session does not exists or in opening/closing state.
     pub(crate) fn get_remote_session(&self, id: usize) -> Option<Cell<SessionInner>> {
         let inner = self.0.get_ref();
@@ -195,7 +387,7 @@ impl Connection {
         let begin = Begin {
             remote_channel: Some(channel_id),
             next_outgoing_id: 1,
-            incoming_window: std::u32::MAX,
+            incoming_window: inner.initial_window(),
             outgoing_window: begin.incoming_window(),
             handle_max: std::u32::MAX,
             offered_capabilities: None,
@@ -210,9 +402,35 @@ impl Connection {
             .map(|_| ())
     }
 
+    /// End session with error
+    pub(crate) fn end_session(&self, id: u16, err: Option<Error>) {
+        self.end_session_with(id, err, None)
+    }
+
+    /// End session, `tx` is notified once peer confirms end
+    pub(crate) fn end_session_with(
+        &self,
+        id: u16,
+        err: Option<Error>,
+        tx: Option<oneshot::Sender<Result<(), AmqpProtocolError>>>,
+    ) {
+        let inner = self.0.get_mut();
+        if let Some(channel) = inner.sessions.get_mut(id as usize) {
+            if let ChannelState::Established(ref session) = channel {
+                let session = session.clone();
+                session.get_mut().flush_dispositions();
+                *channel = ChannelState::Closing(tx);
+                session
+                    .get_mut()
+                    .set_error(AmqpProtocolError::SessionEnded(err.clone()));
+                inner.post_frame(AmqpFrame::new(id, End { error: err }.into()));
+            }
+        }
+    }
+
     pub(crate) fn post_frame(&self, frame: AmqpFrame) {
         #[cfg(feature = "frame-trace")]
-        log::trace!("outcoming: {:#?}", frame);
+        log::trace!("outcoming: {}", crate::codec::pretty(&frame));
 
         let inner = self.0.get_mut();
         if let Err(e) = inner.state.write().encode(frame, &inner.codec) {
@@ -222,6 +440,13 @@ impl Connection {
 }
 
 impl ConnectionInner {
+    /// Initial session incoming window
+    pub(crate) fn initial_window(&self) -> u32 {
+        self.session_window
+            .map(|(min, _)| min)
+            .unwrap_or(std::u32::MAX)
+    }
+
     pub(crate) fn set_error(&mut self, err: AmqpProtocolError) {
         log::trace!("Set connection error: {:?}", err);
         for (_, channel) in self.sessions.iter_mut() {
@@ -240,6 +465,84 @@ impl ConnectionInner {
         }
     }
 
+    /// Period of connection maintenance checks
+    pub(crate) fn maintenance_period(&self) -> Option<Duration> {
+        let period = match (self.settlement_timeout, self.starvation_threshold) {
+            (Some(t1), Some(t2)) => Some(std::cmp::min(t1, t2) / 2),
+            (Some(t), None) | (None, Some(t)) => Some(t / 2),
+            (None, None) => None,
+        };
+        match (period, self.disposition_batch) {
+            (Some(period), Some((window, _))) => Some(std::cmp::min(period, window)),
+            (None, Some((window, _))) => Some(window),
+            (period, None) => period,
+        }
+    }
+
+    /// Settle deliveries that are not settled within settlement timeout,
+    /// check sender links for credit starvation and flush batched dispositions
+    pub(crate) fn maintenance(&mut self) {
+        for (_, channel) in self.sessions.iter_mut() {
+            if let ChannelState::Established(ref mut ses) = channel {
+                let ses = ses.get_mut();
+                ses.flush_dispositions();
+                if let Some(timeout) = self.settlement_timeout {
+                    ses.sweep_unsettled(timeout);
+                }
+                if let Some(threshold) = self.starvation_threshold {
+                    ses.check_starvation(threshold);
+                }
+            }
+        }
+    }
+
+    /// Register session with sendable queued transfers
+    pub(crate) fn ready_session(&mut self, id: usize) {
+        if !self.ready_sessions.contains(&id) {
+            self.ready_sessions.push_back(id);
+            self.state.wake_dispatcher();
+        }
+    }
+
+    /// Send queued transfers of ready sessions in round-robin order
+    pub(crate) fn schedule(&mut self) {
+        if let Some(quantum) = self.session_quantum {
+            while let Some(id) = self.ready_sessions.pop_front() {
+                if let Some(ChannelState::Established(ses)) = self.sessions.get(id) {
+                    let ses = ses.clone();
+                    let ses = ses.get_mut();
+                    if ses.drain_pending(quantum.saturating_mul(ses.weight)) {
+                        self.ready_sessions.push_back(id);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send close frame, connection is closed once peer confirms close
+    pub(crate) fn close_local(&mut self) {
+        for (_, channel) in self.sessions.iter() {
+            if let ChannelState::Established(ref ses) = channel {
+                ses.get_mut().flush_dispositions();
+            }
+        }
+        self.post_frame(AmqpFrame::new(0, Close { error: None }.into()));
+        self.st = ConnectionState::Closing;
+    }
+
+    /// Close connection with error, used for peer's protocol violations
+    pub(crate) fn close_with_error(&mut self, err: Error) {
+        log::trace!("Close connection with error: {:?}", err);
+        self.post_frame(AmqpFrame::new(
+            0,
+            Frame::Close(Close {
+                error: Some(err.clone()),
+            }),
+        ));
+        self.set_error(AmqpProtocolError::Closed(Some(err)));
+        self.state.close();
+    }
+
     pub(crate) fn post_frame(&mut self, frame: AmqpFrame) {
         if let Err(e) = self.state.write().encode(frame, &self.codec) {
             self.set_error(e.into())
@@ -291,6 +594,8 @@ impl ConnectionInner {
         &mut self,
         frame: AmqpFrame,
     ) -> Result<Option<AmqpFrame>, AmqpProtocolError> {
+        self.last_frame = Instant::now();
+
         if let Frame::Empty = frame.performative() {
             return Ok(None);
         }
@@ -301,6 +606,7 @@ impl ConnectionInner {
             if self.st == ConnectionState::Closing {
                 log::trace!("Connection closed: {:?}", close);
                 self.set_error(AmqpProtocolError::Disconnected);
+                self.state.close();
             } else {
                 log::trace!("Connection closed remotely: {:?}", close);
                 let close = Close { error: None };
@@ -315,6 +621,11 @@ impl ConnectionInner {
             return Ok(None);
         }
 
+        // unknown performatives are handled by control service
+        if let Frame::Unknown(_) = frame.performative() {
+            return Ok(Some(frame));
+        }
+
         // get local session id
         let state = if let Some(token) = self.sessions_map.get(&frame.channel_id()) {
             if let Some(state) = self.sessions.get_mut(*token) {
@@ -367,6 +678,7 @@ impl ConnectionInner {
                 Frame::End(remote_end) => {
                     trace!("Remote session end: {}", frame.channel_id());
                     let end = End { error: None };
+                    session.get_mut().flush_dispositions();
                     session
                         .get_mut()
                         .set_error(AmqpProtocolError::SessionEnded(remote_end.error.clone()));
@@ -377,6 +689,14 @@ impl ConnectionInner {
                     }
                     Ok(None)
                 }
+                Frame::Transfer(transfer)
+                    if !session.get_ref().is_remote_handle(transfer.handle()) =>
+                {
+                    Err(AmqpProtocolError::UnattachedHandle(
+                        frame.channel_id() as usize,
+                        transfer.handle(),
+                    ))
+                }
                 _ => {
                     session.get_mut().handle_frame(frame.into_parts().1);
                     Ok(None)
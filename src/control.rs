@@ -24,13 +24,30 @@ impl fmt::Debug for ControlFrame {
 }
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ControlFrameKind {
     AttachReceiver(ReceiverLink),
     AttachSender(Box<protocol::Attach>, SenderLink),
     Flow(protocol::Flow, SenderLink),
+    /// Session flow without link handle, updates session window only.
+    /// Emitted if `Configuration::session_flow_events()` is enabled,
+    /// control service error ends the session
+    SessionFlow(protocol::Flow),
     DetachSender(protocol::Detach, SenderLink),
     DetachReceiver(protocol::Detach, ReceiverLink),
     ProtocolError(AmqpProtocolError),
+    /// Frame with unknown performative, contains channel id and raw frame
+    Unknown(u16, Box<protocol::UnknownFrame>),
+    /// Remote peer began new session, control service error ends the session
+    SessionBegin,
+    /// Session is ended by remote peer, contains peer's error
+    SessionEnded(Option<protocol::Error>),
+    /// Frame is received on channel without session, connection
+    /// is closed with `amqp:not-allowed` error
+    UnknownChannel(u16),
+    /// Frame refers to link handle that is not attached, session
+    /// is ended with `amqp:session:unattached-handle` error
+    UnattachedHandle(protocol::Handle),
     Closed(bool),
 }
 
@@ -42,6 +59,13 @@ impl ControlFrame {
         }))
     }
 
+    pub(crate) fn with_session(
+        session: Option<Cell<SessionInner>>,
+        kind: ControlFrameKind,
+    ) -> Self {
+        ControlFrame(Cell::new(FrameInner { session, kind }))
+    }
+
     pub(crate) fn new_kind(kind: ControlFrameKind) -> Self {
         ControlFrame(Cell::new(FrameInner {
             session: None,
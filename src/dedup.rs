@@ -0,0 +1,76 @@
+use std::collections::{HashSet, VecDeque};
+
+use ntex::util::ByteString;
+
+use crate::codec::protocol::{MessageId, Transfer, TransferBody};
+use crate::codec::{types::Variant, Decode, Message};
+
+/// Message key used for duplicate detection
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DedupKey {
+    /// Use `message-id` message property
+    MessageId,
+    /// Use application property with specified name
+    AppProperty(ByteString),
+}
+
+/// Sliding window of recently seen message keys
+#[derive(Debug)]
+pub(crate) struct DedupFilter {
+    key: DedupKey,
+    window: usize,
+    seen: HashSet<Variant>,
+    order: VecDeque<Variant>,
+}
+
+impl DedupFilter {
+    pub(crate) fn new(key: DedupKey, window: usize) -> Self {
+        let window = std::cmp::max(window, 1);
+        DedupFilter {
+            key,
+            window,
+            seen: HashSet::with_capacity(window),
+            order: VecDeque::with_capacity(window),
+        }
+    }
+
+    /// Check if transfer is a duplicate, remember key otherwise
+    ///
+    /// Transfers without key are never treated as duplicates.
+    pub(crate) fn is_duplicate(&mut self, transfer: &Transfer) -> bool {
+        let key = match transfer.body {
+            Some(TransferBody::Data(ref data)) => match Message::decode(data) {
+                Ok((_, msg)) => self.message_key(&msg),
+                Err(_) => None,
+            },
+            Some(TransferBody::Message(ref msg)) => self.message_key(msg),
+            None => None,
+        };
+
+        if let Some(key) = key {
+            if self.seen.contains(&key) {
+                return true;
+            }
+            if self.order.len() >= self.window {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.seen.remove(&oldest);
+                }
+            }
+            self.seen.insert(key.clone());
+            self.order.push_back(key);
+        }
+        false
+    }
+
+    fn message_key(&self, msg: &Message) -> Option<Variant> {
+        match self.key {
+            DedupKey::MessageId => msg.properties()?.message_id.as_ref().map(|id| match id {
+                MessageId::Ulong(v) => Variant::Ulong(*v),
+                MessageId::Uuid(v) => Variant::Uuid(*v),
+                MessageId::Binary(v) => Variant::Binary(v.clone()),
+                MessageId::String(v) => Variant::from(v.clone()),
+            }),
+            DedupKey::AppProperty(ref name) => msg.app_property(name).cloned(),
+        }
+    }
+}
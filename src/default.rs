@@ -56,6 +56,12 @@ impl<S, E> Default for DefaultControlService<S, E> {
     }
 }
 
+impl<S, E> Clone for DefaultControlService<S, E> {
+    fn clone(&self) -> Self {
+        DefaultControlService(PhantomData)
+    }
+}
+
 impl<S, E> ServiceFactory for DefaultControlService<S, E> {
     type Config = State<S>;
     type Request = ControlFrame;
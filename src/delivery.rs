@@ -0,0 +1,283 @@
+use std::{io, pin::Pin};
+
+use ntex::codec::AsyncWrite;
+use ntex::util::{next, poll_fn, Buf, Bytes, BytesMut};
+use ntex_amqp_codec::protocol::{
+    DeliveryState, Disposition, Modified, Role, Section, Transfer, TransferBody,
+};
+use ntex_amqp_codec::{types::List, AmqpParseError, Decode, Encode};
+
+use crate::error::DeliveryError;
+use crate::{rcvlink::ReceiverLink, types::Outcome};
+
+const DATA_DESCRIPTOR: &[u8] = &[0x00, 0x53, 0x75];
+const FORMATCODE_BINARY8: u8 = 0xa0;
+const FORMATCODE_BINARY32: u8 = 0xb0;
+
+/// Incoming delivery of streaming receiver link
+///
+/// Delivery holds first transfer frame, rest of the frames are
+/// read from the link by `write_body_to()`.
+#[derive(Debug)]
+pub struct IncomingDelivery {
+    link: ReceiverLink,
+    transfer: Transfer,
+    complete: bool,
+    buf: BytesMut,
+}
+
+impl IncomingDelivery {
+    pub(crate) fn new(link: ReceiverLink, transfer: Transfer) -> Self {
+        IncomingDelivery {
+            complete: !transfer.more,
+            link,
+            transfer,
+            buf: BytesMut::new(),
+        }
+    }
+
+    /// First transfer frame of the delivery
+    pub fn transfer(&self) -> &Transfer {
+        &self.transfer
+    }
+
+    /// Check if all transfer frames are received
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Write content of message data sections to `dst`
+    ///
+    /// Frames are written as they arrive, other message sections are
+    /// skipped. IncomingDelivery is settled as accepted after last frame is
+    /// written and flushed, on failure delivery is settled as modified
+    /// with `delivery-failed` flag. Returns number of written bytes.
+    pub async fn write_body_to<W>(mut self, dst: &mut W) -> Result<u64, DeliveryError>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let res = self.write_sections(dst).await;
+        match res {
+            Ok(_) => self.link.settle(&self.transfer, Outcome::Accepted),
+            Err(DeliveryError::Aborted) => (),
+            Err(_) => self.settle_failed(),
+        }
+        res
+    }
+
+    /// Read next `amqp-sequence` message section
+    ///
+    /// Sections are decoded as soon as all their frames are received,
+    /// other message sections are skipped. Returns `None` after last
+    /// frame of the delivery. Delivery must be settled with `settle()`.
+    pub async fn next_sequence(&mut self) -> Result<Option<List>, DeliveryError> {
+        loop {
+            match self.transfer.body.take() {
+                Some(TransferBody::Data(data)) => self.buf.extend_from_slice(&data),
+                Some(TransferBody::Message(msg)) => msg.encode(&mut self.buf),
+                None => (),
+            }
+
+            while !self.buf.is_empty() {
+                match Section::decode(&self.buf) {
+                    Ok((rest, section)) => {
+                        let size = self.buf.len() - rest.len();
+                        self.buf.advance(size);
+                        if let Section::AmqpSequence(seq) = section {
+                            return Ok(Some(seq));
+                        }
+                    }
+                    Err(AmqpParseError::Incomplete(_)) => break,
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            if self.complete {
+                return if self.buf.is_empty() {
+                    Ok(None)
+                } else {
+                    Err(DeliveryError::Parse(AmqpParseError::Incomplete(None)))
+                };
+            }
+
+            let transfer = next(&mut self.link).await.ok_or(DeliveryError::Protocol(
+                crate::error::AmqpProtocolError::Disconnected,
+            ))??;
+            if transfer.aborted {
+                self.complete = true;
+                return Err(DeliveryError::Aborted);
+            }
+            self.complete = !transfer.more;
+            self.transfer.body = transfer.body;
+        }
+    }
+
+    /// Settle delivery
+    ///
+    /// Rest of the frames of incomplete delivery are skipped.
+    pub fn settle(self, outcome: Outcome) {
+        self.link.settle(&self.transfer, outcome);
+    }
+
+    async fn write_sections<W>(&mut self, dst: &mut W) -> Result<u64, DeliveryError>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut sections = DataSections::default();
+        let mut written = 0;
+        let mut body = self.transfer.body.take();
+
+        loop {
+            match body {
+                Some(TransferBody::Data(data)) => sections.feed(&data),
+                Some(TransferBody::Message(msg)) => {
+                    let mut buf = BytesMut::with_capacity(msg.encoded_size());
+                    msg.encode(&mut buf);
+                    sections.feed(&buf);
+                }
+                None => (),
+            }
+            while let Some(chunk) = sections.next_chunk()? {
+                write_all(dst, &chunk).await?;
+                written += chunk.len() as u64;
+            }
+            if self.complete {
+                break;
+            }
+
+            let transfer = next(&mut self.link).await.ok_or(DeliveryError::Protocol(
+                crate::error::AmqpProtocolError::Disconnected,
+            ))??;
+            if transfer.aborted {
+                self.complete = true;
+                return Err(DeliveryError::Aborted);
+            }
+            self.complete = !transfer.more;
+            body = transfer.body;
+        }
+
+        if !sections.is_empty() {
+            return Err(DeliveryError::Parse(AmqpParseError::Incomplete(None)));
+        }
+        poll_fn(|cx| Pin::new(&mut *dst).poll_flush(cx)).await?;
+        Ok(written)
+    }
+
+    fn settle_failed(&self) {
+        if self.transfer.is_settled() {
+            return;
+        }
+        if let Some(delivery_id) = self.transfer.delivery_id {
+            self.link.send_disposition(Disposition {
+                role: Role::Receiver,
+                first: delivery_id,
+                last: None,
+                settled: true,
+                state: Some(DeliveryState::Modified(Modified {
+                    delivery_failed: Some(true),
+                    undeliverable_here: None,
+                    message_annotations: None,
+                })),
+                batchable: false,
+            });
+        }
+    }
+}
+
+impl Drop for IncomingDelivery {
+    fn drop(&mut self) {
+        if !self.complete {
+            // rest of the frames are not needed
+            self.link.skip_streamed();
+        }
+    }
+}
+
+async fn write_all<W>(dst: &mut W, mut buf: &[u8]) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    while !buf.is_empty() {
+        let size = poll_fn(|cx| Pin::new(&mut *dst).poll_write(cx, buf)).await?;
+        if size == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        buf = &buf[size..];
+    }
+    Ok(())
+}
+
+/// Incremental parser of message sections
+///
+/// Content of data sections is returned as soon as it is received,
+/// other sections are buffered until they are complete and skipped.
+#[derive(Default)]
+struct DataSections {
+    buf: BytesMut,
+    remaining: usize,
+}
+
+impl DataSections {
+    fn feed(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.remaining == 0 && self.buf.is_empty()
+    }
+
+    fn next_chunk(&mut self) -> Result<Option<Bytes>, AmqpParseError> {
+        loop {
+            if self.remaining > 0 {
+                if self.buf.is_empty() {
+                    return Ok(None);
+                }
+                let size = std::cmp::min(self.remaining, self.buf.len());
+                self.remaining -= size;
+                return Ok(Some(self.buf.split_to(size).freeze()));
+            }
+            if self.buf.len() < DATA_DESCRIPTOR.len() {
+                return Ok(None);
+            }
+
+            if &self.buf[..DATA_DESCRIPTOR.len()] == DATA_DESCRIPTOR {
+                let len = DATA_DESCRIPTOR.len();
+                match self.buf.get(len) {
+                    None => return Ok(None),
+                    Some(&FORMATCODE_BINARY8) => {
+                        if self.buf.len() < len + 2 {
+                            return Ok(None);
+                        }
+                        self.remaining = self.buf[len + 1] as usize;
+                        self.buf.advance(len + 2);
+                        continue;
+                    }
+                    Some(&FORMATCODE_BINARY32) => {
+                        if self.buf.len() < len + 5 {
+                            return Ok(None);
+                        }
+                        let mut size = [0; 4];
+                        size.copy_from_slice(&self.buf[len + 1..len + 5]);
+                        self.remaining = u32::from_be_bytes(size) as usize;
+                        self.buf.advance(len + 5);
+                        continue;
+                    }
+                    Some(_) => (),
+                }
+            }
+
+            match Section::decode(&self.buf) {
+                Ok((rest, section)) => {
+                    let size = self.buf.len() - rest.len();
+                    self.buf.advance(size);
+                    if let Section::Data(data) = section {
+                        if !data.is_empty() {
+                            return Ok(Some(data));
+                        }
+                    }
+                }
+                Err(AmqpParseError::Incomplete(_)) => return Ok(None),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
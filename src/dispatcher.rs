@@ -1,16 +1,21 @@
-use std::{cell::RefCell, fmt, future::Future, pin::Pin, task::Context, task::Poll, time};
+use std::time;
+use std::{cell::RefCell, fmt, future::Future, pin::Pin, rc::Rc, task::Context, task::Poll};
 
 use ntex::framed::DispatchItem;
 use ntex::rt::time::{sleep, Sleep};
 use ntex::service::Service;
-use ntex::util::Ready;
+use ntex::util::{ByteString, Either, Ready};
 
 use crate::cell::Cell;
-use crate::codec::protocol::{Frame, Role};
+use crate::codec::protocol::{AmqpError, Frame, Handle, Role, SessionError};
 use crate::codec::{AmqpCodec, AmqpFrame};
 use crate::error::{AmqpProtocolError, DispatcherError, Error};
 use crate::sndlink::{SenderLink, SenderLinkInner};
-use crate::{connection::Connection, types, ControlFrame, ControlFrameKind, State};
+use crate::{connection::Connection, types, ControlFrame, ControlFrameKind, LinkState, State};
+
+/// Handler for links attached by remote receivers
+pub(crate) type OutgoingHandler<St> =
+    Rc<dyn Fn(types::OutgoingLink<St>) -> Pin<Box<dyn Future<Output = Result<(), Error>>>>>;
 
 /// Amqp server dispatcher service.
 pub(crate) struct Dispatcher<St, Sr, Ctl: Service> {
@@ -18,10 +23,12 @@ pub(crate) struct Dispatcher<St, Sr, Ctl: Service> {
     sink: Connection,
     service: Sr,
     ctl_service: Ctl,
+    outgoing: Option<OutgoingHandler<St>>,
     ctl_fut: RefCell<Option<(ControlFrame, Pin<Box<Ctl::Future>>)>>,
     shutdown: std::cell::Cell<bool>,
     expire: RefCell<Pin<Box<Sleep>>>,
     idle_timeout: usize,
+    maintenance: Option<(time::Duration, RefCell<Pin<Box<Sleep>>>)>,
 }
 
 impl<St, Sr, Ctl> Dispatcher<St, Sr, Ctl>
@@ -40,12 +47,19 @@ where
         ctl_service: Ctl,
         idle_timeout: usize,
     ) -> Self {
+        let maintenance = sink.0.get_ref().maintenance_period().map(|period| {
+            let period = std::cmp::max(period, time::Duration::from_millis(1));
+            (period, RefCell::new(Box::pin(sleep(period))))
+        });
+
         Dispatcher {
+            maintenance,
             sink,
             state,
             service,
             ctl_service,
             idle_timeout,
+            outgoing: None,
             ctl_fut: RefCell::new(None),
             shutdown: std::cell::Cell::new(false),
             expire: RefCell::new(Box::pin(sleep(time::Duration::from_secs(
@@ -54,6 +68,12 @@ where
         }
     }
 
+    /// Set handler for links attached by remote receivers
+    pub(crate) fn outgoing(mut self, handler: Option<OutgoingHandler<St>>) -> Self {
+        self.outgoing = handler;
+        self
+    }
+
     fn handle_idle_timeout(&self, cx: &mut Context<'_>) {
         let idle_timeout = self.idle_timeout;
         if idle_timeout > 0 {
@@ -67,6 +87,17 @@ where
         }
     }
 
+    fn handle_maintenance(&self, cx: &mut Context<'_>) {
+        if let Some((period, ref timer)) = self.maintenance {
+            let mut timer = timer.borrow_mut();
+            if Pin::new(&mut *timer).poll(cx).is_ready() {
+                self.sink.0.get_mut().maintenance();
+                *timer = Box::pin(sleep(period));
+                let _ = Pin::new(&mut *timer).poll(cx);
+            }
+        }
+    }
+
     fn handle_control_fut(&self, cx: &mut Context<'_>) -> Result<bool, DispatcherError> {
         let mut inner = self.ctl_fut.borrow_mut();
 
@@ -87,6 +118,43 @@ where
         Ok(true)
     }
 
+    /// Close connection, frame is received on channel without session
+    fn unknown_channel(&self, id: usize) {
+        log::trace!("Frame is received on unknown channel: {}", id);
+        self.sink.0.get_mut().close_with_error(Error {
+            condition: AmqpError::NotAllowed.into(),
+            description: Some(format!("Unknown channel: {}", id).into()),
+            info: None,
+        });
+
+        let frame = ControlFrame::new_kind(ControlFrameKind::UnknownChannel(id as u16));
+        *self.ctl_fut.borrow_mut() = Some((frame.clone(), Box::pin(self.ctl_service.call(frame))));
+    }
+
+    /// End session, frame refers to link handle that is not attached
+    fn unattached_handle(&self, id: usize, handle: Handle) {
+        log::trace!(
+            "Frame refers to unattached handle {} on channel {}",
+            handle,
+            id
+        );
+        if let Some(session) = self.sink.get_remote_session(id) {
+            let local_id = session.get_ref().id();
+            let frame = ControlFrame::new(session, ControlFrameKind::UnattachedHandle(handle));
+            *self.ctl_fut.borrow_mut() =
+                Some((frame.clone(), Box::pin(self.ctl_service.call(frame))));
+
+            self.sink.end_session(
+                local_id,
+                Some(Error {
+                    condition: SessionError::UnattachedHandle.into(),
+                    description: Some(format!("Unattached handle: {}", handle).into()),
+                    info: None,
+                }),
+            );
+        }
+    }
+
     fn handle_control_frame(
         &self,
         frame: ControlFrame,
@@ -97,11 +165,12 @@ where
                 ControlFrameKind::AttachReceiver(ref link) => {
                     let _ = link.close_with_error(err);
                 }
-                ControlFrameKind::AttachSender(ref frm, _) => {
+                ControlFrameKind::AttachSender(ref frm, ref link) => {
                     frame
                         .session_cell()
                         .get_mut()
                         .detach_unconfirmed_sender_link(&frm, Some(err));
+                    link.inner().observer.set(LinkState::Closed);
                 }
                 ControlFrameKind::Flow(_, ref link) => {
                     let _ = link.close_with_error(err);
@@ -113,12 +182,24 @@ where
                     let _ = link.close_with_error(err);
                 }
                 ControlFrameKind::ProtocolError(ref err) => return Err(err.clone().into()),
+                ControlFrameKind::SessionBegin | ControlFrameKind::SessionFlow(_) => {
+                    let id = frame.session_cell().get_ref().id();
+                    self.sink.end_session(id, Some(err));
+                }
                 _ => (),
             }
         } else {
             match frame.0.get_mut().kind {
                 ControlFrameKind::AttachReceiver(ref link) => {
                     let link = link.clone();
+                    let sink = self.sink.0.get_ref();
+                    if let Some(ref names) = sink.link_names {
+                        names.register(
+                            &sink.remote_container_id,
+                            link.frame(),
+                            Either::Right(link.clone()),
+                        );
+                    }
                     let fut = self
                         .service
                         .call(types::Link::new(link.clone(), self.state.clone()));
@@ -134,9 +215,29 @@ where
                     frame
                         .session_cell()
                         .get_mut()
-                        .confirm_sender_link_inner(&frm, link.inner.clone());
+                        .confirm_sender_link_inner(&frm, link.inner().clone());
+
+                    let sink = self.sink.0.get_ref();
+                    if let Some(ref names) = sink.link_names {
+                        names.register(&sink.remote_container_id, frm, Either::Left(link.clone()));
+                    }
+
+                    if let Some(ref f) = self.outgoing {
+                        let fut = (*f)(types::OutgoingLink::new(
+                            link.clone(),
+                            frm.clone(),
+                            self.state.clone(),
+                        ));
+                        let link = link.clone();
+                        ntex::rt::spawn(async move {
+                            match fut.await {
+                                Ok(_) => link.close().await,
+                                Err(err) => link.close_with_error(err).await,
+                            }
+                        });
+                    }
                 }
-                ControlFrameKind::Flow(ref frm, _) => {
+                ControlFrameKind::Flow(ref frm, _) | ControlFrameKind::SessionFlow(ref frm) => {
                     frame.session_cell().get_mut().apply_flow(frm);
                 }
                 ControlFrameKind::DetachSender(ref mut frm, _) => {
@@ -169,9 +270,14 @@ where
     type Future = Ready<Self::Response, Self::Error>;
 
     fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.handle_maintenance(cx);
+
         // process control frame
         let res0 = !self.handle_control_fut(cx)?;
 
+        // send queued transfers of ready sessions
+        self.sink.0.get_mut().schedule();
+
         // check readiness
         let res1 = self.service.poll_ready(cx).map_err(|err| {
             error!("Error during publish service readiness check: {:?}", err);
@@ -200,6 +306,12 @@ where
             }
             sink.on_close.notify();
             sink.set_error(AmqpProtocolError::Disconnected);
+            if let Some(ref names) = sink.link_names {
+                names.cleanup();
+            }
+            if let Some(ids) = sink.container_ids.take() {
+                ids.remove(&sink.remote_container_id, &self.sink);
+            }
             let fut = self
                 .ctl_service
                 .call(ControlFrame::new_kind(ControlFrameKind::Closed(is_error)));
@@ -221,17 +333,37 @@ where
         match request {
             DispatchItem::Item(frame) => {
                 #[cfg(feature = "frame-trace")]
-                log::trace!("incoming: {:#?}", frame);
+                log::trace!("incoming: {}", crate::codec::pretty(&frame));
 
-                let item = try_ready_err!(self
-                    .sink
-                    .0
-                    .get_mut()
-                    .handle_frame(frame)
-                    .map_err(DispatcherError::Protocol));
+                // session ended by remote peer
+                let ended = if let Frame::End(ref end) = frame.performative() {
+                    self.sink
+                        .get_remote_session(frame.channel_id() as usize)
+                        .map(|session| (session, end.error.clone()))
+                } else {
+                    None
+                };
+
+                let item = match self.sink.0.get_mut().handle_frame(frame) {
+                    Ok(item) => item,
+                    Err(AmqpProtocolError::UnknownSession(id, _)) => {
+                        self.unknown_channel(id);
+                        return Ready::Ok(());
+                    }
+                    Err(AmqpProtocolError::UnattachedHandle(id, handle)) => {
+                        self.unattached_handle(id, handle);
+                        return Ready::Ok(());
+                    }
+                    Err(err) => return Ready::Err(DispatcherError::Protocol(err)),
+                };
                 let frame = if let Some(item) = item {
                     item
                 } else {
+                    if let Some((session, err)) = ended {
+                        let frame = ControlFrame::new(session, ControlFrameKind::SessionEnded(err));
+                        *self.ctl_fut.borrow_mut() =
+                            Some((frame.clone(), Box::pin(self.ctl_service.call(frame))));
+                    }
                     return Ready::Ok(());
                 };
 
@@ -239,30 +371,54 @@ where
 
                 // remote session
                 if let Frame::Begin(frm) = frame {
-                    return Ready::from(
-                        self.sink
-                            .register_remote_session(channel_id, &frm)
-                            .map_err(DispatcherError::Codec),
-                    );
+                    if let Err(err) = self.sink.register_remote_session(channel_id, &frm) {
+                        return Ready::Err(DispatcherError::Codec(err));
+                    }
+                    if let Some(session) = self.sink.get_remote_session(channel_id as usize) {
+                        let frame = ControlFrame::new(session, ControlFrameKind::SessionBegin);
+                        *self.ctl_fut.borrow_mut() =
+                            Some((frame.clone(), Box::pin(self.ctl_service.call(frame))));
+                    }
+                    return Ready::Ok(());
                 }
 
                 let id = channel_id as usize;
+
+                // extension performative
+                if let Frame::Unknown(frm) = frame {
+                    let frame = ControlFrame::with_session(
+                        self.sink.get_remote_session(id),
+                        ControlFrameKind::Unknown(channel_id, frm),
+                    );
+                    *self.ctl_fut.borrow_mut() =
+                        Some((frame.clone(), Box::pin(self.ctl_service.call(frame))));
+                    return Ready::from(Ok(()));
+                }
+
                 let session = match self.sink.get_remote_session(id) {
                     Some(session) => session,
                     None => {
-                        return Ready::from(Err(AmqpProtocolError::UnknownSession(
-                            id,
-                            Box::new(frame),
-                        )
-                        .into()))
+                        self.unknown_channel(id);
+                        return Ready::Ok(());
                     }
                 };
 
+                // detach could confirm rejected attach, handle of such link is
+                // not registered, so only link flow is checked
+                if let Frame::Flow(ref frm) = frame {
+                    if let Some(handle) = frm
+                        .handle
+                        .filter(|h| !session.get_ref().is_remote_handle(*h))
+                    {
+                        self.unattached_handle(id, handle);
+                        return Ready::Ok(());
+                    }
+                }
+
                 let result = match frame {
                     Frame::Flow(frm) => {
                         // apply flow to specific link
                         if let Some(link_id) = frm.handle {
-                            // TODO: close session if link is not found
                             if let Some(link) = session.get_sender_link_by_handle(link_id) {
                                 let frame = ControlFrame::new(
                                     session.clone(),
@@ -272,11 +428,45 @@ where
                                     Some((frame.clone(), Box::pin(self.ctl_service.call(frame))));
                                 return Ready::from(Ok(()));
                             }
+                        } else if self.sink.0.get_ref().session_flow_events {
+                            // session window update
+                            let frame = ControlFrame::new(
+                                session.clone(),
+                                ControlFrameKind::SessionFlow(frm),
+                            );
+                            *self.ctl_fut.borrow_mut() =
+                                Some((frame.clone(), Box::pin(self.ctl_service.call(frame))));
+                            return Ready::from(Ok(()));
                         }
                         session.get_mut().apply_flow(&frm);
                         Ok(())
                     }
                     Frame::Attach(attach) => {
+                        let sink = self.sink.0.get_ref();
+                        if let Some(ref names) = sink.link_names {
+                            if !names.check(&sink.remote_container_id, &attach) {
+                                let err = Error {
+                                    condition: AmqpError::ResourceLocked.into(),
+                                    description: Some(ByteString::from_static(
+                                        "Link name is in use",
+                                    )),
+                                    info: None,
+                                };
+                                match attach.role {
+                                    Role::Receiver => session
+                                        .get_mut()
+                                        .detach_unconfirmed_sender_link(&attach, Some(err)),
+                                    Role::Sender => {
+                                        let link = session
+                                            .get_mut()
+                                            .open_receiver_link(session.clone(), attach);
+                                        ntex::rt::spawn(link.close_with_error(err));
+                                    }
+                                }
+                                return Ready::Ok(());
+                            }
+                        }
+
                         match attach.role {
                             Role::Receiver => {
                                 // remotly opened sender link
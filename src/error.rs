@@ -4,7 +4,7 @@ use ntex::util::{ByteString, Either};
 
 pub use crate::codec::protocol::Error;
 pub use crate::codec::{AmqpCodecError, AmqpParseError, ProtocolIdError};
-use crate::{codec::protocol, types::Outcome};
+use crate::{codec::protocol, codec::types::Variant, types::Outcome};
 
 /// Errors which can occur when attempting to handle amqp connection.
 #[derive(Debug, Display, From)]
@@ -33,6 +33,23 @@ impl From<Either<AmqpCodecError, io::Error>> for DispatcherError {
     }
 }
 
+/// Errors which can occur when streaming delivery body
+#[derive(Debug, Display, From)]
+pub enum DeliveryError {
+    /// Amqp protocol error
+    #[display(fmt = "Amqp protocol error: {}", _0)]
+    Protocol(AmqpProtocolError),
+    /// Malformed message sections
+    #[display(fmt = "Message parse error: {}", _0)]
+    Parse(AmqpParseError),
+    /// Delivery is aborted by sender
+    #[display(fmt = "Delivery is aborted")]
+    Aborted,
+    /// Io error
+    #[display(fmt = "Io error: {}", _0)]
+    Io(io::Error),
+}
+
 #[derive(Clone, Debug, Display)]
 pub enum AmqpProtocolError {
     Codec(AmqpCodecError),
@@ -41,6 +58,8 @@ pub enum AmqpProtocolError {
     Disconnected,
     #[display(fmt = "Unknown session: {} {:?}", _0, _1)]
     UnknownSession(usize, Box<protocol::Frame>),
+    #[display(fmt = "Unattached handle: {} {}", _0, _1)]
+    UnattachedHandle(usize, protocol::Handle),
     #[display(fmt = "Connection closed, error: {:?}", _0)]
     Closed(Option<protocol::Error>),
     #[display(fmt = "Session ended, error: {:?}", _0)]
@@ -51,6 +70,78 @@ pub enum AmqpProtocolError {
     UnexpectedOpeningState(Box<protocol::Frame>),
     #[display(fmt = "Unexpected frame, got: {:?}", _0)]
     Unexpected(Box<protocol::Frame>),
+    #[display(fmt = "Message size exceeds remote max message size: {}", _0)]
+    MessageSizeExceeded(u64),
+    #[display(fmt = "Sender link in-flight deliveries limit is reached")]
+    InflightLimit,
+    #[display(fmt = "Outgoing transfers queue limit is reached")]
+    BufferLimit,
+    #[display(fmt = "Link is not attached by peer within attach timeout")]
+    AttachTimeout,
+    #[display(fmt = "Delivery is not settled within settlement timeout")]
+    SettlementTimeout,
+    #[display(fmt = "Message expired before it could be sent")]
+    MessageExpired,
+    #[display(fmt = "Message is rejected by interceptor: {:?}", _0)]
+    Intercepted(protocol::Error),
+    #[display(fmt = "Granted target is rejected: {}", _0)]
+    TargetRejected(ByteString),
+    #[display(fmt = "Management request failed: {} {:?}", _0, _1)]
+    Management(i32, Option<ByteString>),
+}
+
+impl AmqpProtocolError {
+    /// Redirect details, if link is detached with `amqp:link:redirect` error
+    pub fn redirect(&self) -> Option<LinkRedirect> {
+        match self {
+            AmqpProtocolError::LinkDetached(Some(err)) => LinkRedirect::from_error(err),
+            _ => None,
+        }
+    }
+}
+
+/// Link redirect details
+///
+/// Peer detaches link with `amqp:link:redirect` error if link
+/// must be re-attached to different node or container.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinkRedirect {
+    /// Hostname of the container, could be used as sasl/tls hostname
+    pub hostname: Option<ByteString>,
+    /// Network host of the container
+    pub network_host: Option<ByteString>,
+    /// Network port of the container
+    pub port: Option<u16>,
+    /// Address of the node
+    pub address: Option<ByteString>,
+}
+
+impl LinkRedirect {
+    /// Parse redirect details from detach error
+    pub fn from_error(err: &Error) -> Option<Self> {
+        if err.condition != protocol::ErrorCondition::LinkError(protocol::LinkError::Redirect) {
+            return None;
+        }
+
+        let mut redirect = LinkRedirect::default();
+        if let Some(ref info) = err.info {
+            let string = |key: &str| {
+                info.get(key)
+                    .and_then(|val| val.as_str())
+                    .map(ByteString::from)
+            };
+            redirect.hostname = string("hostname");
+            redirect.network_host = string("network-host");
+            redirect.address = string("address");
+            redirect.port = match info.get("port") {
+                Some(Variant::Ushort(port)) => Some(*port),
+                Some(Variant::Uint(port)) => u16::try_from(*port).ok(),
+                Some(Variant::Int(port)) => u16::try_from(*port).ok(),
+                _ => None,
+            };
+        }
+        Some(redirect)
+    }
 }
 
 impl From<AmqpCodecError> for AmqpProtocolError {
@@ -121,6 +212,12 @@ impl AmqpError {
         self.description = Some(text);
         self
     }
+
+    #[allow(clippy::mutable_key_type)]
+    pub fn fields(mut self, fields: protocol::Fields) -> Self {
+        self.info = Some(fields);
+        self
+    }
 }
 
 impl From<AmqpError> for protocol::Error {
@@ -141,7 +238,7 @@ impl TryFrom<AmqpError> for Outcome {
     type Error = Error;
 
     fn try_from(err: AmqpError) -> Result<Self, Error> {
-        Ok(Outcome::Error(err.into()))
+        Ok(Outcome::from(Error::from(err)))
     }
 }
 
@@ -219,6 +316,46 @@ impl TryFrom<LinkError> for Outcome {
     type Error = Error;
 
     fn try_from(err: LinkError) -> Result<Self, Error> {
-        Ok(Outcome::Error(err.into()))
+        Ok(Outcome::from(Error::from(err)))
+    }
+}
+
+/// Conversion of handler error to delivery outcome
+///
+/// Router settles delivery with outcome of handler error. Condition,
+/// description and info map of rejected outcome are delivered to
+/// remote sender.
+pub trait ErrorOutcome {
+    /// Delivery outcome for handler error
+    fn outcome(self) -> Outcome;
+}
+
+impl ErrorOutcome for Error {
+    fn outcome(self) -> Outcome {
+        Outcome::Rejected { error: Some(self) }
+    }
+}
+
+impl ErrorOutcome for AmqpError {
+    fn outcome(self) -> Outcome {
+        Error::from(self).outcome()
+    }
+}
+
+impl ErrorOutcome for LinkError {
+    fn outcome(self) -> Outcome {
+        Error::from(self).outcome()
+    }
+}
+
+impl ErrorOutcome for AmqpParseError {
+    fn outcome(self) -> Outcome {
+        Error::from(self).outcome()
+    }
+}
+
+impl ErrorOutcome for Outcome {
+    fn outcome(self) -> Outcome {
+        self
     }
 }
@@ -0,0 +1,74 @@
+use std::fmt;
+
+use ntex::util::{Bytes, HashMap};
+
+use crate::codec::protocol::{MessageFormat, Transfer, TransferBody};
+use crate::codec::{AmqpParseError, Decode, Encode};
+
+type Decoder<T> = Box<dyn Fn(&Bytes) -> Result<T, AmqpParseError>>;
+
+/// Message decoders registry
+///
+/// Selects decoder by transfer's `message-format` field, format `0` is
+/// decoded with `T::decode()`. Missing `message-format` means format `0`.
+pub struct MessageDecoders<T> {
+    decoders: HashMap<MessageFormat, Decoder<T>>,
+}
+
+impl<T: Decode + 'static> Default for MessageDecoders<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Decode + 'static> MessageDecoders<T> {
+    /// Create registry with decoder for default message format
+    pub fn new() -> Self {
+        let mut decoders: HashMap<MessageFormat, Decoder<T>> = HashMap::default();
+        decoders.insert(0, Box::new(|data: &Bytes| Ok(T::decode(data)?.1)));
+        MessageDecoders { decoders }
+    }
+}
+
+impl<T> MessageDecoders<T> {
+    /// Register decoder for message format
+    pub fn register<F>(mut self, format: MessageFormat, f: F) -> Self
+    where
+        F: Fn(&Bytes) -> Result<T, AmqpParseError> + 'static,
+    {
+        self.decoders.insert(format, Box::new(f));
+        self
+    }
+
+    /// Check if decoder for message format is registered
+    pub fn contains(&self, format: MessageFormat) -> bool {
+        self.decoders.contains_key(&format)
+    }
+
+    /// Decode transfer body
+    pub fn decode(&self, transfer: &Transfer) -> Result<T, AmqpParseError> {
+        let format = transfer.message_format.unwrap_or(0);
+        let decoder = self
+            .decoders
+            .get(&format)
+            .ok_or(AmqpParseError::UnexpectedType("message-format"))?;
+
+        match transfer.body {
+            Some(TransferBody::Data(ref data)) => (*decoder)(data),
+            Some(TransferBody::Message(ref msg)) => {
+                let mut buf = ntex::util::BytesMut::with_capacity(msg.encoded_size());
+                msg.encode(&mut buf);
+                (*decoder)(&buf.freeze())
+            }
+            None => Err(AmqpParseError::UnexpectedType("body")),
+        }
+    }
+}
+
+impl<T> fmt::Debug for MessageDecoders<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageDecoders")
+            .field("formats", &self.decoders.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
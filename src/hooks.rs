@@ -0,0 +1,48 @@
+//! Connection hooks
+//!
+//! Connection, sessions and links call hooks, server provides
+//! implementations.
+use ntex::util::{ByteString, Either};
+
+use crate::codec::protocol::{Attach, Error};
+use crate::{Connection, ReceiverLink, SenderLink};
+
+/// Inbound message quota of connection
+pub(crate) trait DeliveryQuota {
+    /// Account incoming message of `size` bytes
+    fn check(&self, size: usize) -> QuotaCheck;
+}
+
+/// Result of quota check
+#[derive(Debug)]
+pub(crate) enum QuotaCheck {
+    Allow,
+    Reject(Error),
+    Detach(Error),
+}
+
+/// Registry of remotely attached links
+pub(crate) trait LinkRegistry {
+    /// Check attach frame, returns false if attach must be rejected
+    fn check(&self, container_id: &ByteString, attach: &Attach) -> bool;
+
+    /// Register remotely attached link
+    fn register(
+        &self,
+        container_id: &ByteString,
+        attach: &Attach,
+        link: Either<SenderLink, ReceiverLink>,
+    );
+
+    /// Remove detached links
+    fn cleanup(&self);
+}
+
+/// Registry of connections by remote container id
+pub(crate) trait ContainerRegistry {
+    /// Register new connection, returns false if connection must be closed
+    fn register(&self, container_id: &ByteString, conn: &Connection) -> bool;
+
+    /// Remove closed connection
+    fn remove(&self, container_id: &ByteString, conn: &Connection);
+}
@@ -0,0 +1,155 @@
+use std::{fmt, rc::Rc};
+
+use ntex::util::ByteString;
+
+use crate::codec::protocol::{AmqpError, Error, Transfer, TransferBody};
+use crate::codec::{Decode, Message, MessageBody};
+
+type Interceptor = Rc<dyn Fn(&mut Message) -> Result<(), Error>>;
+
+#[derive(Clone)]
+enum Incoming {
+    Intercept(Interceptor),
+    Validate(Rc<dyn MessageValidator>),
+}
+
+/// Message interceptor chain
+///
+/// Interceptors run in registration order. Outgoing interceptors run before
+/// message is queued for transfer, incoming interceptors run before delivery
+/// is yielded from receiver link. Any interceptor could modify message or
+/// reject it by returning error, in that case rest of the chain is skipped.
+///
+/// Chain could be set for whole connection with `Connection::interceptors()`
+/// or for a single link with link builder. Connection chain runs first.
+#[derive(Clone, Default)]
+pub struct Interceptors {
+    outgoing: Vec<Interceptor>,
+    incoming: Vec<Incoming>,
+}
+
+impl Interceptors {
+    /// Create empty chain
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add interceptor for outgoing messages
+    pub fn outgoing<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut Message) -> Result<(), Error> + 'static,
+    {
+        self.outgoing.push(Rc::new(f));
+        self
+    }
+
+    /// Add interceptor for incoming messages
+    pub fn incoming<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut Message) -> Result<(), Error> + 'static,
+    {
+        self.incoming.push(Incoming::Intercept(Rc::new(f)));
+        self
+    }
+
+    /// Add validator for incoming message bodies
+    ///
+    /// Messages that fail validation are rejected with `amqp:decode-error`
+    /// condition, validation error is used as error description.
+    pub fn validate<V>(mut self, validator: V) -> Self
+    where
+        V: MessageValidator + 'static,
+    {
+        self.incoming.push(Incoming::Validate(Rc::new(validator)));
+        self
+    }
+
+    /// Check if chain is empty
+    pub fn is_empty(&self) -> bool {
+        self.outgoing.is_empty() && self.incoming.is_empty()
+    }
+
+    /// Append interceptors of other chain
+    pub(crate) fn extend(&mut self, other: &Interceptors) {
+        self.outgoing.extend(other.outgoing.iter().cloned());
+        self.incoming.extend(other.incoming.iter().cloned());
+    }
+
+    /// Run outgoing interceptors
+    ///
+    /// Raw body gets decoded, bodies that are not valid amqp messages
+    /// are passed as is.
+    pub(crate) fn apply_outgoing(&self, body: &mut TransferBody) -> Result<(), Box<Error>> {
+        if self.outgoing.is_empty() {
+            return Ok(());
+        }
+        if let Some(msg) = as_message(body) {
+            for f in &self.outgoing {
+                (*f)(msg).map_err(Box::new)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run incoming interceptors
+    pub(crate) fn apply_incoming(&self, transfer: &mut Transfer) -> Result<(), Box<Error>> {
+        if self.incoming.is_empty() {
+            return Ok(());
+        }
+        if let Some(msg) = transfer.body.as_mut().and_then(as_message) {
+            for item in &self.incoming {
+                match item {
+                    Incoming::Intercept(f) => (*f)(msg).map_err(Box::new)?,
+                    Incoming::Validate(v) => v.validate(msg.body()).map_err(|reason| {
+                        Box::new(Error {
+                            condition: AmqpError::DecodeError.into(),
+                            description: Some(reason),
+                            info: None,
+                        })
+                    })?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Message body validator (json schema, protobuf, etc)
+pub trait MessageValidator {
+    /// Validate message body, returns failure details
+    fn validate(&self, body: &MessageBody) -> Result<(), ByteString>;
+}
+
+impl<F> MessageValidator for F
+where
+    F: Fn(&MessageBody) -> Result<(), ByteString>,
+{
+    fn validate(&self, body: &MessageBody) -> Result<(), ByteString> {
+        (*self)(body)
+    }
+}
+
+fn as_message(body: &mut TransferBody) -> Option<&mut Message> {
+    if let TransferBody::Data(ref data) = body {
+        match Message::decode(data) {
+            Ok((_, msg)) => *body = TransferBody::Message(Box::new(msg)),
+            Err(err) => {
+                log::trace!("Cannot decode message for interceptors: {:?}", err);
+                return None;
+            }
+        }
+    }
+    match body {
+        TransferBody::Message(ref mut msg) => Some(msg.as_mut()),
+        TransferBody::Data(_) => None,
+    }
+}
+
+impl fmt::Debug for Interceptors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interceptors")
+            .field("outgoing", &self.outgoing.len())
+            .field("incoming", &self.incoming.len())
+            .finish()
+    }
+}
@@ -6,39 +6,73 @@ extern crate derive_more;
 #[macro_use]
 extern crate log;
 
-use std::{future::Future, pin::Pin, task::Context, task::Poll};
+use std::{future::Future, pin::Pin, task::Context, task::Poll, time::Duration};
 
 use ntex::channel::oneshot;
 use ntex::util::ByteString;
-use ntex_amqp_codec::protocol::{Disposition, Handle, Milliseconds, Open};
+use ntex_amqp_codec::protocol::{
+    Disposition, Fields, Handle, Milliseconds, Open, ReceiverSettleMode, SenderSettleMode,
+};
+use ntex_amqp_codec::types::{Symbol, Variant};
 use uuid::Uuid;
 
-#[macro_use]
-mod utils;
-
+pub mod bench;
 mod cell;
 pub mod client;
 mod connection;
 mod control;
+mod dedup;
 mod default;
+mod delivery;
 mod dispatcher;
 pub mod error;
 pub mod error_code;
+mod format;
 mod hb;
+mod hooks;
+mod interceptor;
+mod mgmt;
+pub mod negotiate;
+mod observer;
+mod profile;
 mod rcvlink;
+mod retry;
 mod router;
+mod scram;
 pub mod server;
 mod session;
+mod shutdown;
 mod sndlink;
+mod socket;
+mod spawner;
 mod state;
+mod stats;
+mod store;
+mod terminus;
 pub mod types;
 
 pub use self::connection::Connection;
 pub use self::control::{ControlFrame, ControlFrameKind};
+pub use self::dedup::DedupKey;
+pub use self::delivery::IncomingDelivery;
+pub use self::format::MessageDecoders;
+pub use self::interceptor::{Interceptors, MessageValidator};
+pub use self::mgmt::ManagementLink;
+pub use self::observer::LinkState;
+pub use self::profile::{DeadLetterInfo, PeerProfile};
 pub use self::rcvlink::{ReceiverLink, ReceiverLinkBuilder};
+pub use self::retry::{ExponentialBackoff, RetryPolicy, RetryableError};
+pub use self::scram::{ScramCredentials, ScramMechanism};
 pub use self::session::Session;
-pub use self::sndlink::{SenderLink, SenderLinkBuilder};
+pub use self::shutdown::{ShutdownReport, ShutdownTimeouts};
+pub use self::sndlink::{
+    CreditEvent, InflightPolicy, SenderLink, SenderLinkBuilder, StreamingDelivery,
+};
+pub use self::socket::SocketOptions;
+pub use self::spawner::Spawner;
 pub use self::state::State;
+pub use self::store::{LinkStateStore, MemoryLinkStateStore};
+pub use self::terminus::{Terminus, TerminusBuilder};
 
 pub mod codec {
     pub use ntex_amqp_codec::*;
@@ -85,6 +119,26 @@ pub struct Configuration {
     pub channel_max: usize,
     pub idle_time_out: Milliseconds,
     pub hostname: Option<ByteString>,
+    pub container_id: Option<ByteString>,
+    pub properties: Option<Fields>,
+    pub session_window: Option<(u32, u32)>,
+    pub max_pending_transfers: Option<usize>,
+    pub max_queued_transfers: Option<usize>,
+    pub snd_settle_mode: SenderSettleMode,
+    pub rcv_settle_mode: ReceiverSettleMode,
+    pub auto_accept: bool,
+    pub prefetch: u32,
+    pub settlement_timeout: Option<Duration>,
+    pub starvation_threshold: Option<Duration>,
+    pub disposition_batch: Option<(Duration, usize)>,
+    pub attach_timeout: Option<Duration>,
+    pub session_quantum: Option<usize>,
+    pub malformed_capture: usize,
+    pub compact_encoding: bool,
+    pub session_per_link: bool,
+    pub session_flow_events: bool,
+    pub link_resume: bool,
+    pub sasl_scram: bool,
 }
 
 impl Default for Configuration {
@@ -101,6 +155,26 @@ impl Configuration {
             channel_max: 1024,
             idle_time_out: 120_000,
             hostname: None,
+            container_id: None,
+            properties: None,
+            session_window: None,
+            max_pending_transfers: None,
+            max_queued_transfers: None,
+            snd_settle_mode: SenderSettleMode::Mixed,
+            rcv_settle_mode: ReceiverSettleMode::First,
+            auto_accept: false,
+            prefetch: 0,
+            settlement_timeout: None,
+            starvation_threshold: None,
+            disposition_batch: None,
+            attach_timeout: None,
+            session_quantum: None,
+            malformed_capture: 64,
+            compact_encoding: false,
+            session_per_link: false,
+            session_flow_events: false,
+            link_resume: false,
+            sasl_scram: true,
         }
     }
 
@@ -143,10 +217,253 @@ impl Configuration {
         self
     }
 
+    /// Set container id
+    ///
+    /// By default random container id is generated for each connection
+    pub fn container_id(&mut self, id: &str) -> &mut Self {
+        self.container_id = Some(ByteString::from(id));
+        self
+    }
+
+    /// Set human-readable connection name
+    ///
+    /// Name is sent to the peer as `connection-name` connection property
+    pub fn connection_name(&mut self, name: &str) -> &mut Self {
+        self.property(
+            Symbol::from_static("connection-name"),
+            ByteString::from(name),
+        )
+    }
+
+    /// Enable session incoming-window auto-tuning
+    ///
+    /// Session starts with `min` window, window grows if peer consumes it quickly
+    /// and shrinks if receivers do not keep up with incoming transfers.
+    /// By default incoming window is not limited.
+    pub fn session_window(&mut self, min: u32, max: u32) -> &mut Self {
+        let min = std::cmp::max(min, 1);
+        self.session_window = Some((min, std::cmp::max(min, max)));
+        self
+    }
+
+    /// Set max number of outgoing transfers queued per sender link.
+    ///
+    /// Transfers are queued while sender link has no credit or remote
+    /// incoming window is exhausted. Sending a message fails with
+    /// `AmqpProtocolError::BufferLimit` error once limit is reached.
+    /// By default queue is not limited.
+    pub fn max_pending_transfers(&mut self, num: usize) -> &mut Self {
+        self.max_pending_transfers = Some(std::cmp::max(num, 1));
+        self
+    }
+
+    /// Set max number of incoming transfers queued per receiver link.
+    ///
+    /// Connection get closed with `resource-limit-exceeded` error once
+    /// limit is exceeded. By default queue is not limited.
+    pub fn max_queued_transfers(&mut self, num: usize) -> &mut Self {
+        self.max_queued_transfers = Some(std::cmp::max(num, 1));
+        self
+    }
+
+    /// Set settlement modes requested by links opened with link builders.
+    ///
+    /// Sender links in `settled` mode send deliveries pre-settled, send
+    /// futures resolve once delivery is sent. Receiver links in `second`
+    /// mode do not settle deliveries, sender settles them after receiver's
+    /// disposition. By default settlement modes are `mixed` and `first`.
+    pub fn settle_modes(&mut self, snd: SenderSettleMode, rcv: ReceiverSettleMode) -> &mut Self {
+        self.snd_settle_mode = snd;
+        self.rcv_settle_mode = rcv;
+        self
+    }
+
+    /// Accept unsettled deliveries once receiver link yields them.
+    ///
+    /// Applies to links opened with `ReceiverLinkBuilder`. Disabled by default.
+    pub fn auto_accept(&mut self, val: bool) -> &mut Self {
+        self.auto_accept = val;
+        self
+    }
+
+    /// Set number of deliveries prefetched by receiver links.
+    ///
+    /// Links opened with `ReceiverLinkBuilder` issue `num` credits on attach
+    /// and restore credit as deliveries get consumed. By default receiver
+    /// links do not issue credit.
+    pub fn prefetch(&mut self, num: u32) -> &mut Self {
+        self.prefetch = num;
+        self
+    }
+
+    /// Set settlement timeout for deliveries.
+    ///
+    /// Sent deliveries that are not settled by peer within timeout get
+    /// settled locally and send futures fail with
+    /// `AmqpProtocolError::SettlementTimeout` error. Received deliveries
+    /// that are not settled by application get released. Deliveries are
+    /// checked every half of the timeout. By default timeout is not set.
+    pub fn settlement_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.settlement_timeout = Some(timeout);
+        self
+    }
+
+    /// Set credit starvation threshold for sender links.
+    ///
+    /// Sender link is starved if it has queued transfers but no credit.
+    /// If starvation lasts longer than threshold, warning with queue depth
+    /// and last received flow is logged and `CreditEvent::Starved` event
+    /// is sent. By default starvation is not reported.
+    pub fn starvation_threshold(&mut self, threshold: Duration) -> &mut Self {
+        self.starvation_threshold = Some(threshold);
+        self
+    }
+
+    /// Batch outgoing dispositions.
+    ///
+    /// Dispositions are buffered for `window` or until `max` settlements
+    /// are collected, adjacent dispositions with the same outcome are
+    /// coalesced into ranges. By default dispositions are sent immediately.
+    pub fn disposition_batch(&mut self, window: Duration, max: usize) -> &mut Self {
+        self.disposition_batch = Some((window, std::cmp::max(max, 1)));
+        self
+    }
+
+    /// Set attach timeout for locally opened links.
+    ///
+    /// If peer does not respond to attach within timeout, half-open link
+    /// gets detached and open future fails with
+    /// `AmqpProtocolError::AttachTimeout` error. By default timeout is not set.
+    pub fn attach_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.attach_timeout = Some(timeout);
+        self
+    }
+
+    /// Schedule queued transfers of sessions in round-robin order.
+    ///
+    /// Once peer opens session window, session sends at most `quantum`
+    /// queued transfers per round (multiplied by session weight, see
+    /// `Session::set_weight()`), so transfers of sessions sharing
+    /// connection are interleaved. By default session sends all
+    /// queued transfers at once.
+    pub fn session_quantum(&mut self, quantum: usize) -> &mut Self {
+        self.session_quantum = Some(std::cmp::max(quantum, 1));
+        self
+    }
+
+    /// Set number of bytes captured from first malformed frame.
+    ///
+    /// Captured bytes are available with `Connection::codec_metrics()`.
+    /// By default 64 bytes are captured, `0` disables capture.
+    pub fn malformed_capture(&mut self, size: usize) -> &mut Self {
+        self.malformed_capture = size;
+        self
+    }
+
+    /// Prefer compact encodings for outgoing frames.
+    ///
+    /// Empty lists are encoded as `list0`, arrays of short symbols and
+    /// strings use 8-bit element encodings. Peer must accept all valid
+    /// encodings. Disabled by default.
+    pub fn compact_encoding(&mut self, val: bool) -> &mut Self {
+        self.compact_encoding = val;
+        self
+    }
+
+    /// Open each link in its own session.
+    ///
+    /// Flow-control stall or error of one link does not block links
+    /// in other sessions, at the cost of a session per link. Applies to
+    /// links opened with `Connection::link_session()`, reconnecting
+    /// client and subscriptions. Disabled by default.
+    pub fn session_per_link(&mut self, val: bool) -> &mut Self {
+        self.session_per_link = val;
+        self
+    }
+
+    /// Pass session flows without link handle to control service.
+    ///
+    /// If enabled, such flows are delivered as `ControlFrameKind::SessionFlow`
+    /// and control service error ends the session. Otherwise session window
+    /// is updated without calling control service. Disabled by default.
+    pub fn session_flow_events(&mut self, val: bool) -> &mut Self {
+        self.session_flow_events = val;
+        self
+    }
+
+    /// Resume sender links on re-attach.
+    ///
+    /// Sender links record deliveries in connection's in-memory state store,
+    /// see `SenderLinkBuilder::state_store()`. Reconnecting client shares the
+    /// store between connections. Disabled by default.
+    pub fn link_resume(&mut self, val: bool) -> &mut Self {
+        self.link_resume = val;
+        self
+    }
+
+    /// Authenticate with `SCRAM-SHA-256` or `SCRAM-SHA-1` if server offers it.
+    ///
+    /// If disabled client sasl negotiation always uses `PLAIN` mechanism.
+    /// Enabled by default.
+    pub fn sasl_scram(&mut self, val: bool) -> &mut Self {
+        self.sasl_scram = val;
+        self
+    }
+
+    /// Preset for at-most-once delivery.
+    ///
+    /// Senders send deliveries pre-settled, receivers accept and
+    /// prefetch `prefetch` deliveries. Lost deliveries are not re-sent,
+    /// links are not resumed.
+    pub fn at_most_once(&mut self, prefetch: u32) -> &mut Self {
+        self.settle_modes(SenderSettleMode::Settled, ReceiverSettleMode::First)
+            .auto_accept(true)
+            .link_resume(false)
+            .prefetch(prefetch)
+    }
+
+    /// Preset for at-least-once delivery.
+    ///
+    /// Deliveries are settled by receiver once application handled them,
+    /// deliveries could be re-sent after failure. Sender links are resumed,
+    /// see `Configuration::link_resume()`.
+    pub fn at_least_once(&mut self, prefetch: u32) -> &mut Self {
+        self.settle_modes(SenderSettleMode::Unsettled, ReceiverSettleMode::First)
+            .auto_accept(false)
+            .link_resume(true)
+            .prefetch(prefetch)
+    }
+
+    /// Preset for exactly-once delivery.
+    ///
+    /// Receiver settles delivery only after sender settled it. Sender links
+    /// are resumed, see `Configuration::link_resume()`.
+    pub fn exactly_once(&mut self, prefetch: u32) -> &mut Self {
+        self.settle_modes(SenderSettleMode::Unsettled, ReceiverSettleMode::Second)
+            .auto_accept(false)
+            .link_resume(true)
+            .prefetch(prefetch)
+    }
+
+    /// Set connection property
+    pub fn property<K, V>(&mut self, key: K, value: V) -> &mut Self
+    where
+        K: Into<Symbol>,
+        V: Into<Variant>,
+    {
+        self.properties
+            .get_or_insert_with(Fields::default)
+            .insert(key.into(), value.into());
+        self
+    }
+
     /// Create `Open` performative for this configuration.
     pub fn to_open(&self) -> Open {
         Open {
-            container_id: ByteString::from(Uuid::new_v4().to_simple().to_string()),
+            container_id: self
+                .container_id
+                .clone()
+                .unwrap_or_else(|| ByteString::from(Uuid::new_v4().to_simple().to_string())),
             hostname: self.hostname.clone(),
             max_frame_size: self.max_frame_size,
             channel_max: self.channel_max as u16,
@@ -159,7 +476,7 @@ impl Configuration {
             incoming_locales: None,
             offered_capabilities: None,
             desired_capabilities: None,
-            properties: None,
+            properties: self.properties.clone(),
         }
     }
 
@@ -187,6 +504,26 @@ impl<'a> From<&'a Open> for Configuration {
             channel_max: open.channel_max as usize,
             idle_time_out: open.idle_time_out.unwrap_or(0),
             hostname: open.hostname.clone(),
+            container_id: Some(open.container_id.clone()),
+            properties: open.properties.clone(),
+            session_window: None,
+            max_pending_transfers: None,
+            max_queued_transfers: None,
+            snd_settle_mode: SenderSettleMode::Mixed,
+            rcv_settle_mode: ReceiverSettleMode::First,
+            auto_accept: false,
+            prefetch: 0,
+            settlement_timeout: None,
+            starvation_threshold: None,
+            disposition_batch: None,
+            attach_timeout: None,
+            session_quantum: None,
+            malformed_capture: 64,
+            compact_encoding: false,
+            session_per_link: false,
+            session_flow_events: false,
+            link_resume: false,
+            sasl_scram: true,
         }
     }
 }
@@ -0,0 +1,220 @@
+use std::cell::Cell as StdCell;
+
+use ntex::util::{next, ByteString, Bytes};
+
+use crate::codec::protocol::{
+    Accepted, DeliveryState, Disposition, MessageId, Role, Target, TerminusDurability,
+    TerminusExpiryPolicy, TransferBody,
+};
+use crate::codec::types::{List, Variant, VariantMap};
+use crate::codec::{AmqpParseError, Decode, Message};
+use crate::error::AmqpProtocolError;
+use crate::{ReceiverLink, SenderLink, Session};
+
+const RECEIVE_BY_SEQUENCE_NUMBER: &str = "com.microsoft:receive-by-sequence-number";
+
+/// Request/response client for peer's management node
+///
+/// Requests are sent over sender link to management node, responses are
+/// received over receiver link attached to `reply-to` address. Requests are
+/// expected to be issued one at a time.
+pub struct ManagementLink {
+    sender: SenderLink,
+    receiver: ReceiverLink,
+    reply_to: ByteString,
+    next_id: StdCell<u64>,
+}
+
+impl ManagementLink {
+    /// Open management links to `$management` node
+    pub async fn open(session: &mut Session, name: &str) -> Result<Self, AmqpProtocolError> {
+        Self::open_with_address(session, name, "$management").await
+    }
+
+    /// Open management links to specified management node
+    pub async fn open_with_address(
+        session: &mut Session,
+        name: &str,
+        address: &str,
+    ) -> Result<Self, AmqpProtocolError> {
+        let address = ByteString::from(address);
+        let reply_to = ByteString::from(format!("{}-reply", name));
+
+        let sender = session
+            .build_sender_link(format!("{}-sender", name), address.clone())
+            .open()
+            .await?;
+        let receiver = session
+            .build_receiver_link(format!("{}-receiver", name), address)
+            .with_frame(|frame| {
+                if let Some(target) = frame.target.as_mut() {
+                    target.address = Some(reply_to.clone());
+                } else {
+                    frame.target = Some(Target {
+                        address: Some(reply_to.clone()),
+                        durable: TerminusDurability::None,
+                        expiry_policy: TerminusExpiryPolicy::SessionEnd,
+                        timeout: 0,
+                        dynamic: false,
+                        dynamic_node_properties: None,
+                        capabilities: None,
+                    });
+                }
+            })
+            .open()
+            .await?;
+        receiver.set_link_credit(1);
+
+        Ok(ManagementLink {
+            sender,
+            receiver,
+            reply_to,
+            next_id: StdCell::new(0),
+        })
+    }
+
+    /// Execute management operation
+    ///
+    /// Response with status code other than 2xx is returned as
+    /// `AmqpProtocolError::Management` error.
+    pub async fn request(
+        &self,
+        operation: &'static str,
+        body: Variant,
+    ) -> Result<Message, AmqpProtocolError> {
+        let id = self.next_id.get();
+        self.next_id.set(id.wrapping_add(1));
+
+        let mut msg = Message::default();
+        msg.set_properties(|props| {
+            props.message_id = Some(MessageId::Ulong(id));
+            props.reply_to = Some(self.reply_to.clone());
+        })
+        .set_app_property("operation", operation)
+        .set_value(body);
+        self.sender.send(msg).await?;
+
+        let mut receiver = self.receiver.clone();
+        loop {
+            let transfer = match next(&mut receiver).await {
+                Some(res) => res?,
+                None => return Err(AmqpProtocolError::Disconnected),
+            };
+            if let Some(delivery_id) = transfer.delivery_id {
+                if !transfer.is_settled() {
+                    receiver.send_disposition(Disposition {
+                        role: Role::Receiver,
+                        first: delivery_id,
+                        last: None,
+                        settled: true,
+                        state: Some(DeliveryState::Accepted(Accepted {})),
+                        batchable: false,
+                    });
+                }
+            }
+            receiver.set_link_credit(1);
+
+            let response = match transfer.body {
+                Some(TransferBody::Data(data)) => {
+                    decode_message(&data).map_err(|e| AmqpProtocolError::Codec(e.into()))?
+                }
+                Some(TransferBody::Message(msg)) => *msg,
+                None => continue,
+            };
+            let correlation_id = response
+                .properties()
+                .and_then(|props| props.correlation_id.as_ref());
+            if correlation_id != Some(&MessageId::Ulong(id)) {
+                log::warn!(
+                    "Unexpected management response, correlation id: {:?}",
+                    correlation_id
+                );
+                continue;
+            }
+
+            let status = response
+                .app_property("statusCode")
+                .or_else(|| response.app_property("status-code"))
+                .and_then(variant_to_i32)
+                .unwrap_or(200);
+            return if (200..300).contains(&status) {
+                Ok(response)
+            } else {
+                let description = match response
+                    .app_property("statusDescription")
+                    .or_else(|| response.app_property("status-description"))
+                {
+                    Some(Variant::String(s)) => Some(s.to_bytes_str()),
+                    _ => None,
+                };
+                Err(AmqpProtocolError::Management(status, description))
+            };
+        }
+    }
+
+    /// Fetch deferred messages by their sequence numbers
+    ///
+    /// Uses Service Bus `receive-by-sequence-number` operation, fetched
+    /// messages are locked for the receiver.
+    pub async fn receive_deferred(
+        &self,
+        sequence_numbers: &[i64],
+    ) -> Result<Vec<Message>, AmqpProtocolError> {
+        let body = Variant::Map(VariantMap::new(
+            vec![
+                (
+                    Variant::from("sequence-numbers"),
+                    Variant::List(List(
+                        sequence_numbers
+                            .iter()
+                            .map(|num| Variant::Long(*num))
+                            .collect(),
+                    )),
+                ),
+                (Variant::from("receiver-settle-mode"), Variant::Uint(1)),
+            ]
+            .into_iter()
+            .collect(),
+        ));
+        let response = self.request(RECEIVE_BY_SEQUENCE_NUMBER, body).await?;
+
+        let mut messages = Vec::new();
+        if let Some(Variant::Map(map)) = response.value() {
+            if let Some(Variant::List(items)) = map.map.get(&Variant::from("messages")) {
+                for item in items.0.iter() {
+                    if let Variant::Map(item) = item {
+                        if let Some(Variant::Binary(data)) = item.map.get(&Variant::from("message"))
+                        {
+                            messages.push(
+                                decode_message(data)
+                                    .map_err(|e| AmqpProtocolError::Codec(e.into()))?,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Close management links
+    pub async fn close(&self) -> Result<(), AmqpProtocolError> {
+        self.sender.close().await?;
+        self.receiver.close().await
+    }
+}
+
+fn decode_message(data: &Bytes) -> Result<Message, AmqpParseError> {
+    Message::decode(data).map(|(_, msg)| msg)
+}
+
+fn variant_to_i32(val: &Variant) -> Option<i32> {
+    match val {
+        Variant::Int(v) => Some(*v),
+        Variant::Uint(v) => Some(*v as i32),
+        Variant::Long(v) => Some(*v as i32),
+        Variant::Short(v) => Some(*v as i32),
+        Variant::Ushort(v) => Some(*v as i32),
+        _ => None,
+    }
+}
@@ -0,0 +1,308 @@
+//! Composable protocol negotiation steps
+//!
+//! Client connector uses these steps for connection handshake, server
+//! uses the same protocol header negotiation and handshake types.
+//! Custom servers and listeners could reuse them on their own transports.
+use std::rc::Rc;
+
+use ntex::codec::{AsyncRead, AsyncWrite, Decoder};
+use ntex::framed::State;
+use ntex::util::{Bytes, BytesMut};
+
+use crate::client::{ConnectError, SaslAuth};
+use crate::codec::protocol::{
+    Frame, ProtocolId, SaslCode, SaslFrameBody, SaslInit, SaslResponse, Symbols,
+};
+use crate::codec::{types::Symbol, AmqpCodec, AmqpFrame, ProtocolIdCodec, SaslFrame};
+use crate::error::ProtocolIdError;
+use crate::scram::{ScramClient, ScramMechanism};
+use crate::server::{HandshakeAmqp, HandshakeError, Sasl};
+use crate::Configuration;
+
+const PROTOCOL_HEADER_LEN: usize = 8;
+
+/// Server side protocol header negotiation
+///
+/// Reads protocol header of the peer. Supported header is confirmed,
+/// otherwise first supported header is sent back and
+/// `ProtocolIdError::Unexpected` error is returned.
+pub async fn negotiate_protocol_header<T>(
+    io: &mut T,
+    state: &State,
+    supported: &[ProtocolId],
+) -> Result<ProtocolId, HandshakeError>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let (_, protocol) = read_protocol_header(io, state).await?;
+    confirm_protocol_header(io, state, protocol?, supported).await
+}
+
+/// Read protocol header of the peer, returns raw header and protocol id
+pub(crate) async fn read_protocol_header<T>(
+    io: &mut T,
+    state: &State,
+) -> Result<(Bytes, Result<ProtocolId, ProtocolIdError>), HandshakeError>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let header = state
+        .next(io, &ProtocolHeaderCodec)
+        .await
+        .map_err(HandshakeError::from)?
+        .ok_or_else(|| {
+            log::trace!("Peer is disconnected during protocol negotiation");
+            HandshakeError::Disconnected
+        })?;
+
+    let protocol = match ProtocolIdCodec.decode(&mut BytesMut::from(&header[..])) {
+        Ok(Some(protocol)) => Ok(protocol),
+        Ok(None) => Err(ProtocolIdError::InvalidHeader),
+        Err(err) => Err(err),
+    };
+    Ok((header, protocol))
+}
+
+/// Confirm supported protocol header, otherwise send back
+/// first supported header
+pub(crate) async fn confirm_protocol_header<T>(
+    io: &mut T,
+    state: &State,
+    protocol: ProtocolId,
+    supported: &[ProtocolId],
+) -> Result<ProtocolId, HandshakeError>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    if supported.contains(&protocol) {
+        state
+            .send(io, &ProtocolIdCodec, protocol)
+            .await
+            .map_err(HandshakeError::from)?;
+        Ok(protocol)
+    } else {
+        let exp = supported.first().copied().unwrap_or(ProtocolId::Amqp);
+        log::trace!(
+            "Protocol {:?} is not supported, expected {:?}",
+            protocol,
+            exp
+        );
+        state
+            .send(io, &ProtocolIdCodec, exp)
+            .await
+            .map_err(HandshakeError::from)?;
+        Err(ProtocolIdError::Unexpected { exp, got: protocol }.into())
+    }
+}
+
+/// Server side sasl negotiation
+///
+/// Protocol header must be negotiated with `negotiate_protocol_header()`.
+/// Returned `Sasl` drives negotiation up to the peer's `Open` frame,
+/// peer could retry failed authentication `max_attempts` times.
+pub fn sasl_server<T>(io: T, state: State, config: Rc<Configuration>, max_attempts: u8) -> Sasl<T> {
+    Sasl::with_attempts(io, state, config, max_attempts)
+}
+
+/// Server side amqp negotiation
+///
+/// Protocol header must be negotiated with `negotiate_protocol_header()`.
+/// Use `HandshakeAmqp::open()` to wait for the peer's `Open` frame.
+pub fn amqp_server<T>(io: T, state: State, config: Rc<Configuration>) -> HandshakeAmqp<T> {
+    HandshakeAmqp::new(io, state, config)
+}
+
+/// Decoder for raw protocol header bytes
+struct ProtocolHeaderCodec;
+
+impl Decoder for ProtocolHeaderCodec {
+    type Item = Bytes;
+    type Error = ProtocolIdError;
+
+    fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.len() < PROTOCOL_HEADER_LEN {
+            Ok(None)
+        } else {
+            Ok(Some(src.split_to(PROTOCOL_HEADER_LEN).freeze()))
+        }
+    }
+}
+
+/// Client side sasl negotiation
+///
+/// Sends sasl protocol header and authenticates with strongest mechanism
+/// offered by server, `SCRAM-SHA-256`, `SCRAM-SHA-1` or `PLAIN`. Scram
+/// mechanisms are not used if `Configuration::sasl_scram` is disabled.
+/// After successful negotiation connection continues with `open_connection()`.
+pub async fn sasl_client<T>(
+    io: &mut T,
+    state: &State,
+    auth: &SaslAuth,
+    config: &Configuration,
+) -> Result<(), ConnectError>
+where
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    trace!("Negotiation client protocol id: AmqpSasl");
+
+    state
+        .send(io, &ProtocolIdCodec, ProtocolId::AmqpSasl)
+        .await?;
+
+    let proto = state
+        .next(io, &ProtocolIdCodec)
+        .await
+        .map_err(ConnectError::from)
+        .and_then(|res| {
+            res.ok_or_else(|| {
+                log::trace!("Amqp server is disconnected during handshake");
+                ConnectError::Disconnected
+            })
+        })?;
+    if proto != ProtocolId::AmqpSasl {
+        return Err(ConnectError::from(ProtocolIdError::Unexpected {
+            exp: ProtocolId::AmqpSasl,
+            got: proto,
+        }));
+    }
+
+    let codec = AmqpCodec::<SaslFrame>::new();
+
+    // processing sasl-mechanisms
+    let mechanisms = match state
+        .next(io, &codec)
+        .await
+        .map_err(ConnectError::from)
+        .and_then(|res| res.ok_or(ConnectError::Disconnected))?
+        .body
+    {
+        SaslFrameBody::SaslMechanisms(frame) => frame.sasl_server_mechanisms,
+        _ => Symbols::default(),
+    };
+
+    // prefer strongest scram mechanism offered by server
+    let mut scram = [ScramMechanism::Sha256, ScramMechanism::Sha1]
+        .iter()
+        .filter(|_| config.sasl_scram)
+        .find(|m| mechanisms.iter().any(|s| s.as_str() == m.name()))
+        .map(|m| ScramClient::new(*m, &auth.authz_id, &auth.authn_id, auth.password.clone()));
+
+    let sasl_init = if let Some(ref scram) = scram {
+        trace!("Authenticating with {} mechanism", scram.mechanism());
+        SaslInit {
+            hostname: config.hostname.clone(),
+            mechanism: Symbol::from(scram.mechanism().name()),
+            initial_response: Some(scram.client_first()),
+        }
+    } else {
+        SaslInit {
+            hostname: config.hostname.clone(),
+            mechanism: Symbol::from("PLAIN"),
+            initial_response: Some(SaslInit::prepare_response(
+                &auth.authz_id,
+                &auth.authn_id,
+                &auth.password,
+            )),
+        }
+    };
+    state.send(io, &codec, sasl_init.into()).await?;
+
+    loop {
+        let sasl_frame = state
+            .next(io, &codec)
+            .await
+            .map_err(ConnectError::from)
+            .and_then(|res| res.ok_or(ConnectError::Disconnected))?;
+
+        match (sasl_frame.body, scram.as_mut()) {
+            // processing sasl-challenge
+            (SaslFrameBody::SaslChallenge(challenge), Some(scram)) => {
+                let response = scram
+                    .client_final(&challenge.challenge)
+                    .map_err(ConnectError::Scram)?;
+                state
+                    .send(io, &codec, SaslResponse { response }.into())
+                    .await?;
+            }
+            // processing sasl-outcome
+            (SaslFrameBody::SaslOutcome(outcome), scram) => {
+                return match outcome.code() {
+                    SaslCode::Ok => {
+                        if let Some(scram) = scram {
+                            scram
+                                .verify(outcome.additional_data.as_deref().unwrap_or(&[]))
+                                .map_err(ConnectError::Scram)?;
+                        }
+                        Ok(())
+                    }
+                    SaslCode::Auth => Err(ConnectError::SaslAuth(outcome.additional_data)),
+                    code => Err(ConnectError::SaslSystem(code, outcome.additional_data)),
+                };
+            }
+            _ => return Err(ConnectError::Disconnected),
+        }
+    }
+}
+
+/// Client side amqp negotiation
+///
+/// Sends amqp protocol header and `Open` frame, waits for peer's `Open`.
+/// Returns frame codec and remote configuration.
+pub async fn open_connection<T>(
+    io: &mut T,
+    state: &State,
+    config: &Configuration,
+) -> Result<(AmqpCodec<AmqpFrame>, Configuration), ConnectError>
+where
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    trace!("Negotiation client protocol id: Amqp");
+
+    state.send(io, &ProtocolIdCodec, ProtocolId::Amqp).await?;
+
+    let proto = state
+        .next(io, &ProtocolIdCodec)
+        .await
+        .map_err(ConnectError::from)
+        .and_then(|res| {
+            res.ok_or_else(|| {
+                log::trace!("Amqp server is disconnected during handshake");
+                ConnectError::Disconnected
+            })
+        })?;
+
+    if proto != ProtocolId::Amqp {
+        return Err(ConnectError::from(ProtocolIdError::Unexpected {
+            exp: ProtocolId::Amqp,
+            got: proto,
+        }));
+    }
+
+    let open = config.to_open();
+    let codec = AmqpCodec::<AmqpFrame>::new()
+        .max_size(config.max_frame_size as usize)
+        .compact(config.compact_encoding);
+
+    trace!("Open client amqp connection: {:?}", open);
+    state
+        .send(io, &codec, AmqpFrame::new(0, Frame::Open(open)))
+        .await?;
+
+    let frame = state
+        .next(io, &codec)
+        .await
+        .map_err(ConnectError::from)
+        .and_then(|res| {
+            res.ok_or_else(|| {
+                log::trace!("Amqp server is disconnected during handshake");
+                ConnectError::Disconnected
+            })
+        })?;
+
+    if let Frame::Open(open) = frame.performative() {
+        trace!("Open confirmed: {:?}", open);
+        Ok((codec, open.into()))
+    } else {
+        Err(ConnectError::ExpectOpenFrame(Box::new(frame)))
+    }
+}
@@ -0,0 +1,89 @@
+use ntex::channel::mpsc;
+
+use crate::cell::Cell;
+
+/// Link state
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LinkState {
+    /// Attach frame is sent or received, link is not confirmed yet
+    Attaching,
+    /// Link is attached by both peers
+    Attached,
+    /// Local endpoint sent detach, waiting for peer's detach
+    DetachSent,
+    /// Peer detached link
+    DetachReceived,
+    /// Link is detached by both peers or connection is lost
+    Closed,
+    /// Handle is moving to new link, e.g. after reconnect
+    Resuming,
+}
+
+/// Link state shared between link and session
+#[derive(Clone, Debug)]
+pub(crate) struct LinkObserver(Cell<ObserverInner>);
+
+#[derive(Debug)]
+struct ObserverInner {
+    state: LinkState,
+    events: Option<mpsc::Sender<LinkState>>,
+}
+
+impl LinkObserver {
+    pub(crate) fn new(state: LinkState) -> Self {
+        LinkObserver(Cell::new(ObserverInner {
+            state,
+            events: None,
+        }))
+    }
+
+    /// Current link state
+    pub(crate) fn state(&self) -> LinkState {
+        self.0.get_ref().state
+    }
+
+    /// Move to new state and notify subscriber
+    pub(crate) fn set(&self, state: LinkState) {
+        let inner = self.0.get_mut();
+        if inner.state != state {
+            inner.state = state;
+            inner.notify(state);
+        }
+    }
+
+    /// Get stream of state transitions, stream starts with current state
+    pub(crate) fn events(&self) -> mpsc::Receiver<LinkState> {
+        let inner = self.0.get_mut();
+        let (tx, rx) = mpsc::channel();
+        if let Some(tx) = inner.events.replace(tx) {
+            tx.close();
+        }
+        inner.notify(inner.state);
+        rx
+    }
+
+    /// Move subscriber of `other` to this link
+    pub(crate) fn resume(&self, other: &LinkObserver) {
+        if self.0.ptr_eq(&other.0) {
+            return;
+        }
+        if let Some(tx) = other.0.get_mut().events.take() {
+            let inner = self.0.get_mut();
+            if let Some(tx) = inner.events.replace(tx) {
+                tx.close();
+            }
+            inner.notify(LinkState::Resuming);
+            inner.notify(inner.state);
+        }
+    }
+}
+
+impl ObserverInner {
+    fn notify(&mut self, state: LinkState) {
+        if let Some(ref tx) = self.events {
+            if tx.send(state).is_err() {
+                self.events = None;
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use ntex::util::ByteString;
+
+use crate::codec::{protocol::MessageId, types::Variant, Message};
+
+/// Broker specific conventions
+///
+/// Some features are not covered by amqp spec, brokers implement them
+/// with custom addresses, properties or annotations.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PeerProfile {
+    /// Plain amqp 1.0 peer
+    Generic,
+    /// Azure Service Bus
+    ServiceBus,
+    /// ActiveMQ Artemis
+    Artemis,
+}
+
+impl Default for PeerProfile {
+    fn default() -> Self {
+        PeerProfile::Generic
+    }
+}
+
+impl PeerProfile {
+    /// Address of node's dead-letter queue
+    ///
+    /// Artemis uses shared dead-letter address by default.
+    pub fn dead_letter_address(&self, address: &str) -> Option<ByteString> {
+        match self {
+            PeerProfile::Generic => None,
+            PeerProfile::ServiceBus => {
+                Some(ByteString::from(format!("{}/$deadletterqueue", address)))
+            }
+            PeerProfile::Artemis => Some(ByteString::from_static("DLQ")),
+        }
+    }
+
+    /// Stamp message with idempotency key
+    ///
+    /// Message id is derived from application `key`, so message re-sent
+    /// after reconnect carries same id. Broker specific de-duplication
+    /// properties are set as well.
+    pub fn set_idempotency_key(&self, msg: &mut Message, key: &str) {
+        let key = ByteString::from(key);
+        msg.set_properties(|props| props.message_id = Some(MessageId::String(key.clone())));
+
+        if let PeerProfile::Artemis = self {
+            msg.set_app_property("_AMQ_DUPL_ID", key);
+        }
+    }
+
+    /// Dead-letter details of received message
+    pub fn dead_letter_info<'a>(&self, msg: &'a Message) -> Option<DeadLetterInfo<'a>> {
+        let info = match self {
+            PeerProfile::Generic => return None,
+            PeerProfile::ServiceBus => DeadLetterInfo {
+                reason: string(msg.app_property("DeadLetterReason")),
+                description: string(msg.app_property("DeadLetterErrorDescription")),
+                source: string(msg.message_annotation("x-opt-deadletter-source")),
+            },
+            PeerProfile::Artemis => DeadLetterInfo {
+                reason: None,
+                description: None,
+                source: string(msg.app_property("_AMQ_ORIG_ADDRESS"))
+                    .or_else(|| string(msg.message_annotation("x-opt-ORIG-ADDRESS"))),
+            },
+        };
+
+        if info.reason.is_some() || info.description.is_some() || info.source.is_some() {
+            Some(info)
+        } else {
+            None
+        }
+    }
+}
+
+/// Dead-letter details of a message received from dead-letter queue
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DeadLetterInfo<'a> {
+    /// Reason message was dead-lettered
+    pub reason: Option<&'a str>,
+    /// Error description
+    pub description: Option<&'a str>,
+    /// Original node of the message
+    pub source: Option<&'a str>,
+}
+
+fn string(val: Option<&Variant>) -> Option<&str> {
+    match val {
+        Some(Variant::String(s)) => Some(s.as_str()),
+        Some(Variant::Symbol(s)) => Some(s.as_str()),
+        _ => None,
+    }
+}
@@ -1,17 +1,28 @@
+use std::time::{Duration, Instant};
 use std::{collections::VecDeque, future::Future, pin::Pin, task::Context, task::Poll};
 
-use ntex::util::{ByteString, BytesMut};
+use ntex::channel::{mpsc, oneshot};
+use ntex::rt::time::delay_for;
+use ntex::task::LocalWaker;
+use ntex::util::{next, poll_fn, select, ByteString, BytesMut, Either, HashMap};
 use ntex::Stream;
-use ntex::{channel::oneshot, task::LocalWaker};
 use ntex_amqp_codec::protocol::{
-    Attach, DeliveryNumber, Disposition, Error, Handle, LinkError, ReceiverSettleMode, Role,
-    SenderSettleMode, Source, TerminusDurability, TerminusExpiryPolicy, Transfer, TransferBody,
+    Accepted, AmqpError, Attach, DeliveryNumber, DeliveryState, Disposition, Error, Fields, Flow,
+    Handle, LinkError, Modified, ReceiverSettleMode, Rejected, Released, Role, SenderSettleMode,
+    Source, TerminusDurability, TerminusExpiryPolicy, Transfer, TransferBody,
 };
 use ntex_amqp_codec::Encode;
 
 use crate::cell::Cell;
+use crate::dedup::{DedupFilter, DedupKey};
+use crate::delivery::IncomingDelivery;
 use crate::error::AmqpProtocolError;
+use crate::hooks::QuotaCheck;
+use crate::interceptor::Interceptors;
+use crate::observer::{LinkObserver, LinkState};
+use crate::retry::{retry, RetryableError};
 use crate::session::{Session, SessionInner};
+use crate::types::Outcome;
 
 #[derive(Clone, Debug)]
 pub struct ReceiverLink {
@@ -23,10 +34,28 @@ impl ReceiverLink {
         ReceiverLink { inner }
     }
 
+    /// Local link handle
     pub fn handle(&self) -> Handle {
         self.inner.get_ref().handle as Handle
     }
 
+    /// Link name
+    pub fn name(&self) -> &ByteString {
+        &self.inner.get_ref().attach.name
+    }
+
+    /// Link handle used by remote peer
+    ///
+    /// Returns `None` if link is not attached by peer.
+    pub fn remote_handle(&self) -> Option<Handle> {
+        let inner = self.inner.get_ref();
+        inner
+            .session
+            .inner
+            .get_ref()
+            .remote_handle(inner.handle as usize)
+    }
+
     pub fn credit(&self) -> u32 {
         self.inner.get_ref().credit
     }
@@ -43,6 +72,10 @@ impl ReceiverLink {
         &self.inner.get_ref().attach
     }
 
+    pub(crate) fn frame_mut(&self) -> &mut Attach {
+        &mut self.inner.get_mut().attach
+    }
+
     pub fn open(&mut self) {
         let inner = self.inner.get_mut();
         inner
@@ -56,6 +89,65 @@ impl ReceiverLink {
         self.inner.get_mut().set_link_credit(credit);
     }
 
+    /// Set link credit.
+    ///
+    /// Replaces available credit, `0` revokes credit. Links attached by
+    /// router are replenished by router unless initial credit is set with
+    /// `Link::initial_credit()`.
+    pub fn set_credit(&self, credit: u32) {
+        self.inner.get_mut().set_credit(credit);
+    }
+
+    /// Add link credit.
+    pub fn add_credit(&self, credit: u32) {
+        self.inner.get_mut().add_credit(credit);
+    }
+
+    /// Ask remote sender to use up available credit.
+    ///
+    /// Sender transfers available messages and then sets credit to zero.
+    pub fn drain(&self) {
+        self.inner.get_mut().drain();
+    }
+
+    /// Credit granted by router once link is attached
+    pub(crate) fn initial_credit(&self) -> Option<u32> {
+        self.inner.get_ref().initial_credit
+    }
+
+    pub(crate) fn set_initial_credit(&self, credit: u32) {
+        self.inner.get_mut().initial_credit = Some(credit);
+    }
+
+    /// Pause link.
+    ///
+    /// Link stops issuing credit, available credit is revoked. If `drain`
+    /// is set, remote sender is asked to use up available credit instead.
+    /// Credit granted while link is paused is issued on `resume()`.
+    pub fn pause(&self, drain: bool) {
+        self.inner.get_mut().pause(drain);
+    }
+
+    /// Resume paused link.
+    ///
+    /// Restores link credit available before `pause()` call.
+    pub fn resume(&self) {
+        self.inner.get_mut().resume();
+    }
+
+    /// Check if link is paused
+    pub fn is_paused(&self) -> bool {
+        self.inner.get_ref().paused
+    }
+
+    /// Request link flow state from the peer.
+    ///
+    /// Sends link `Flow` frame with `echo` flag set, remote sender
+    /// responds with its delivery count and available messages.
+    pub fn request_flow_echo(&self) {
+        self.inner.get_mut().send_flow(true);
+    }
+
     /// Set max total size for partial transfers.
     ///
     /// Default is 256Kb
@@ -63,14 +155,113 @@ impl ReceiverLink {
         self.inner.get_mut().set_max_partial_transfer(size);
     }
 
+    /// Stream multi-frame deliveries
+    ///
+    /// Frames of multi-frame delivery are returned as they arrive instead
+    /// of being assembled in memory, use `recv_delivery()` to read them.
+    /// Queued frames count towards session incoming window, so session
+    /// window bounds limit amount of buffered data.
+    pub fn set_stream_body(&self, stream: bool) {
+        self.inner.get_mut().stream_body = stream;
+    }
+
+    /// Receive next delivery
+    ///
+    /// Use with `set_stream_body()` for large messages.
+    pub async fn recv_delivery(&mut self) -> Option<Result<IncomingDelivery, AmqpProtocolError>> {
+        match next(self).await? {
+            Ok(transfer) => Some(Ok(IncomingDelivery::new(self.clone(), transfer))),
+            Err(err) => Some(Err(err)),
+        }
+    }
+
+    /// Discard remaining frames of current streamed delivery
+    pub(crate) fn skip_streamed(&self) {
+        let inner = self.inner.get_mut();
+        if inner.read_state == ReadState::Deliver {
+            inner.read_state = ReadState::Skip;
+        }
+    }
+
+    /// Enable duplicate detection.
+    ///
+    /// Link remembers keys of last `window` messages, duplicate deliveries
+    /// are settled as accepted and are not returned to the consumer.
+    /// Credit consumed by duplicate is replenished.
+    pub fn set_dedup_filter(&self, key: DedupKey, window: usize) {
+        self.inner.get_mut().dedup = Some(DedupFilter::new(key, window));
+    }
+
+    /// Current link state
+    pub fn state(&self) -> LinkState {
+        self.inner.get_ref().observer.state()
+    }
+
+    /// Get stream of link state transitions.
+    ///
+    /// Stream starts with current state. Only one stream could be active,
+    /// subsequent call replaces previous stream.
+    pub fn state_events(&self) -> mpsc::Receiver<LinkState> {
+        self.inner.get_ref().observer.events()
+    }
+
     /// Send disposition frame
     pub fn send_disposition(&self, disp: Disposition) {
-        self.inner
-            .get_mut()
-            .session
-            .inner
-            .get_mut()
-            .post_frame(disp.into());
+        let inner = self.inner.get_mut();
+        if disp.settled && !inner.unsettled.is_empty() {
+            let last = disp.last.unwrap_or(disp.first);
+            inner
+                .unsettled
+                .retain(|id, _| *id < disp.first || *id > last);
+            inner.on_settled.wake();
+        }
+        inner.post_disposition(disp);
+    }
+
+    /// Settle delivery with outcome
+    ///
+    /// Pre-settled deliveries are ignored. Disposition of batchable
+    /// delivery is sent as batchable.
+    pub fn settle(&self, transfer: &Transfer, outcome: Outcome) {
+        if transfer.is_settled() {
+            return;
+        }
+        if let Some(delivery_id) = transfer.delivery_id {
+            self.send_disposition(Disposition {
+                role: Role::Receiver,
+                first: delivery_id,
+                last: None,
+                settled: true,
+                state: outcome.into_delivery_state(),
+                batchable: transfer.batchable,
+            });
+        }
+    }
+
+    /// Defer delivery.
+    ///
+    /// Delivery is settled with `modified` outcome with `undeliverable-here`
+    /// flag set, brokers that support deferral (Service Bus) keep message
+    /// aside, deferred message could be fetched by its sequence number
+    /// with `ManagementLink::receive_deferred()`.
+    pub fn defer(&self, transfer: &Transfer, annotations: Option<Fields>) {
+        if transfer.is_settled() {
+            return;
+        }
+        if let Some(delivery_id) = transfer.delivery_id {
+            self.send_disposition(Disposition {
+                role: Role::Receiver,
+                first: delivery_id,
+                last: None,
+                settled: true,
+                state: Some(DeliveryState::Modified(Modified {
+                    delivery_failed: Some(false),
+                    undeliverable_here: Some(true),
+                    message_annotations: annotations,
+                })),
+                batchable: false,
+            });
+        }
     }
 
     /// Wait for disposition with specified number
@@ -95,12 +286,99 @@ impl ReceiverLink {
         self.inner.get_mut().close(Some(error.into()))
     }
 
+    /// Drain link and detach it once in-flight deliveries are settled.
+    ///
+    /// Remote sender is asked to use up available credit, link stops
+    /// issuing new credit. Link gets detached when drain is completed and
+    /// all received deliveries are consumed and settled, or after `timeout`.
+    /// Deliveries that are not settled in time are released.
+    pub async fn close_gracefully(&self, timeout: Duration) -> Result<(), AmqpProtocolError> {
+        let inner = self.inner.get_mut();
+        inner.closing = true;
+        inner.drain();
+
+        let link = self.inner.clone();
+        let drained = poll_fn(move |cx| {
+            let inner = link.get_ref();
+            if inner.is_drained() {
+                Poll::Ready(())
+            } else {
+                inner.on_settled.register(cx.waker());
+                Poll::Pending
+            }
+        });
+        if let Either::Left(_) = select(delay_for(timeout), drained).await {
+            trace!("Receiver link is not drained in time, release deliveries");
+            self.inner.get_mut().release_outstanding();
+        }
+        self.inner.get_mut().close(None).await
+    }
+
+    /// Process incoming deliveries with provided handler.
+    ///
+    /// Up to `concurrency` deliveries get handled at the same time, link
+    /// credit is replenished as handlers complete. Unsettled deliveries are
+    /// settled with handler's outcome, handler error rejects delivery.
+    /// Returned future resolves when link is closed.
+    pub fn start<F, R, E>(
+        self,
+        concurrency: u32,
+        f: F,
+    ) -> impl Future<Output = Result<(), AmqpProtocolError>>
+    where
+        F: Fn(Transfer) -> R + 'static,
+        R: Future<Output = Result<Outcome, E>> + 'static,
+        Error: From<E>,
+    {
+        let mut link = self;
+        // credit is managed by handler loop
+        link.inner.get_mut().prefetch = 0;
+        async move {
+            link.set_link_credit(std::cmp::max(concurrency, 1));
+
+            while let Some(transfer) = next(&mut link).await {
+                let transfer = transfer?;
+                let delivery_id = if transfer.is_settled() {
+                    None
+                } else {
+                    transfer.delivery_id
+                };
+                let fut = f(transfer);
+                let link = link.clone();
+
+                ntex::rt::spawn(async move {
+                    let state = match fut.await {
+                        Ok(outcome) => outcome.into_delivery_state(),
+                        Err(e) => Some(DeliveryState::Rejected(Rejected {
+                            error: Some(e.into()),
+                        })),
+                    };
+                    if !link.inner.get_ref().closed {
+                        if let Some(id) = delivery_id {
+                            link.send_disposition(Disposition {
+                                state,
+                                role: Role::Receiver,
+                                first: id,
+                                last: None,
+                                settled: true,
+                                batchable: false,
+                            });
+                        }
+                        link.add_credit(1);
+                    }
+                });
+            }
+            Ok(())
+        }
+    }
+
     pub(crate) fn remote_closed(&self, error: Option<Error>) {
         trace!("Receiver link has been closed remotely");
         let inner = self.inner.get_mut();
         inner.closed = true;
         inner.error = error;
         inner.reader_task.wake();
+        inner.on_settled.wake();
     }
 }
 
@@ -110,8 +388,51 @@ impl Stream for ReceiverLink {
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let inner = self.inner.get_mut();
 
-        if inner.partial_body.is_some() && inner.queue.len() == 1 {
-            if inner.closed {
+        loop {
+            return if inner.partial_body.is_some() && inner.queue.len() == 1 {
+                if inner.closed {
+                    if let Some(err) = inner.error.take() {
+                        Poll::Ready(Some(Err(AmqpProtocolError::LinkDetached(Some(err)))))
+                    } else {
+                        Poll::Ready(None)
+                    }
+                } else {
+                    inner.reader_task.register(cx.waker());
+                    Poll::Pending
+                }
+            } else if let Some(mut tr) = inner.queue.pop_front() {
+                // continuation frames of streamed delivery
+                match inner.read_state {
+                    ReadState::Idle => (),
+                    ReadState::Deliver | ReadState::Skip => {
+                        let deliver = inner.read_state == ReadState::Deliver;
+                        if !tr.more || tr.aborted {
+                            inner.read_state = ReadState::Idle;
+                        }
+                        if deliver {
+                            return Poll::Ready(Some(Ok(tr)));
+                        }
+                        continue;
+                    }
+                }
+
+                let streamed = inner.stream_body && tr.more;
+                if inner.skip_duplicate(&tr)
+                    || inner.skip_over_quota(&tr)
+                    || inner.skip_intercepted(&mut tr)
+                {
+                    if streamed {
+                        inner.read_state = ReadState::Skip;
+                    }
+                    continue;
+                }
+                inner.delivered(&tr);
+                inner.on_settled.wake();
+                if streamed {
+                    inner.read_state = ReadState::Deliver;
+                }
+                Poll::Ready(Some(Ok(tr)))
+            } else if inner.closed {
                 if let Some(err) = inner.error.take() {
                     Poll::Ready(Some(Err(AmqpProtocolError::LinkDetached(Some(err)))))
                 } else {
@@ -120,35 +441,48 @@ impl Stream for ReceiverLink {
             } else {
                 inner.reader_task.register(cx.waker());
                 Poll::Pending
-            }
-        } else if let Some(tr) = inner.queue.pop_front() {
-            Poll::Ready(Some(Ok(tr)))
-        } else if inner.closed {
-            if let Some(err) = inner.error.take() {
-                Poll::Ready(Some(Err(AmqpProtocolError::LinkDetached(Some(err)))))
-            } else {
-                Poll::Ready(None)
-            }
-        } else {
-            inner.reader_task.register(cx.waker());
-            Poll::Pending
+            };
         }
     }
 }
 
+/// Reader state for frames of streamed delivery
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ReadState {
+    Idle,
+    Deliver,
+    Skip,
+}
+
 #[derive(Debug)]
 pub(crate) struct ReceiverLinkInner {
     handle: Handle,
     attach: Attach,
     session: Session,
     closed: bool,
+    closing: bool,
     reader_task: LocalWaker,
     queue: VecDeque<Transfer>,
     credit: u32,
+    paused: bool,
+    paused_credit: u32,
     delivery_count: u32,
     error: Option<Error>,
     partial_body: Option<BytesMut>,
     partial_body_max: usize,
+    stream_body: bool,
+    streaming: Option<DeliveryNumber>,
+    read_state: ReadState,
+    dedup: Option<DedupFilter>,
+    auto_accept: bool,
+    prefetch: u32,
+    settlement_timeout: Option<Duration>,
+    unsettled: HashMap<DeliveryNumber, Instant>,
+    on_settled: LocalWaker,
+    interceptors: Option<Interceptors>,
+    initial_credit: Option<u32>,
+    pub(crate) rcv_settle_mode: ReceiverSettleMode,
+    pub(crate) observer: LinkObserver,
 }
 
 impl ReceiverLinkInner {
@@ -157,21 +491,234 @@ impl ReceiverLinkInner {
         handle: Handle,
         attach: Attach,
     ) -> ReceiverLinkInner {
+        let settlement_timeout = session.get_ref().settlement_timeout();
         ReceiverLinkInner {
             handle,
             session: Session::new(session),
             closed: false,
+            closing: false,
             reader_task: LocalWaker::new(),
             queue: VecDeque::with_capacity(4),
             credit: 0,
+            paused: false,
+            paused_credit: 0,
             error: None,
             partial_body: None,
             partial_body_max: 262144,
+            stream_body: false,
+            streaming: None,
+            read_state: ReadState::Idle,
+            dedup: None,
+            auto_accept: false,
+            prefetch: 0,
+            settlement_timeout,
+            unsettled: HashMap::default(),
+            on_settled: LocalWaker::new(),
+            interceptors: None,
+            initial_credit: None,
+            observer: LinkObserver::new(LinkState::Attaching),
+            rcv_settle_mode: attach.rcv_settle_mode(),
             delivery_count: attach.initial_delivery_count().unwrap_or(0),
             attach,
         }
     }
 
+    /// Settle duplicate delivery and restore consumed credit
+    fn skip_duplicate(&mut self, transfer: &Transfer) -> bool {
+        let is_duplicate = self
+            .dedup
+            .as_mut()
+            .map(|dedup| dedup.is_duplicate(transfer))
+            .unwrap_or(false);
+
+        if is_duplicate {
+            trace!("Skip duplicate delivery {:?}", transfer.delivery_id);
+            self.accept(transfer);
+            self.add_credit(1);
+        }
+        is_duplicate
+    }
+
+    /// Run connection and link interceptors, reject delivery
+    /// and restore consumed credit if message is rejected
+    fn skip_intercepted(&mut self, transfer: &mut Transfer) -> bool {
+        let mut res = self
+            .session
+            .inner
+            .get_ref()
+            .interceptors()
+            .apply_incoming(transfer);
+        if res.is_ok() {
+            if let Some(ref chain) = self.interceptors {
+                res = chain.apply_incoming(transfer);
+            }
+        }
+
+        if let Err(err) = res {
+            trace!(
+                "Delivery {:?} is rejected by interceptor: {:?}",
+                transfer.delivery_id,
+                err
+            );
+            self.reject(transfer, *err);
+            self.add_credit(1);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Check delivery against connection quota
+    fn skip_over_quota(&mut self, transfer: &Transfer) -> bool {
+        let size = transfer.body.as_ref().map(|body| body.len()).unwrap_or(0);
+        let check = if let Some(quota) = self.session.inner.get_ref().quota() {
+            quota.check(size)
+        } else {
+            return false;
+        };
+
+        match check {
+            QuotaCheck::Allow => false,
+            QuotaCheck::Reject(err) => {
+                trace!(
+                    "Delivery {:?} is rejected by quota: {:?}",
+                    transfer.delivery_id,
+                    err
+                );
+                self.reject(transfer, err);
+                self.add_credit(1);
+                true
+            }
+            QuotaCheck::Detach(err) => {
+                trace!(
+                    "Detach link {:?} after quota violations: {:?}",
+                    self.attach.name,
+                    err
+                );
+                self.reject(transfer, err.clone());
+                ntex::rt::spawn(self.close(Some(err)));
+                true
+            }
+        }
+    }
+
+    /// Reject and settle delivery
+    fn reject(&mut self, transfer: &Transfer, err: Error) {
+        if !transfer.is_settled() {
+            if let Some(delivery_id) = transfer.delivery_id {
+                self.post_disposition(Disposition {
+                    role: Role::Receiver,
+                    first: delivery_id,
+                    last: None,
+                    settled: true,
+                    state: Some(DeliveryState::Rejected(Rejected { error: Some(err) })),
+                    batchable: false,
+                });
+            }
+        }
+    }
+
+    /// Apply auto-accept and prefetch settings to delivery passed to application
+    fn delivered(&mut self, transfer: &Transfer) {
+        if self.auto_accept {
+            self.accept(transfer);
+        } else if !transfer.is_settled() {
+            if let Some(delivery_id) = transfer.delivery_id {
+                self.unsettled.insert(delivery_id, Instant::now());
+            }
+        }
+        if self.prefetch > 0 {
+            self.add_credit(1);
+        }
+    }
+
+    /// Release deliveries that are not settled by application within `timeout`
+    pub(crate) fn sweep_unsettled(&mut self, timeout: Duration) {
+        let expired: Vec<_> = self
+            .unsettled
+            .iter()
+            .filter(|(_, received)| received.elapsed() >= timeout)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in expired {
+            log::trace!("Delivery {} is not settled in time, release", id);
+            self.unsettled.remove(&id);
+            self.post_disposition(Disposition {
+                role: Role::Receiver,
+                first: id,
+                last: None,
+                settled: true,
+                state: Some(DeliveryState::Released(Released {})),
+                batchable: false,
+            });
+        }
+    }
+
+    /// Check if drain is completed and all deliveries are settled
+    fn is_drained(&self) -> bool {
+        self.closed || (self.credit == 0 && self.queue.is_empty() && self.unsettled.is_empty())
+    }
+
+    /// Release received and queued deliveries that are not settled
+    fn release_outstanding(&mut self) {
+        let queued = self
+            .queue
+            .drain(..)
+            .filter(|tr| !tr.is_settled())
+            .filter_map(|tr| tr.delivery_id);
+        let ids: Vec<_> = self
+            .unsettled
+            .drain()
+            .map(|(id, _)| id)
+            .chain(queued)
+            .collect();
+        self.partial_body = None;
+
+        for id in ids {
+            self.post_disposition(Disposition {
+                role: Role::Receiver,
+                first: id,
+                last: None,
+                settled: true,
+                state: Some(DeliveryState::Released(Released {})),
+                batchable: false,
+            });
+        }
+    }
+
+    /// Send disposition of received delivery.
+    ///
+    /// In `second` mode receiver does not settle delivery, delivery
+    /// is settled once sender settles it.
+    fn post_disposition(&mut self, mut disp: Disposition) {
+        if self.rcv_settle_mode == ReceiverSettleMode::Second {
+            disp.settled = false;
+        }
+        self.session.inner.get_mut().post_frame(disp.into());
+    }
+
+    /// Settle unsettled delivery as accepted
+    fn accept(&mut self, transfer: &Transfer) {
+        if !transfer.is_settled() {
+            if let Some(delivery_id) = transfer.delivery_id {
+                self.post_disposition(Disposition {
+                    role: Role::Receiver,
+                    first: delivery_id,
+                    last: None,
+                    settled: true,
+                    state: Some(DeliveryState::Accepted(Accepted {})),
+                    batchable: false,
+                });
+            }
+        }
+    }
+
+    /// Number of transfers waiting to be consumed
+    pub(crate) fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
     pub(crate) fn detached(&mut self) {
         // drop pending transfers
         self.queue.clear();
@@ -186,6 +733,7 @@ impl ReceiverLinkInner {
         if self.closed {
             let _ = tx.send(Ok(()));
         } else {
+            self.observer.set(LinkState::DetachSent);
             self.session
                 .inner
                 .get_mut()
@@ -207,15 +755,177 @@ impl ReceiverLinkInner {
     }
 
     pub(crate) fn set_link_credit(&mut self, credit: u32) {
+        if self.closing {
+            return;
+        }
+        if self.paused {
+            self.paused_credit += credit;
+            return;
+        }
+        // link-credit is absolute, send resulting credit
         self.credit += credit;
-        self.session
-            .inner
-            .get_mut()
-            .rcv_link_flow(self.handle as u32, self.delivery_count, credit);
+        self.session.inner.get_mut().rcv_link_flow(
+            self.handle as u32,
+            self.delivery_count,
+            self.credit,
+            false,
+            false,
+        );
+    }
+
+    fn set_credit(&mut self, credit: u32) {
+        if self.closing {
+            return;
+        }
+        if self.paused {
+            self.paused_credit = credit;
+            return;
+        }
+        self.credit = credit;
+        self.session.inner.get_mut().rcv_link_flow(
+            self.handle,
+            self.delivery_count,
+            self.credit,
+            false,
+            false,
+        );
+    }
+
+    fn add_credit(&mut self, credit: u32) {
+        if self.closing {
+            return;
+        }
+        if self.paused {
+            self.paused_credit = self.paused_credit.saturating_add(credit);
+            return;
+        }
+        self.credit = self.credit.saturating_add(credit);
+        self.session.inner.get_mut().rcv_link_flow(
+            self.handle,
+            self.delivery_count,
+            self.credit,
+            false,
+            false,
+        );
+    }
+
+    fn drain(&mut self) {
+        if !self.paused && self.credit != 0 {
+            self.session.inner.get_mut().rcv_link_flow(
+                self.handle,
+                self.delivery_count,
+                self.credit,
+                true,
+                false,
+            );
+        }
+    }
+
+    fn pause(&mut self, drain: bool) {
+        if !self.paused {
+            self.paused = true;
+            self.paused_credit += self.credit;
+            if drain {
+                self.session.inner.get_mut().rcv_link_flow(
+                    self.handle,
+                    self.delivery_count,
+                    self.credit,
+                    true,
+                    false,
+                );
+            } else {
+                self.credit = 0;
+                self.send_flow(false);
+            }
+        }
+    }
+
+    fn resume(&mut self) {
+        if self.paused {
+            self.paused = false;
+            self.credit = std::mem::take(&mut self.paused_credit);
+            self.send_flow(false);
+        }
+    }
+
+    /// Send link flow frame with current receiver state
+    pub(crate) fn send_flow(&mut self, echo: bool) {
+        self.session.inner.get_mut().rcv_link_flow(
+            self.handle,
+            self.delivery_count,
+            self.credit,
+            false,
+            echo,
+        );
+    }
+
+    pub(crate) fn apply_flow(&mut self, flow: &Flow) {
+        // remote sender completed drain
+        if flow.drain() && flow.link_credit() == Some(0) {
+            if let Some(delivery_count) = flow.delivery_count() {
+                self.delivery_count = delivery_count;
+            }
+            self.credit = 0;
+            self.on_settled.wake();
+        }
+
+        // #2.7.4 receiver responds to echo with its own link state
+        if flow.echo() {
+            self.send_flow(false);
+        }
     }
 
     pub(crate) fn handle_transfer(&mut self, mut transfer: Transfer) {
-        if self.credit == 0 {
+        // #2.6.14 aborted delivery, discard partial state
+        if transfer.aborted {
+            if self.streaming.take().is_some() {
+                trace!("Streamed delivery is aborted");
+                self.delivery_count += 1;
+                self.queue.push_back(transfer);
+                self.reader_task.wake();
+                return;
+            }
+            if self.partial_body.take().is_some() {
+                trace!("Delivery is aborted, discard partial transfers");
+                self.queue.pop_back();
+                self.delivery_count += 1;
+            }
+            return;
+        }
+
+        // #2.7.3 sender in settled mode sends pre-settled deliveries only
+        if transfer.settled.is_none() && self.attach.snd_settle_mode() == SenderSettleMode::Settled
+        {
+            transfer.settled = Some(true);
+        }
+
+        // frames of streamed delivery are queued as they arrive
+        if let Some(delivery_id) = self.streaming {
+            if transfer
+                .delivery_id
+                .map(|id| id != delivery_id)
+                .unwrap_or(false)
+            {
+                let err = Error {
+                    condition: LinkError::DetachForced.into(),
+                    description: Some(ByteString::from_static("delivery_id is wrong")),
+                    info: None,
+                };
+                ntex::rt::spawn(self.close(Some(err)));
+                return;
+            }
+            if !transfer.more {
+                self.streaming = None;
+                self.delivery_count += 1;
+            }
+            self.queue.push_back(transfer);
+            self.reader_task.wake();
+            return;
+        }
+
+        // paused link accepts in-flight transfers,
+        // only first transfer of the delivery consumes credit
+        if self.credit == 0 && !self.paused && self.partial_body.is_none() {
             // check link credit
             let err = Error {
                 condition: LinkError::TransferLimitExceeded.into(),
@@ -224,7 +934,32 @@ impl ReceiverLinkInner {
             };
             let _ = self.close(Some(err));
         } else {
-            self.credit -= 1;
+            if self.partial_body.is_none()
+                && self
+                    .session
+                    .inner
+                    .get_ref()
+                    .queued_limit_reached(self.queue.len())
+            {
+                let err = Error {
+                    condition: AmqpError::ResourceLimitExceeded.into(),
+                    description: Some(ByteString::from_static(
+                        "Incoming transfers queue limit is reached",
+                    )),
+                    info: None,
+                };
+                self.session.inner.get_mut().close_connection(err);
+                return;
+            }
+
+            if self.partial_body.is_some() {
+                // continuation of partial delivery
+            } else if self.credit == 0 {
+                // in-flight transfer consumes credit saved on pause
+                self.paused_credit = self.paused_credit.saturating_sub(1);
+            } else {
+                self.credit -= 1;
+            }
 
             if let Some(ref mut body) = self.partial_body {
                 if transfer.delivery_id.is_some() {
@@ -245,6 +980,13 @@ impl ReceiverLinkInner {
                     }
                 }
 
+                // settled flag could be set on any transfer of the delivery
+                if transfer.is_settled() {
+                    if let Some(back) = self.queue.back_mut() {
+                        back.settled = Some(true);
+                    }
+                }
+
                 // merge transfer data and check size
                 if let Some(transfer_body) = transfer.body.take() {
                     if body.len() + transfer_body.len() > self.partial_body_max {
@@ -289,6 +1031,10 @@ impl ReceiverLinkInner {
                         info: None,
                     };
                     let _ = self.close(Some(err));
+                } else if self.stream_body {
+                    self.streaming = transfer.delivery_id;
+                    self.queue.push_back(transfer);
+                    self.reader_task.wake();
                 } else {
                     let body = if let Some(body) = transfer.body.take() {
                         match body {
@@ -319,6 +1065,7 @@ impl ReceiverLinkInner {
 pub struct ReceiverLinkBuilder {
     frame: Attach,
     session: Cell<SessionInner>,
+    interceptors: Option<Interceptors>,
 }
 
 impl ReceiverLinkBuilder {
@@ -336,12 +1083,13 @@ impl ReceiverLinkBuilder {
             outcomes: None,
             capabilities: None,
         };
+        let (snd_settle_mode, rcv_settle_mode) = session.get_ref().settle_modes();
         let frame = Attach {
             name,
             handle: 0_u32,
             role: Role::Receiver,
-            snd_settle_mode: SenderSettleMode::Mixed,
-            rcv_settle_mode: ReceiverSettleMode::First,
+            snd_settle_mode,
+            rcv_settle_mode,
             source: Some(source),
             target: None,
             unsettled: None,
@@ -353,7 +1101,11 @@ impl ReceiverLinkBuilder {
             properties: None,
         };
 
-        ReceiverLinkBuilder { frame, session }
+        ReceiverLinkBuilder {
+            frame,
+            session,
+            interceptors: None,
+        }
     }
 
     pub fn max_message_size(mut self, size: u64) -> Self {
@@ -361,18 +1113,49 @@ impl ReceiverLinkBuilder {
         self
     }
 
+    pub fn with_frame<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut Attach),
+    {
+        f(&mut self.frame);
+        self
+    }
+
+    /// Set interceptors for incoming messages of this link
+    ///
+    /// Link interceptors run after connection interceptors. Rejected
+    /// deliveries are settled with `rejected` outcome and are not
+    /// yielded from the link.
+    pub fn interceptors(mut self, interceptors: Interceptors) -> Self {
+        self.interceptors = Some(interceptors);
+        self
+    }
+
     pub async fn open(self) -> Result<ReceiverLink, AmqpProtocolError> {
-        let cell = self.session.clone();
-        let res = self
-            .session
-            .get_mut()
-            .open_local_receiver_link(cell, self.frame)
-            .await;
+        let session = self.session;
+        let frame = self.frame;
+        let policy = session.get_ref().retry_policy();
+        let (auto_accept, prefetch) = session.get_ref().receiver_defaults();
+
+        let link = retry(
+            policy,
+            |err| RetryableError::Attach(err),
+            || {
+                let (token, rx) = session
+                    .get_mut()
+                    .open_local_receiver_link(session.clone(), frame.clone());
+                SessionInner::wait_attach(session.clone(), token, rx)
+            },
+        )
+        .await?;
 
-        match res {
-            Ok(Ok(res)) => Ok(res),
-            Ok(Err(err)) => Err(err),
-            Err(_) => Err(AmqpProtocolError::Disconnected),
+        let inner = link.inner.get_mut();
+        inner.auto_accept = auto_accept;
+        inner.prefetch = prefetch;
+        inner.interceptors = self.interceptors;
+        if prefetch > 0 {
+            inner.set_link_credit(prefetch);
         }
+        Ok(link)
     }
 }
@@ -0,0 +1,134 @@
+use std::{cmp, future::Future, rc::Rc, time::Duration};
+
+use ntex::rt::time::delay_for;
+
+use crate::client::ConnectError;
+use crate::error::AmqpProtocolError;
+
+/// Operation error checked by retry policy
+#[derive(Debug)]
+pub enum RetryableError<'a> {
+    /// Connect error
+    Connect(&'a ConnectError),
+    /// Link attach error
+    Attach(&'a AmqpProtocolError),
+    /// Delivery error
+    Send(&'a AmqpProtocolError),
+}
+
+/// Retry policy for client operations
+///
+/// Policy is used for connect, link attach and send operations.
+pub trait RetryPolicy {
+    /// Max number of attempts, including first attempt
+    fn max_attempts(&self) -> usize;
+
+    /// Delay before next attempt, `attempt` is number of failed attempts
+    fn backoff(&self, attempt: usize) -> Duration;
+
+    /// Check if failed operation could be retried
+    fn is_retryable(&self, err: &RetryableError<'_>) -> bool;
+}
+
+/// Retry policy with exponential backoff
+#[derive(Debug, Clone)]
+pub struct ExponentialBackoff {
+    max_attempts: usize,
+    initial: Duration,
+    max: Duration,
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl ExponentialBackoff {
+    /// Create new policy
+    ///
+    /// By default initial delay is 100 millis, max delay is 10 seconds.
+    pub fn new(max_attempts: usize) -> Self {
+        ExponentialBackoff {
+            max_attempts,
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(10),
+        }
+    }
+
+    /// Set initial delay
+    pub fn initial_delay(mut self, delay: Duration) -> Self {
+        self.initial = delay;
+        self
+    }
+
+    /// Set max delay
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max = delay;
+        self
+    }
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+
+    fn backoff(&self, attempt: usize) -> Duration {
+        let factor = 1u32 << cmp::min(attempt.saturating_sub(1), 16) as u32;
+        cmp::min(self.initial * factor, self.max)
+    }
+
+    fn is_retryable(&self, err: &RetryableError<'_>) -> bool {
+        match err {
+            RetryableError::Connect(err) => matches!(
+                err,
+                ConnectError::Io(_)
+                    | ConnectError::Connect(_)
+                    | ConnectError::Disconnected
+                    | ConnectError::HandshakeTimeout
+                    | ConnectError::ConnectTimeout
+                    | ConnectError::SaslTimeout
+                    | ConnectError::OpenTimeout
+                    | ConnectError::SaslSystem(..)
+            ),
+            RetryableError::Attach(err) => matches!(err, AmqpProtocolError::LinkDetached(_)),
+            RetryableError::Send(err) => matches!(err, AmqpProtocolError::InflightLimit),
+        }
+    }
+}
+
+/// Run operation until it succeeds or policy rejects retry
+pub(crate) async fn retry<F, Fut, R, E, C>(
+    policy: Option<Rc<dyn RetryPolicy>>,
+    check: C,
+    mut f: F,
+) -> Result<R, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<R, E>>,
+    C: Fn(&E) -> RetryableError<'_>,
+{
+    let mut attempt = 0;
+    loop {
+        let err = match f().await {
+            Ok(res) => return Ok(res),
+            Err(err) => err,
+        };
+        attempt += 1;
+
+        if let Some(ref policy) = policy {
+            if attempt < policy.max_attempts() && policy.is_retryable(&check(&err)) {
+                let delay = policy.backoff(attempt);
+                log::trace!(
+                    "Operation failed, retry in {:?}, attempt {}",
+                    delay,
+                    attempt
+                );
+                delay_for(delay).await;
+                continue;
+            }
+        }
+        return Err(err);
+    }
+}
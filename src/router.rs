@@ -1,19 +1,42 @@
 use std::task::{Context, Poll};
-use std::{convert::TryFrom, future::Future, marker::PhantomData, pin::Pin};
+use std::{future::Future, marker::PhantomData, pin::Pin, rc::Rc};
 
 use ntex::router::{IntoPattern, Router as PatternRouter};
 use ntex::service::{boxed, fn_factory_with_config, IntoServiceFactory, Service, ServiceFactory};
-use ntex::util::{Either, Ready};
+use ntex::util::{Bytes, BytesMut, Either, Ready};
 use ntex::Stream;
 
-use crate::codec::protocol::{DeliveryNumber, DeliveryState, Disposition, Error, Rejected, Role};
-use crate::error::LinkError;
+use crate::codec::protocol::{
+    self, AmqpError, DeliveryNumber, DeliveryState, Disposition, Error, ErrorCondition, Rejected,
+    Released, Role, TransferBody,
+};
+use crate::codec::Encode;
+use crate::error::{ErrorOutcome, LinkError};
+use crate::stats::{Counters, RouterStats};
 use crate::types::{Link, Outcome, Transfer};
 use crate::{cell::Cell, rcvlink::ReceiverLink, State};
 
+const DEFAULT_CREDIT: u32 = 50;
+
 type Handle<S> = boxed::BoxServiceFactory<Link<S>, Transfer<S>, Outcome, Error, Error>;
+type Validator = Rc<dyn Fn(&protocol::Transfer, &Bytes) -> Result<(), Outcome>>;
+
+/// Action for deliveries rejected with `amqp:decode-error`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DecodeErrorAction {
+    /// Reject offending delivery, link and connection stay open
+    Reject,
+    /// Reject delivery and close connection with the error
+    Close,
+}
 
-pub struct Router<S = ()>(Vec<(Vec<String>, Handle<S>)>);
+pub struct Router<S = ()> {
+    services: Vec<(Vec<String>, Handle<S>)>,
+    validator: Option<Validator>,
+    stats: Option<RouterStats>,
+    release_expired: bool,
+    decode_errors: DecodeErrorAction,
+}
 
 impl<S: 'static> Default for Router<S> {
     fn default() -> Router<S> {
@@ -23,7 +46,13 @@ impl<S: 'static> Default for Router<S> {
 
 impl<S: 'static> Router<S> {
     pub fn new() -> Router<S> {
-        Router(Vec::new())
+        Router {
+            services: Vec::new(),
+            validator: None,
+            stats: None,
+            release_expired: false,
+            decode_errors: DecodeErrorAction::Reject,
+        }
     }
 
     pub fn service<T, F, U: 'static>(mut self, address: T, service: F) -> Self
@@ -32,9 +61,9 @@ impl<S: 'static> Router<S> {
         F: IntoServiceFactory<U>,
         U: ServiceFactory<Config = Link<S>, Request = Transfer<S>, Response = Outcome>,
         Error: From<U::Error> + From<U::InitError>,
-        Outcome: TryFrom<U::Error, Error = Error>,
+        U::Error: ErrorOutcome,
     {
-        self.0.push((
+        self.services.push((
             address.patterns(),
             ResourceServiceFactory::create(service.into_factory()),
         ));
@@ -42,6 +71,48 @@ impl<S: 'static> Router<S> {
         self
     }
 
+    /// Set pre-dispatch transfer validator
+    ///
+    /// Validator is called for each complete delivery before handler
+    /// service, with transfer frame and raw message payload. Payload is
+    /// not decoded. `Err(outcome)` settles delivery with outcome and
+    /// delivery is not dispatched to handler.
+    pub fn validate<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&protocol::Transfer, &Bytes) -> Result<(), Outcome> + 'static,
+    {
+        self.validator = Some(Rc::new(f));
+        self
+    }
+
+    /// Collect per-address statistics
+    ///
+    /// Statistics are keyed by link target address.
+    pub fn stats(mut self, stats: RouterStats) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Release expired deliveries
+    ///
+    /// Deliveries with passed deadline, see `Transfer::deadline()`, are
+    /// released without invoking handler service. By default expired
+    /// deliveries are dispatched to handler.
+    pub fn release_expired(mut self) -> Self {
+        self.release_expired = true;
+        self
+    }
+
+    /// Set action for deliveries that fail to decode
+    ///
+    /// Applies to deliveries rejected with `amqp:decode-error` condition,
+    /// for example handler returned `AmqpParseError`. By default only
+    /// offending delivery is rejected.
+    pub fn on_decode_error(mut self, action: DecodeErrorAction) -> Self {
+        self.decode_errors = action;
+        self
+    }
+
     pub fn finish(
         self,
     ) -> impl ServiceFactory<
@@ -52,14 +123,22 @@ impl<S: 'static> Router<S> {
         InitError = std::convert::Infallible,
     > {
         let mut router = PatternRouter::build();
-        for (addr, hnd) in self.0 {
+        for (addr, hnd) in self.services {
             router.path(addr, hnd);
         }
         let router = Cell::new(router.finish());
+        let validator = self.validator;
+        let stats = self.stats;
+        let release_expired = self.release_expired;
+        let decode_errors = self.decode_errors;
 
         fn_factory_with_config(move |_: State<S>| {
             Ready::Ok(RouterService {
                 router: router.clone(),
+                validator: validator.clone(),
+                stats: stats.clone(),
+                release_expired,
+                decode_errors,
             })
         })
     }
@@ -67,6 +146,10 @@ impl<S: 'static> Router<S> {
 
 struct RouterService<S> {
     router: Cell<PatternRouter<Handle<S>>>,
+    validator: Option<Validator>,
+    stats: Option<RouterStats>,
+    release_expired: bool,
+    decode_errors: DecodeErrorAction,
 }
 
 impl<S: 'static> Service for RouterService<S> {
@@ -88,6 +171,7 @@ impl<S: 'static> Service for RouterService<S> {
             .and_then(|target| target.address.as_ref().cloned());
 
         if let Some(path) = path {
+            let counters = self.stats.as_ref().map(|stats| stats.attach(&path));
             link.path_mut().set(path);
             if let Some((hnd, _info)) = self.router.recognize(link.path_mut()) {
                 trace!("Create handler service for {}", link.path().get_ref());
@@ -95,9 +179,16 @@ impl<S: 'static> Service for RouterService<S> {
                 Either::Right(RouterServiceResponse {
                     link: link.link.clone(),
                     app_state: link.state.clone(),
+                    validator: self.validator.clone(),
+                    counters,
+                    release_expired: self.release_expired,
+                    decode_errors: self.decode_errors,
                     state: RouterServiceResponseState::NewService(fut),
                 })
             } else {
+                if let Some(counters) = counters {
+                    counters.detach();
+                }
                 trace!(
                     "Target address is not recognized: {}",
                     link.path().get_ref()
@@ -124,9 +215,21 @@ impl<S: 'static> Service for RouterService<S> {
 struct RouterServiceResponse<S> {
     link: ReceiverLink,
     app_state: State<S>,
+    validator: Option<Validator>,
+    counters: Option<Rc<Counters>>,
+    release_expired: bool,
+    decode_errors: DecodeErrorAction,
     state: RouterServiceResponseState<S>,
 }
 
+impl<S> Drop for RouterServiceResponse<S> {
+    fn drop(&mut self) {
+        if let Some(ref counters) = self.counters {
+            counters.detach();
+        }
+    }
+}
+
 enum RouterServiceResponseState<S> {
     Service(boxed::BoxService<Transfer<S>, Outcome, Error>),
     NewService(
@@ -143,6 +246,10 @@ impl<S> Future for RouterServiceResponse<S> {
         let mut this = self.as_mut();
         let mut link = this.link.clone();
         let app_state = this.app_state.clone();
+        let validator = this.validator.clone();
+        let counters = this.counters.clone();
+        let release_expired = this.release_expired;
+        let decode_errors = this.decode_errors;
 
         loop {
             match this.state {
@@ -185,26 +292,71 @@ impl<S> Future for RouterServiceResponse<S> {
                                     }
                                 }
                                 Some(delivery_id) => {
-                                    if link.credit() == 0 {
+                                    if link.credit() == 0 && link.initial_credit().is_none() {
                                         // self.has_credit = self.link.credit() != 0;
-                                        link.set_link_credit(50);
+                                        link.set_link_credit(DEFAULT_CREDIT);
+                                    }
+
+                                    // pre-settled deliveries are not acknowledged
+                                    let delivery_id = if transfer.is_settled() {
+                                        None
+                                    } else {
+                                        Some(delivery_id)
+                                    };
+
+                                    if let Some(ref counters) = counters {
+                                        counters.transfer(
+                                            transfer.body.as_ref().map(|b| b.len()).unwrap_or(0),
+                                        );
+                                    }
+
+                                    if let Some(ref validator) = validator {
+                                        if let Err(outcome) =
+                                            (*validator)(&transfer, &payload(&transfer))
+                                        {
+                                            log::trace!("Delivery is not valid: {:?}", outcome);
+                                            settle(
+                                                &mut this.link,
+                                                delivery_id,
+                                                outcome.into_delivery_state(),
+                                                &counters,
+                                                decode_errors,
+                                            );
+                                            continue;
+                                        }
                                     }
 
                                     let msg =
                                         Transfer::new(app_state.clone(), transfer, link.clone());
 
+                                    if release_expired && msg.is_expired() {
+                                        log::trace!("Delivery is expired, release");
+                                        settle(
+                                            &mut this.link,
+                                            delivery_id,
+                                            Some(DeliveryState::Released(Released {})),
+                                            &counters,
+                                            decode_errors,
+                                        );
+                                        continue;
+                                    }
+
                                     let mut fut = srv.call(msg);
                                     match Pin::new(&mut fut).poll(cx) {
                                         Poll::Ready(Ok(outcome)) => settle(
                                             &mut this.link,
                                             delivery_id,
                                             outcome.into_delivery_state(),
+                                            &counters,
+                                            decode_errors,
                                         ),
                                         Poll::Pending => {
                                             ntex::rt::spawn(HandleMessage {
                                                 fut,
                                                 delivery_id,
                                                 link: this.link.clone(),
+                                                counters: counters.clone(),
+                                                decode_errors,
                                             });
                                         }
                                         Poll::Ready(Err(e)) => {
@@ -212,9 +364,11 @@ impl<S> Future for RouterServiceResponse<S> {
                                             settle(
                                                 &mut this.link,
                                                 delivery_id,
-                                                DeliveryState::Rejected(Rejected {
+                                                Some(DeliveryState::Rejected(Rejected {
                                                     error: Some(e),
-                                                }),
+                                                })),
+                                                &counters,
+                                                decode_errors,
                                             )
                                         }
                                     }
@@ -247,7 +401,10 @@ impl<S> Future for RouterServiceResponse<S> {
                                 .unwrap_or("")
                         );
                         this.link.open();
-                        this.link.set_link_credit(50);
+                        let credit = this.link.initial_credit().unwrap_or(DEFAULT_CREDIT);
+                        if credit > 0 {
+                            this.link.set_link_credit(credit);
+                        }
                         this.state = RouterServiceResponseState::Service(srv);
                         continue;
                     }
@@ -273,8 +430,10 @@ impl<S> Future for RouterServiceResponse<S> {
 
 struct HandleMessage {
     link: ReceiverLink,
-    delivery_id: DeliveryNumber,
+    delivery_id: Option<DeliveryNumber>,
     fut: Pin<Box<dyn Future<Output = Result<Outcome, Error>>>>,
+    counters: Option<Rc<Counters>>,
+    decode_errors: DecodeErrorAction,
 }
 
 impl Future for HandleMessage {
@@ -297,7 +456,15 @@ impl Future for HandleMessage {
                         .unwrap_or("")
                 );
                 let delivery_id = this.delivery_id;
-                settle(&mut this.link, delivery_id, outcome.into_delivery_state());
+                let counters = this.counters.clone();
+                let decode_errors = this.decode_errors;
+                settle(
+                    &mut this.link,
+                    delivery_id,
+                    outcome.into_delivery_state(),
+                    &counters,
+                    decode_errors,
+                );
                 Poll::Ready(())
             }
             Poll::Ready(Err(e)) => {
@@ -313,10 +480,14 @@ impl Future for HandleMessage {
                 );
 
                 let delivery_id = this.delivery_id;
+                let counters = this.counters.clone();
+                let decode_errors = this.decode_errors;
                 settle(
                     &mut this.link,
                     delivery_id,
-                    DeliveryState::Rejected(Rejected { error: Some(e) }),
+                    Some(DeliveryState::Rejected(Rejected { error: Some(e) })),
+                    &counters,
+                    decode_errors,
                 );
                 Poll::Ready(())
             }
@@ -324,16 +495,56 @@ impl Future for HandleMessage {
     }
 }
 
-fn settle(link: &mut ReceiverLink, id: DeliveryNumber, state: DeliveryState) {
-    let disposition = Disposition {
-        state: Some(state),
-        role: Role::Receiver,
-        first: id,
-        last: None,
-        settled: true,
-        batchable: false,
-    };
-    link.send_disposition(disposition);
+/// Raw message payload of the transfer
+fn payload(transfer: &protocol::Transfer) -> Bytes {
+    match transfer.body {
+        Some(TransferBody::Data(ref data)) => data.clone(),
+        Some(TransferBody::Message(ref msg)) => {
+            let mut buf = BytesMut::with_capacity(msg.encoded_size());
+            msg.encode(&mut buf);
+            buf.freeze()
+        }
+        None => Bytes::new(),
+    }
+}
+
+fn settle(
+    link: &mut ReceiverLink,
+    id: Option<DeliveryNumber>,
+    state: Option<DeliveryState>,
+    counters: &Option<Rc<Counters>>,
+    decode_errors: DecodeErrorAction,
+) {
+    let mut close = None;
+    if let Some(DeliveryState::Rejected(ref rejected)) = state {
+        if let Some(counters) = counters {
+            counters.rejected();
+        }
+        if decode_errors == DecodeErrorAction::Close {
+            close = rejected
+                .error
+                .as_ref()
+                .filter(|err| err.condition == ErrorCondition::AmqpError(AmqpError::DecodeError))
+                .cloned();
+        }
+    }
+
+    if let Some(id) = id {
+        let disposition = Disposition {
+            state,
+            role: Role::Receiver,
+            first: id,
+            last: None,
+            settled: true,
+            batchable: false,
+        };
+        link.send_disposition(disposition);
+    }
+
+    if let Some(err) = close {
+        log::trace!("Delivery cannot be decoded, close connection: {:?}", err);
+        link.session().inner.get_mut().close_connection(err);
+    }
 }
 
 struct ResourceServiceFactory<S, T> {
@@ -346,7 +557,7 @@ where
     S: 'static,
     T: ServiceFactory<Config = Link<S>, Request = Transfer<S>, Response = Outcome> + 'static,
     Error: From<T::Error> + From<T::InitError>,
-    Outcome: TryFrom<T::Error, Error = Error>,
+    T::Error: ErrorOutcome,
 {
     fn create(factory: T) -> Handle<S> {
         boxed::factory(ResourceServiceFactory {
@@ -360,7 +571,7 @@ impl<S, T> ServiceFactory for ResourceServiceFactory<S, T>
 where
     T: ServiceFactory<Config = Link<S>, Request = Transfer<S>, Response = Outcome>,
     Error: From<T::Error> + From<T::InitError>,
-    Outcome: TryFrom<T::Error, Error = Error>,
+    T::Error: ErrorOutcome,
 {
     type Config = Link<S>;
     type Request = Transfer<S>;
@@ -389,7 +600,7 @@ impl<S, T> Future for ResourceServiceFactoryFut<S, T>
 where
     T: ServiceFactory<Config = Link<S>, Request = Transfer<S>, Response = Outcome>,
     Error: From<T::Error> + From<T::InitError>,
-    Outcome: TryFrom<T::Error, Error = Error>,
+    T::Error: ErrorOutcome,
 {
     type Output = Result<ResourceService<S, T::Service>, Error>;
 
@@ -415,7 +626,7 @@ impl<S, T> Service for ResourceService<S, T>
 where
     T: Service<Request = Transfer<S>, Response = Outcome>,
     Error: From<T::Error>,
-    Outcome: TryFrom<T::Error, Error = Error>,
+    T::Error: ErrorOutcome,
 {
     type Request = Transfer<S>;
     type Response = Outcome;
@@ -452,14 +663,18 @@ impl<S, T> Future for ResourceServiceFut<S, T>
 where
     T: Service<Request = Transfer<S>, Response = Outcome>,
     Error: From<T::Error>,
-    Outcome: TryFrom<T::Error, Error = Error>,
+    T::Error: ErrorOutcome,
 {
     type Output = Result<Outcome, Error>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         Poll::Ready(match self.project().fut.poll(cx) {
             Poll::Ready(Ok(res)) => Ok(res),
-            Poll::Ready(Err(err)) => Outcome::try_from(err),
+            Poll::Ready(Err(err)) => {
+                let outcome = err.outcome();
+                log::trace!("Handler error, settle delivery with {:?}", outcome);
+                Ok(outcome)
+            }
             Poll::Pending => return Poll::Pending,
         })
     }
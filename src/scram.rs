@@ -0,0 +1,468 @@
+use std::{fmt, str};
+
+use hmac::{Hmac, Mac, NewMac};
+use ntex::util::{ByteString, Bytes};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+/// SCRAM mechanism
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScramMechanism {
+    /// `SCRAM-SHA-1`
+    Sha1,
+    /// `SCRAM-SHA-256`
+    Sha256,
+}
+
+impl ScramMechanism {
+    /// Sasl mechanism name
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScramMechanism::Sha1 => "SCRAM-SHA-1",
+            ScramMechanism::Sha256 => "SCRAM-SHA-256",
+        }
+    }
+
+    /// Get mechanism by sasl mechanism name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "SCRAM-SHA-1" => Some(ScramMechanism::Sha1),
+            "SCRAM-SHA-256" => Some(ScramMechanism::Sha256),
+            _ => None,
+        }
+    }
+
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            ScramMechanism::Sha1 => Sha1::digest(data).to_vec(),
+            ScramMechanism::Sha256 => Sha256::digest(data).to_vec(),
+        }
+    }
+
+    fn hmac(&self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        match self {
+            ScramMechanism::Sha1 => {
+                let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("any key size");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+            ScramMechanism::Sha256 => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("any key size");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+        }
+    }
+
+    /// Salted password, `Hi()` function of RFC 5802
+    fn salted_password(&self, password: &[u8], salt: &[u8], iterations: u32) -> Vec<u8> {
+        let mut data = salt.to_vec();
+        data.extend_from_slice(&[0, 0, 0, 1]);
+
+        let mut u = self.hmac(password, &data);
+        let mut result = u.clone();
+        for _ in 1..iterations {
+            u = self.hmac(password, &u);
+            result.iter_mut().zip(u.iter()).for_each(|(r, u)| *r ^= u);
+        }
+        result
+    }
+}
+
+impl fmt::Display for ScramMechanism {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Stored SCRAM credentials of a user
+///
+/// Server keeps salted keys instead of password. Passwords are used
+/// as is, SASLprep normalization is not applied.
+#[derive(Clone)]
+pub struct ScramCredentials {
+    /// Password salt
+    pub salt: Bytes,
+    /// Number of hash iterations
+    pub iterations: u32,
+    /// Stored key, `H(HMAC(SaltedPassword, "Client Key"))`
+    pub stored_key: Bytes,
+    /// Server key, `HMAC(SaltedPassword, "Server Key")`
+    pub server_key: Bytes,
+}
+
+impl ScramCredentials {
+    /// Derive credentials from password
+    pub fn new(mechanism: ScramMechanism, password: &str, salt: &[u8], iterations: u32) -> Self {
+        let salted = mechanism.salted_password(password.as_bytes(), salt, iterations);
+        let client_key = mechanism.hmac(&salted, b"Client Key");
+
+        ScramCredentials {
+            salt: Bytes::copy_from_slice(salt),
+            iterations,
+            stored_key: Bytes::from(mechanism.hash(&client_key)),
+            server_key: Bytes::from(mechanism.hmac(&salted, b"Server Key")),
+        }
+    }
+}
+
+impl fmt::Debug for ScramCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScramCredentials")
+            .field("iterations", &self.iterations)
+            .finish()
+    }
+}
+
+/// Client side of SCRAM exchange
+pub(crate) struct ScramClient {
+    mechanism: ScramMechanism,
+    password: ByteString,
+    nonce: String,
+    gs2_header: String,
+    client_first_bare: String,
+    server_signature: Vec<u8>,
+}
+
+impl ScramClient {
+    pub(crate) fn new(
+        mechanism: ScramMechanism,
+        authz_id: &str,
+        username: &str,
+        password: ByteString,
+    ) -> Self {
+        Self::with_nonce(mechanism, authz_id, username, password, nonce())
+    }
+
+    fn with_nonce(
+        mechanism: ScramMechanism,
+        authz_id: &str,
+        username: &str,
+        password: ByteString,
+        nonce: String,
+    ) -> Self {
+        // channel binding is not supported
+        let gs2_header = if authz_id.is_empty() {
+            "n,,".to_string()
+        } else {
+            format!("n,a={},", escape(authz_id))
+        };
+        let client_first_bare = format!("n={},r={}", escape(username), nonce);
+        ScramClient {
+            mechanism,
+            password,
+            nonce,
+            gs2_header,
+            client_first_bare,
+            server_signature: Vec::new(),
+        }
+    }
+
+    pub(crate) fn mechanism(&self) -> ScramMechanism {
+        self.mechanism
+    }
+
+    /// Initial response, `client-first-message`
+    pub(crate) fn client_first(&self) -> Bytes {
+        Bytes::from(format!("{}{}", self.gs2_header, self.client_first_bare))
+    }
+
+    /// Response to challenge, `client-final-message`
+    pub(crate) fn client_final(&mut self, server_first: &[u8]) -> Result<Bytes, &'static str> {
+        let server_first = str::from_utf8(server_first).map_err(|_| "Malformed challenge")?;
+        let nonce = attr(server_first, 'r').ok_or("Nonce is missing")?;
+        let salt = attr(server_first, 's')
+            .and_then(|s| base64::decode(s).ok())
+            .ok_or("Salt is missing")?;
+        let iterations = attr(server_first, 'i')
+            .and_then(|s| s.parse::<u32>().ok())
+            .filter(|i| *i > 0)
+            .ok_or("Iteration count is missing")?;
+        if !nonce.starts_with(&self.nonce) || nonce.len() == self.nonce.len() {
+            return Err("Server nonce does not match");
+        }
+
+        let client_final_bare = format!("c={},r={}", base64::encode(&self.gs2_header), nonce);
+        let auth_message = format!(
+            "{},{},{}",
+            self.client_first_bare, server_first, client_final_bare
+        );
+
+        let m = self.mechanism;
+        let salted = m.salted_password(self.password.as_bytes(), &salt, iterations);
+        let client_key = m.hmac(&salted, b"Client Key");
+        let signature = m.hmac(&m.hash(&client_key), auth_message.as_bytes());
+        let proof: Vec<u8> = client_key
+            .iter()
+            .zip(signature.iter())
+            .map(|(k, s)| k ^ s)
+            .collect();
+        self.server_signature = m.hmac(&m.hmac(&salted, b"Server Key"), auth_message.as_bytes());
+
+        Ok(Bytes::from(format!(
+            "{},p={}",
+            client_final_bare,
+            base64::encode(proof)
+        )))
+    }
+
+    /// Verify `server-final-message`
+    pub(crate) fn verify(&self, server_final: &[u8]) -> Result<(), &'static str> {
+        let server_final = str::from_utf8(server_final).map_err(|_| "Malformed outcome")?;
+        if let Some(err) = attr(server_final, 'e') {
+            log::trace!("Scram server error: {:?}", err);
+            return Err("Server rejected authentication");
+        }
+        match attr(server_final, 'v').and_then(|v| base64::decode(v).ok()) {
+            Some(ref sig) if !sig.is_empty() && ct_eq(sig, &self.server_signature) => Ok(()),
+            _ => Err("Server signature does not match"),
+        }
+    }
+}
+
+/// Server side of SCRAM exchange
+pub(crate) struct ScramServer {
+    mechanism: ScramMechanism,
+    gs2_header: String,
+    client_first_bare: String,
+    server_first: String,
+    nonce: String,
+    username: ByteString,
+}
+
+impl ScramServer {
+    /// Parse `client-first-message`
+    pub(crate) fn new(mechanism: ScramMechanism, client_first: &[u8]) -> Option<Self> {
+        let client_first = str::from_utf8(client_first).ok()?;
+
+        // channel binding is not supported
+        let mut parts = client_first.splitn(3, ',');
+        let flag = parts.next()?;
+        if flag != "n" && flag != "y" {
+            return None;
+        }
+        let authzid = parts.next()?;
+        if !authzid.is_empty() && !authzid.starts_with("a=") {
+            return None;
+        }
+        let bare = parts.next()?;
+
+        let username = unescape(attr(bare, 'n')?)?;
+        let client_nonce = attr(bare, 'r').filter(|r| !r.is_empty())?;
+        Some(ScramServer {
+            mechanism,
+            gs2_header: format!("{},{},", flag, authzid),
+            client_first_bare: bare.to_string(),
+            server_first: String::new(),
+            nonce: format!("{}{}", client_nonce, nonce()),
+            username: ByteString::from(username),
+        })
+    }
+
+    /// Authentication identity
+    pub(crate) fn username(&self) -> &ByteString {
+        &self.username
+    }
+
+    /// Challenge, `server-first-message`
+    pub(crate) fn server_first(&mut self, credentials: &ScramCredentials) -> Bytes {
+        self.server_first = format!(
+            "r={},s={},i={}",
+            self.nonce,
+            base64::encode(&credentials.salt),
+            credentials.iterations
+        );
+        Bytes::from(self.server_first.clone())
+    }
+
+    /// Verify `client-final-message`, returns `server-final-message`
+    pub(crate) fn verify(
+        &self,
+        credentials: &ScramCredentials,
+        client_final: &[u8],
+    ) -> Option<Bytes> {
+        let client_final = str::from_utf8(client_final).ok()?;
+        let idx = client_final.rfind(",p=")?;
+        let client_final_bare = &client_final[..idx];
+        let proof = base64::decode(&client_final[idx + 3..]).ok()?;
+
+        let binding = base64::decode(attr(client_final_bare, 'c')?).ok()?;
+        if binding != self.gs2_header.as_bytes() || attr(client_final_bare, 'r')? != self.nonce {
+            return None;
+        }
+
+        let m = self.mechanism;
+        let auth_message = format!(
+            "{},{},{}",
+            self.client_first_bare, self.server_first, client_final_bare
+        );
+        let signature = m.hmac(&credentials.stored_key, auth_message.as_bytes());
+        if proof.len() != signature.len() {
+            return None;
+        }
+        let client_key: Vec<u8> = proof
+            .iter()
+            .zip(signature.iter())
+            .map(|(p, s)| p ^ s)
+            .collect();
+        if !ct_eq(&m.hash(&client_key), &credentials.stored_key) {
+            return None;
+        }
+
+        let server_signature = m.hmac(&credentials.server_key, auth_message.as_bytes());
+        Some(Bytes::from(format!(
+            "v={}",
+            base64::encode(server_signature)
+        )))
+    }
+}
+
+/// Value of `k=value` attribute
+fn attr(msg: &str, key: char) -> Option<&str> {
+    msg.split(',').find_map(|part| {
+        let mut chars = part.chars();
+        if chars.next() == Some(key) && chars.next() == Some('=') {
+            Some(&part[2..])
+        } else {
+            None
+        }
+    })
+}
+
+/// Constant-time comparison of keys and signatures
+fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+fn escape(name: &str) -> String {
+    name.replace('=', "=3D").replace(',', "=2C")
+}
+
+fn unescape(name: &str) -> Option<String> {
+    let mut result = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(idx) = rest.find('=') {
+        result.push_str(&rest[..idx]);
+        match rest.get(idx + 1..idx + 3) {
+            Some("2C") => result.push(','),
+            Some("3D") => result.push('='),
+            _ => return None,
+        }
+        rest = &rest[idx + 3..];
+    }
+    result.push_str(rest);
+    Some(result)
+}
+
+fn nonce() -> String {
+    let mut data = uuid::Uuid::new_v4().as_bytes().to_vec();
+    data.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+    base64::encode(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exchange(
+        mechanism: ScramMechanism,
+        client_nonce: &str,
+        server_nonce: &str,
+        salt: &str,
+    ) -> (ScramClient, ScramServer, ScramCredentials) {
+        let client = ScramClient::with_nonce(
+            mechanism,
+            "",
+            "user",
+            ByteString::from_static("pencil"),
+            client_nonce.to_string(),
+        );
+        let mut server = ScramServer::new(mechanism, &client.client_first()).unwrap();
+        server.nonce = server_nonce.to_string();
+        let creds =
+            ScramCredentials::new(mechanism, "pencil", &base64::decode(salt).unwrap(), 4096);
+        (client, server, creds)
+    }
+
+    #[test]
+    fn test_rfc5802() {
+        let (mut client, mut server, creds) = exchange(
+            ScramMechanism::Sha1,
+            "fyko+d2lbbFgONRv9qkxdawL",
+            "fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j",
+            "QSXCR+Q6sek8bf92",
+        );
+        assert_eq!(
+            &client.client_first()[..],
+            b"n,,n=user,r=fyko+d2lbbFgONRv9qkxdawL"
+        );
+
+        let server_first = server.server_first(&creds);
+        assert_eq!(
+            &server_first[..],
+            &b"r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096"[..]
+        );
+        let client_final = client.client_final(&server_first).unwrap();
+        assert_eq!(
+            &client_final[..],
+            &b"c=biws,r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,p=v0X8v3Bz2T0CJGbJQyF0X+HI4Ts="
+                [..]
+        );
+        let server_final = server.verify(&creds, &client_final).unwrap();
+        assert_eq!(&server_final[..], b"v=rmF9pqV8S7suAoZWja4dJRkFsKQ=");
+        assert!(client.verify(&server_final).is_ok());
+    }
+
+    #[test]
+    fn test_rfc7677() {
+        let (mut client, mut server, creds) = exchange(
+            ScramMechanism::Sha256,
+            "rOprNGfwEbeRWgbNEkqO",
+            "rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0",
+            "W22ZaJ0SNY7soEsUEjb6gQ==",
+        );
+        let server_first = server.server_first(&creds);
+        let client_final = client.client_final(&server_first).unwrap();
+        assert_eq!(
+            &client_final[..],
+            &b"c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="[..]
+        );
+        let server_final = server.verify(&creds, &client_final).unwrap();
+        assert_eq!(
+            &server_final[..],
+            &b"v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4="[..]
+        );
+        assert!(client.verify(&server_final).is_ok());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let creds = ScramCredentials::new(ScramMechanism::Sha256, "secret", b"salt", 16);
+        let mut client = ScramClient::new(
+            ScramMechanism::Sha256,
+            "admin",
+            "user",
+            ByteString::from_static("secret"),
+        );
+        assert!(client.client_first().starts_with(b"n,a=admin,n=user,"));
+
+        let mut server = ScramServer::new(ScramMechanism::Sha256, &client.client_first()).unwrap();
+        assert_eq!(server.username(), "user");
+        let client_final = client.client_final(&server.server_first(&creds)).unwrap();
+        let server_final = server.verify(&creds, &client_final).unwrap();
+        assert!(client.verify(&server_final).is_ok());
+
+        // wrong password
+        let mut client = ScramClient::new(
+            ScramMechanism::Sha256,
+            "",
+            "user",
+            ByteString::from_static("wrong"),
+        );
+        let mut server = ScramServer::new(ScramMechanism::Sha256, &client.client_first()).unwrap();
+        let client_final = client.client_final(&server.server_first(&creds)).unwrap();
+        assert!(server.verify(&creds, &client_final).is_none());
+        assert!(client.verify(b"v=AAAA").is_err());
+    }
+}
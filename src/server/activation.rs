@@ -0,0 +1,52 @@
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::os::unix::net::UnixListener;
+use std::{env, io, net::TcpListener, process};
+
+use socket2::Socket;
+
+/// First file descriptor passed by service manager
+const LISTEN_FDS_START: RawFd = 3;
+
+/// Listener passed by service manager
+#[derive(Debug)]
+pub enum ActivatedListener {
+    /// Tcp listener
+    Tcp(TcpListener),
+    /// Unix domain socket listener
+    Unix(UnixListener),
+}
+
+/// Take listeners passed with systemd socket activation protocol.
+///
+/// Returns empty list if process is not socket activated. Environment
+/// variables are cleared, so listeners could be taken only once.
+/// Listeners could be registered with `ntex::server::ServerBuilder::listen()`
+/// and `listen_uds()` methods.
+pub fn activated_listeners() -> io::Result<Vec<ActivatedListener>> {
+    let pid = env::var("LISTEN_PID").ok();
+    let fds = env::var("LISTEN_FDS").ok();
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+
+    match pid.and_then(|pid| pid.parse::<u32>().ok()) {
+        Some(pid) if pid == process::id() => (),
+        _ => return Ok(Vec::new()),
+    }
+    let fds = fds
+        .and_then(|fds| fds.parse::<RawFd>().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid LISTEN_FDS"))?;
+
+    let mut listeners = Vec::with_capacity(fds as usize);
+    for fd in LISTEN_FDS_START..LISTEN_FDS_START + fds {
+        // fds are passed to the process by service manager and owned by it
+        let socket = unsafe { Socket::from_raw_fd(fd) };
+        socket.set_nonblocking(true)?;
+        if socket.local_addr()?.as_socket().is_some() {
+            listeners.push(ActivatedListener::Tcp(socket.into()));
+        } else {
+            listeners.push(ActivatedListener::Unix(socket.into()));
+        }
+    }
+    Ok(listeners)
+}
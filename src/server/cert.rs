@@ -0,0 +1,17 @@
+use ntex::util::{ByteString, Bytes};
+
+/// Peer certificate details
+///
+/// Details are provided by the certificate extractor configured with
+/// `Server::peer_certificate()`.
+#[derive(Debug, Clone, Default)]
+pub struct PeerCertificate {
+    /// Der encoded certificate chain, peer certificate goes first
+    pub chain: Vec<Bytes>,
+    /// Certificate subject
+    pub subject: Option<ByteString>,
+    /// Subject alternative names
+    pub san: Vec<ByteString>,
+    /// Certificate fingerprint
+    pub fingerprint: Option<Bytes>,
+}
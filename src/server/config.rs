@@ -0,0 +1,43 @@
+use std::sync::{Arc, RwLock};
+
+use crate::Configuration;
+
+/// Shared handle to server configuration
+///
+/// Handle could be updated at runtime, new settings are applied to
+/// connections accepted after update. Established connections keep
+/// settings they were accepted with.
+#[derive(Clone, Debug)]
+pub struct ServerConfig(Arc<RwLock<Configuration>>);
+
+impl ServerConfig {
+    /// Create configuration handle
+    pub fn new(config: Configuration) -> Self {
+        ServerConfig(Arc::new(RwLock::new(config)))
+    }
+
+    /// Get copy of current configuration
+    pub fn get(&self) -> Configuration {
+        match self.0.read() {
+            Ok(config) => config.clone(),
+            Err(err) => err.into_inner().clone(),
+        }
+    }
+
+    /// Update configuration
+    pub fn update<F>(&self, f: F)
+    where
+        F: FnOnce(&mut Configuration),
+    {
+        match self.0.write() {
+            Ok(mut config) => f(&mut config),
+            Err(err) => f(&mut err.into_inner()),
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self::new(Configuration::default())
+    }
+}
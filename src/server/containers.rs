@@ -0,0 +1,88 @@
+use std::{cell::RefCell, rc::Rc};
+
+use ntex::util::{ByteString, HashMap};
+
+use crate::codec::protocol::{AmqpError, ConnectionError, Error};
+use crate::{hooks::ContainerRegistry, Connection};
+
+/// Policy for connections that reuse container id of existing connection
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ContainerIdPolicy {
+    /// Do not check container ids
+    Allow,
+    /// Close new connection
+    Reject,
+    /// Close existing connection and accept new one
+    CloseOld,
+}
+
+pub(crate) type ContainerIdHook = Rc<dyn Fn(&ByteString, ContainerIdPolicy)>;
+
+/// Registry of open connections by remote container id, local to server worker
+pub(crate) struct ContainerIds {
+    policy: ContainerIdPolicy,
+    on_conflict: Option<ContainerIdHook>,
+    connections: RefCell<HashMap<ByteString, Connection>>,
+}
+
+impl ContainerIds {
+    pub(crate) fn new(policy: ContainerIdPolicy, on_conflict: Option<ContainerIdHook>) -> Self {
+        ContainerIds {
+            policy,
+            on_conflict,
+            connections: RefCell::new(HashMap::default()),
+        }
+    }
+}
+
+impl ContainerRegistry for ContainerIds {
+    fn register(&self, container_id: &ByteString, conn: &Connection) -> bool {
+        let mut connections = self.connections.borrow_mut();
+
+        if let Some(existing) = connections.get(container_id) {
+            if existing.0.get_ref().error.is_none() {
+                if let Some(ref hook) = self.on_conflict {
+                    (*hook)(container_id, self.policy);
+                }
+
+                match self.policy {
+                    ContainerIdPolicy::Allow => (),
+                    ContainerIdPolicy::Reject => {
+                        log::trace!(
+                            "Container id {:?} is in use, close connection",
+                            container_id
+                        );
+                        conn.0.get_mut().close_with_error(Error {
+                            condition: AmqpError::InvalidField.into(),
+                            description: Some(ByteString::from_static("Container id is in use")),
+                            info: None,
+                        });
+                        return false;
+                    }
+                    ContainerIdPolicy::CloseOld => {
+                        log::trace!(
+                            "Container id {:?} is in use, close existing connection",
+                            container_id
+                        );
+                        existing.0.get_mut().close_with_error(Error {
+                            condition: ConnectionError::ConnectionForced.into(),
+                            description: Some(ByteString::from_static("Connection is taken over")),
+                            info: None,
+                        });
+                    }
+                }
+            }
+        }
+        connections.insert(container_id.clone(), conn.clone());
+        true
+    }
+
+    fn remove(&self, container_id: &ByteString, conn: &Connection) {
+        let mut connections = self.connections.borrow_mut();
+        if let Some(existing) = connections.get(container_id) {
+            if existing.0.ptr_eq(&conn.0) {
+                connections.remove(container_id);
+            }
+        }
+    }
+}
@@ -4,6 +4,8 @@ use ntex::util::{ByteString, Either};
 use crate::codec::{protocol, AmqpCodecError, AmqpFrame, ProtocolIdError, SaslFrame};
 use crate::error::AmqpProtocolError;
 
+use super::proxy::ProxyProtocolError;
+
 /// Errors which can occur when attempting to handle amqp connection.
 #[derive(Debug, Display)]
 pub enum ServerError<E> {
@@ -76,6 +78,15 @@ pub enum HandshakeError {
     Sasl(protocol::SaslCode),
     #[display(fmt = "Peer disconnected")]
     Disconnected,
+    /// PROXY protocol header error
+    #[display(fmt = "PROXY protocol error: {}", _0)]
+    ProxyProtocol(ProxyProtocolError),
+    /// Peer identity could not be resolved
+    #[display(fmt = "Peer identity is not resolved")]
+    Unauthenticated,
+    /// Connection with unsupported protocol header is passed to other handler
+    #[display(fmt = "Connection is diverted")]
+    Diverted,
     /// Unexpected io error
     Io(std::io::Error),
 }
@@ -91,6 +102,15 @@ impl From<Either<AmqpCodecError, std::io::Error>> for HandshakeError {
     }
 }
 
+impl From<Either<ProxyProtocolError, std::io::Error>> for HandshakeError {
+    fn from(err: Either<ProxyProtocolError, std::io::Error>) -> Self {
+        match err {
+            Either::Left(err) => HandshakeError::ProxyProtocol(err),
+            Either::Right(err) => HandshakeError::Io(err),
+        }
+    }
+}
+
 impl From<Either<ProtocolIdError, std::io::Error>> for HandshakeError {
     fn from(err: Either<ProtocolIdError, std::io::Error>) -> Self {
         match err {
@@ -1,13 +1,17 @@
-use std::rc::Rc;
+use std::{net::SocketAddr, rc::Rc};
 
 use ntex::codec::{AsyncRead, AsyncWrite};
 use ntex::framed::State;
+use ntex::util::Extensions;
 
 use crate::codec::protocol::{Frame, Open};
 use crate::codec::{AmqpCodec, AmqpFrame};
 use crate::{connection::Connection, Configuration};
 
-use super::{error::HandshakeError, sasl::Sasl};
+use super::cert::PeerCertificate;
+use super::error::HandshakeError;
+use super::identity::PeerIdentity;
+use super::sasl::{Sasl, SaslLimits};
 
 /// Connection handshake
 pub enum Handshake<Io> {
@@ -16,16 +20,75 @@ pub enum Handshake<Io> {
 }
 
 impl<Io> Handshake<Io> {
-    pub(crate) fn new_plain(io: Io, state: State, local_config: Rc<Configuration>) -> Self {
+    pub(crate) fn new_plain(
+        io: Io,
+        state: State,
+        local_config: Rc<Configuration>,
+        peer_addr: Option<SocketAddr>,
+        peer_cert: Option<Rc<PeerCertificate>>,
+        identity: Option<Rc<PeerIdentity>>,
+    ) -> Self {
         Handshake::Amqp(HandshakeAmqp {
             io,
             state,
             local_config,
+            peer_addr,
+            peer_cert,
+            identity,
         })
     }
 
-    pub(crate) fn new_sasl(io: Io, state: State, local_config: Rc<Configuration>) -> Self {
-        Handshake::Sasl(Sasl::new(io, state, local_config))
+    pub(crate) fn new_sasl(
+        io: Io,
+        state: State,
+        local_config: Rc<Configuration>,
+        limits: Rc<SaslLimits>,
+        peer_addr: Option<SocketAddr>,
+        peer_cert: Option<Rc<PeerCertificate>>,
+    ) -> Self {
+        Handshake::Sasl(Sasl::new(
+            io,
+            state,
+            local_config,
+            limits,
+            peer_addr,
+            peer_cert,
+        ))
+    }
+
+    /// Get local configuration of the listener
+    pub fn local_config(&self) -> &Configuration {
+        match self {
+            Handshake::Amqp(ref item) => item.local_config.as_ref(),
+            Handshake::Sasl(ref item) => item.local_config(),
+        }
+    }
+
+    /// Get remote peer address
+    ///
+    /// Address is taken from PROXY protocol header if it is enabled,
+    /// available for tcp connections otherwise.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        match self {
+            Handshake::Amqp(ref item) => item.peer_addr,
+            Handshake::Sasl(ref item) => item.peer_addr(),
+        }
+    }
+
+    /// Get peer certificate
+    pub fn peer_certificate(&self) -> Option<&PeerCertificate> {
+        match self {
+            Handshake::Amqp(ref item) => item.peer_cert.as_deref(),
+            Handshake::Sasl(ref item) => item.peer_certificate(),
+        }
+    }
+
+    /// Get identity of pre-authenticated peer
+    pub fn identity(&self) -> Option<&PeerIdentity> {
+        match self {
+            Handshake::Amqp(ref item) => item.identity.as_deref(),
+            Handshake::Sasl(_) => None,
+        }
     }
 }
 
@@ -34,9 +97,38 @@ pub struct HandshakeAmqp<Io> {
     io: Io,
     state: State,
     local_config: Rc<Configuration>,
+    peer_addr: Option<SocketAddr>,
+    peer_cert: Option<Rc<PeerCertificate>>,
+    identity: Option<Rc<PeerIdentity>>,
 }
 
 impl<Io> HandshakeAmqp<Io> {
+    pub(crate) fn new(io: Io, state: State, local_config: Rc<Configuration>) -> Self {
+        HandshakeAmqp {
+            io,
+            state,
+            local_config,
+            peer_addr: None,
+            peer_cert: None,
+            identity: None,
+        }
+    }
+
+    /// Get remote peer address
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer_addr
+    }
+
+    /// Get peer certificate
+    pub fn peer_certificate(&self) -> Option<&PeerCertificate> {
+        self.peer_cert.as_deref()
+    }
+
+    /// Get identity of pre-authenticated peer
+    pub fn identity(&self) -> Option<&PeerIdentity> {
+        self.identity.as_deref()
+    }
+
     /// Returns reference to io object
     pub fn get_ref(&self) -> &Io {
         &self.io
@@ -54,6 +146,8 @@ impl<Io: AsyncRead + AsyncWrite + Unpin> HandshakeAmqp<Io> {
         let mut io = self.io;
         let state = self.state;
         let local_config = self.local_config;
+        let peer_cert = self.peer_cert;
+        let identity = self.identity;
         let codec = AmqpCodec::<AmqpFrame>::new();
 
         let frame = state
@@ -71,6 +165,7 @@ impl<Io: AsyncRead + AsyncWrite + Unpin> HandshakeAmqp<Io> {
                 trace!("Got open frame: {:?}", frame);
                 let remote_config = (&frame).into();
                 let sink = Connection::new(state.clone(), &local_config, &remote_config);
+                sink.0.get_mut().identity = identity.as_ref().map(|item| item.id.clone());
                 Ok(HandshakeAmqpOpened {
                     frame,
                     io,
@@ -78,6 +173,9 @@ impl<Io: AsyncRead + AsyncWrite + Unpin> HandshakeAmqp<Io> {
                     state,
                     local_config,
                     remote_config,
+                    peer_cert,
+                    identity,
+                    ext: Extensions::new(),
                 })
             }
             frame => Err(HandshakeError::Unexpected(Box::new(frame))),
@@ -93,9 +191,13 @@ pub struct HandshakeAmqpOpened<Io> {
     state: State,
     local_config: Rc<Configuration>,
     remote_config: Configuration,
+    peer_cert: Option<Rc<PeerCertificate>>,
+    identity: Option<Rc<PeerIdentity>>,
+    ext: Extensions,
 }
 
 impl<Io> HandshakeAmqpOpened<Io> {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         frame: Open,
         io: Io,
@@ -103,6 +205,8 @@ impl<Io> HandshakeAmqpOpened<Io> {
         state: State,
         local_config: Rc<Configuration>,
         remote_config: Configuration,
+        peer_cert: Option<Rc<PeerCertificate>>,
+        ext: Extensions,
     ) -> Self {
         Self {
             frame,
@@ -111,6 +215,9 @@ impl<Io> HandshakeAmqpOpened<Io> {
             state,
             local_config,
             remote_config,
+            peer_cert,
+            identity: None,
+            ext,
         }
     }
 
@@ -139,11 +246,34 @@ impl<Io> HandshakeAmqpOpened<Io> {
         &self.remote_config
     }
 
+    /// Get peer certificate
+    pub fn peer_certificate(&self) -> Option<&PeerCertificate> {
+        self.peer_cert.as_deref()
+    }
+
+    /// Get identity of pre-authenticated peer
+    pub fn identity(&self) -> Option<&PeerIdentity> {
+        self.identity.as_deref()
+    }
+
     /// Connection sink
     pub fn sink(&self) -> &Connection {
         &self.sink
     }
 
+    /// Connection extensions
+    ///
+    /// Extensions are available to link and control services
+    /// with `State::extensions()`.
+    pub fn extensions(&self) -> &Extensions {
+        &self.ext
+    }
+
+    /// Mutable connection extensions
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.ext
+    }
+
     /// Ack connect message and set state
     pub fn ack<St>(self, st: St) -> HandshakeAck<Io, St> {
         HandshakeAck {
@@ -152,6 +282,7 @@ impl<Io> HandshakeAmqpOpened<Io> {
             sink: self.sink,
             state: self.state,
             idle_timeout: self.remote_config.timeout_remote_secs(),
+            ext: self.ext,
         }
     }
 }
@@ -163,10 +294,23 @@ pub struct HandshakeAck<Io, St> {
     sink: Connection,
     state: State,
     idle_timeout: usize,
+    ext: Extensions,
 }
 
 impl<Io, St> HandshakeAck<Io, St> {
-    pub(crate) fn into_inner(self) -> (St, Io, Connection, State, usize) {
-        (self.st, self.io, self.sink, self.state, self.idle_timeout)
+    /// Connection extensions
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.ext
+    }
+
+    pub(crate) fn into_inner(self) -> (St, Io, Connection, State, usize, Extensions) {
+        (
+            self.st,
+            self.io,
+            self.sink,
+            self.state,
+            self.idle_timeout,
+            self.ext,
+        )
     }
 }
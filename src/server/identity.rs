@@ -0,0 +1,30 @@
+use std::{future::Future, net::SocketAddr, pin::Pin, rc::Rc};
+
+use ntex::util::ByteString;
+
+/// Identity of pre-authenticated peer
+///
+/// Identity is provided by resolver configured with `Server::pre_authenticated()`.
+#[derive(Debug, Clone, Default)]
+pub struct PeerIdentity {
+    /// Authenticated identity
+    pub id: ByteString,
+    /// Remote peer address, e.g. client address from proxy
+    pub peer_addr: Option<SocketAddr>,
+    /// Identity groups or roles
+    pub groups: Vec<ByteString>,
+}
+
+impl PeerIdentity {
+    /// Create identity
+    pub fn new<T: Into<ByteString>>(id: T) -> Self {
+        PeerIdentity {
+            id: id.into(),
+            peer_addr: None,
+            groups: Vec::new(),
+        }
+    }
+}
+
+pub(crate) type IdentityResolver<Io> =
+    Rc<dyn Fn(&Io, Option<SocketAddr>) -> Pin<Box<dyn Future<Output = Option<PeerIdentity>>>>>;
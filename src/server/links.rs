@@ -0,0 +1,113 @@
+use std::cell::RefCell;
+
+use ntex::util::{ByteString, Either, HashMap};
+
+use crate::codec::protocol::{Attach, Error, LinkError, Role};
+use crate::hooks::LinkRegistry;
+use crate::{ReceiverLink, SenderLink};
+
+/// Policy for attaches that reuse name of existing link
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LinkNamePolicy {
+    /// Do not check link names
+    Allow,
+    /// Reject new attach
+    Reject,
+    /// Detach existing link and accept new attach
+    Steal,
+}
+
+type Key = (ByteString, ByteString, bool);
+
+/// Remotely attached links registry, local to server worker
+pub(crate) struct LinkNames {
+    policy: LinkNamePolicy,
+    links: RefCell<HashMap<Key, Either<SenderLink, ReceiverLink>>>,
+}
+
+impl LinkNames {
+    pub(crate) fn new(policy: LinkNamePolicy) -> Self {
+        LinkNames {
+            policy,
+            links: RefCell::new(HashMap::default()),
+        }
+    }
+}
+
+impl LinkRegistry for LinkNames {
+    fn check(&self, container_id: &ByteString, attach: &Attach) -> bool {
+        let key = (
+            container_id.clone(),
+            attach.name().clone(),
+            attach.role == Role::Sender,
+        );
+        let mut links = self.links.borrow_mut();
+
+        let link = if let Some(link) = links.remove(&key) {
+            link
+        } else {
+            return true;
+        };
+        if !is_attached(&link) {
+            return true;
+        }
+
+        match self.policy {
+            LinkNamePolicy::Allow => {
+                links.insert(key, link);
+                true
+            }
+            LinkNamePolicy::Reject => {
+                log::trace!("Link name {:?} is in use, reject attach", attach.name());
+                links.insert(key, link);
+                false
+            }
+            LinkNamePolicy::Steal => {
+                log::trace!("Link name {:?} is in use, detach old link", attach.name());
+                let err = Error {
+                    condition: LinkError::Stolen.into(),
+                    description: None,
+                    info: None,
+                };
+                match link {
+                    Either::Left(link) => {
+                        ntex::rt::spawn(async move {
+                            let _ = link.close_with_error(err).await;
+                        });
+                    }
+                    Either::Right(link) => {
+                        ntex::rt::spawn(async move {
+                            let _ = link.close_with_error(err).await;
+                        });
+                    }
+                }
+                true
+            }
+        }
+    }
+
+    fn register(
+        &self,
+        container_id: &ByteString,
+        attach: &Attach,
+        link: Either<SenderLink, ReceiverLink>,
+    ) {
+        let key = (
+            container_id.clone(),
+            attach.name().clone(),
+            attach.role == Role::Sender,
+        );
+        self.links.borrow_mut().insert(key, link);
+    }
+
+    fn cleanup(&self) {
+        self.links.borrow_mut().retain(|_, link| is_attached(link));
+    }
+}
+
+fn is_attached(link: &Either<SenderLink, ReceiverLink>) -> bool {
+    match link {
+        Either::Left(link) => link.session().inner.get_ref().is_sender_attached(link),
+        Either::Right(link) => link.session().inner.get_ref().is_receiver_attached(link),
+    }
+}
@@ -1,14 +1,39 @@
+#[cfg(unix)]
+mod activation;
+mod cert;
+mod config;
+pub(crate) mod containers;
 mod error;
 mod handshake;
+mod identity;
+pub(crate) mod links;
+mod mux;
+mod protocol;
+mod proxy;
+mod quota;
+mod registry;
 pub mod sasl;
 mod service;
 
+#[cfg(unix)]
+pub use self::activation::{activated_listeners, ActivatedListener};
+pub use self::cert::PeerCertificate;
+pub use self::config::ServerConfig;
+pub use self::containers::ContainerIdPolicy;
 pub use self::error::{HandshakeError, ServerError};
 pub use self::handshake::{Handshake, HandshakeAck, HandshakeAmqp, HandshakeAmqpOpened};
-pub use self::sasl::Sasl;
+pub use self::identity::PeerIdentity;
+pub use self::links::LinkNamePolicy;
+pub use self::mux::{MultiplexError, Multiplexer, MultiplexerService};
+pub use self::protocol::{ProtocolAction, UnsupportedProtocol};
+pub use self::proxy::{ProxyHeader, ProxyProtocolError};
+pub use self::quota::{Quota, Quotas};
+pub use self::registry::{ConnectionInfo, ConnectionRegistry};
+pub use self::sasl::{Sasl, SaslFailure};
 pub use self::service::Server;
 pub use crate::control::{ControlFrame, ControlFrameKind};
-pub use crate::error::{Error, LinkError};
-pub use crate::router::Router;
+pub use crate::error::{Error, ErrorOutcome, LinkError};
+pub use crate::router::{DecodeErrorAction, Router};
 pub use crate::state::State;
-pub use crate::types::{Link, Outcome, Transfer};
+pub use crate::stats::{AddressStats, RouterStats};
+pub use crate::types::{Link, Outcome, OutgoingLink, Transfer};
@@ -0,0 +1,188 @@
+use std::task::{Context, Poll};
+use std::{fmt, future::Future, io, pin::Pin, rc::Rc, time::Duration};
+
+use derive_more::Display;
+use ntex::rt::{net::TcpStream, time::delay_for};
+use ntex::service::{IntoServiceFactory, Service, ServiceFactory};
+
+const AMQP_PREFIX: &[u8] = b"AMQP";
+
+/// Protocol multiplexer
+///
+/// Multiplexer peeks first bytes of tcp stream without consuming them.
+/// Streams that start with amqp protocol header are passed to amqp server,
+/// other streams, e.g. http health checks, are passed to fallback service
+/// that listens on the same port.
+pub struct Multiplexer<A, B> {
+    amqp: A,
+    fallback: B,
+    timeout: Duration,
+}
+
+/// Multiplexer errors
+#[derive(Debug, Display)]
+pub enum MultiplexError<A, B> {
+    /// Amqp server error
+    #[display(fmt = "Amqp server error: {:?}", _0)]
+    Amqp(A),
+    /// Fallback service error
+    #[display(fmt = "Fallback service error: {:?}", _0)]
+    Fallback(B),
+    /// Peer did not send enough data for protocol detection
+    #[display(fmt = "Protocol detection timeout")]
+    Timeout,
+    /// Io error
+    #[display(fmt = "Io error: {}", _0)]
+    Io(io::Error),
+}
+
+impl<A, B> Multiplexer<A, B>
+where
+    A: ServiceFactory<Config = (), Request = TcpStream, Response = ()>,
+    B: ServiceFactory<Config = (), Request = TcpStream, Response = ()>,
+{
+    /// Create multiplexer, default detection timeout is 5 seconds
+    pub fn new<F1, F2>(amqp: F1, fallback: F2) -> Self
+    where
+        F1: IntoServiceFactory<A>,
+        F2: IntoServiceFactory<B>,
+    {
+        Multiplexer {
+            amqp: amqp.into_factory(),
+            fallback: fallback.into_factory(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Set max time to wait for first bytes of the stream
+    pub fn detect_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl<A, B> ServiceFactory for Multiplexer<A, B>
+where
+    A: ServiceFactory<Config = (), Request = TcpStream, Response = ()>,
+    A::InitError: fmt::Debug,
+    A::Service: 'static,
+    A::Future: 'static,
+    B: ServiceFactory<Config = (), Request = TcpStream, Response = ()>,
+    B::InitError: fmt::Debug,
+    B::Service: 'static,
+    B::Future: 'static,
+{
+    type Config = ();
+    type Request = TcpStream;
+    type Response = ();
+    type Error = MultiplexError<A::Error, B::Error>;
+    type Service = MultiplexerService<A::Service, B::Service>;
+    type InitError = ();
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Service, Self::InitError>>>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        let amqp = self.amqp.new_service(());
+        let fallback = self.fallback.new_service(());
+        let timeout = self.timeout;
+
+        Box::pin(async move {
+            let amqp = amqp
+                .await
+                .map_err(|e| error!("Amqp server init error: {:?}", e))?;
+            let fallback = fallback
+                .await
+                .map_err(|e| error!("Fallback service init error: {:?}", e))?;
+            Ok(MultiplexerService {
+                amqp: Rc::new(amqp),
+                fallback: Rc::new(fallback),
+                timeout,
+            })
+        })
+    }
+}
+
+/// Multiplexer service
+pub struct MultiplexerService<A, B> {
+    amqp: Rc<A>,
+    fallback: Rc<B>,
+    timeout: Duration,
+}
+
+impl<A, B> Service for MultiplexerService<A, B>
+where
+    A: Service<Request = TcpStream, Response = ()> + 'static,
+    B: Service<Request = TcpStream, Response = ()> + 'static,
+{
+    type Request = TcpStream;
+    type Response = ();
+    type Error = MultiplexError<A::Error, B::Error>;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Self::Error>>>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let amqp = self.amqp.poll_ready(cx).map_err(MultiplexError::Amqp)?;
+        let fallback = self
+            .fallback
+            .poll_ready(cx)
+            .map_err(MultiplexError::Fallback)?;
+        if amqp.is_ready() && fallback.is_ready() {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let amqp = self.amqp.poll_shutdown(cx, is_error);
+        let fallback = self.fallback.poll_shutdown(cx, is_error);
+        if amqp.is_ready() && fallback.is_ready() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&self, io: TcpStream) -> Self::Future {
+        let amqp = self.amqp.clone();
+        let fallback = self.fallback.clone();
+        let timeout = self.timeout;
+
+        Box::pin(async move {
+            let is_amqp = ntex::rt::time::timeout(timeout, detect(&io))
+                .await
+                .map_err(|_| MultiplexError::Timeout)?
+                .map_err(MultiplexError::Io)?;
+
+            if let Some(true) = is_amqp {
+                trace!("Amqp protocol header is detected");
+                amqp.call(io).await.map_err(MultiplexError::Amqp)
+            } else if is_amqp.is_some() {
+                trace!("Non amqp stream, use fallback service");
+                fallback.call(io).await.map_err(MultiplexError::Fallback)
+            } else {
+                trace!("Peer is disconnected during protocol detection");
+                Ok(())
+            }
+        })
+    }
+}
+
+/// Peek stream until it is possible to decide if it starts with amqp header
+///
+/// Returns `None` if peer is disconnected.
+async fn detect(io: &TcpStream) -> io::Result<Option<bool>> {
+    let mut buf = [0; 4];
+    loop {
+        let size = io.peek(&mut buf).await?;
+        if size == 0 {
+            return Ok(None);
+        }
+        if buf[..size] != AMQP_PREFIX[..size] {
+            return Ok(Some(false));
+        }
+        if size == AMQP_PREFIX.len() {
+            return Ok(Some(true));
+        }
+        // partial prefix, peek does not wait for new data
+        delay_for(Duration::from_millis(5)).await;
+    }
+}
@@ -0,0 +1,42 @@
+use std::{future::Future, net::SocketAddr, pin::Pin, rc::Rc};
+
+use ntex::util::Bytes;
+
+use crate::codec::{protocol::ProtocolId, ProtocolIdError};
+
+/// Details of unsupported protocol header
+#[derive(Debug, Clone)]
+pub struct UnsupportedProtocol {
+    /// Remote peer address, available for tcp connections
+    pub peer_addr: Option<SocketAddr>,
+    /// Protocol header bytes sent by peer
+    pub header: Bytes,
+    /// Protocol header error
+    pub error: ProtocolIdError,
+}
+
+/// Server action for unsupported protocol header
+pub enum ProtocolAction<Io> {
+    /// Send supported protocol header and close connection (#2.2)
+    Respond(ProtocolId),
+    /// Close connection without response
+    Close,
+    /// Pass connection to other handler
+    ///
+    /// Handler receives io object and all bytes read from the peer,
+    /// including protocol header.
+    Divert(Box<dyn FnOnce(Io, Bytes) -> Pin<Box<dyn Future<Output = ()>>>>),
+}
+
+impl<Io> ProtocolAction<Io> {
+    /// Create `Divert` action
+    pub fn divert<F, R>(f: F) -> Self
+    where
+        F: FnOnce(Io, Bytes) -> R + 'static,
+        R: Future<Output = ()> + 'static,
+    {
+        ProtocolAction::Divert(Box::new(move |io, buf| Box::pin(f(io, buf))))
+    }
+}
+
+pub(crate) type UnsupportedProtocolHook<Io> = Rc<dyn Fn(UnsupportedProtocol) -> ProtocolAction<Io>>;
@@ -0,0 +1,236 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+
+use ntex::codec::Decoder;
+use ntex::util::{Buf, BytesMut};
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+const V1_MAX_LEN: usize = 107;
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+const V2_HEADER_LEN: usize = 16;
+
+/// Connection details from PROXY protocol header
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProxyHeader {
+    /// Original client address
+    pub source: Option<SocketAddr>,
+    /// Original destination address
+    pub destination: Option<SocketAddr>,
+}
+
+/// PROXY protocol header errors
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyProtocolError {
+    /// Malformed v1 header
+    #[display(fmt = "Invalid PROXY protocol v1 header")]
+    InvalidV1Header,
+    /// Malformed v2 header
+    #[display(fmt = "Invalid PROXY protocol v2 header")]
+    InvalidV2Header,
+    /// Unsupported protocol version
+    #[display(fmt = "Unsupported PROXY protocol version")]
+    UnsupportedVersion,
+    /// Stream does not start with PROXY protocol header
+    #[display(fmt = "PROXY protocol header is missing")]
+    MissingHeader,
+}
+
+impl std::error::Error for ProxyProtocolError {}
+
+/// Decoder for PROXY protocol v1/v2 header
+///
+/// Fails with `MissingHeader` error if stream does not start
+/// with PROXY protocol signature.
+pub(crate) struct ProxyProtocolCodec;
+
+impl Decoder for ProxyProtocolCodec {
+    type Item = ProxyHeader;
+    type Error = ProxyProtocolError;
+
+    fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match src.first() {
+            None => Ok(None),
+            Some(b'P') => decode_v1(src),
+            Some(b'\r') => decode_v2(src),
+            Some(_) => Err(ProxyProtocolError::MissingHeader),
+        }
+    }
+}
+
+fn decode_v1(src: &mut BytesMut) -> Result<Option<ProxyHeader>, ProxyProtocolError> {
+    let len = std::cmp::min(src.len(), V1_PREFIX.len());
+    if src[..len] != V1_PREFIX[..len] {
+        return Err(ProxyProtocolError::InvalidV1Header);
+    }
+    // header line including CRLF must fit into max length
+    let len = std::cmp::min(src.len(), V1_MAX_LEN);
+    let pos = match src[..len].windows(2).position(|w| w == b"\r\n") {
+        Some(pos) => pos,
+        None if src.len() >= V1_MAX_LEN => return Err(ProxyProtocolError::InvalidV1Header),
+        None => return Ok(None),
+    };
+    let line = src.split_to(pos + 2);
+    let line = std::str::from_utf8(&line[V1_PREFIX.len()..pos])
+        .map_err(|_| ProxyProtocolError::InvalidV1Header)?;
+
+    let mut parts = line.split(' ');
+    match parts.next() {
+        Some("TCP4") | Some("TCP6") => (),
+        Some("UNKNOWN") => return Ok(Some(ProxyHeader::default())),
+        _ => return Err(ProxyProtocolError::InvalidV1Header),
+    }
+    let parts: Vec<_> = parts.collect();
+    if parts.len() != 4 {
+        return Err(ProxyProtocolError::InvalidV1Header);
+    }
+    let addr = |ip: &str, port: &str| -> Result<SocketAddr, ProxyProtocolError> {
+        let ip = IpAddr::from_str(ip).map_err(|_| ProxyProtocolError::InvalidV1Header)?;
+        let port = u16::from_str(port).map_err(|_| ProxyProtocolError::InvalidV1Header)?;
+        Ok(SocketAddr::new(ip, port))
+    };
+    Ok(Some(ProxyHeader {
+        source: Some(addr(parts[0], parts[2])?),
+        destination: Some(addr(parts[1], parts[3])?),
+    }))
+}
+
+fn decode_v2(src: &mut BytesMut) -> Result<Option<ProxyHeader>, ProxyProtocolError> {
+    let len = std::cmp::min(src.len(), V2_SIGNATURE.len());
+    if src[..len] != V2_SIGNATURE[..len] {
+        return Err(ProxyProtocolError::InvalidV2Header);
+    }
+    if src.len() < V2_HEADER_LEN {
+        return Ok(None);
+    }
+    let ver_cmd = src[12];
+    let family = src[13];
+    let addr_len = u16::from_be_bytes([src[14], src[15]]) as usize;
+    if ver_cmd >> 4 != 2 {
+        return Err(ProxyProtocolError::UnsupportedVersion);
+    }
+    if src.len() < V2_HEADER_LEN + addr_len {
+        return Ok(None);
+    }
+    src.advance(V2_HEADER_LEN);
+    let addr = src.split_to(addr_len);
+
+    // LOCAL command, connection is established by proxy itself
+    if ver_cmd & 0x0f == 0 {
+        return Ok(Some(ProxyHeader::default()));
+    }
+
+    match family >> 4 {
+        // AF_INET
+        1 => {
+            if addr.len() < 12 {
+                return Err(ProxyProtocolError::InvalidV2Header);
+            }
+            let ip = |b: &[u8]| IpAddr::V4(Ipv4Addr::new(b[0], b[1], b[2], b[3]));
+            Ok(Some(ProxyHeader {
+                source: Some(SocketAddr::new(ip(&addr[0..4]), port(&addr[8..10]))),
+                destination: Some(SocketAddr::new(ip(&addr[4..8]), port(&addr[10..12]))),
+            }))
+        }
+        // AF_INET6
+        2 => {
+            if addr.len() < 36 {
+                return Err(ProxyProtocolError::InvalidV2Header);
+            }
+            let ip = |b: &[u8]| {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(b);
+                IpAddr::V6(Ipv6Addr::from(octets))
+            };
+            Ok(Some(ProxyHeader {
+                source: Some(SocketAddr::new(ip(&addr[0..16]), port(&addr[32..34]))),
+                destination: Some(SocketAddr::new(ip(&addr[16..32]), port(&addr[34..36]))),
+            }))
+        }
+        // AF_UNSPEC, AF_UNIX
+        _ => Ok(Some(ProxyHeader::default())),
+    }
+}
+
+fn port(b: &[u8]) -> u16 {
+    u16::from_be_bytes([b[0], b[1]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(data: &[u8]) -> Result<Option<ProxyHeader>, ProxyProtocolError> {
+        ProxyProtocolCodec.decode(&mut BytesMut::from(data))
+    }
+
+    #[test]
+    fn test_v1() {
+        let mut buf =
+            BytesMut::from(&b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 5672\r\nAMQP"[..]);
+        let hdr = ProxyProtocolCodec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(hdr.source, Some("192.168.0.1:56324".parse().unwrap()));
+        assert_eq!(hdr.destination, Some("192.168.0.11:5672".parse().unwrap()));
+        assert_eq!(&buf[..], b"AMQP");
+
+        let hdr = decode(b"PROXY UNKNOWN\r\n").unwrap().unwrap();
+        assert_eq!(hdr, ProxyHeader::default());
+        assert_eq!(
+            decode(b"PROXY TCP4 192.168.0.1\r\n"),
+            Err(ProxyProtocolError::InvalidV1Header)
+        );
+    }
+
+    #[test]
+    fn test_v2() {
+        let mut data = V2_SIGNATURE.to_vec();
+        data.extend_from_slice(&[0x21, 0x11, 0, 12]);
+        data.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2, 0x1f, 0x90, 0x16, 0x28]);
+        data.extend_from_slice(b"AMQP");
+        let mut buf = BytesMut::from(&data[..]);
+        let hdr = ProxyProtocolCodec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(hdr.source, Some("10.0.0.1:8080".parse().unwrap()));
+        assert_eq!(hdr.destination, Some("10.0.0.2:5672".parse().unwrap()));
+        assert_eq!(&buf[..], b"AMQP");
+
+        // LOCAL command
+        let mut data = V2_SIGNATURE.to_vec();
+        data.extend_from_slice(&[0x20, 0x00, 0, 0]);
+        assert_eq!(decode(&data).unwrap(), Some(ProxyHeader::default()));
+
+        let mut data = V2_SIGNATURE.to_vec();
+        data.extend_from_slice(&[0x11, 0x11, 0, 0]);
+        assert_eq!(decode(&data), Err(ProxyProtocolError::UnsupportedVersion));
+    }
+
+    #[test]
+    fn test_truncated() {
+        assert_eq!(decode(b""), Ok(None));
+        assert_eq!(decode(b"PRO"), Ok(None));
+        assert_eq!(decode(b"PROXY TCP4 192.168.0.1"), Ok(None));
+        assert_eq!(decode(&V2_SIGNATURE[..5]), Ok(None));
+
+        let mut data = V2_SIGNATURE.to_vec();
+        data.extend_from_slice(&[0x21, 0x11, 0, 12, 10, 0, 0, 1]);
+        assert_eq!(decode(&data), Ok(None));
+    }
+
+    #[test]
+    fn test_oversized() {
+        let mut data = b"PROXY TCP4 ".to_vec();
+        data.resize(V1_MAX_LEN, b'1');
+        assert_eq!(decode(&data), Err(ProxyProtocolError::InvalidV1Header));
+
+        // line terminator beyond max length
+        data.extend_from_slice(b"\r\n");
+        assert_eq!(decode(&data), Err(ProxyProtocolError::InvalidV1Header));
+    }
+
+    #[test]
+    fn test_missing_header() {
+        assert_eq!(
+            decode(b"AMQP\x00\x01\x00\x00"),
+            Err(ProxyProtocolError::MissingHeader)
+        );
+        assert_eq!(decode(b"PRXY"), Err(ProxyProtocolError::InvalidV1Header));
+    }
+}
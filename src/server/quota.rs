@@ -0,0 +1,209 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use ntex::util::{ByteString, HashMap};
+
+use crate::codec::protocol::{AmqpError, Error};
+use crate::hooks::{DeliveryQuota, QuotaCheck};
+
+/// Inbound message quota
+///
+/// Transfers that exceed quota are rejected with
+/// `amqp:resource-limit-exceeded` condition.
+#[derive(Debug, Clone, Default)]
+pub struct Quota {
+    max_message_size: Option<usize>,
+    rate: Option<(u32, Duration)>,
+    detach_after: Option<u32>,
+}
+
+impl Quota {
+    /// Create quota without limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set max size of message body in bytes
+    pub fn max_message_size(mut self, size: usize) -> Self {
+        self.max_message_size = Some(size);
+        self
+    }
+
+    /// Set max number of messages per period
+    ///
+    /// Rate is shared between all connections of the same identity.
+    pub fn rate(mut self, messages: u32, period: Duration) -> Self {
+        self.rate = Some((messages, period));
+        self
+    }
+
+    /// Detach link after number of violations
+    ///
+    /// Link gets detached with `amqp:resource-limit-exceeded` error.
+    /// By default offending transfers are rejected but link is kept open.
+    pub fn detach_after(mut self, violations: u32) -> Self {
+        self.detach_after = Some(violations);
+        self
+    }
+}
+
+/// Inbound message quotas keyed by authenticated identity
+///
+/// Identity is sasl `PLAIN` authentication identity or identity
+/// of pre-authenticated peer. Default quota applies to identities
+/// without explicit quota and to anonymous connections. Usage is shared
+/// between clones, clones could be used by servers of different workers.
+#[derive(Debug, Clone, Default)]
+pub struct Quotas {
+    default: Option<Quota>,
+    identities: HashMap<ByteString, Quota>,
+    usage: Arc<Mutex<HashMap<ByteString, Arc<Mutex<Usage>>>>>,
+}
+
+impl Quotas {
+    /// Create empty quotas
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set default quota
+    pub fn default_quota(mut self, quota: Quota) -> Self {
+        self.default = Some(quota);
+        self
+    }
+
+    /// Set quota for identity
+    pub fn identity<T: Into<ByteString>>(mut self, id: T, quota: Quota) -> Self {
+        self.identities.insert(id.into(), quota);
+        self
+    }
+
+    /// Quota state for new connection
+    pub(crate) fn get(&self, identity: Option<&ByteString>) -> Option<QuotaState> {
+        let quota = identity
+            .and_then(|id| self.identities.get(id))
+            .or(self.default.as_ref())?
+            .clone();
+        let period = quota.rate.map(|(_, period)| period).unwrap_or_default();
+
+        let usage = if let Some(id) = identity {
+            let mut usage = lock(&self.usage);
+            if let Some(item) = usage.get(id) {
+                item.clone()
+            } else {
+                // drop usage of identities without connections and
+                // with expired rate window
+                usage.retain(|_, item| Arc::strong_count(item) > 1 || !lock(item).is_idle());
+
+                let item = Arc::new(Mutex::new(Usage::new(period)));
+                usage.insert(id.clone(), item.clone());
+                item
+            }
+        } else {
+            Arc::new(Mutex::new(Usage::new(period)))
+        };
+        Some(QuotaState { quota, usage })
+    }
+}
+
+fn lock<T>(item: &Mutex<T>) -> MutexGuard<'_, T> {
+    item.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[derive(Debug)]
+struct Usage {
+    period: Duration,
+    window: Instant,
+    messages: u32,
+    violations: u32,
+}
+
+impl Usage {
+    fn new(period: Duration) -> Self {
+        Usage {
+            period,
+            window: Instant::now(),
+            messages: 0,
+            violations: 0,
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.window.elapsed() >= self.period
+    }
+}
+
+/// Quota with usage of connection identity
+#[derive(Debug, Clone)]
+pub(crate) struct QuotaState {
+    quota: Quota,
+    usage: Arc<Mutex<Usage>>,
+}
+
+impl DeliveryQuota for QuotaState {
+    fn check(&self, size: usize) -> QuotaCheck {
+        let mut usage = lock(&self.usage);
+
+        let reason = if matches!(self.quota.max_message_size, Some(max) if size > max) {
+            "Message size quota is exceeded"
+        } else if let Some((messages, period)) = self.quota.rate {
+            let now = Instant::now();
+            if now.duration_since(usage.window) >= period {
+                usage.window = now;
+                usage.messages = 0;
+            }
+            if usage.messages >= messages {
+                "Message rate quota is exceeded"
+            } else {
+                usage.messages += 1;
+                return QuotaCheck::Allow;
+            }
+        } else {
+            return QuotaCheck::Allow;
+        };
+
+        let err = Error {
+            condition: AmqpError::ResourceLimitExceeded.into(),
+            description: Some(ByteString::from_static(reason)),
+            info: None,
+        };
+        usage.violations += 1;
+        if matches!(self.quota.detach_after, Some(max) if usage.violations >= max) {
+            usage.violations = 0;
+            QuotaCheck::Detach(err)
+        } else {
+            QuotaCheck::Reject(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_usage() {
+        let quotas = Quotas::new().default_quota(Quota::new().rate(2, Duration::from_secs(60)));
+        let id = ByteString::from_static("user");
+
+        let q1 = quotas.get(Some(&id)).unwrap();
+        let q2 = quotas.clone().get(Some(&id)).unwrap();
+        assert!(matches!(q1.check(10), QuotaCheck::Allow));
+        assert!(matches!(q2.check(10), QuotaCheck::Allow));
+        assert!(matches!(q1.check(10), QuotaCheck::Reject(_)));
+
+        // usage of identity without connections is kept until window expires
+        drop((q1, q2));
+        let _ = quotas.get(Some(&ByteString::from_static("other")));
+        assert_eq!(lock(&quotas.usage).len(), 2);
+    }
+
+    #[test]
+    fn test_drop_idle_usage() {
+        let quotas = Quotas::new().default_quota(Quota::new().max_message_size(10));
+        for idx in 0..10 {
+            let _ = quotas.get(Some(&ByteString::from(format!("user{}", idx))));
+        }
+        assert_eq!(lock(&quotas.usage).len(), 1);
+    }
+}
@@ -0,0 +1,163 @@
+use std::{cell::RefCell, net::SocketAddr, rc::Rc, time::SystemTime};
+
+use ntex::util::{ByteString, HashMap};
+
+use crate::codec::CodecMetrics;
+use crate::connection::ChannelState;
+use crate::Connection;
+
+/// Registry of live server connections
+///
+/// Connections are registered once amqp handshake is complete and
+/// removed on disconnect. Registry is local to server worker, each
+/// worker should use its own registry.
+#[derive(Clone, Default)]
+pub struct ConnectionRegistry(Rc<RefCell<RegistryInner>>);
+
+#[derive(Default)]
+struct RegistryInner {
+    next_id: u64,
+    connections: HashMap<u64, ConnectionInfo>,
+}
+
+/// Live connection info
+#[derive(Clone)]
+pub struct ConnectionInfo {
+    id: u64,
+    peer_addr: Option<SocketAddr>,
+    opened: SystemTime,
+    connection: Connection,
+}
+
+impl ConnectionRegistry {
+    /// Create empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of live connections
+    pub fn len(&self) -> usize {
+        self.0.borrow().connections.len()
+    }
+
+    /// Check if registry is empty
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().connections.is_empty()
+    }
+
+    /// Live connections, ordered by connection id
+    pub fn connections(&self) -> Vec<ConnectionInfo> {
+        let mut connections: Vec<_> = self.0.borrow().connections.values().cloned().collect();
+        connections.sort_by_key(|info| info.id);
+        connections
+    }
+
+    /// Get connection by id
+    pub fn get(&self, id: u64) -> Option<ConnectionInfo> {
+        self.0.borrow().connections.get(&id).cloned()
+    }
+
+    /// Gracefully close connection
+    ///
+    /// Returns false if connection is not registered.
+    pub fn close(&self, id: u64) -> bool {
+        if let Some(info) = self.get(id) {
+            log::trace!("Closing connection {} from registry", id);
+            ntex::rt::spawn(async move {
+                let _ = info.connection.close().await;
+            });
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(crate) fn register(&self, conn: &Connection, peer_addr: Option<SocketAddr>) -> u64 {
+        let mut inner = self.0.borrow_mut();
+        inner.next_id += 1;
+        let id = inner.next_id;
+        inner.connections.insert(
+            id,
+            ConnectionInfo {
+                id,
+                peer_addr,
+                opened: SystemTime::now(),
+                connection: conn.clone(),
+            },
+        );
+        id
+    }
+
+    pub(crate) fn remove(&self, id: u64) {
+        self.0.borrow_mut().connections.remove(&id);
+    }
+}
+
+impl ConnectionInfo {
+    /// Connection id, unique within registry
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Remote container id
+    pub fn container_id(&self) -> ByteString {
+        self.connection.0.get_ref().remote_container_id.clone()
+    }
+
+    /// Authenticated identity of the peer
+    pub fn identity(&self) -> Option<ByteString> {
+        self.connection.0.get_ref().identity.clone()
+    }
+
+    /// Peer address
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer_addr
+    }
+
+    /// Time when connection is opened
+    pub fn opened(&self) -> SystemTime {
+        self.opened
+    }
+
+    /// Number of established sessions
+    pub fn sessions(&self) -> usize {
+        self.connection
+            .0
+            .get_ref()
+            .sessions
+            .iter()
+            .filter(|(_, ch)| matches!(ch, ChannelState::Established(_)))
+            .count()
+    }
+
+    /// Codec error metrics of the connection
+    pub fn codec_metrics(&self) -> CodecMetrics {
+        self.connection.codec_metrics()
+    }
+
+    /// Connection
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+impl std::fmt::Debug for ConnectionRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionRegistry")
+            .field("connections", &self.len())
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for ConnectionInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionInfo")
+            .field("id", &self.id)
+            .field("container_id", &self.container_id())
+            .field("identity", &self.identity())
+            .field("peer_addr", &self.peer_addr)
+            .field("opened", &self.opened)
+            .field("sessions", &self.sessions())
+            .finish()
+    }
+}
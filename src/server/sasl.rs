@@ -1,41 +1,153 @@
-use std::{fmt, rc::Rc};
+use std::{convert::TryFrom, fmt, future::Future, net::SocketAddr, pin::Pin, rc::Rc};
 
 use ntex::codec::{AsyncRead, AsyncWrite};
 use ntex::framed::State;
-use ntex::util::{ByteString, Bytes};
+use ntex::util::{ByteString, Bytes, Extensions};
 
 use crate::codec::protocol::{
     self, ProtocolId, SaslChallenge, SaslCode, SaslFrameBody, SaslMechanisms, SaslOutcome, Symbols,
 };
-use crate::codec::{AmqpCodec, AmqpFrame, ProtocolIdCodec, ProtocolIdError, SaslFrame};
+use crate::codec::{
+    types::Symbol, AmqpCodec, AmqpFrame, ProtocolIdCodec, ProtocolIdError, SaslFrame,
+};
 
-use super::{handshake::HandshakeAmqpOpened, HandshakeError};
+use super::{cert::PeerCertificate, handshake::HandshakeAmqpOpened, HandshakeError};
+use crate::scram::{ScramCredentials, ScramMechanism, ScramServer};
 use crate::{connection::Connection, Configuration};
 
 pub struct Sasl<Io> {
     io: Io,
     state: State,
-    mechanisms: Symbols,
+    ctx: SaslContext,
     local_config: Rc<Configuration>,
 }
 
+/// Sasl authentication failure
+#[derive(Debug, Clone)]
+pub struct SaslFailure {
+    /// Remote peer address, available for tcp connections
+    pub peer_addr: Option<SocketAddr>,
+    /// Sasl mechanism
+    pub mechanism: Symbol,
+    /// Authentication identity, available for `PLAIN` and `SCRAM` mechanisms
+    pub authn_id: Option<ByteString>,
+    /// Sasl outcome code
+    pub code: SaslCode,
+    /// Number of failed attempts for the connection
+    pub attempts: u8,
+}
+
+pub(crate) type SaslFailureHook = Rc<dyn Fn(SaslFailure) -> Pin<Box<dyn Future<Output = ()>>>>;
+
+/// Sasl negotiation limits
+pub(crate) struct SaslLimits {
+    pub(super) max_attempts: u8,
+    pub(super) on_failure: Option<SaslFailureHook>,
+}
+
+struct SaslContext {
+    limits: Rc<SaslLimits>,
+    peer_addr: Option<SocketAddr>,
+    peer_cert: Option<Rc<PeerCertificate>>,
+    mechanisms: Symbols,
+    mechanism: Symbol,
+    authn_id: Option<ByteString>,
+    failures: u8,
+}
+
+impl SaslContext {
+    fn init(&mut self, frame: &protocol::SaslInit) {
+        self.mechanism = frame.mechanism.clone();
+        self.authn_id = None;
+
+        // PLAIN initial response is "authzid\0authcid\0passwd"
+        if frame.mechanism == "PLAIN" {
+            if let Some(ref resp) = frame.initial_response {
+                let mut parts = resp.split(|b| *b == 0);
+                if let (Some(_), Some(authn_id)) = (parts.next(), parts.next()) {
+                    self.authn_id = ByteString::try_from(authn_id).ok();
+                }
+            }
+        }
+    }
+
+    /// Register failed attempt, returns true if client could retry
+    async fn failed(&mut self, code: SaslCode) -> bool {
+        self.failures = self.failures.saturating_add(1);
+        if let Some(ref f) = self.limits.on_failure {
+            f(SaslFailure {
+                code,
+                peer_addr: self.peer_addr,
+                mechanism: self.mechanism.clone(),
+                authn_id: self.authn_id.clone(),
+                attempts: self.failures,
+            })
+            .await;
+        }
+        self.failures < self.limits.max_attempts
+    }
+}
+
 impl<Io> fmt::Debug for Sasl<Io> {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt.debug_struct("SaslAuth")
-            .field("mechanisms", &self.mechanisms)
+            .field("mechanisms", &self.ctx.mechanisms)
             .finish()
     }
 }
 
 impl<Io> Sasl<Io> {
-    pub(crate) fn new(io: Io, state: State, local_config: Rc<Configuration>) -> Self {
+    pub(crate) fn new(
+        io: Io,
+        state: State,
+        local_config: Rc<Configuration>,
+        limits: Rc<SaslLimits>,
+        peer_addr: Option<SocketAddr>,
+        peer_cert: Option<Rc<PeerCertificate>>,
+    ) -> Self {
         Sasl {
             io,
             state,
             local_config,
-            mechanisms: Symbols::default(),
+            ctx: SaslContext {
+                limits,
+                peer_addr,
+                peer_cert,
+                mechanisms: Symbols::default(),
+                mechanism: Symbol::from_static(""),
+                authn_id: None,
+                failures: 0,
+            },
         }
     }
+
+    pub(crate) fn with_attempts(
+        io: Io,
+        state: State,
+        local_config: Rc<Configuration>,
+        max_attempts: u8,
+    ) -> Self {
+        let limits = Rc::new(SaslLimits {
+            max_attempts: std::cmp::max(max_attempts, 1),
+            on_failure: None,
+        });
+        Sasl::new(io, state, local_config, limits, None, None)
+    }
+
+    /// Get local configuration
+    pub fn local_config(&self) -> &Configuration {
+        self.local_config.as_ref()
+    }
+
+    /// Get remote peer address
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.ctx.peer_addr
+    }
+
+    /// Get peer certificate
+    pub fn peer_certificate(&self) -> Option<&PeerCertificate> {
+        self.ctx.peer_cert.as_deref()
+    }
 }
 
 impl<Io> Sasl<Io>
@@ -54,46 +166,64 @@ where
 
     /// Add supported sasl mechanism
     pub fn mechanism<U: Into<String>>(mut self, symbol: U) -> Self {
-        self.mechanisms.push(ByteString::from(symbol.into()).into());
+        self.ctx
+            .mechanisms
+            .push(ByteString::from(symbol.into()).into());
         self
     }
 
     /// Initialize sasl auth procedure
     pub async fn init(self) -> Result<SaslInit<Io>, HandshakeError> {
         let Sasl {
-            mut io,
+            io,
             state,
-            mechanisms,
+            ctx,
             local_config,
-            ..
         } = self;
 
-        let frame = SaslMechanisms {
-            sasl_server_mechanisms: mechanisms,
-        }
-        .into();
-
-        let codec = AmqpCodec::<SaslFrame>::new();
-        state
-            .send(&mut io, &codec, frame)
-            .await
-            .map_err(HandshakeError::from)?;
-        let frame = state
-            .next(&mut io, &codec)
-            .await
-            .map_err(HandshakeError::from)?
-            .ok_or(HandshakeError::Disconnected)?;
+        init(io, state, AmqpCodec::<SaslFrame>::new(), local_config, ctx).await
+    }
+}
 
-        match frame.body {
-            SaslFrameBody::SaslInit(frame) => Ok(SaslInit {
+/// Send sasl mechanisms and wait for sasl init frame
+async fn init<Io>(
+    mut io: Io,
+    state: State,
+    codec: AmqpCodec<SaslFrame>,
+    local_config: Rc<Configuration>,
+    mut ctx: SaslContext,
+) -> Result<SaslInit<Io>, HandshakeError>
+where
+    Io: AsyncRead + AsyncWrite + Unpin,
+{
+    let frame = SaslMechanisms {
+        sasl_server_mechanisms: ctx.mechanisms.clone(),
+    }
+    .into();
+
+    state
+        .send(&mut io, &codec, frame)
+        .await
+        .map_err(HandshakeError::from)?;
+    let frame = state
+        .next(&mut io, &codec)
+        .await
+        .map_err(HandshakeError::from)?
+        .ok_or(HandshakeError::Disconnected)?;
+
+    match frame.body {
+        SaslFrameBody::SaslInit(frame) => {
+            ctx.init(&frame);
+            Ok(SaslInit {
                 frame,
                 io,
                 state,
                 codec,
                 local_config,
-            }),
-            body => Err(HandshakeError::UnexpectedSaslBodyFrame(body)),
+                ctx,
+            })
         }
+        body => Err(HandshakeError::UnexpectedSaslBodyFrame(body)),
     }
 }
 
@@ -104,6 +234,7 @@ pub struct SaslInit<Io> {
     state: State,
     codec: AmqpCodec<SaslFrame>,
     local_config: Rc<Configuration>,
+    ctx: SaslContext,
 }
 
 impl<Io> fmt::Debug for SaslInit<Io> {
@@ -133,6 +264,11 @@ where
         self.frame.hostname.as_ref().map(|b| b.as_ref())
     }
 
+    /// Get peer certificate
+    pub fn peer_certificate(&self) -> Option<&PeerCertificate> {
+        self.ctx.peer_cert.as_deref()
+    }
+
     /// Returns reference to io object
     pub fn get_ref(&self) -> &Io {
         &self.io
@@ -157,6 +293,7 @@ where
         let state = self.state;
         let codec = self.codec;
         let local_config = self.local_config;
+        let ctx = self.ctx;
         let frame = SaslChallenge { challenge }.into();
 
         state
@@ -176,33 +313,110 @@ where
                 state,
                 codec,
                 local_config,
+                ctx,
             }),
             body => Err(HandshakeError::UnexpectedSaslBodyFrame(body)),
         }
     }
 
-    /// Sasl challenge outcome
-    pub async fn outcome(self, code: SaslCode) -> Result<SaslSuccess<Io>, HandshakeError> {
-        let mut io = self.io;
-        let state = self.state;
-        let codec = self.codec;
-        let local_config = self.local_config;
+    /// Fail sasl negotiation.
+    ///
+    /// Sends sasl outcome with failure code and optional additional data.
+    /// Returned error should be used for handshake service error, connection
+    /// gets closed after that.
+    pub async fn fail(mut self, code: SaslCode, additional_data: Option<Bytes>) -> HandshakeError {
+        let _ = self.ctx.failed(code).await;
+        fail(self.io, self.state, self.codec, code, additional_data).await
+    }
+
+    /// Fail current sasl attempt and restart sasl negotiation.
+    ///
+    /// Sends sasl outcome with failure code and waits for new sasl init
+    /// frame. If number of failed attempts reaches limit, connection
+    /// gets closed and `HandshakeError::Sasl` error is returned.
+    pub async fn retry(mut self, code: SaslCode) -> Result<SaslInit<Io>, HandshakeError> {
+        if !self.ctx.failed(code).await {
+            return Err(fail(self.io, self.state, self.codec, code, None).await);
+        }
 
+        let mut io = self.io;
         let frame = SaslOutcome {
             code,
             additional_data: None,
         }
         .into();
-        state
-            .send(&mut io, &codec, frame)
+        self.state
+            .send(&mut io, &self.codec, frame)
             .await
             .map_err(HandshakeError::from)?;
 
-        Ok(SaslSuccess {
-            io,
-            state,
-            local_config,
-        })
+        init(io, self.state, self.codec, self.local_config, self.ctx).await
+    }
+
+    /// Sasl challenge outcome
+    pub async fn outcome(self, code: SaslCode) -> Result<SaslSuccess<Io>, HandshakeError> {
+        outcome(
+            self.io,
+            self.state,
+            self.codec,
+            self.local_config,
+            self.ctx,
+            code,
+            None,
+        )
+        .await
+    }
+
+    /// Authenticate with `SCRAM-SHA-1` or `SCRAM-SHA-256` mechanism
+    ///
+    /// Runs challenge/response exchange, `credentials` returns stored
+    /// credentials of the user for the mechanism. Unknown user or invalid
+    /// client proof fails negotiation with `SaslCode::Auth`. Server signature
+    /// is sent with successful outcome, authentication identity is set
+    /// to user name.
+    pub async fn scram<F, R>(mut self, credentials: F) -> Result<SaslSuccess<Io>, HandshakeError>
+    where
+        F: FnOnce(&str, ScramMechanism) -> R,
+        R: Future<Output = Option<ScramCredentials>>,
+    {
+        let mechanism = match ScramMechanism::from_name(self.mechanism()) {
+            Some(mechanism) => mechanism,
+            None => {
+                return Err(HandshakeError::UnsupportedSaslMechanism(
+                    self.mechanism().to_string(),
+                ))
+            }
+        };
+        let mut scram = match self
+            .initial_response()
+            .and_then(|resp| ScramServer::new(mechanism, resp))
+        {
+            Some(scram) => scram,
+            None => return Err(self.fail(SaslCode::Auth, None).await),
+        };
+        self.ctx.authn_id = Some(scram.username().clone());
+
+        let creds = match credentials(scram.username(), mechanism).await {
+            Some(creds) => creds,
+            None => return Err(self.fail(SaslCode::Auth, None).await),
+        };
+
+        let response = self.challenge_with(scram.server_first(&creds)).await?;
+        match scram.verify(&creds, response.response()) {
+            Some(server_final) => {
+                outcome(
+                    response.io,
+                    response.state,
+                    response.codec,
+                    response.local_config,
+                    response.ctx,
+                    SaslCode::Ok,
+                    Some(server_final),
+                )
+                .await
+            }
+            None => Err(response.fail(SaslCode::Auth, None).await),
+        }
     }
 }
 
@@ -212,6 +426,7 @@ pub struct SaslResponse<Io> {
     state: State,
     codec: AmqpCodec<SaslFrame>,
     local_config: Rc<Configuration>,
+    ctx: SaslContext,
 }
 
 impl<Io> fmt::Debug for SaslResponse<Io> {
@@ -231,33 +446,57 @@ where
         &self.frame.response[..]
     }
 
-    /// Sasl challenge outcome
-    pub async fn outcome(self, code: SaslCode) -> Result<SaslSuccess<Io>, HandshakeError> {
-        let mut io = self.io;
-        let state = self.state;
-        let codec = self.codec;
-        let local_config = self.local_config;
+    /// Get peer certificate
+    pub fn peer_certificate(&self) -> Option<&PeerCertificate> {
+        self.ctx.peer_cert.as_deref()
+    }
+
+    /// Fail sasl negotiation.
+    ///
+    /// Sends sasl outcome with failure code and optional additional data.
+    /// Returned error should be used for handshake service error, connection
+    /// gets closed after that.
+    pub async fn fail(mut self, code: SaslCode, additional_data: Option<Bytes>) -> HandshakeError {
+        let _ = self.ctx.failed(code).await;
+        fail(self.io, self.state, self.codec, code, additional_data).await
+    }
+
+    /// Fail current sasl attempt and restart sasl negotiation.
+    ///
+    /// Sends sasl outcome with failure code and waits for new sasl init
+    /// frame. If number of failed attempts reaches limit, connection
+    /// gets closed and `HandshakeError::Sasl` error is returned.
+    pub async fn retry(mut self, code: SaslCode) -> Result<SaslInit<Io>, HandshakeError> {
+        if !self.ctx.failed(code).await {
+            return Err(fail(self.io, self.state, self.codec, code, None).await);
+        }
 
+        let mut io = self.io;
         let frame = SaslOutcome {
             code,
             additional_data: None,
         }
         .into();
-        state
-            .send(&mut io, &codec, frame)
+        self.state
+            .send(&mut io, &self.codec, frame)
             .await
             .map_err(HandshakeError::from)?;
-        state
-            .next(&mut io, &codec)
-            .await
-            .map_err(HandshakeError::from)?
-            .ok_or(HandshakeError::Disconnected)?;
 
-        Ok(SaslSuccess {
-            io,
-            state,
-            local_config,
-        })
+        init(io, self.state, self.codec, self.local_config, self.ctx).await
+    }
+
+    /// Sasl challenge outcome
+    pub async fn outcome(self, code: SaslCode) -> Result<SaslSuccess<Io>, HandshakeError> {
+        outcome(
+            self.io,
+            self.state,
+            self.codec,
+            self.local_config,
+            self.ctx,
+            code,
+            None,
+        )
+        .await
     }
 }
 
@@ -265,6 +504,9 @@ pub struct SaslSuccess<Io> {
     io: Io,
     state: State,
     local_config: Rc<Configuration>,
+    peer_cert: Option<Rc<PeerCertificate>>,
+    authn_id: Option<ByteString>,
+    ext: Extensions,
 }
 
 impl<Io> SaslSuccess<Io>
@@ -281,6 +523,14 @@ where
         &mut self.io
     }
 
+    /// Connection extensions
+    ///
+    /// Authentication data stored in extensions is available
+    /// to link and control services.
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.ext
+    }
+
     /// Wait for connection open frame
     pub async fn open(self) -> Result<HandshakeAmqpOpened<Io>, HandshakeError> {
         let mut io = self.io;
@@ -316,6 +566,7 @@ where
                         let local_config = self.local_config;
                         let remote_config = (&frame).into();
                         let sink = Connection::new(state.clone(), &local_config, &remote_config);
+                        sink.0.get_mut().identity = self.authn_id;
 
                         Ok(HandshakeAmqpOpened::new(
                             frame,
@@ -324,6 +575,8 @@ where
                             state,
                             local_config,
                             remote_config,
+                            self.peer_cert,
+                            self.ext,
                         ))
                     }
                     frame => Err(HandshakeError::Unexpected(Box::new(frame))),
@@ -337,3 +590,57 @@ where
         }
     }
 }
+
+/// Send sasl outcome
+async fn outcome<Io>(
+    mut io: Io,
+    state: State,
+    codec: AmqpCodec<SaslFrame>,
+    local_config: Rc<Configuration>,
+    ctx: SaslContext,
+    code: SaslCode,
+    additional_data: Option<Bytes>,
+) -> Result<SaslSuccess<Io>, HandshakeError>
+where
+    Io: AsyncRead + AsyncWrite + Unpin,
+{
+    let frame = SaslOutcome {
+        code,
+        additional_data,
+    }
+    .into();
+    state
+        .send(&mut io, &codec, frame)
+        .await
+        .map_err(HandshakeError::from)?;
+
+    Ok(SaslSuccess {
+        io,
+        state,
+        local_config,
+        peer_cert: ctx.peer_cert,
+        authn_id: ctx.authn_id,
+        ext: Extensions::new(),
+    })
+}
+
+async fn fail<Io>(
+    mut io: Io,
+    state: State,
+    codec: AmqpCodec<SaslFrame>,
+    code: SaslCode,
+    additional_data: Option<Bytes>,
+) -> HandshakeError
+where
+    Io: AsyncRead + AsyncWrite + Unpin,
+{
+    let frame = SaslOutcome {
+        code,
+        additional_data,
+    }
+    .into();
+    match state.send(&mut io, &codec, frame).await {
+        Ok(_) => HandshakeError::Sasl(code),
+        Err(err) => HandshakeError::from(err),
+    }
+}
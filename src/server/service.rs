@@ -1,41 +1,121 @@
-use std::{fmt, future::Future, marker, pin::Pin, rc::Rc, task::Context, task::Poll, time};
+use std::{fmt, future::Future, marker, net::SocketAddr, pin::Pin, rc::Rc, task::Context};
+use std::{task::Poll, time};
 
 use ntex::codec::{AsyncRead, AsyncWrite};
 use ntex::framed::{Dispatcher as FramedDispatcher, State as IoState, Timer};
 use ntex::service::{IntoServiceFactory, Service, ServiceFactory};
+use ntex::util::{ByteString, BytesMut};
 
 use crate::codec::{protocol::ProtocolId, AmqpCodec, AmqpFrame, ProtocolIdCodec, ProtocolIdError};
-use crate::dispatcher::Dispatcher;
-use crate::types::Link;
+use crate::dispatcher::{Dispatcher, OutgoingHandler};
+use crate::hooks::{ContainerRegistry, LinkRegistry};
+use crate::negotiate::{confirm_protocol_header, read_protocol_header};
+use crate::types::{Link, OutgoingLink};
+use crate::SocketOptions;
 use crate::{default::DefaultControlService, Configuration, Connection, ControlFrame, State};
 
+use super::cert::PeerCertificate;
+use super::config::ServerConfig;
+use super::containers::{ContainerIdHook, ContainerIdPolicy, ContainerIds};
 use super::handshake::{Handshake, HandshakeAck};
+use super::identity::{IdentityResolver, PeerIdentity};
+use super::links::{LinkNamePolicy, LinkNames};
+use super::protocol::{ProtocolAction, UnsupportedProtocol, UnsupportedProtocolHook};
+use super::proxy::ProxyProtocolCodec;
+use super::quota::Quotas;
+use super::registry::ConnectionRegistry;
+use super::sasl::{SaslFailure, SaslFailureHook, SaslLimits};
 use super::{Error, HandshakeError, ServerError};
 
+type PeerCertExtractor<Io> = Rc<dyn Fn(&Io) -> Option<PeerCertificate>>;
+
 /// Server dispatcher factory
+///
+/// Factory accepts any io object, e.g. tcp or unix domain socket streams.
+///
+/// Server could be cloned if handshake and control services are cloneable,
+/// so handlers could be shared between multiple listeners with
+/// distinct per-listener settings.
 pub struct Server<Io, St, H, Ctl> {
     handshake: H,
     control: Ctl,
     config: Rc<Configuration>,
+    shared_config: Option<ServerConfig>,
     max_size: usize,
     lw: u16,
     read_hw: u16,
     write_hw: u16,
     handshake_timeout: u64,
     disconnect_timeout: u16,
+    socket_options: Option<SocketOptions>,
+    proxy_protocol: bool,
+    link_name_policy: LinkNamePolicy,
+    container_id_policy: ContainerIdPolicy,
+    on_container_id_conflict: Option<ContainerIdHook>,
+    sasl_required: bool,
+    sasl_attempts: u8,
+    on_sasl_failure: Option<SaslFailureHook>,
+    peer_cert: Option<PeerCertExtractor<Io>>,
+    identity: Option<IdentityResolver<Io>>,
+    on_unsupported_protocol: Option<UnsupportedProtocolHook<Io>>,
+    quotas: Option<Quotas>,
+    registry: Option<ConnectionRegistry>,
+    outgoing: Option<OutgoingHandler<St>>,
     _t: marker::PhantomData<(Io, St)>,
 }
 
+impl<Io, St, H: Clone, Ctl: Clone> Clone for Server<Io, St, H, Ctl> {
+    fn clone(&self) -> Self {
+        Server {
+            handshake: self.handshake.clone(),
+            control: self.control.clone(),
+            config: self.config.clone(),
+            shared_config: self.shared_config.clone(),
+            max_size: self.max_size,
+            lw: self.lw,
+            read_hw: self.read_hw,
+            write_hw: self.write_hw,
+            handshake_timeout: self.handshake_timeout,
+            disconnect_timeout: self.disconnect_timeout,
+            socket_options: self.socket_options.clone(),
+            proxy_protocol: self.proxy_protocol,
+            link_name_policy: self.link_name_policy,
+            container_id_policy: self.container_id_policy,
+            on_container_id_conflict: self.on_container_id_conflict.clone(),
+            sasl_required: self.sasl_required,
+            sasl_attempts: self.sasl_attempts,
+            on_sasl_failure: self.on_sasl_failure.clone(),
+            peer_cert: self.peer_cert.clone(),
+            identity: self.identity.clone(),
+            on_unsupported_protocol: self.on_unsupported_protocol.clone(),
+            quotas: self.quotas.clone(),
+            registry: self.registry.clone(),
+            outgoing: self.outgoing.clone(),
+            _t: marker::PhantomData,
+        }
+    }
+}
+
 pub(super) struct ServerInner<St, Ctl, Pb> {
     control: Ctl,
     publish: Pb,
     config: Rc<Configuration>,
+    shared_config: Option<ServerConfig>,
     max_size: usize,
     handshake_timeout: u64,
     disconnect_timeout: u16,
     lw: u16,
     read_hw: u16,
     write_hw: u16,
+    socket_options: Option<SocketOptions>,
+    proxy_protocol: bool,
+    link_names: Option<Rc<dyn LinkRegistry>>,
+    container_ids: Option<Rc<dyn ContainerRegistry>>,
+    sasl_required: bool,
+    sasl_limits: Rc<SaslLimits>,
+    quotas: Option<Quotas>,
+    registry: Option<ConnectionRegistry>,
+    outgoing: Option<OutgoingHandler<St>>,
     time: Timer,
     _t: marker::PhantomData<St>,
 }
@@ -63,6 +143,21 @@ where
             control: DefaultControlService::default(),
             max_size: 0,
             config: Rc::new(Configuration::default()),
+            shared_config: None,
+            socket_options: None,
+            proxy_protocol: false,
+            link_name_policy: LinkNamePolicy::Allow,
+            container_id_policy: ContainerIdPolicy::Allow,
+            on_container_id_conflict: None,
+            sasl_required: false,
+            sasl_attempts: 1,
+            on_sasl_failure: None,
+            peer_cert: None,
+            identity: None,
+            on_unsupported_protocol: None,
+            quotas: None,
+            registry: None,
+            outgoing: None,
             _t: marker::PhantomData,
         }
     }
@@ -75,6 +170,16 @@ impl<Io, St, H, Ctl> Server<Io, St, H, Ctl> {
         self
     }
 
+    /// Provide shared connection configuration
+    ///
+    /// Configuration could be updated at runtime through the handle,
+    /// updated settings apply to new connections. Overrides configuration
+    /// set with `Server::config()`.
+    pub fn shared_config(mut self, config: ServerConfig) -> Self {
+        self.shared_config = Some(config);
+        self
+    }
+
     /// Set max inbound frame size.
     ///
     /// If max size is set to `0`, size is unlimited.
@@ -121,6 +226,162 @@ impl<Io, St, H, Ctl> Server<Io, St, H, Ctl> {
         self
     }
 
+    /// Set tcp socket options for accepted connections
+    ///
+    /// Options are applied only if io object is `TcpStream`
+    pub fn socket_options(mut self, options: SocketOptions) -> Self {
+        self.socket_options = Some(options);
+        self
+    }
+
+    /// Require PROXY protocol v1/v2 header.
+    ///
+    /// Connections without header are rejected, so enable it only for
+    /// listeners that are reachable through proxy only.
+    /// Client address from header is reported as peer address,
+    /// e.g. `Handshake::peer_addr()`. Disabled by default.
+    pub fn proxy_protocol(mut self, val: bool) -> Self {
+        self.proxy_protocol = val;
+        self
+    }
+
+    /// Require SASL authentication.
+    ///
+    /// Connections that do not negotiate SASL layer get rejected
+    /// during protocol negotiation. By default SASL is optional.
+    pub fn sasl_required(mut self, val: bool) -> Self {
+        self.sasl_required = val;
+        self
+    }
+
+    /// Set max number of sasl attempts per connection.
+    ///
+    /// Sasl negotiation could be restarted with `SaslInit::retry()` until
+    /// limit is reached. By default only one attempt is allowed.
+    pub fn max_sasl_attempts(mut self, attempts: u8) -> Self {
+        self.sasl_attempts = std::cmp::max(attempts, 1);
+        self
+    }
+
+    /// Set sasl failure hook.
+    ///
+    /// Hook is called for each failed sasl attempt, sasl outcome is sent
+    /// after hook's future completes.
+    pub fn on_sasl_failure<F, R>(mut self, f: F) -> Self
+    where
+        F: Fn(SaslFailure) -> R + 'static,
+        R: Future<Output = ()> + 'static,
+    {
+        self.on_sasl_failure = Some(Rc::new(move |failure| Box::pin(f(failure))));
+        self
+    }
+
+    /// Set peer certificate extractor.
+    ///
+    /// Extractor is called for each new connection, extracted certificate
+    /// details are available during handshake, e.g. `Sasl::peer_certificate()`.
+    pub fn peer_certificate<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Io) -> Option<PeerCertificate> + 'static,
+    {
+        self.peer_cert = Some(Rc::new(f));
+        self
+    }
+
+    /// Enable pre-authenticated mode.
+    ///
+    /// Authentication is done by fronting proxy, so SASL layer is not
+    /// negotiated and connections that request SASL get rejected. Resolver
+    /// derives peer identity for each new connection from io object and
+    /// remote peer address, identity is available
+    /// during handshake, e.g. `HandshakeAmqp::identity()`. Connection is
+    /// rejected if resolver returns `None`.
+    pub fn pre_authenticated<F, R>(mut self, f: F) -> Self
+    where
+        F: Fn(&Io, Option<SocketAddr>) -> R + 'static,
+        R: Future<Output = Option<PeerIdentity>> + 'static,
+    {
+        self.identity = Some(Rc::new(move |io, addr| Box::pin(f(io, addr))));
+        self
+    }
+
+    /// Set unsupported protocol header hook
+    ///
+    /// Hook is called if peer sends malformed, incompatible or unsupported
+    /// protocol header, e.g. `AmqpTls`. By default connection is closed
+    /// without response.
+    pub fn on_unsupported_protocol<F>(mut self, f: F) -> Self
+    where
+        F: Fn(UnsupportedProtocol) -> ProtocolAction<Io> + 'static,
+    {
+        self.on_unsupported_protocol = Some(Rc::new(f));
+        self
+    }
+
+    /// Set inbound message quotas
+    ///
+    /// Quotas are keyed by authenticated identity, transfers that exceed
+    /// quota are rejected with `amqp:resource-limit-exceeded` condition.
+    /// Usage is shared between clones of `quotas`, use clones for servers
+    /// of all workers to share usage between workers.
+    pub fn quotas(mut self, quotas: Quotas) -> Self {
+        self.quotas = Some(quotas);
+        self
+    }
+
+    /// Set registry of live connections
+    ///
+    /// Connections are registered after successful handshake and
+    /// removed on disconnect. Registry is local to server worker.
+    pub fn connections(mut self, registry: ConnectionRegistry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Set handler for links attached by remote receivers
+    ///
+    /// Handler is called once attach is confirmed by control service,
+    /// messages are pushed to remote receiver with `OutgoingLink::sender()`.
+    /// Link is detached when returned future completes.
+    pub fn outgoing<F, R>(mut self, f: F) -> Self
+    where
+        F: Fn(OutgoingLink<St>) -> R + 'static,
+        R: Future<Output = Result<(), Error>> + 'static,
+    {
+        self.outgoing = Some(Rc::new(move |link| Box::pin(f(link))));
+        self
+    }
+
+    /// Set policy for attaches that reuse name of existing link
+    ///
+    /// Links are identified by remote container id, link name and role
+    /// and are tracked per server worker. By default link names are not checked.
+    pub fn link_name_policy(mut self, policy: LinkNamePolicy) -> Self {
+        self.link_name_policy = policy;
+        self
+    }
+
+    /// Set policy for connections that reuse container id of existing connection
+    ///
+    /// Connections are tracked per server worker.
+    /// By default container ids are not checked.
+    pub fn container_id_policy(mut self, policy: ContainerIdPolicy) -> Self {
+        self.container_id_policy = policy;
+        self
+    }
+
+    /// Set container id conflict hook
+    ///
+    /// Hook is called with remote container id and applied policy
+    /// for each new connection that reuses container id.
+    pub fn on_container_id_conflict<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&ByteString, ContainerIdPolicy) + 'static,
+    {
+        self.on_container_id_conflict = Some(Rc::new(f));
+        self
+    }
+
     #[inline]
     #[doc(hidden)]
     #[deprecated(since = "0.4.3")]
@@ -169,6 +430,7 @@ where
     {
         Server {
             config: self.config,
+            shared_config: self.shared_config,
             handshake: self.handshake,
             handshake_timeout: self.handshake_timeout,
             disconnect_timeout: self.disconnect_timeout,
@@ -177,6 +439,20 @@ where
             lw: self.lw,
             read_hw: self.read_hw,
             write_hw: self.write_hw,
+            socket_options: self.socket_options,
+            proxy_protocol: self.proxy_protocol,
+            link_name_policy: self.link_name_policy,
+            container_id_policy: self.container_id_policy,
+            on_container_id_conflict: self.on_container_id_conflict,
+            sasl_required: self.sasl_required,
+            sasl_attempts: self.sasl_attempts,
+            on_sasl_failure: self.on_sasl_failure,
+            peer_cert: self.peer_cert,
+            identity: self.identity,
+            on_unsupported_protocol: self.on_unsupported_protocol,
+            quotas: self.quotas,
+            registry: self.registry,
+            outgoing: self.outgoing,
             _t: marker::PhantomData,
         }
     }
@@ -201,9 +477,13 @@ where
     {
         ServerImpl {
             handshake: self.handshake,
+            peer_cert: self.peer_cert,
+            identity: self.identity,
+            on_unsupported_protocol: self.on_unsupported_protocol,
             inner: Rc::new(ServerInner {
                 handshake_timeout: self.handshake_timeout,
                 config: self.config,
+                shared_config: self.shared_config,
                 publish: service.into_factory(),
                 control: self.control,
                 disconnect_timeout: self.disconnect_timeout,
@@ -211,6 +491,31 @@ where
                 lw: self.lw,
                 read_hw: self.read_hw,
                 write_hw: self.write_hw,
+                socket_options: self.socket_options,
+                proxy_protocol: self.proxy_protocol,
+                link_names: if self.link_name_policy == LinkNamePolicy::Allow {
+                    None
+                } else {
+                    Some(Rc::new(LinkNames::new(self.link_name_policy)))
+                },
+                container_ids: if self.container_id_policy == ContainerIdPolicy::Allow
+                    && self.on_container_id_conflict.is_none()
+                {
+                    None
+                } else {
+                    Some(Rc::new(ContainerIds::new(
+                        self.container_id_policy,
+                        self.on_container_id_conflict,
+                    )))
+                },
+                sasl_required: self.sasl_required,
+                sasl_limits: Rc::new(SaslLimits {
+                    max_attempts: self.sasl_attempts,
+                    on_failure: self.on_sasl_failure,
+                }),
+                quotas: self.quotas,
+                registry: self.registry,
+                outgoing: self.outgoing,
                 time: Timer::with(time::Duration::from_secs(1)),
                 _t: marker::PhantomData,
             }),
@@ -219,8 +524,22 @@ where
     }
 }
 
+impl<St, Ctl, Pb> ServerInner<St, Ctl, Pb> {
+    /// Configuration for new connection
+    fn config(&self) -> Rc<Configuration> {
+        if let Some(ref shared) = self.shared_config {
+            Rc::new(shared.get())
+        } else {
+            self.config.clone()
+        }
+    }
+}
+
 struct ServerImpl<Io, St, H, Ctl, Pb> {
     handshake: H,
+    peer_cert: Option<PeerCertExtractor<Io>>,
+    identity: Option<IdentityResolver<Io>>,
+    on_unsupported_protocol: Option<UnsupportedProtocolHook<Io>>,
     inner: Rc<ServerInner<St, Ctl, Pb>>,
     _t: marker::PhantomData<(Io,)>,
 }
@@ -250,11 +569,17 @@ where
 
     fn new_service(&self, _: ()) -> Self::Future {
         let inner = self.inner.clone();
+        let peer_cert = self.peer_cert.clone();
+        let identity = self.identity.clone();
+        let on_unsupported_protocol = self.on_unsupported_protocol.clone();
         let fut = self.handshake.new_service(());
 
         Box::pin(async move {
             fut.await.map(move |handshake| ServerImplService {
                 inner,
+                peer_cert,
+                identity,
+                on_unsupported_protocol,
                 handshake: Rc::new(handshake),
                 _t: marker::PhantomData,
             })
@@ -264,6 +589,9 @@ where
 
 struct ServerImplService<Io, St, H, Ctl, Pb> {
     handshake: Rc<H>,
+    peer_cert: Option<PeerCertExtractor<Io>>,
+    identity: Option<IdentityResolver<Io>>,
+    on_unsupported_protocol: Option<UnsupportedProtocolHook<Io>>,
     inner: Rc<ServerInner<St, Ctl, Pb>>,
     _t: marker::PhantomData<(Io,)>,
 }
@@ -302,18 +630,22 @@ where
 
     fn call(&self, req: Self::Request) -> Self::Future {
         let timeout = self.inner.handshake_timeout;
-        let keepalive = self.inner.config.idle_time_out / 1000;
+        let config = self.inner.config();
+        let keepalive = config.idle_time_out / 1000;
         let disconnect_timeout = self.inner.disconnect_timeout;
         let inner = self.inner.clone();
         let fut = handshake(
             req,
-            self.inner.max_size,
             self.handshake.clone(),
             self.inner.clone(),
+            config,
+            self.peer_cert.clone(),
+            self.identity.clone(),
+            self.on_unsupported_protocol.clone(),
         );
 
         Box::pin(async move {
-            let (io, state, codec, sink, st, idle_timeout) = if timeout == 0 {
+            let (io, state, codec, sink, st, idle_timeout, peer_addr) = if timeout == 0 {
                 fut.await?
             } else {
                 ntex::rt::time::timeout(time::Duration::from_millis(timeout), fut)
@@ -333,23 +665,48 @@ where
                 ServerError::ControlServiceError
             })?;
 
+            sink.0.get_mut().link_names = inner.link_names.clone();
+            if let Some(ref ids) = inner.container_ids {
+                if ids.register(&sink.0.get_ref().remote_container_id, &sink) {
+                    sink.0.get_mut().container_ids = Some(ids.clone());
+                }
+            }
+            if let Some(ref quotas) = inner.quotas {
+                if let Some(quota) = quotas.get(sink.0.get_ref().identity.as_ref()) {
+                    sink.0.get_mut().quota = Some(Box::new(quota));
+                }
+            }
+
+            let registered = inner
+                .registry
+                .as_ref()
+                .map(|registry| registry.register(&sink, peer_addr));
+
             let dispatcher = Dispatcher::new(st, sink, pb_srv, ctl_srv, idle_timeout)
+                .outgoing(inner.outgoing.clone())
                 .map(|_| Option::<AmqpFrame>::None);
 
-            FramedDispatcher::new(io, codec, state, dispatcher, inner.time.clone())
+            let res = FramedDispatcher::new(io, codec, state, dispatcher, inner.time.clone())
                 .keepalive_timeout(keepalive as u16)
                 .disconnect_timeout(disconnect_timeout)
-                .await
-                .map_err(|_| ServerError::Disconnected)
+                .await;
+
+            if let (Some(registry), Some(id)) = (inner.registry.as_ref(), registered) {
+                registry.remove(id);
+            }
+            res.map_err(|_| ServerError::Disconnected)
         })
     }
 }
 
 async fn handshake<Io, St, H, Ctl, Pb>(
     mut io: Io,
-    max_size: usize,
     handshake: Rc<H>,
     inner: Rc<ServerInner<St, Ctl, Pb>>,
+    config: Rc<Configuration>,
+    peer_cert: Option<PeerCertExtractor<Io>>,
+    identity: Option<IdentityResolver<Io>>,
+    on_unsupported: Option<UnsupportedProtocolHook<Io>>,
 ) -> Result<
     (
         Io,
@@ -358,6 +715,7 @@ async fn handshake<Io, St, H, Ctl, Pb>(
         Connection,
         State<St>,
         usize,
+        Option<SocketAddr>,
     ),
     ServerError<H::Error>,
 >
@@ -368,6 +726,10 @@ where
     Ctl: ServiceFactory<Config = State<St>, Request = ControlFrame, Response = ()> + 'static,
     Pb: ServiceFactory<Config = State<St>, Request = Link<St>, Response = ()> + 'static,
 {
+    if let Some(ref options) = inner.socket_options {
+        options.apply(&io);
+    }
+
     let state = IoState::with_params(
         inner.read_hw,
         inner.write_hw,
@@ -375,55 +737,120 @@ where
         inner.disconnect_timeout,
     );
 
-    let protocol = state
-        .next(&mut io, &ProtocolIdCodec)
-        .await
-        .map_err(HandshakeError::from)?
-        .ok_or_else(|| {
-            log::trace!("Server amqp is disconnected during handshake");
-            HandshakeError::Disconnected
-        })?;
-
-    let (io, sink, state, codec, st, idle_timeout) = match protocol {
-        // start amqp processing
-        ProtocolId::Amqp | ProtocolId::AmqpSasl => {
-            state
-                .send(&mut io, &ProtocolIdCodec, protocol)
-                .await
-                .map_err(HandshakeError::from)?;
-
-            let ack = handshake
-                .call(if protocol == ProtocolId::Amqp {
-                    Handshake::new_plain(io, state, inner.config.clone())
-                } else {
-                    Handshake::new_sasl(io, state, inner.config.clone())
+    let peer_addr = if inner.proxy_protocol {
+        let header = state
+            .next(&mut io, &ProxyProtocolCodec)
+            .await
+            .map_err(HandshakeError::from)?
+            .ok_or_else(|| {
+                log::trace!("Server amqp is disconnected during handshake");
+                HandshakeError::Disconnected
+            })?;
+        header.source.or_else(|| SocketOptions::peer_addr(&io))
+    } else {
+        SocketOptions::peer_addr(&io)
+    };
+
+    let (header, protocol) = read_protocol_header(&mut io, &state).await?;
+    let protocol = match protocol {
+        Ok(ProtocolId::AmqpTls) => Err(ProtocolIdError::Unexpected {
+            exp: ProtocolId::Amqp,
+            got: ProtocolId::AmqpTls,
+        }),
+        res => res,
+    };
+    let protocol = match protocol {
+        Ok(protocol) => protocol,
+        Err(err) => {
+            log::debug!(
+                "Unsupported protocol header {:?} from {:?}: {}",
+                header,
+                peer_addr,
+                err
+            );
+            let action = on_unsupported
+                .map(|f| {
+                    (*f)(UnsupportedProtocol {
+                        peer_addr,
+                        header: header.clone(),
+                        error: err.clone(),
+                    })
                 })
-                .await
-                .map_err(ServerError::Service)?;
+                .unwrap_or(ProtocolAction::Close);
+
+            match action {
+                ProtocolAction::Respond(id) => {
+                    state
+                        .send(&mut io, &ProtocolIdCodec, id)
+                        .await
+                        .map_err(HandshakeError::from)?;
+                }
+                ProtocolAction::Close => (),
+                ProtocolAction::Divert(f) => {
+                    let mut buf = BytesMut::from(&header[..]);
+                    state.read().with_buf(|b| buf.extend_from_slice(&b.split()));
+                    ntex::rt::spawn(f(io, buf.freeze()));
+                    return Err(HandshakeError::Diverted.into());
+                }
+            }
+            return Err(HandshakeError::from(err).into());
+        }
+    };
 
-            let (st, mut io, sink, state, idle_timeout) = ack.into_inner();
+    // plain amqp is not allowed if sasl is required,
+    // sasl is not allowed for pre-authenticated connections
+    let supported: &[ProtocolId] = if inner.sasl_required {
+        &[ProtocolId::AmqpSasl]
+    } else if identity.is_some() {
+        &[ProtocolId::Amqp]
+    } else {
+        &[ProtocolId::Amqp, ProtocolId::AmqpSasl]
+    };
+    let protocol = confirm_protocol_header(&mut io, &state, protocol, supported).await?;
+
+    let peer_cert = peer_cert.and_then(|f| (*f)(&io)).map(Rc::new);
+    let identity = if let Some(f) = identity {
+        match (*f)(&io, peer_addr).await {
+            Some(identity) => Some(Rc::new(identity)),
+            None => {
+                log::trace!("Peer identity is not resolved");
+                return Err(HandshakeError::Unauthenticated.into());
+            }
+        }
+    } else {
+        None
+    };
+    let ack = handshake
+        .call(if protocol == ProtocolId::Amqp {
+            Handshake::new_plain(io, state, config.clone(), peer_addr, peer_cert, identity)
+        } else {
+            Handshake::new_sasl(
+                io,
+                state,
+                config.clone(),
+                inner.sasl_limits.clone(),
+                peer_addr,
+                peer_cert,
+            )
+        })
+        .await
+        .map_err(ServerError::Service)?;
 
-            let codec = AmqpCodec::new().max_size(max_size);
+    let (st, mut io, sink, state, idle_timeout, ext) = ack.into_inner();
 
-            // confirm Open
-            let local = inner.config.to_open();
-            state
-                .send(&mut io, &codec, AmqpFrame::new(0, local.into()))
-                .await
-                .map_err(HandshakeError::from)?;
+    let codec = AmqpCodec::new()
+        .max_size(inner.max_size)
+        .compact(config.compact_encoding)
+        .metrics(sink.codec_metrics());
 
-            let st = State::new(st);
+    // confirm Open
+    let local = config.to_open();
+    state
+        .send(&mut io, &codec, AmqpFrame::new(0, local.into()))
+        .await
+        .map_err(HandshakeError::from)?;
 
-            (io, sink, state, codec, st, idle_timeout)
-        }
-        ProtocolId::AmqpTls => {
-            return Err(HandshakeError::from(ProtocolIdError::Unexpected {
-                exp: ProtocolId::Amqp,
-                got: ProtocolId::AmqpTls,
-            })
-            .into())
-        }
-    };
+    let st = State::with_extensions(st, ext, &sink);
 
-    Ok((io, state, codec, sink, st, idle_timeout))
+    Ok((io, state, codec, sink, st, idle_timeout, peer_addr))
 }
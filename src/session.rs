@@ -1,5 +1,7 @@
 use std::collections::VecDeque;
 use std::future::Future;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use ntex::channel::oneshot;
 use ntex::util::{BufMut, ByteString, Bytes, BytesMut, Either, HashMap, Ready};
@@ -15,11 +17,19 @@ use ntex_amqp_codec::AmqpFrame;
 use crate::cell::Cell;
 use crate::connection::Connection;
 use crate::error::AmqpProtocolError;
+use crate::hooks::DeliveryQuota;
+use crate::interceptor::Interceptors;
+use crate::observer::{LinkObserver, LinkState};
+use crate::profile::PeerProfile;
 use crate::rcvlink::{ReceiverLink, ReceiverLinkBuilder, ReceiverLinkInner};
+use crate::retry::RetryPolicy;
 use crate::sndlink::{SenderLink, SenderLinkBuilder, SenderLinkInner};
+use crate::store::LinkStateStore;
 use crate::DeliveryPromise;
 
 const INITIAL_OUTGOING_ID: TransferNumber = 0;
+const WINDOW_GROW_PERIOD: Duration = Duration::from_secs(1);
+const TAG_POOL_SIZE: usize = 4096;
 
 #[derive(Clone)]
 pub struct Session {
@@ -41,6 +51,34 @@ impl Session {
         Ready::Ok(())
     }
 
+    pub(crate) fn connection(&self) -> Connection {
+        self.inner.get_ref().sink.clone()
+    }
+
+    /// End session, session is not usable afterwards
+    pub(crate) fn end(&self) {
+        let inner = self.inner.get_ref();
+        inner.sink.end_session(inner.id(), None);
+    }
+
+    /// Local channel number
+    pub fn channel(&self) -> u16 {
+        self.inner.get_ref().id()
+    }
+
+    /// Channel number used by remote peer
+    pub fn remote_channel(&self) -> u16 {
+        self.inner.get_ref().remote_channel_id
+    }
+
+    /// Set session scheduling weight.
+    ///
+    /// Session sends `weight` quantums of queued transfers per round,
+    /// see `Configuration::session_quantum()`. Default weight is 1.
+    pub fn set_weight(&self, weight: usize) {
+        self.inner.get_mut().weight = std::cmp::max(weight, 1);
+    }
+
     pub fn get_sender_link(&self, name: &str) -> Option<&SenderLink> {
         let inner = self.inner.get_ref();
 
@@ -83,6 +121,20 @@ impl Session {
         ReceiverLinkBuilder::new(name, address, self.inner.clone())
     }
 
+    /// Open receiver link to node's dead-letter queue
+    ///
+    /// Returns `None` if peer profile does not define dead-letter queues.
+    pub fn build_dead_letter_receiver_link<U: Into<ByteString>>(
+        &mut self,
+        name: U,
+        address: &str,
+        profile: PeerProfile,
+    ) -> Option<ReceiverLinkBuilder> {
+        profile
+            .dead_letter_address(address)
+            .map(|address| self.build_receiver_link(name, address))
+    }
+
     /// Detach receiver link
     pub fn detach_receiver_link(
         &mut self,
@@ -116,13 +168,24 @@ impl Session {
     ) -> impl Future<Output = Result<Disposition, AmqpProtocolError>> {
         self.inner.get_mut().wait_disposition(id)
     }
+
+    /// Request session flow state from the peer.
+    ///
+    /// Sends session `Flow` frame with `echo` flag set, peer responds
+    /// with its current session flow state.
+    pub fn request_flow_echo(&self) {
+        self.inner.get_mut().send_flow(true);
+    }
 }
 
 #[derive(Debug)]
 enum SenderLinkState {
     Established(SenderLink),
     Opening(Option<oneshot::Sender<Result<SenderLink, AmqpProtocolError>>>),
-    Closing(Option<oneshot::Sender<Result<(), AmqpProtocolError>>>),
+    Closing(
+        Option<oneshot::Sender<Result<(), AmqpProtocolError>>>,
+        Option<LinkObserver>,
+    ),
 }
 
 #[derive(Debug)]
@@ -135,7 +198,10 @@ enum ReceiverLinkState {
         )>,
     ),
     Established(ReceiverLink),
-    Closing(Option<oneshot::Sender<Result<(), AmqpProtocolError>>>),
+    Closing(
+        Option<oneshot::Sender<Result<(), AmqpProtocolError>>>,
+        Option<LinkObserver>,
+    ),
 }
 
 impl SenderLinkState {
@@ -160,17 +226,108 @@ pub(crate) struct SessionInner {
     next_incoming_id: TransferNumber,
     remote_outgoing_window: u32,
     remote_incoming_window: u32,
+    incoming_window: IncomingWindow,
 
-    unsettled_deliveries: HashMap<DeliveryNumber, DeliveryPromise>,
+    unsettled_deliveries: HashMap<DeliveryNumber, UnsettledDelivery>,
+    tags: DeliveryTags,
 
     links: Slab<Either<SenderLinkState, ReceiverLinkState>>,
     links_by_name: HashMap<ByteString, usize>,
     remote_handles: HashMap<Handle, usize>,
     pending_transfers: VecDeque<PendingTransfer>,
     disposition_subscribers: HashMap<DeliveryNumber, oneshot::Sender<Disposition>>,
+    dispositions: Vec<Disposition>,
+    dispositions_count: usize,
+    pub(crate) weight: usize,
     error: Option<AmqpProtocolError>,
 }
 
+/// Auto-tuned session incoming window
+struct IncomingWindow {
+    bounds: Option<(u32, u32)>,
+    size: u32,
+    remaining: u32,
+    updated: Instant,
+}
+
+impl IncomingWindow {
+    fn new(bounds: Option<(u32, u32)>) -> Self {
+        let size = bounds.map(|(min, _)| min).unwrap_or(std::u32::MAX);
+        IncomingWindow {
+            bounds,
+            size,
+            remaining: size,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Register incoming transfer, returns true if window has to be re-opened
+    fn transfer(&mut self) -> bool {
+        if self.bounds.is_some() {
+            self.remaining = self.remaining.saturating_sub(1);
+            self.remaining <= self.size / 2
+        } else {
+            false
+        }
+    }
+
+    /// Re-calculate window size based on consumption rate and number of queued transfers
+    fn tune(&mut self, queued: usize) {
+        if let Some((min, max)) = self.bounds {
+            if queued > self.size as usize {
+                // receivers do not keep up, shrink window
+                self.size = std::cmp::max(min, self.size / 2);
+            } else if self.updated.elapsed() < WINDOW_GROW_PERIOD {
+                // half of the window is consumed quickly, grow window
+                self.size = std::cmp::min(max, self.size.saturating_mul(2));
+            }
+            self.remaining = self.size;
+            self.updated = Instant::now();
+            trace!("Session incoming window is set to {}", self.size);
+        }
+    }
+}
+
+/// Counter based delivery tags
+///
+/// Tags are 4 bytes long while counter fits into u32 and 8 bytes afterwards,
+/// counter wraps around at u64::MAX. Tag bytes are split from shared pool buffer.
+struct DeliveryTags {
+    counter: u64,
+    pool: BytesMut,
+}
+
+impl DeliveryTags {
+    fn new() -> Self {
+        DeliveryTags {
+            counter: 0,
+            pool: BytesMut::new(),
+        }
+    }
+
+    fn next(&mut self) -> Bytes {
+        let counter = self.counter;
+        self.counter = self.counter.wrapping_add(1);
+
+        if self.pool.capacity() < 8 {
+            self.pool.reserve(TAG_POOL_SIZE);
+        }
+        if counter <= std::u32::MAX as u64 {
+            self.pool.put_u32(counter as u32);
+        } else {
+            self.pool.put_u64(counter);
+        }
+        self.pool.split().freeze()
+    }
+}
+
+struct UnsettledDelivery {
+    link_handle: Handle,
+    tag: Bytes,
+    promise: DeliveryPromise,
+    sent: Instant,
+}
+
 struct PendingTransfer {
     link_handle: Handle,
     idx: u32,
@@ -187,12 +344,13 @@ pub(crate) enum TransferState {
     Continue,
     Last,
     Only(DeliveryPromise),
+    Aborted(Bytes),
 }
 
 impl TransferState {
     fn more(&self) -> bool {
         match self {
-            TransferState::Only(_) | TransferState::Last => false,
+            TransferState::Only(_) | TransferState::Last | TransferState::Aborted(_) => false,
             _ => true,
         }
     }
@@ -208,6 +366,7 @@ impl SessionInner {
         remote_incoming_window: u32,
         remote_outgoing_window: u32,
     ) -> SessionInner {
+        let session_window = sink.0.get_ref().session_window;
         SessionInner {
             id,
             local,
@@ -216,12 +375,17 @@ impl SessionInner {
             remote_channel_id,
             remote_incoming_window,
             remote_outgoing_window,
+            incoming_window: IncomingWindow::new(session_window),
             next_outgoing_id: INITIAL_OUTGOING_ID,
             unsettled_deliveries: HashMap::default(),
+            tags: DeliveryTags::new(),
             links: Slab::new(),
             links_by_name: HashMap::default(),
             remote_handles: HashMap::default(),
             pending_transfers: VecDeque::new(),
+            dispositions: Vec::new(),
+            dispositions_count: 0,
+            weight: 1,
             disposition_subscribers: HashMap::default(),
             error: None,
         }
@@ -232,6 +396,30 @@ impl SessionInner {
         self.id as u16
     }
 
+    /// Attached sender and receiver links
+    pub(crate) fn established_links(&self) -> Vec<Either<SenderLink, ReceiverLink>> {
+        self.links
+            .iter()
+            .filter_map(|(_, link)| match link {
+                Either::Left(SenderLinkState::Established(link)) => {
+                    Some(Either::Left(link.clone()))
+                }
+                Either::Right(ReceiverLinkState::Established(link)) => {
+                    Some(Either::Right(link.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Remote handle of the link
+    pub(crate) fn remote_handle(&self, token: usize) -> Option<Handle> {
+        self.remote_handles
+            .iter()
+            .find(|(_, t)| **t == token)
+            .map(|(hnd, _)| *hnd)
+    }
+
     /// Set error. New operations will return error.
     pub(crate) fn set_error(&mut self, err: AmqpProtocolError) {
         log::trace!("Connection is failed, dropping state: {:?}", err);
@@ -249,15 +437,27 @@ impl SessionInner {
             match st {
                 Either::Left(SenderLinkState::Opening(_)) => (),
                 Either::Left(SenderLinkState::Established(ref mut link)) => {
-                    link.inner.get_mut().detached(err.clone())
+                    link.inner().get_mut().detached(err.clone());
+                    link.inner().observer.set(LinkState::Closed);
                 }
-                Either::Left(SenderLinkState::Closing(ref mut link)) => {
+                Either::Left(SenderLinkState::Closing(ref mut link, ref observer)) => {
                     if let Some(tx) = link.take() {
                         let _ = tx.send(Err(err.clone()));
                     }
+                    if let Some(observer) = observer {
+                        observer.set(LinkState::Closed);
+                    }
                 }
                 Either::Right(ReceiverLinkState::Established(ref mut link)) => {
-                    link.remote_closed(None)
+                    link.remote_closed(None);
+                    link.inner.observer.set(LinkState::Closed);
+                }
+                Either::Right(ReceiverLinkState::Opening(Some(ref inner)))
+                | Either::Right(ReceiverLinkState::OpeningLocal(Some((ref inner, _)))) => {
+                    inner.observer.set(LinkState::Closed);
+                }
+                Either::Right(ReceiverLinkState::Closing(_, Some(ref observer))) => {
+                    observer.set(LinkState::Closed);
                 }
                 _ => (),
             }
@@ -280,6 +480,73 @@ impl SessionInner {
         self.sink.0.max_frame_size
     }
 
+    pub(crate) fn interceptors(&self) -> &Interceptors {
+        &self.sink.0.interceptors
+    }
+
+    pub(crate) fn quota(&self) -> Option<&dyn DeliveryQuota> {
+        self.sink.0.quota.as_deref()
+    }
+
+    pub(crate) fn retry_policy(&self) -> Option<Rc<dyn RetryPolicy>> {
+        self.sink.0.retry_policy.clone()
+    }
+
+    /// Settlement state store for locally opened sender links
+    pub(crate) fn link_store(&self) -> Option<Rc<dyn LinkStateStore>> {
+        self.sink.0.link_store.clone()
+    }
+
+    /// Settlement modes for locally opened links
+    pub(crate) fn settle_modes(&self) -> (SenderSettleMode, ReceiverSettleMode) {
+        (self.sink.0.snd_settle_mode, self.sink.0.rcv_settle_mode)
+    }
+
+    pub(crate) fn attach_timeout(&self) -> Option<Duration> {
+        self.sink.0.attach_timeout
+    }
+
+    pub(crate) fn settlement_timeout(&self) -> Option<Duration> {
+        self.sink.0.settlement_timeout
+    }
+
+    /// Auto-accept and prefetch settings for locally opened receiver links
+    pub(crate) fn receiver_defaults(&self) -> (bool, u32) {
+        (self.sink.0.auto_accept, self.sink.0.prefetch)
+    }
+
+    /// Check if outgoing transfers queue limit of the link is reached
+    ///
+    /// Link's transfers waiting for session window count towards the limit.
+    pub(crate) fn pending_limit_reached(&self, handle: Handle, link_pending: usize) -> bool {
+        self.sink
+            .0
+            .max_pending_transfers
+            .map(|max| {
+                let session_pending = self
+                    .pending_transfers
+                    .iter()
+                    .filter(|tr| tr.link_handle == handle)
+                    .count();
+                link_pending + session_pending >= max
+            })
+            .unwrap_or(false)
+    }
+
+    /// Check if incoming transfers queue limit is reached
+    pub(crate) fn queued_limit_reached(&self, queued: usize) -> bool {
+        self.sink
+            .0
+            .max_queued_transfers
+            .map(|max| queued >= max)
+            .unwrap_or(false)
+    }
+
+    /// Close connection with error
+    pub(crate) fn close_connection(&mut self, err: Error) {
+        self.sink.0.get_mut().close_with_error(err);
+    }
+
     /// Detach unconfirmed sender link
     pub(crate) fn detach_unconfirmed_sender_link(&mut self, attach: &Attach, error: Option<Error>) {
         let detach = Detach {
@@ -318,6 +585,7 @@ impl SessionInner {
         }
 
         link.get_mut().id = token;
+        link.observer.set(LinkState::Attached);
         self.remote_handles.insert(attach.handle(), token);
         entry.insert(Either::Left(SenderLinkState::Established(SenderLink::new(
             link.clone(),
@@ -366,7 +634,10 @@ impl SessionInner {
         &mut self,
         cell: Cell<SessionInner>,
         mut frame: Attach,
-    ) -> oneshot::Receiver<Result<ReceiverLink, AmqpProtocolError>> {
+    ) -> (
+        usize,
+        oneshot::Receiver<Result<ReceiverLink, AmqpProtocolError>>,
+    ) {
         let (tx, rx) = oneshot::channel();
 
         let entry = self.links.vacant_entry();
@@ -381,7 +652,38 @@ impl SessionInner {
 
         self.links_by_name.insert(frame.name.clone(), token);
         self.post_frame(Frame::Attach(frame));
-        rx
+        (token, rx)
+    }
+
+    /// Detach locally opened link that is not attached by peer yet
+    fn detach_opening_link(&mut self, token: usize) {
+        let opening = match self.links.get_mut(token) {
+            Some(Either::Left(item)) if item.is_opening() => {
+                *item = SenderLinkState::Closing(None, None);
+                true
+            }
+            Some(Either::Right(item)) if item.is_opening() => {
+                let mut observer = None;
+                if let ReceiverLinkState::OpeningLocal(Some((ref inner, _))) = item {
+                    inner.get_mut().detached();
+                    inner.observer.set(LinkState::DetachSent);
+                    observer = Some(inner.observer.clone());
+                }
+                *item = ReceiverLinkState::Closing(None, observer);
+                true
+            }
+            _ => false,
+        };
+
+        if opening {
+            trace!("Link is not attached by peer in time, detach: {}", token);
+            let detach = Detach {
+                handle: token as Handle,
+                closed: true,
+                error: None,
+            };
+            self.post_frame(detach.into());
+        }
     }
 
     pub(crate) fn confirm_receiver_link(&mut self, token: Handle, attach: &Attach) {
@@ -405,6 +707,8 @@ impl SessionInner {
                             desired_capabilities: None,
                             properties: None,
                         };
+                        l.observer.set(LinkState::Attached);
+                        l.get_mut().rcv_settle_mode = ReceiverSettleMode::First;
                         *link = ReceiverLinkState::Established(ReceiverLink::new(l));
                         self.post_frame(attach.into());
                         return;
@@ -427,26 +731,30 @@ impl SessionInner {
     ) {
         if let Some(Either::Right(link)) = self.links.get_mut(id as usize) {
             match link {
-                ReceiverLinkState::Opening(_inner) => {
+                ReceiverLinkState::Opening(inner) => {
                     let detach = Detach {
                         handle: id,
                         closed,
                         error,
                     };
+                    if let Some(inner) = inner {
+                        inner.observer.set(LinkState::Closed);
+                    }
                     self.post_frame(detach.into());
                     let _ = tx.send(Ok(()));
                     let _ = self.links.remove(id as usize);
                 }
-                ReceiverLinkState::Established(_) => {
+                ReceiverLinkState::Established(l) => {
                     let detach = Detach {
                         handle: id,
                         closed,
                         error,
                     };
-                    *link = ReceiverLinkState::Closing(Some(tx));
+                    let observer = l.inner.observer.clone();
+                    *link = ReceiverLinkState::Closing(Some(tx), Some(observer));
                     self.post_frame(detach.into());
                 }
-                ReceiverLinkState::Closing(_) => {
+                ReceiverLinkState::Closing(..) => {
                     let _ = tx.send(Ok(()));
                     let _ = self.links.remove(id as usize);
                     error!("Unexpected receiver link state: closing - {}", id);
@@ -474,19 +782,20 @@ impl SessionInner {
                         closed,
                         error,
                     };
-                    *link = SenderLinkState::Closing(Some(tx));
+                    *link = SenderLinkState::Closing(Some(tx), None);
                     self.post_frame(detach.into());
                 }
-                SenderLinkState::Established(_) => {
+                SenderLinkState::Established(l) => {
                     let detach = Detach {
                         handle: id as u32,
                         closed,
                         error,
                     };
-                    *link = SenderLinkState::Closing(Some(tx));
+                    let observer = l.inner().observer.clone();
+                    *link = SenderLinkState::Closing(Some(tx), Some(observer));
                     self.post_frame(detach.into());
                 }
-                SenderLinkState::Closing(_) => {
+                SenderLinkState::Closing(..) => {
                     let _ = tx.send(Ok(()));
                     error!("Unexpected receiver link state: closing - {}", id);
                 }
@@ -497,6 +806,11 @@ impl SessionInner {
         }
     }
 
+    /// Check if remote handle is attached to this session
+    pub(crate) fn is_remote_handle(&self, hnd: Handle) -> bool {
+        self.remote_handles.contains_key(&hnd)
+    }
+
     pub(crate) fn get_sender_link_by_handle(&self, hnd: Handle) -> Option<&SenderLink> {
         if let Some(id) = self.remote_handles.get(&hnd) {
             if let Some(Either::Left(SenderLinkState::Established(ref link))) = self.links.get(*id)
@@ -507,6 +821,35 @@ impl SessionInner {
         None
     }
 
+    /// Check if sender link is attached to this session
+    pub(crate) fn is_sender_attached(&self, link: &SenderLink) -> bool {
+        if self.error.is_some() {
+            return false;
+        }
+        match self.links.get(link.inner().get_ref().id) {
+            Some(Either::Left(SenderLinkState::Established(ref l))) => {
+                l.inner().ptr_eq(link.inner())
+            }
+            _ => false,
+        }
+    }
+
+    /// Check if receiver link is attached to this session
+    pub(crate) fn is_receiver_attached(&self, link: &ReceiverLink) -> bool {
+        if self.error.is_some() {
+            return false;
+        }
+        match self.links.get(link.handle() as usize) {
+            Some(Either::Right(ReceiverLinkState::Established(ref l))) => {
+                l.inner.ptr_eq(&link.inner)
+            }
+            Some(Either::Right(ReceiverLinkState::Opening(Some(ref inner)))) => {
+                inner.ptr_eq(&link.inner)
+            }
+            _ => false,
+        }
+    }
+
     pub(crate) fn get_receiver_link_by_handle(&self, hnd: Handle) -> Option<&ReceiverLink> {
         if let Some(id) = self.remote_handles.get(&hnd) {
             if let Some(Either::Right(ReceiverLinkState::Established(ref link))) =
@@ -525,11 +868,19 @@ impl SessionInner {
                 Frame::Disposition(disp) => {
                     if let Some(sender) = self.disposition_subscribers.remove(&disp.first) {
                         let _ = sender.send(disp);
+                    } else if disp.role == Role::Sender {
+                        // remote sender settles deliveries of receiver links in `second` mode
+                        trace!("Deliveries are settled by sender: {:?}", disp.first);
                     } else {
                         self.settle_deliveries(disp);
                     }
                 }
                 Frame::Transfer(transfer) => {
+                    self.next_incoming_id = self.next_incoming_id.wrapping_add(1);
+                    if self.incoming_window.transfer() {
+                        self.update_incoming_window();
+                    }
+
                     let idx = if let Some(idx) = self.remote_handles.get(&transfer.handle()) {
                         *idx
                     } else {
@@ -556,11 +907,9 @@ impl SessionInner {
                                     );
                                 }
                                 ReceiverLinkState::Established(link) => {
-                                    // self.outgoing_window -= 1;
-                                    let _ = self.next_incoming_id.wrapping_add(1);
                                     link.inner.get_mut().handle_transfer(transfer);
                                 }
-                                ReceiverLinkState::Closing(_) => (),
+                                ReceiverLinkState::Closing(..) => (),
                             },
                         }
                     } else {
@@ -579,6 +928,30 @@ impl SessionInner {
         }
     }
 
+    /// Wait for peer's attach, detach link if peer does not respond within attach timeout
+    pub(crate) async fn wait_attach<T>(
+        cell: Cell<SessionInner>,
+        token: usize,
+        rx: oneshot::Receiver<Result<T, AmqpProtocolError>>,
+    ) -> Result<T, AmqpProtocolError> {
+        let res = if let Some(timeout) = cell.get_ref().attach_timeout() {
+            match ntex::rt::time::timeout(timeout, rx).await {
+                Ok(res) => res,
+                Err(_) => {
+                    cell.get_mut().detach_opening_link(token);
+                    return Err(AmqpProtocolError::AttachTimeout);
+                }
+            }
+        } else {
+            rx.await
+        };
+
+        match res {
+            Ok(res) => res,
+            Err(_) => Err(AmqpProtocolError::Disconnected),
+        }
+    }
+
     /// Handle `Attach` frame. return false if attach frame is remote and can not be handled
     pub(crate) fn handle_attach(&mut self, attach: &Attach, cell: Cell<SessionInner>) -> bool {
         let name = attach.name();
@@ -596,13 +969,17 @@ impl SessionInner {
 
                         self.remote_handles.insert(attach.handle(), *index);
                         let delivery_count = attach.initial_delivery_count.unwrap_or(0);
-                        let link = Cell::new(SenderLinkInner::new(
+                        let mut inner = SenderLinkInner::new(
                             *index,
                             name.clone(),
                             attach.handle(),
                             delivery_count,
                             cell,
-                        ));
+                        );
+                        inner.max_message_size = attach.max_message_size();
+                        inner.remote_target = attach.target.clone();
+                        inner.remote_unsettled = attach.unsettled.clone();
+                        let link = Cell::new(inner);
                         let local_sender = std::mem::replace(
                             item,
                             SenderLinkState::Established(SenderLink::new(link.clone())),
@@ -611,6 +988,9 @@ impl SessionInner {
                         if let SenderLinkState::Opening(Some(tx)) = local_sender {
                             let _ = tx.send(Ok(SenderLink::new(link)));
                         }
+                    } else if let SenderLinkState::Closing(..) = item {
+                        // late attach for detached link, wait for peer's detach
+                        self.remote_handles.insert(attach.handle(), *index);
                     }
                 }
                 Some(Either::Right(item)) => {
@@ -624,6 +1004,7 @@ impl SessionInner {
                         if let ReceiverLinkState::OpeningLocal(opt_item) = item {
                             if let Some((link, tx)) = opt_item.take() {
                                 self.remote_handles.insert(attach.handle(), *index);
+                                link.observer.set(LinkState::Attached);
 
                                 *item =
                                     ReceiverLinkState::Established(ReceiverLink::new(link.clone()));
@@ -633,6 +1014,9 @@ impl SessionInner {
                                 error!("Inconsistent session state, bug");
                             }
                         }
+                    } else if let ReceiverLinkState::Closing(..) = item {
+                        // late attach for detached link, wait for peer's detach
+                        self.remote_handles.insert(attach.handle(), *index);
                     }
                 }
                 _ => {
@@ -672,18 +1056,18 @@ impl SessionInner {
                     SenderLinkState::Established(link) => {
                         // detach from remote endpoint
                         let detach = Detach {
-                            handle: link.inner.get_ref().id(),
+                            handle: link.inner().get_ref().id(),
                             closed: true,
                             error: detach.error.clone(),
                         };
                         let err = AmqpProtocolError::LinkDetached(detach.error.clone());
 
                         // remove name
-                        self.links_by_name.remove(link.inner.name());
+                        self.links_by_name.remove(link.inner().name());
 
                         // drop pending transfers
                         let mut idx = 0;
-                        let handle = link.inner.get_ref().remote_handle();
+                        let handle = link.inner().get_ref().remote_handle();
                         while idx < self.pending_transfers.len() {
                             if self.pending_transfers[idx].link_handle == handle {
                                 let tr = self.pending_transfers.remove(idx).unwrap();
@@ -697,18 +1081,35 @@ impl SessionInner {
                         }
 
                         // detach snd link
-                        link.inner.get_mut().detached(err);
+                        link.inner().observer.set(LinkState::DetachReceived);
+                        link.inner().get_mut().detached(err);
                         self.sink
                             .post_frame(AmqpFrame::new(self.remote_channel_id, detach.into()));
+                        link.inner().observer.set(LinkState::Closed);
+                        true
+                    }
+                    SenderLinkState::Closing(tx, ref observer) => {
+                        if let Some(observer) = observer {
+                            observer.set(LinkState::Closed);
+                        }
+                        // detach confirmation
+                        if let Some(tx) = tx.take() {
+                            if let Some(err) = detach.error.clone() {
+                                let _ = tx.send(Err(AmqpProtocolError::LinkDetached(Some(err))));
+                            } else {
+                                let _ = tx.send(Ok(()));
+                            }
+                        }
                         true
                     }
-                    SenderLinkState::Closing(_) => true,
                 },
                 Either::Right(link) => match link {
                     ReceiverLinkState::Opening(_) => false,
                     ReceiverLinkState::OpeningLocal(ref mut item) => {
                         if let Some((inner, tx)) = item.take() {
+                            inner.observer.set(LinkState::DetachReceived);
                             inner.get_mut().detached();
+                            inner.observer.set(LinkState::Closed);
                             if let Some(err) = detach.error.clone() {
                                 let _ = tx.send(Err(AmqpProtocolError::LinkDetached(Some(err))));
                             } else {
@@ -721,6 +1122,7 @@ impl SessionInner {
                         true
                     }
                     ReceiverLinkState::Established(link) => {
+                        link.inner.observer.set(LinkState::DetachReceived);
                         link.remote_closed(detach.error.take());
 
                         // detach from remote endpoint
@@ -733,9 +1135,13 @@ impl SessionInner {
                         // detach rcv link
                         self.sink
                             .post_frame(AmqpFrame::new(self.remote_channel_id, detach.into()));
+                        link.inner.observer.set(LinkState::Closed);
                         true
                     }
-                    ReceiverLinkState::Closing(tx) => {
+                    ReceiverLinkState::Closing(tx, observer) => {
+                        if let Some(observer) = observer {
+                            observer.set(LinkState::Closed);
+                        }
                         // detach confirmation
                         if let Some(tx) = tx.take() {
                             if let Some(err) = detach.error.clone() {
@@ -763,7 +1169,7 @@ impl SessionInner {
         let to = disposition.last.unwrap_or(from);
 
         if cfg!(feature = "frame-trace") {
-            trace!("Settle delivery: {:#?}", disposition);
+            trace!("Settle delivery: {}", crate::codec::pretty(&disposition));
         } else {
             trace!(
                 "Settle delivery from {}, state {:?} settled: {:?}",
@@ -779,24 +1185,87 @@ impl SessionInner {
                     let mut disp = disposition.clone();
                     disp.role = Role::Sender;
                     disp.settled = true;
-                    disp.state = Some(DeliveryState::Accepted(Accepted {}));
                     self.post_frame(Frame::Disposition(disp));
                 }
-                let _ = val.send(Ok(disposition));
+                self.notify_delivery_state(&val, &disposition);
+                let _ = val.promise.send(Ok(disposition));
             }
         } else {
             if !disposition.settled {
                 let mut disp = disposition.clone();
                 disp.role = Role::Sender;
                 disp.settled = true;
-                disp.state = Some(DeliveryState::Accepted(Accepted {}));
                 self.post_frame(Frame::Disposition(disp));
             }
 
             for k in from..=to {
                 if let Some(val) = self.unsettled_deliveries.remove(&k) {
-                    let _ = val.send(Ok(disposition.clone()));
+                    self.notify_delivery_state(&val, &disposition);
+                    let _ = val.promise.send(Ok(disposition.clone()));
+                }
+            }
+        }
+    }
+
+    /// Settle deliveries that are not settled within `timeout`
+    pub(crate) fn sweep_unsettled(&mut self, timeout: Duration) {
+        let expired: Vec<_> = self
+            .unsettled_deliveries
+            .iter()
+            .filter(|(_, delivery)| delivery.sent.elapsed() >= timeout)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in expired {
+            if let Some(delivery) = self.unsettled_deliveries.remove(&id) {
+                log::trace!("Delivery {} is not settled in time, settle locally", id);
+                self.post_frame(Frame::Disposition(Disposition {
+                    role: Role::Sender,
+                    first: id,
+                    last: None,
+                    settled: true,
+                    state: None,
+                    batchable: false,
+                }));
+                if let Some(Either::Left(SenderLinkState::Established(ref link))) =
+                    self.links.get(delivery.link_handle as usize)
+                {
+                    let link = link.inner().get_mut();
+                    link.delivery_settled();
+                    link.store_settled(&delivery.tag, None);
                 }
+                let _ = delivery
+                    .promise
+                    .send(Err(AmqpProtocolError::SettlementTimeout));
+            }
+        }
+
+        for (_, link) in self.links.iter() {
+            if let Either::Right(ReceiverLinkState::Established(ref link)) = link {
+                link.inner.get_mut().sweep_unsettled(timeout);
+            }
+        }
+    }
+
+    /// Check sender links for credit starvation
+    pub(crate) fn check_starvation(&mut self, threshold: Duration) {
+        for (_, link) in self.links.iter() {
+            if let Either::Left(SenderLinkState::Established(ref link)) = link {
+                link.inner().get_mut().check_starvation(threshold);
+            }
+        }
+    }
+
+    fn notify_delivery_state(&mut self, delivery: &UnsettledDelivery, disp: &Disposition) {
+        if let Some(Either::Left(SenderLinkState::Established(ref link))) =
+            self.links.get(delivery.link_handle as usize)
+        {
+            let link = link.inner().get_mut();
+            link.delivery_settled();
+            link.notify_latency(delivery.sent.elapsed());
+            link.store_settled(&delivery.tag, disp.state.as_ref());
+            if let Some(ref state) = disp.state {
+                link.notify_delivery_state(delivery.tag.clone(), state.clone());
             }
         }
     }
@@ -819,47 +1288,141 @@ impl SessionInner {
             self.pending_transfers.len()
         );
 
-        while let Some(t) = self.pending_transfers.pop_front() {
-            self.send_transfer(
+        if self.sink.0.session_quantum.is_none() {
+            self.drain_pending(usize::MAX);
+        } else if self.remote_incoming_window != 0 && !self.pending_transfers.is_empty() {
+            self.sink.0.get_mut().ready_session(self.id() as usize);
+        }
+
+        // apply link flow
+        if let Some(link) = flow
+            .handle()
+            .and_then(|h| self.remote_handles.get(&h).copied())
+            .and_then(|h| self.links.get_mut(h))
+        {
+            match link {
+                Either::Left(SenderLinkState::Established(ref mut link)) => {
+                    link.inner().get_mut().apply_flow(&flow);
+                }
+                Either::Right(ReceiverLinkState::Established(ref mut link)) => {
+                    link.inner.get_mut().apply_flow(flow);
+                }
+                _ => warn!("Received flow frame"),
+            }
+        } else if flow.echo() {
+            self.send_flow(false);
+        }
+    }
+
+    /// Send at most `max` queued transfers, returns true if session
+    /// still has transfers that could be sent
+    pub(crate) fn drain_pending(&mut self, max: usize) -> bool {
+        let mut sent = 0;
+        while self.remote_incoming_window != 0 && sent < max {
+            let t = match self.next_pending_transfer() {
+                Some(idx) => self.pending_transfers.remove(idx).unwrap(),
+                None => break,
+            };
+            sent += 1;
+            let frame = self.prepare_transfer(
                 t.link_handle,
-                t.idx,
                 t.body,
                 t.state,
                 t.tag,
                 t.settled,
                 t.message_format,
             );
+            self.post_frame(frame);
             if self.remote_outgoing_window == 0 {
-                break;
+                return false;
             }
         }
+        self.remote_incoming_window != 0 && !self.pending_transfers.is_empty()
+    }
 
-        // apply link flow
-        if let Some(Either::Left(link)) = flow
-            .handle()
-            .and_then(|h| self.remote_handles.get(&h).copied())
-            .and_then(|h| self.links.get_mut(h))
-        {
-            match link {
-                SenderLinkState::Established(ref mut link) => {
-                    link.inner.get_mut().apply_flow(&flow);
+    /// Index of next pending transfer to send
+    ///
+    /// Transfers of links with higher priority are sent first, transfers
+    /// of the same link are always sent in order.
+    fn next_pending_transfer(&self) -> Option<usize> {
+        let mut next: Option<(usize, u8)> = None;
+        for (idx, tr) in self.pending_transfers.iter().enumerate() {
+            let priority = match self.links.get(tr.link_handle as usize) {
+                Some(Either::Left(SenderLinkState::Established(link))) => {
+                    link.inner().get_ref().priority
                 }
-                _ => warn!("Received flow frame"),
+                _ => 0,
+            };
+            if next.map(|(_, p)| priority > p).unwrap_or(true) {
+                next = Some((idx, priority));
             }
         }
-        if flow.echo() {
-            self.send_flow();
-        }
+        next.map(|(idx, _)| idx)
     }
 
-    fn send_flow(&mut self) {
-        let flow = Flow {
-            next_incoming_id: if self.local {
-                Some(self.next_incoming_id)
-            } else {
-                None
-            },
-            incoming_window: std::u32::MAX,
+    /// Tune session incoming window and notify peer
+    fn update_incoming_window(&mut self) {
+        let queued = self
+            .links
+            .iter()
+            .map(|(_, link)| match link {
+                Either::Right(ReceiverLinkState::Established(link)) => {
+                    link.inner.get_ref().queued()
+                }
+                _ => 0,
+            })
+            .sum();
+        self.incoming_window.tune(queued);
+        self.send_flow(false);
+    }
+
+    /// Send session flow frame
+    pub(crate) fn send_flow(&mut self, echo: bool) {
+        let mut flow = self.session_flow();
+        flow.echo = echo;
+        self.post_frame(flow.into());
+    }
+
+    pub(crate) fn rcv_link_flow(
+        &mut self,
+        handle: u32,
+        delivery_count: u32,
+        credit: u32,
+        drain: bool,
+        echo: bool,
+    ) {
+        let mut flow = self.session_flow();
+        flow.handle = Some(handle);
+        flow.delivery_count = Some(delivery_count);
+        flow.link_credit = Some(credit);
+        flow.drain = drain;
+        flow.echo = echo;
+        self.post_frame(flow.into());
+    }
+
+    pub(crate) fn snd_link_flow(
+        &mut self,
+        handle: u32,
+        delivery_count: u32,
+        credit: u32,
+        available: u32,
+        drain: bool,
+        echo: bool,
+    ) {
+        let mut flow = self.session_flow();
+        flow.handle = Some(handle);
+        flow.delivery_count = Some(delivery_count);
+        flow.link_credit = Some(credit);
+        flow.available = Some(available);
+        flow.drain = drain;
+        flow.echo = echo;
+        self.post_frame(flow.into());
+    }
+
+    fn session_flow(&self) -> Flow {
+        Flow {
+            next_incoming_id: Some(self.next_incoming_id),
+            incoming_window: self.incoming_window.remaining,
             next_outgoing_id: self.next_outgoing_id,
             outgoing_window: self.remote_incoming_window,
             handle: None,
@@ -869,40 +1432,76 @@ impl SessionInner {
             drain: false,
             echo: false,
             properties: None,
-        };
-        self.post_frame(flow.into());
+        }
     }
 
-    pub(crate) fn rcv_link_flow(&mut self, handle: u32, delivery_count: u32, credit: u32) {
-        let flow = Flow {
-            next_incoming_id: if self.local {
-                Some(self.next_incoming_id)
+    pub(crate) fn post_frame(&mut self, frame: Frame) {
+        match frame {
+            Frame::Disposition(disp) if self.sink.0.disposition_batch.is_some() => {
+                self.batch_disposition(disp)
+            }
+            frame => self
+                .sink
+                .post_frame(AmqpFrame::new(self.remote_channel_id, frame)),
+        }
+    }
+
+    /// Buffer disposition, adjacent dispositions with the same
+    /// outcome are coalesced into one range
+    fn batch_disposition(&mut self, disp: Disposition) {
+        let last = disp.last.unwrap_or(disp.first);
+        self.dispositions_count += (last.wrapping_sub(disp.first) as usize) + 1;
+
+        let merged = if let Some(prev) = self.dispositions.last_mut() {
+            let prev_last = prev.last.unwrap_or(prev.first);
+            if prev.role == disp.role
+                && prev.settled == disp.settled
+                && prev.state == disp.state
+                && prev_last.wrapping_add(1) == disp.first
+            {
+                prev.last = Some(last);
+                prev.batchable &= disp.batchable;
+                true
             } else {
-                None
-            },
-            incoming_window: std::u32::MAX,
-            next_outgoing_id: self.next_outgoing_id,
-            outgoing_window: self.remote_incoming_window,
-            handle: Some(handle),
-            delivery_count: Some(delivery_count),
-            link_credit: Some(credit),
-            available: None,
-            drain: false,
-            echo: false,
-            properties: None,
+                false
+            }
+        } else {
+            false
         };
-        self.post_frame(flow.into());
+        if !merged {
+            self.dispositions.push(disp);
+        }
+
+        let max = self
+            .sink
+            .0
+            .disposition_batch
+            .map(|(_, max)| max)
+            .unwrap_or(1);
+        if self.dispositions_count >= max {
+            self.flush_dispositions();
+        }
     }
 
-    pub(crate) fn post_frame(&mut self, frame: Frame) {
-        self.sink
-            .post_frame(AmqpFrame::new(self.remote_channel_id, frame));
+    /// Send batched dispositions
+    pub(crate) fn flush_dispositions(&mut self) {
+        self.dispositions_count = 0;
+        for mut disp in self.dispositions.drain(..) {
+            if disp.last == Some(disp.first) {
+                disp.last = None;
+            }
+            self.sink
+                .post_frame(AmqpFrame::new(self.remote_channel_id, disp.into()));
+        }
     }
 
     pub(crate) fn open_sender_link(
         &mut self,
         mut frame: Attach,
-    ) -> oneshot::Receiver<Result<SenderLink, AmqpProtocolError>> {
+    ) -> (
+        usize,
+        oneshot::Receiver<Result<SenderLink, AmqpProtocolError>>,
+    ) {
         let (tx, rx) = oneshot::channel();
 
         let entry = self.links.vacant_entry();
@@ -913,7 +1512,11 @@ impl SessionInner {
 
         self.links_by_name.insert(frame.name.clone(), token);
         self.post_frame(Frame::Attach(frame));
-        rx
+        (token, rx)
+    }
+
+    pub(crate) fn next_delivery_tag(&mut self) -> Bytes {
+        self.tags.next()
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -927,9 +1530,12 @@ impl SessionInner {
         settled: Option<bool>,
         message_format: Option<MessageFormat>,
     ) {
-        if self.remote_incoming_window == 0 {
+        // scheduled session keeps order of queued transfers
+        let scheduled = self.sink.0.session_quantum.is_some() && !self.pending_transfers.is_empty();
+
+        if self.remote_incoming_window == 0 || scheduled {
             log::trace!(
-                "Remote window is 0, push to pending queue, hnd:{:?}",
+                "Remote window is 0 or session is scheduled, push to pending queue, hnd:{:?}",
                 link_handle
             );
             self.pending_transfers.push_back(PendingTransfer {
@@ -941,6 +1547,9 @@ impl SessionInner {
                 settled,
                 message_format,
             });
+            if self.remote_incoming_window != 0 {
+                self.sink.0.get_mut().ready_session(self.id() as usize);
+            }
         } else {
             let frame =
                 self.prepare_transfer(link_handle, body, state, tag, settled, message_format);
@@ -988,23 +1597,47 @@ impl SessionInner {
         };
 
         let more = tr_state.more();
+        let batchable = matches!(
+            self.links.get(link_handle as usize),
+            Some(Either::Left(SenderLinkState::Established(link))) if link.inner().get_ref().batchable
+        );
         match tr_state {
             TransferState::First(promise) | TransferState::Only(promise) => {
                 let delivery_id = self.next_outgoing_id;
-                self.next_outgoing_id += 1;
+                self.next_outgoing_id = self.next_outgoing_id.wrapping_add(1);
 
-                transfer.delivery_id = Some(delivery_id);
-                transfer.delivery_tag = if let Some(tag) = delivery_tag {
-                    Some(tag)
+                let tag = if let Some(tag) = delivery_tag {
+                    tag
                 } else {
-                    let mut buf = BytesMut::new();
-                    buf.put_u32(delivery_id);
-                    Some(buf.freeze())
+                    self.tags.next()
                 };
+                transfer.delivery_id = Some(delivery_id);
+                transfer.delivery_tag = Some(tag.clone());
 
                 transfer.more = more;
-                transfer.batchable = more;
-                self.unsettled_deliveries.insert(delivery_id, promise);
+                transfer.batchable = more || batchable;
+
+                // pre-settled delivery is never settled by receiver
+                if settled2 {
+                    let _ = promise.send(Ok(Disposition {
+                        role: Role::Receiver,
+                        first: delivery_id,
+                        last: None,
+                        settled: true,
+                        state: None,
+                        batchable: false,
+                    }));
+                    return Frame::Transfer(transfer);
+                }
+                self.unsettled_deliveries.insert(
+                    delivery_id,
+                    UnsettledDelivery {
+                        link_handle,
+                        tag,
+                        promise,
+                        sent: Instant::now(),
+                    },
+                );
             }
             TransferState::Continue => {
                 transfer.more = true;
@@ -1012,6 +1645,19 @@ impl SessionInner {
             }
             TransferState::Last => {
                 transfer.more = false;
+                transfer.batchable = batchable;
+            }
+            TransferState::Aborted(tag) => {
+                // #2.6.14 aborted delivery is never settled
+                transfer.aborted = true;
+                let id = self
+                    .unsettled_deliveries
+                    .iter()
+                    .find(|(_, d)| d.link_handle == link_handle && d.tag == tag)
+                    .map(|(id, _)| *id);
+                if let Some(id) = id {
+                    self.unsettled_deliveries.remove(&id);
+                }
             }
         }
 
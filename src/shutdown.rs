@@ -0,0 +1,138 @@
+use std::{future::Future, pin::Pin, time::Duration, time::Instant};
+
+use ntex::channel::oneshot;
+use ntex::rt::time::delay_for;
+use ntex::util::{select, Either};
+
+use crate::cell::Cell;
+use crate::connection::{ChannelState, Connection};
+use crate::error::AmqpProtocolError;
+use crate::session::SessionInner;
+
+type CloseFut = Pin<Box<dyn Future<Output = Result<(), AmqpProtocolError>>>>;
+
+/// Timeouts of shutdown phases
+#[derive(Debug, Copy, Clone)]
+pub struct ShutdownTimeouts {
+    /// Time to wait for links detach confirmations
+    pub detach: Duration,
+    /// Time to wait for sessions end confirmations
+    pub end: Duration,
+    /// Time to wait for connection close confirmation
+    pub close: Duration,
+}
+
+impl Default for ShutdownTimeouts {
+    fn default() -> Self {
+        ShutdownTimeouts {
+            detach: Duration::from_secs(5),
+            end: Duration::from_secs(5),
+            close: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Result of ordered shutdown
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Number of links detached by peer in time
+    pub links_detached: usize,
+    /// Number of links that are not detached in time or detached with error
+    pub links_failed: usize,
+    /// Number of sessions ended by peer in time
+    pub sessions_ended: usize,
+    /// Number of sessions that are not ended in time
+    pub sessions_failed: usize,
+    /// Peer confirmed connection close without error
+    pub closed: bool,
+}
+
+impl ShutdownReport {
+    /// Check if all phases are completed cleanly
+    pub fn is_clean(&self) -> bool {
+        self.links_failed == 0 && self.sessions_failed == 0 && self.closed
+    }
+}
+
+pub(crate) async fn shutdown(sink: Connection, timeouts: ShutdownTimeouts) -> ShutdownReport {
+    let mut report = ShutdownReport::default();
+    if sink.get_error().is_some() {
+        return report;
+    }
+
+    // detach links
+    let links: Vec<_> = established_sessions(&sink)
+        .iter()
+        .flat_map(|ses| ses.get_ref().established_links())
+        .collect();
+    trace!("Shutdown: detaching {} links", links.len());
+    let futs: Vec<CloseFut> = links
+        .iter()
+        .map(|link| match link {
+            Either::Left(link) => Box::pin(link.close()) as CloseFut,
+            Either::Right(link) => Box::pin(link.close()) as CloseFut,
+        })
+        .collect();
+    let (ok, failed) = wait_all(futs, timeouts.detach).await;
+    report.links_detached = ok;
+    report.links_failed = failed;
+
+    // end sessions
+    let sessions = established_sessions(&sink);
+    trace!("Shutdown: ending {} sessions", sessions.len());
+    let futs: Vec<CloseFut> = sessions
+        .iter()
+        .map(|ses| {
+            let (tx, rx) = oneshot::channel();
+            sink.end_session_with(ses.get_ref().id(), None, Some(tx));
+            Box::pin(async move { rx.await.map_err(|_| AmqpProtocolError::Disconnected)? })
+                as CloseFut
+        })
+        .collect();
+    let (ok, failed) = wait_all(futs, timeouts.end).await;
+    report.sessions_ended = ok;
+    report.sessions_failed = failed;
+
+    // close connection
+    trace!("Shutdown: closing connection");
+    let closed = sink.closed();
+    sink.0.get_mut().close_local();
+    match select(delay_for(timeouts.close), closed).await {
+        Either::Left(_) => {
+            trace!("Shutdown: close is not confirmed in time");
+            sink.force_close();
+        }
+        Either::Right(err) => {
+            report.closed = matches!(err, Some(AmqpProtocolError::Closed(None)));
+        }
+    }
+    report
+}
+
+fn established_sessions(sink: &Connection) -> Vec<Cell<SessionInner>> {
+    sink.0
+        .get_ref()
+        .sessions
+        .iter()
+        .filter_map(|(_, channel)| match channel {
+            ChannelState::Established(ses) => Some(ses.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Wait for futures until deadline, returns number of succeeded and failed futures
+async fn wait_all(futs: Vec<CloseFut>, timeout: Duration) -> (usize, usize) {
+    let deadline = Instant::now() + timeout;
+    let total = futs.len();
+    let mut ok = 0;
+    for fut in futs {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match select(fut, delay_for(remaining)).await {
+            Either::Left(Ok(_)) => ok += 1,
+            Either::Left(Err(err)) => trace!("Shutdown: cannot complete: {:?}", err),
+            Either::Right(_) => break,
+        }
+    }
+    (ok, total - ok)
+}
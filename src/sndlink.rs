@@ -1,33 +1,76 @@
 use std::collections::VecDeque;
-use std::future::Future;
+use std::time::{Duration, Instant};
+use std::{future::Future, rc::Rc};
 
-use ntex::channel::{condition, oneshot};
+use ntex::channel::{condition, mpsc, oneshot};
 use ntex::util::{ByteString, Bytes, BytesMut, Either, Ready};
 use ntex_amqp_codec::protocol::{
-    Attach, DeliveryNumber, DeliveryState, Disposition, Error, Flow, MessageFormat,
-    ReceiverSettleMode, Role, SenderSettleMode, SequenceNo, Target, TerminusDurability,
-    TerminusExpiryPolicy, TransferBody,
+    AmqpError, Attach, DeliveryNumber, DeliveryState, Disposition, Error, Flow, Map, MessageFormat,
+    Role, SenderSettleMode, SequenceNo, Target, TerminusDurability, TerminusExpiryPolicy,
+    TransferBody,
 };
 use ntex_amqp_codec::Encode;
 
 use crate::cell::Cell;
+use crate::codec::types::{Descriptor, List, Variant};
 use crate::error::AmqpProtocolError;
+use crate::interceptor::Interceptors;
+use crate::observer::{LinkObserver, LinkState};
+use crate::retry::{retry, RetryableError};
 use crate::session::{Session, SessionInner, TransferState};
-use crate::{Delivery, Handle};
+use crate::store::{state_to_variant, variant_to_state, LinkStateStore};
+use crate::terminus::Terminus;
+use crate::types::{message_deadline, Outcome};
+use crate::{Delivery, DeliveryPromise, Handle};
 
+/// Sender link handle
+///
+/// Handle is a facade over underlying link, underlying link could be
+/// replaced, for example after reconnect or link redirect. All clones
+/// of the handle use replaced link.
 #[derive(Clone)]
 pub struct SenderLink {
-    pub(crate) inner: Cell<SenderLinkInner>,
+    slot: Cell<Cell<SenderLinkInner>>,
 }
 
 impl std::fmt::Debug for SenderLink {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         fmt.debug_tuple("SenderLink")
-            .field(&std::ops::Deref::deref(&self.inner.get_ref().name))
+            .field(&std::ops::Deref::deref(&self.inner().get_ref().name))
             .finish()
     }
 }
 
+/// Sender link credit event
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CreditEvent {
+    /// Remote receiver granted credit, contains available link credit
+    Granted(u32),
+    /// Link credit is exhausted, new transfers get queued
+    Exhausted,
+    /// Remote receiver requested link drain
+    Drain,
+    /// Link has queued transfers but no credit for longer than
+    /// starvation threshold
+    Starved {
+        /// Number of queued transfers
+        queued: usize,
+        /// Starvation duration
+        duration: Duration,
+    },
+}
+
+/// Sender link behavior when in-flight deliveries limit is reached
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InflightPolicy {
+    /// Wait until one of in-flight deliveries gets settled
+    Wait,
+    /// Fail new delivery with `InflightLimit` error
+    Error,
+    /// Drop oldest delivery that is not sent yet
+    DropOldest,
+}
+
 pub(crate) struct SenderLinkInner {
     pub(crate) id: usize,
     idx: u32,
@@ -36,14 +79,33 @@ pub(crate) struct SenderLinkInner {
     remote_handle: Handle,
     delivery_count: SequenceNo,
     link_credit: u32,
+    pub(crate) max_message_size: Option<u64>,
+    pub(crate) remote_target: Option<Target>,
+    pub(crate) priority: u8,
+    pub(crate) batchable: bool,
+    snd_settle_mode: SenderSettleMode,
     pending_transfers: VecDeque<PendingTransfer>,
+    last_flow: Option<Flow>,
+    starved: Option<(Instant, bool)>,
     error: Option<AmqpProtocolError>,
     closed: bool,
     on_close: condition::Condition,
+    credit_events: Option<mpsc::Sender<CreditEvent>>,
+    delivery_states: Option<mpsc::Sender<(Bytes, DeliveryState)>>,
+    on_latency: Option<Box<dyn Fn(Duration)>>,
+    inflight: usize,
+    max_inflight: Option<(usize, InflightPolicy)>,
+    on_capacity: condition::Condition,
+    store: Option<Rc<dyn LinkStateStore>>,
+    pub(crate) remote_unsettled: Option<Map>,
+    unresolved: Vec<Bytes>,
+    interceptors: Option<Interceptors>,
+    pub(crate) observer: LinkObserver,
 }
 
 struct PendingTransfer {
     idx: u32,
+    expires: Option<Instant>,
     tag: Option<Bytes>,
     body: Option<TransferBody>,
     state: TransferState,
@@ -53,53 +115,243 @@ struct PendingTransfer {
 
 impl SenderLink {
     pub(crate) fn new(inner: Cell<SenderLinkInner>) -> SenderLink {
-        SenderLink { inner }
+        SenderLink {
+            slot: Cell::new(inner),
+        }
+    }
+
+    #[inline]
+    /// Underlying link
+    pub(crate) fn inner(&self) -> &Cell<SenderLinkInner> {
+        self.slot.get_ref()
+    }
+
+    /// Replace underlying link.
+    ///
+    /// All clones of this handle start to use `link`, handle keeps
+    /// working after connection is re-established. Deliveries that are
+    /// in-flight on previous link are not moved. Handles owned by session
+    /// must not be reattached, session keeps links by handle id.
+    pub(crate) fn reattach(&self, link: SenderLink) {
+        let inner = link.inner().clone();
+        trace!(
+            "Reattach sender link {:?} to handle {}",
+            inner.name,
+            inner.id
+        );
+        inner.observer.resume(&self.inner().observer);
+        *self.slot.get_mut() = inner;
     }
 
+    /// Check if handles use the same underlying link
+    pub fn is_same_link(&self, other: &SenderLink) -> bool {
+        self.inner().ptr_eq(other.inner())
+    }
+
+    /// Local link handle
     pub fn id(&self) -> u32 {
-        self.inner.id as u32
+        self.inner().id as u32
     }
 
+    /// Link name
     pub fn name(&self) -> &ByteString {
-        &self.inner.name
+        &self.inner().name
     }
 
+    /// Link handle used by remote peer
     pub fn remote_handle(&self) -> Handle {
-        self.inner.remote_handle
+        self.inner().remote_handle
+    }
+
+    /// Available link credit
+    pub fn credit(&self) -> u32 {
+        self.inner().get_ref().link_credit
+    }
+
+    /// Max message size advertised by remote receiver
+    ///
+    /// Sending larger messages fails with `MessageSizeExceeded` error
+    pub fn max_message_size(&self) -> Option<u64> {
+        self.inner()
+            .get_ref()
+            .max_message_size
+            .filter(|size| *size > 0)
     }
 
     pub fn session(&self) -> &Session {
-        &self.inner.get_ref().session
+        &self.inner().get_ref().session
     }
 
     pub fn session_mut(&mut self) -> &mut Session {
-        &mut self.inner.get_mut().session
+        &mut self.inner().get_mut().session
     }
 
-    pub fn send<T>(&self, body: T) -> impl Future<Output = Result<Disposition, AmqpProtocolError>>
+    pub fn send<T>(&self, body: T) -> impl Future<Output = Result<Outcome, AmqpProtocolError>>
     where
         T: Into<TransferBody>,
     {
-        self.inner.get_mut().send(body, None)
+        self.send_inner(body.into(), None, None)
+    }
+
+    /// Send delivery with specified message format
+    pub fn send_with_format<T>(
+        &self,
+        body: T,
+        format: MessageFormat,
+    ) -> impl Future<Output = Result<Outcome, AmqpProtocolError>>
+    where
+        T: Into<TransferBody>,
+    {
+        self.send_inner(body.into(), None, Some(format))
     }
 
     pub fn send_with_tag<T>(
         &self,
         body: T,
         tag: Bytes,
-    ) -> impl Future<Output = Result<Disposition, AmqpProtocolError>>
+    ) -> impl Future<Output = Result<Outcome, AmqpProtocolError>>
     where
         T: Into<TransferBody>,
     {
-        self.inner.get_mut().send(body, Some(tag))
+        self.send_inner(body.into(), Some(tag), None)
+    }
+
+    fn send_inner(
+        &self,
+        body: TransferBody,
+        tag: Option<Bytes>,
+        format: Option<MessageFormat>,
+    ) -> impl Future<Output = Result<Outcome, AmqpProtocolError>> {
+        let fut = self.send_delivery(body, tag, format);
+        async move { fut.await.map(|disp| Outcome::from_state(disp.state)) }
+    }
+
+    fn send_delivery(
+        &self,
+        mut body: TransferBody,
+        tag: Option<Bytes>,
+        format: Option<MessageFormat>,
+    ) -> impl Future<Output = Result<Disposition, AmqpProtocolError>> {
+        if let Err(err) = self.inner().get_ref().intercept(&mut body) {
+            log::trace!("Outgoing message is rejected by interceptor: {:?}", err);
+            return Either::Left(Ready::Err(AmqpProtocolError::Intercepted(*err)));
+        }
+
+        let policy = self
+            .inner()
+            .get_ref()
+            .session
+            .inner
+            .get_ref()
+            .retry_policy();
+        if policy.is_none() {
+            return Either::Right(Either::Left(self.send_once(body, tag, format)));
+        }
+
+        let link = self.clone();
+        Either::Right(Either::Right(async move {
+            retry(
+                policy,
+                |err| RetryableError::Send(err),
+                || link.send_once(body.clone(), tag.clone(), format),
+            )
+            .await
+        }))
+    }
+
+    fn send_once(
+        &self,
+        body: TransferBody,
+        tag: Option<Bytes>,
+        format: Option<MessageFormat>,
+    ) -> impl Future<Output = Result<Disposition, AmqpProtocolError>> {
+        let inner = self.inner().get_mut();
+        if !inner.wait_capacity() {
+            return Either::Left(inner.send(body, tag, format));
+        }
+
+        let cell = self.inner().clone();
+        Either::Right(async move {
+            while cell.get_ref().wait_capacity() {
+                cell.get_ref().on_capacity.wait().await;
+            }
+            cell.get_mut().send(body, tag, format).await
+        })
+    }
+
+    /// Set max number of in-flight deliveries.
+    ///
+    /// In-flight deliveries are queued locally or sent but not settled yet
+    /// by remote receiver. `policy` defines behavior for new deliveries
+    /// once limit is reached. By default number of deliveries is not limited.
+    pub fn max_inflight(&self, max: usize, policy: InflightPolicy) {
+        let inner = self.inner().get_mut();
+        inner.max_inflight = Some((std::cmp::max(max, 1), policy));
+        inner.on_capacity.notify();
+    }
+
+    /// Set link priority.
+    ///
+    /// If session's outgoing window is exhausted, queued transfers of
+    /// links with higher priority are sent first once peer opens
+    /// the window. Default priority is 0.
+    pub fn set_priority(&self, priority: u8) {
+        self.inner().get_mut().priority = priority;
+    }
+
+    /// Link priority
+    pub fn priority(&self) -> u8 {
+        self.inner().get_ref().priority
+    }
+
+    /// Mark transfers of the link as batchable.
+    ///
+    /// Batchable transfers hint receiver that delivery state updates
+    /// are not urgent and could be batched. Default is false.
+    pub fn set_batchable(&self, batchable: bool) {
+        self.inner().get_mut().batchable = batchable;
+    }
+
+    /// Last flow frame received for this link
+    pub fn last_flow(&self) -> Option<Flow> {
+        self.inner().get_ref().last_flow.clone()
+    }
+
+    /// Number of in-flight deliveries
+    pub fn inflight(&self) -> usize {
+        self.inner().get_ref().inflight
+    }
+
+    /// Start multi-frame delivery.
+    ///
+    /// Delivery body is sent in chunks, in-progress delivery could be aborted.
+    /// Unfinished delivery gets aborted on drop.
+    pub fn start_delivery(&self) -> StreamingDelivery {
+        let (tx, rx) = oneshot::channel();
+        let tag = self
+            .inner()
+            .get_ref()
+            .session
+            .inner
+            .get_mut()
+            .next_delivery_tag();
+
+        StreamingDelivery {
+            tag,
+            rx: Some(rx),
+            tx: Some(tx),
+            link: self.inner().clone(),
+            started: false,
+            finished: false,
+        }
     }
 
     pub fn settle_message(&self, id: DeliveryNumber, state: DeliveryState) {
-        self.inner.get_mut().settle_message(id, state)
+        self.inner().get_mut().settle_message(id, state)
     }
 
     pub fn close(&self) -> impl Future<Output = Result<(), AmqpProtocolError>> {
-        self.inner.get_mut().close(None)
+        self.inner().get_mut().close(None)
     }
 
     pub fn close_with_error<E>(
@@ -109,11 +361,80 @@ impl SenderLink {
     where
         Error: From<E>,
     {
-        self.inner.get_mut().close(Some(error.into()))
+        self.inner().get_mut().close(Some(error.into()))
     }
 
     pub fn on_close(&self) -> condition::Waiter {
-        self.inner.get_ref().on_close.wait()
+        self.inner().get_ref().on_close.wait()
+    }
+
+    /// Get stream of link credit events.
+    ///
+    /// Only one stream could be active, subsequent call replaces previous stream.
+    /// Stream terminates when link get detached.
+    pub fn credit_events(&self) -> mpsc::Receiver<CreditEvent> {
+        let (tx, rx) = mpsc::channel();
+        if let Some(tx) = self.inner().get_mut().credit_events.replace(tx) {
+            tx.close();
+        }
+        rx
+    }
+
+    /// Current link state
+    pub fn state(&self) -> LinkState {
+        self.inner().get_ref().observer.state()
+    }
+
+    /// Get stream of link state transitions.
+    ///
+    /// Stream starts with current state. Subscription survives
+    /// link replacement, in that case stream yields `Resuming`
+    /// followed by state of new link. Only one stream could be active,
+    /// subsequent call replaces previous stream.
+    pub fn state_events(&self) -> mpsc::Receiver<LinkState> {
+        self.inner().get_ref().observer.events()
+    }
+
+    /// Get stream of remote delivery state updates.
+    ///
+    /// Stream yields delivery tag and remote delivery state for each
+    /// disposition received for this link. Only one stream could be active,
+    /// subsequent call replaces previous stream.
+    pub fn delivery_states(&self) -> mpsc::Receiver<(Bytes, DeliveryState)> {
+        let (tx, rx) = mpsc::channel();
+        if let Some(tx) = self.inner().get_mut().delivery_states.replace(tx) {
+            tx.close();
+        }
+        rx
+    }
+
+    /// Deliveries restored from state store that peer did not settle
+    ///
+    /// Peer has no terminal outcome for these deliveries on link attach,
+    /// deliveries are removed from the store and could be re-sent with
+    /// `send_with_tag()`.
+    pub fn unresolved(&self) -> Vec<Bytes> {
+        self.inner().get_ref().unresolved.clone()
+    }
+
+    /// Set disposition latency callback.
+    ///
+    /// Callback receives time between sending a transfer and
+    /// receiving its disposition, could be used to feed latency histogram.
+    /// Subsequent call replaces previous callback.
+    pub fn on_disposition_latency<F>(&self, f: F)
+    where
+        F: Fn(Duration) + 'static,
+    {
+        self.inner().get_mut().on_latency = Some(Box::new(f));
+    }
+
+    /// Request link flow state from the peer.
+    ///
+    /// Sends link `Flow` frame with `echo` flag set, could be used
+    /// to resynchronize link credit with remote receiver.
+    pub fn request_flow_echo(&self) {
+        self.inner().get_mut().send_flow(true);
     }
 }
 
@@ -133,10 +454,28 @@ impl SenderLinkInner {
             session: Session::new(session),
             remote_handle: handle,
             link_credit: 0,
+            max_message_size: None,
+            remote_target: None,
+            priority: 0,
+            batchable: false,
+            snd_settle_mode: SenderSettleMode::Mixed,
             pending_transfers: VecDeque::new(),
+            last_flow: None,
+            starved: None,
             error: None,
             closed: false,
             on_close: condition::Condition::new(),
+            credit_events: None,
+            delivery_states: None,
+            on_latency: None,
+            inflight: 0,
+            max_inflight: None,
+            on_capacity: condition::Condition::new(),
+            store: None,
+            remote_unsettled: None,
+            unresolved: Vec::new(),
+            interceptors: None,
+            observer: LinkObserver::new(LinkState::Attached),
         }
     }
 
@@ -157,10 +496,28 @@ impl SenderLinkInner {
             session: Session::new(session),
             remote_handle: frame.handle(),
             link_credit: 0,
+            max_message_size: frame.max_message_size(),
+            remote_target: frame.target.clone(),
+            priority: 0,
+            batchable: false,
+            snd_settle_mode: SenderSettleMode::Mixed,
             pending_transfers: VecDeque::new(),
+            last_flow: None,
+            starved: None,
             error: None,
             closed: false,
             on_close: condition::Condition::new(),
+            credit_events: None,
+            delivery_states: None,
+            on_latency: None,
+            inflight: 0,
+            max_inflight: None,
+            on_capacity: condition::Condition::new(),
+            store: None,
+            remote_unsettled: None,
+            unresolved: Vec::new(),
+            interceptors: None,
+            observer: LinkObserver::new(LinkState::Attaching),
         }
     }
 
@@ -187,7 +544,156 @@ impl SenderLinkInner {
         }
 
         self.error = Some(err);
+        self.inflight = 0;
         self.on_close.notify();
+        self.on_capacity.notify();
+        if let Some(tx) = self.credit_events.take() {
+            tx.close();
+        }
+        if let Some(tx) = self.delivery_states.take() {
+            tx.close();
+        }
+    }
+
+    pub(crate) fn notify_delivery_state(&mut self, tag: Bytes, state: DeliveryState) {
+        if let Some(ref tx) = self.delivery_states {
+            if tx.send((tag, state)).is_err() {
+                self.delivery_states = None;
+            }
+        }
+    }
+
+    /// Record settled delivery in state store
+    pub(crate) fn store_settled(&self, tag: &Bytes, state: Option<&DeliveryState>) {
+        if let Some(ref store) = self.store {
+            store.delivery_settled(&self.name, tag, state);
+        }
+    }
+
+    /// Reconcile stored unsettled deliveries with peer's unsettled map
+    ///
+    /// Deliveries with terminal outcome on peer's side are settled,
+    /// other deliveries must be re-sent by application.
+    pub(crate) fn resume(&mut self) {
+        let store = if let Some(ref store) = self.store {
+            store.clone()
+        } else {
+            return;
+        };
+        let remote = self.remote_unsettled.take().unwrap_or_default();
+
+        for (tag, _) in store.unsettled(&self.name) {
+            let state = remote
+                .get(&Variant::Binary(tag.clone()))
+                .and_then(variant_to_state);
+            match state {
+                Some(state) if is_terminal(&state) => {
+                    trace!("Delivery {:?} is settled by peer with {:?}", tag, state);
+                    store.delivery_settled(&self.name, &tag, Some(&state));
+                    self.notify_delivery_state(tag, state);
+                }
+                _ => {
+                    trace!("Delivery {:?} is not settled by peer", tag);
+                    store.delivery_settled(&self.name, &tag, None);
+                    self.unresolved.push(tag);
+                }
+            }
+        }
+    }
+
+    /// Remote receiver settled in-flight delivery
+    pub(crate) fn delivery_settled(&mut self) {
+        self.inflight = self.inflight.saturating_sub(1);
+        self.on_capacity.notify();
+    }
+
+    /// Deliveries are sent pre-settled and never get in-flight
+    fn presettled(&self) -> bool {
+        self.snd_settle_mode == SenderSettleMode::Settled
+    }
+
+    fn is_full(&self) -> bool {
+        self.max_inflight
+            .map(|(max, _)| self.inflight >= max)
+            .unwrap_or(false)
+    }
+
+    /// New delivery has to wait for in-flight delivery settlement
+    fn wait_capacity(&self) -> bool {
+        self.error.is_none()
+            && self.is_full()
+            && matches!(self.max_inflight, Some((_, InflightPolicy::Wait)))
+    }
+
+    /// Drop oldest delivery from pending queue
+    fn drop_oldest(&mut self) -> bool {
+        let pos = self
+            .pending_transfers
+            .iter()
+            .position(|tr| matches!(tr.state, TransferState::First(_) | TransferState::Only(_)));
+
+        if let Some(pos) = pos {
+            if let Some(tr) = self.pending_transfers.remove(pos) {
+                if let TransferState::First(tx) | TransferState::Only(tx) = tr.state {
+                    trace!(
+                        "Drop oldest pending delivery for sender link {:?}",
+                        self.name
+                    );
+                    let _ = tx.send(Err(AmqpProtocolError::InflightLimit));
+                }
+            }
+            // remove rest of partial delivery
+            while let Some(tr) = self.pending_transfers.get(pos) {
+                if let TransferState::Continue | TransferState::Last | TransferState::Aborted(_) =
+                    tr.state
+                {
+                    self.pending_transfers.remove(pos);
+                } else {
+                    break;
+                }
+            }
+            self.inflight = self.inflight.saturating_sub(1);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(crate) fn notify_latency(&self, latency: Duration) {
+        if let Some(ref f) = self.on_latency {
+            (*f)(latency);
+        }
+    }
+
+    fn notify_credit(&mut self, event: CreditEvent) {
+        if let Some(ref tx) = self.credit_events {
+            if tx.send(event).is_err() {
+                self.credit_events = None;
+            }
+        }
+    }
+
+    /// Report credit starvation that lasts longer than `threshold`
+    pub(crate) fn check_starvation(&mut self, threshold: Duration) {
+        if self.link_credit > 0 || self.pending_transfers.is_empty() {
+            self.starved = None;
+            return;
+        }
+
+        let (since, reported) = self.starved.get_or_insert((Instant::now(), false));
+        let duration = since.elapsed();
+        if !*reported && duration >= threshold {
+            *reported = true;
+            let queued = self.pending_transfers.len();
+            log::warn!(
+                "Sender link {:?} is starved for {:?}, queued transfers: {}, last flow: {:?}",
+                self.name,
+                duration,
+                queued,
+                self.last_flow
+            );
+            self.notify_credit(CreditEvent::Starved { queued, duration });
+        }
     }
 
     pub(crate) fn close(
@@ -199,6 +705,7 @@ impl SenderLinkInner {
         } else {
             self.closed = true;
             self.on_close.notify();
+            self.observer.set(LinkState::DetachSent);
 
             let (tx, rx) = oneshot::channel();
 
@@ -218,6 +725,8 @@ impl SenderLinkInner {
     }
 
     pub(crate) fn apply_flow(&mut self, flow: &Flow) {
+        self.last_flow = Some(flow.clone());
+
         // #2.7.6
         if let Some(credit) = flow.link_credit() {
             trace!(
@@ -233,15 +742,44 @@ impl SenderLinkInner {
                 .unwrap_or(0)
                 .saturating_add(credit)
                 .saturating_sub(self.delivery_count);
-            self.link_credit += delta;
+            self.link_credit = delta;
 
             let session = self.session.inner.get_mut();
 
-            // credit became available => drain pending_transfers
-            while self.link_credit > 0 {
+            // credit became available => drain pending_transfers,
+            // only first transfer of the delivery consumes credit
+            while let Some(first) = self
+                .pending_transfers
+                .front()
+                .map(|tr| matches!(tr.state, TransferState::First(_) | TransferState::Only(_)))
+            {
+                if first && self.link_credit == 0 {
+                    break;
+                }
                 if let Some(transfer) = self.pending_transfers.pop_front() {
-                    self.link_credit -= 1;
-                    self.delivery_count = self.delivery_count.saturating_add(1);
+                    // drop expired message, with rest of its transfers
+                    if transfer
+                        .expires
+                        .map(|t| t <= Instant::now())
+                        .unwrap_or(false)
+                    {
+                        trace!("Drop expired delivery for sender link {:?}", self.name);
+                        if let TransferState::First(tx) | TransferState::Only(tx) = transfer.state {
+                            let _ = tx.send(Err(AmqpProtocolError::MessageExpired));
+                        }
+                        while self.pending_transfers.front().map_or(false, |tr| {
+                            matches!(tr.state, TransferState::Continue | TransferState::Last)
+                        }) {
+                            self.pending_transfers.pop_front();
+                        }
+                        self.inflight = self.inflight.saturating_sub(1);
+                        self.on_capacity.notify();
+                        continue;
+                    }
+                    if first {
+                        self.link_credit -= 1;
+                        self.delivery_count = self.delivery_count.saturating_add(1);
+                    }
                     session.send_transfer(
                         self.id as u32,
                         transfer.idx,
@@ -255,30 +793,117 @@ impl SenderLinkInner {
                     break;
                 }
             }
+
+            if delta > 0 {
+                if self.link_credit > 0 {
+                    self.notify_credit(CreditEvent::Granted(self.link_credit));
+                } else {
+                    self.notify_credit(CreditEvent::Exhausted);
+                }
+            }
+        }
+
+        // #2.6.7 drain, consume all remaining credit and
+        // report drained state to the receiver
+        if flow.drain() {
+            self.notify_credit(CreditEvent::Drain);
+            let credit = std::mem::take(&mut self.link_credit);
+            self.delivery_count = self.delivery_count.saturating_add(credit);
+            self.send_flow_inner(true, false);
+            if credit > 0 {
+                self.notify_credit(CreditEvent::Exhausted);
+            }
+            return;
         }
 
         if flow.echo() {
-            // todo: send flow
+            self.send_flow(false);
+        }
+    }
+
+    /// Send link flow frame with current sender state
+    pub(crate) fn send_flow(&mut self, echo: bool) {
+        self.send_flow_inner(false, echo)
+    }
+
+    fn send_flow_inner(&mut self, drain: bool, echo: bool) {
+        self.session.inner.get_mut().snd_link_flow(
+            self.id as u32,
+            self.delivery_count,
+            self.link_credit,
+            self.pending_transfers.len() as u32,
+            drain,
+            echo,
+        );
+    }
+
+    /// Run connection and link interceptors
+    fn intercept(&self, body: &mut TransferBody) -> Result<(), Box<Error>> {
+        self.session
+            .inner
+            .get_ref()
+            .interceptors()
+            .apply_outgoing(body)?;
+        if let Some(ref chain) = self.interceptors {
+            chain.apply_outgoing(body)?;
         }
+        Ok(())
     }
 
-    pub(crate) fn send<T: Into<TransferBody>>(&mut self, body: T, tag: Option<Bytes>) -> Delivery {
+    pub(crate) fn send<T: Into<TransferBody>>(
+        &mut self,
+        body: T,
+        tag: Option<Bytes>,
+        format: Option<MessageFormat>,
+    ) -> Delivery {
         if let Some(ref err) = self.error {
             Delivery::Resolved(Err(err.clone()))
+        } else if self.closed {
+            // link is detached locally, e.g. by `Connection::shutdown()`
+            Delivery::Resolved(Err(AmqpProtocolError::LinkDetached(None)))
         } else {
             let body = body.into();
-            let message_format = body.message_format();
-            let (delivery_tx, delivery_rx) = oneshot::channel();
+            if let Some(max_size) = self.max_message_size.filter(|size| *size > 0) {
+                if body.len() as u64 > max_size {
+                    return Delivery::Resolved(Err(AmqpProtocolError::MessageSizeExceeded(
+                        max_size,
+                    )));
+                }
+            }
+            if self.is_full() {
+                let dropped = matches!(self.max_inflight, Some((_, InflightPolicy::DropOldest)))
+                    && self.drop_oldest();
+                if !dropped {
+                    return Delivery::Resolved(Err(AmqpProtocolError::InflightLimit));
+                }
+            }
+            if self
+                .session
+                .inner
+                .get_ref()
+                .pending_limit_reached(self.id as Handle, self.pending_transfers.len())
+            {
+                return Delivery::Resolved(Err(AmqpProtocolError::BufferLimit));
+            }
+            if !self.presettled() {
+                self.inflight += 1;
+            }
 
-            let max_frame_size = self.session.inner.get_ref().max_frame_size();
-            let max_frame_size = if max_frame_size > 2048 {
-                max_frame_size - 2048
-            } else if max_frame_size == 0 {
-                usize::MAX
+            // persisted deliveries require delivery tag
+            let tag = if let Some(ref store) = self.store {
+                let tag = tag.unwrap_or_else(|| self.session.inner.get_mut().next_delivery_tag());
+                store.delivery_sent(&self.name, &tag);
+                Some(tag)
             } else {
-                max_frame_size
+                tag
             };
 
+            let message_format = format.or_else(|| body.message_format());
+            let expires = message_expiry(&body);
+            let (delivery_tx, delivery_rx) = oneshot::channel();
+
+            let max_frame_size = self.max_transfer_size();
+
             // body is larger than allowed frame size, send body as a set of transfers
             if body.len() > max_frame_size {
                 let mut body = match body {
@@ -296,6 +921,7 @@ impl SenderLinkInner {
                     tag,
                     TransferState::First(delivery_tx),
                     message_format,
+                    expires,
                 );
 
                 loop {
@@ -303,7 +929,13 @@ impl SenderLinkInner {
 
                     // last chunk
                     if body.is_empty() {
-                        self.send_inner(chunk.into(), None, TransferState::Last, message_format);
+                        self.send_inner(
+                            chunk.into(),
+                            None,
+                            TransferState::Last,
+                            message_format,
+                            None,
+                        );
                         break;
                     } else {
                         self.send_inner(
@@ -311,25 +943,52 @@ impl SenderLinkInner {
                             None,
                             TransferState::Continue,
                             message_format,
+                            None,
                         );
                     }
                 }
             } else {
-                self.send_inner(body, tag, TransferState::Only(delivery_tx), message_format);
+                self.send_inner(
+                    body,
+                    tag,
+                    TransferState::Only(delivery_tx),
+                    message_format,
+                    expires,
+                );
             }
 
             Delivery::Pending(delivery_rx)
         }
     }
 
+    /// Max size of transfer body
+    fn max_transfer_size(&self) -> usize {
+        let max_frame_size = self.session.inner.get_ref().max_frame_size();
+        if max_frame_size > 2048 {
+            max_frame_size - 2048
+        } else if max_frame_size == 0 {
+            usize::MAX
+        } else {
+            max_frame_size
+        }
+    }
+
     fn send_inner(
         &mut self,
         body: TransferBody,
         tag: Option<Bytes>,
         state: TransferState,
         message_format: Option<MessageFormat>,
+        expires: Option<Instant>,
     ) {
-        if self.link_credit == 0 {
+        // #2.7.3 sender in settled mode sends pre-settled deliveries,
+        // delivery is complete once first transfer is sent
+        let settled = if self.presettled() { Some(true) } else { None };
+
+        // only first transfer of the delivery consumes credit,
+        // rest of the transfers follow it
+        let first = matches!(state, TransferState::First(_) | TransferState::Only(_));
+        if (first && self.link_credit == 0) || (!first && !self.pending_transfers.is_empty()) {
             log::trace!(
                 "Sender link credit is 0, push to pending queue hnd:{} {:?}, queue size: {}",
                 self.id as u32,
@@ -340,20 +999,26 @@ impl SenderLinkInner {
                 tag,
                 state,
                 message_format,
-                settle: Some(false),
+                settle: settled.or(Some(false)),
                 body: Some(body),
                 idx: self.idx,
+                expires,
             });
         } else {
-            self.link_credit -= 1;
-            self.delivery_count = self.delivery_count.saturating_add(1);
+            if first {
+                self.link_credit -= 1;
+                self.delivery_count = self.delivery_count.saturating_add(1);
+                if self.link_credit == 0 {
+                    self.notify_credit(CreditEvent::Exhausted);
+                }
+            }
             self.session.inner.get_mut().send_transfer(
                 self.id as u32,
                 self.idx,
                 Some(body),
                 state,
                 tag,
-                None,
+                settled,
                 message_format,
             );
         }
@@ -373,11 +1038,158 @@ impl SenderLinkInner {
     }
 }
 
+/// Multi-frame delivery
+pub struct StreamingDelivery {
+    link: Cell<SenderLinkInner>,
+    tag: Bytes,
+    tx: Option<DeliveryPromise>,
+    rx: Option<oneshot::Receiver<Result<Disposition, AmqpProtocolError>>>,
+    started: bool,
+    finished: bool,
+}
+
+impl StreamingDelivery {
+    /// Delivery tag
+    pub fn tag(&self) -> &Bytes {
+        &self.tag
+    }
+
+    /// Send delivery chunk
+    pub fn send(&mut self, chunk: Bytes) -> Result<(), Box<AmqpProtocolError>> {
+        self.send_chunk(chunk, false)
+    }
+
+    /// Send values as `amqp-sequence` message section
+    ///
+    /// Long sequence could be sent as multiple sections, each call
+    /// sends one section. Use `finish()` with empty chunk to complete
+    /// delivery.
+    pub fn send_sequence(&mut self, values: List) -> Result<(), Box<AmqpProtocolError>> {
+        let descriptor = Descriptor::Ulong(118);
+        let mut buf = BytesMut::with_capacity(descriptor.encoded_size() + values.encoded_size());
+        descriptor.encode(&mut buf);
+        values.encode(&mut buf);
+        self.send_chunk(buf.freeze(), false)
+    }
+
+    /// Send last delivery chunk and wait for delivery outcome
+    pub fn finish(
+        mut self,
+        chunk: Bytes,
+    ) -> impl Future<Output = Result<Outcome, AmqpProtocolError>> {
+        let res = self.send_chunk(chunk, true);
+        let rx = self.rx.take();
+        self.finished = true;
+
+        async move {
+            res.map_err(|err| *err)?;
+            match rx {
+                Some(rx) => match rx.await {
+                    Ok(res) => res.map(|disp| Outcome::from_state(disp.state)),
+                    Err(_) => Err(AmqpProtocolError::Disconnected),
+                },
+                None => Err(AmqpProtocolError::Disconnected),
+            }
+        }
+    }
+
+    /// Abort in-progress delivery
+    pub fn abort(mut self) {
+        self.abort_inner();
+    }
+
+    fn abort_inner(&mut self) {
+        if self.started && !self.finished {
+            self.finished = true;
+
+            let link = self.link.get_mut();
+            if link.error.is_none() {
+                trace!("Abort delivery {:?} for link {:?}", self.tag, link.name);
+                link.send_inner(
+                    TransferBody::Data(Bytes::new()),
+                    Some(self.tag.clone()),
+                    TransferState::Aborted(self.tag.clone()),
+                    None,
+                    None,
+                );
+                link.delivery_settled();
+            }
+        }
+    }
+
+    fn send_chunk(&mut self, mut chunk: Bytes, last: bool) -> Result<(), Box<AmqpProtocolError>> {
+        let link = self.link.get_mut();
+        if let Some(ref err) = link.error {
+            return Err(Box::new(err.clone()));
+        }
+        if self.finished {
+            return Err(Box::new(AmqpProtocolError::Disconnected));
+        }
+        if !self.started {
+            if link.is_full() {
+                return Err(Box::new(AmqpProtocolError::InflightLimit));
+            }
+            if !link.presettled() {
+                link.inflight += 1;
+            }
+        }
+
+        let max_size = link.max_transfer_size();
+        loop {
+            let data = chunk.split_to(std::cmp::min(max_size, chunk.len()));
+            let is_last = last && chunk.is_empty();
+
+            let state = if !self.started {
+                self.started = true;
+                let tx = self.tx.take().unwrap();
+                if is_last {
+                    TransferState::Only(tx)
+                } else {
+                    TransferState::First(tx)
+                }
+            } else if is_last {
+                TransferState::Last
+            } else {
+                TransferState::Continue
+            };
+            link.send_inner(data.into(), Some(self.tag.clone()), state, None, None);
+
+            if chunk.is_empty() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Expiry time of message, based on header ttl and absolute expiry time
+fn message_expiry(body: &TransferBody) -> Option<Instant> {
+    if let TransferBody::Message(ref msg) = body {
+        message_deadline(Instant::now(), msg.header(), msg.properties())
+    } else {
+        None
+    }
+}
+
+impl Drop for StreamingDelivery {
+    fn drop(&mut self) {
+        self.abort_inner();
+    }
+}
+
 pub struct SenderLinkBuilder {
     frame: Attach,
     session: Cell<SessionInner>,
+    store: Option<Rc<dyn LinkStateStore>>,
+    validate: Option<TargetValidator>,
+    priority: u8,
+    batchable: bool,
+    interceptors: Option<Interceptors>,
+    redirects: u8,
 }
 
+type TargetValidator = Box<dyn Fn(Option<Terminus<'_>>) -> Result<(), ByteString>>;
+
 impl SenderLinkBuilder {
     pub(crate) fn new(name: ByteString, address: ByteString, session: Cell<SessionInner>) -> Self {
         let target = Target {
@@ -389,12 +1201,14 @@ impl SenderLinkBuilder {
             dynamic_node_properties: None,
             capabilities: None,
         };
+        let (snd_settle_mode, rcv_settle_mode) = session.get_ref().settle_modes();
+        let store = session.get_ref().link_store();
         let frame = Attach {
             name,
             handle: 0_u32,
             role: Role::Sender,
-            snd_settle_mode: SenderSettleMode::Mixed,
-            rcv_settle_mode: ReceiverSettleMode::First,
+            snd_settle_mode,
+            rcv_settle_mode,
             source: None,
             target: Some(target),
             unsettled: None,
@@ -406,7 +1220,16 @@ impl SenderLinkBuilder {
             properties: None,
         };
 
-        SenderLinkBuilder { frame, session }
+        SenderLinkBuilder {
+            frame,
+            session,
+            store,
+            validate: None,
+            priority: 0,
+            batchable: false,
+            interceptors: None,
+            redirects: 0,
+        }
     }
 
     pub fn max_message_size(mut self, size: u64) -> Self {
@@ -422,13 +1245,192 @@ impl SenderLinkBuilder {
         self
     }
 
-    pub async fn open(self) -> Result<SenderLink, AmqpProtocolError> {
-        let result = self.session.get_mut().open_sender_link(self.frame).await;
+    /// Set validation callback for target granted by remote receiver.
+    ///
+    /// Callback is called with target from attach response, if callback
+    /// fails link is detached and open fails with
+    /// `AmqpProtocolError::TargetRejected` error.
+    pub fn validate_target<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Option<Terminus<'_>>) -> Result<(), ByteString> + 'static,
+    {
+        self.validate = Some(Box::new(f));
+        self
+    }
+
+    /// Set interceptors for outgoing messages of this link
+    ///
+    /// Link interceptors run after connection interceptors.
+    /// Streaming deliveries are not intercepted.
+    pub fn interceptors(mut self, interceptors: Interceptors) -> Self {
+        self.interceptors = Some(interceptors);
+        self
+    }
 
-        match result {
-            Ok(Ok(link)) => Ok(link),
-            Ok(Err(e)) => Err(e),
-            Err(_) => Err(AmqpProtocolError::Disconnected),
+    /// Follow link redirects.
+    ///
+    /// If peer detaches link with `amqp:link:redirect` error that contains
+    /// node address, link is re-attached to that address on the same session,
+    /// at most `max` times. Handle is updated in place.
+    /// Redirects to other containers are not followed, redirect details are
+    /// available with `AmqpProtocolError::redirect()`.
+    pub fn follow_redirects(mut self, max: u8) -> Self {
+        self.redirects = max;
+        self
+    }
+
+    /// Set link priority, see `SenderLink::set_priority()`
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Mark transfers as batchable, see `SenderLink::set_batchable()`
+    pub fn batchable(mut self, batchable: bool) -> Self {
+        self.batchable = batchable;
+        self
+    }
+
+    /// Set link settlement state store.
+    ///
+    /// Unsettled deliveries from the store are reported to the peer on attach,
+    /// link records sent deliveries and their outcomes. By default connection's
+    /// store is used if link resume is enabled, see `Configuration::link_resume()`.
+    pub fn state_store(mut self, store: Rc<dyn LinkStateStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub async fn open(mut self) -> Result<SenderLink, AmqpProtocolError> {
+        if let Some(ref store) = self.store {
+            let unsettled = store.unsettled(&self.frame.name);
+            if !unsettled.is_empty() {
+                self.frame.unsettled = Some(
+                    unsettled
+                        .into_iter()
+                        .map(|(tag, state)| {
+                            let state = state
+                                .as_ref()
+                                .map(state_to_variant)
+                                .unwrap_or(Variant::Null);
+                            (Variant::Binary(tag), state)
+                        })
+                        .collect(),
+                );
+            }
+        }
+        let session = self.session;
+        let frame = self.frame;
+        let policy = session.get_ref().retry_policy();
+
+        let link = retry(
+            policy,
+            |err| RetryableError::Attach(err),
+            || {
+                let (token, rx) = session.get_mut().open_sender_link(frame.clone());
+                SessionInner::wait_attach(session.clone(), token, rx)
+            },
+        )
+        .await?;
+
+        if let Some(validate) = self.validate {
+            let res = validate(
+                link.inner()
+                    .get_ref()
+                    .remote_target
+                    .as_ref()
+                    .map(Terminus::from_target),
+            );
+            if let Err(reason) = res {
+                log::trace!("Granted target is rejected: {:?}", reason);
+                let err = Error {
+                    condition: AmqpError::PreconditionFailed.into(),
+                    description: Some(reason.clone()),
+                    info: None,
+                };
+                let _ = link.close_with_error(err).await;
+                return Err(AmqpProtocolError::TargetRejected(reason));
+            }
+        }
+
+        let inner = link.inner().get_mut();
+        inner.store = self.store;
+        inner.priority = self.priority;
+        inner.batchable = self.batchable;
+        inner.snd_settle_mode = frame.snd_settle_mode();
+        inner.interceptors = self.interceptors;
+        inner.resume();
+
+        if self.redirects > 0 {
+            ntex::rt::spawn(follow_redirects(link.clone(), frame, self.redirects));
         }
+        Ok(link)
     }
 }
+
+async fn follow_redirects(link: SenderLink, mut frame: Attach, mut hops: u8) {
+    loop {
+        if link.inner().get_ref().error.is_none() && !link.inner().get_ref().closed {
+            link.on_close().await;
+        }
+
+        let address = link
+            .inner()
+            .get_ref()
+            .error
+            .as_ref()
+            .and_then(|err| err.redirect())
+            .and_then(|redirect| redirect.address);
+        let address = match address {
+            Some(address) if hops > 0 => address,
+            _ => return,
+        };
+        hops -= 1;
+
+        trace!(
+            "Sender link {:?} is redirected to {:?}",
+            frame.name(),
+            address
+        );
+        if let Some(ref mut target) = frame.target {
+            target.address = Some(address);
+        }
+
+        let session = link.session().inner.clone();
+        let (token, rx) = session.get_mut().open_sender_link(frame.clone());
+        match SessionInner::wait_attach(session, token, rx).await {
+            Ok(new_link) => {
+                let (store, priority, batchable, interceptors) = {
+                    let inner = link.inner().get_ref();
+                    (
+                        inner.store.clone(),
+                        inner.priority,
+                        inner.batchable,
+                        inner.interceptors.clone(),
+                    )
+                };
+                let inner = new_link.inner().get_mut();
+                inner.store = store;
+                inner.priority = priority;
+                inner.batchable = batchable;
+                inner.interceptors = interceptors;
+                inner.resume();
+                link.reattach(new_link);
+            }
+            Err(err) => {
+                trace!("Cannot follow link redirect: {:?}", err);
+                return;
+            }
+        }
+    }
+}
+
+fn is_terminal(state: &DeliveryState) -> bool {
+    matches!(
+        state,
+        DeliveryState::Accepted(_)
+            | DeliveryState::Rejected(_)
+            | DeliveryState::Released(_)
+            | DeliveryState::Modified(_)
+    )
+}
@@ -0,0 +1,101 @@
+use std::{any::Any, io, net::SocketAddr, time::Duration};
+
+use ntex::rt::net::TcpStream;
+use socket2::{SockRef, TcpKeepalive};
+
+/// Tcp socket options
+///
+/// Options are applied only if connection io object is `TcpStream`
+#[derive(Debug, Clone, Default)]
+pub struct SocketOptions {
+    nodelay: Option<bool>,
+    keepalive: Option<Duration>,
+    keepalive_interval: Option<Duration>,
+    send_buffer_size: Option<usize>,
+    recv_buffer_size: Option<usize>,
+}
+
+impl SocketOptions {
+    /// Create new socket options, all options use system defaults
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set `TCP_NODELAY` option
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = Some(nodelay);
+        self
+    }
+
+    /// Enable tcp keepalive, `time` is idle time before first keepalive probe
+    pub fn keepalive(mut self, time: Duration) -> Self {
+        self.keepalive = Some(time);
+        self
+    }
+
+    /// Set interval between keepalive probes
+    ///
+    /// Option is ignored on platforms that do not support it
+    pub fn keepalive_interval(mut self, interval: Duration) -> Self {
+        self.keepalive_interval = Some(interval);
+        self
+    }
+
+    /// Set `SO_SNDBUF` size
+    pub fn send_buffer_size(mut self, size: usize) -> Self {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Set `SO_RCVBUF` size
+    pub fn recv_buffer_size(mut self, size: usize) -> Self {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    pub(crate) fn peer_addr<Io: 'static>(io: &Io) -> Option<SocketAddr> {
+        (io as &dyn Any)
+            .downcast_ref::<TcpStream>()
+            .and_then(|stream| stream.peer_addr().ok())
+    }
+
+    pub(crate) fn apply<Io: 'static>(&self, io: &Io) {
+        if let Some(stream) = (io as &dyn Any).downcast_ref::<TcpStream>() {
+            if let Err(e) = self.apply_tcp(stream) {
+                log::warn!("Cannot apply socket options: {:?}", e);
+            }
+        }
+    }
+
+    fn apply_tcp(&self, stream: &TcpStream) -> io::Result<()> {
+        if let Some(nodelay) = self.nodelay {
+            stream.set_nodelay(nodelay)?;
+        }
+
+        let sock = SockRef::from(stream);
+        if let Some(time) = self.keepalive {
+            #[allow(unused_mut)]
+            let mut params = TcpKeepalive::new().with_time(time);
+            #[cfg(any(
+                target_os = "android",
+                target_os = "freebsd",
+                target_os = "linux",
+                target_vendor = "apple",
+                windows,
+            ))]
+            {
+                if let Some(interval) = self.keepalive_interval {
+                    params = params.with_interval(interval);
+                }
+            }
+            sock.set_tcp_keepalive(&params)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            sock.set_send_buffer_size(size)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            sock.set_recv_buffer_size(size)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+use std::{fmt, future::Future};
+
+use ntex::util::select;
+
+use crate::{cell::Cell, connection::ConnectionInner};
+
+/// Connection scoped task spawner
+///
+/// Tasks are cancelled when connection is closed, tasks spawned
+/// after connection close are dropped without polling.
+#[derive(Clone)]
+pub struct Spawner(pub(crate) Cell<ConnectionInner>);
+
+impl Spawner {
+    /// Spawn task on current thread
+    pub fn spawn<F>(&self, fut: F)
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        if self.is_closed() {
+            log::trace!("Connection is closed, drop spawned task");
+            return;
+        }
+        let on_close = self.0.get_ref().on_close.wait();
+        ntex::rt::spawn(async move {
+            let _ = select(fut, on_close).await;
+        });
+    }
+
+    /// Check if connection is closed
+    pub fn is_closed(&self) -> bool {
+        self.0.get_ref().error.is_some()
+    }
+}
+
+impl fmt::Debug for Spawner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Spawner")
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
@@ -1,15 +1,52 @@
-use std::rc::Rc;
+use std::{cell::Ref, cell::RefCell, cell::RefMut, rc::Rc};
+
+use ntex::util::Extensions;
+
+use crate::{Connection, Spawner};
 
 #[derive(Debug)]
-pub struct State<St>(Rc<St>);
+pub struct State<St>(Rc<StateInner<St>>);
+
+#[derive(Debug)]
+struct StateInner<St> {
+    st: St,
+    ext: RefCell<Extensions>,
+    spawner: Spawner,
+}
 
 impl<St> State<St> {
-    pub(crate) fn new(st: St) -> Self {
-        State(Rc::new(st))
+    pub(crate) fn new(st: St, con: &Connection) -> Self {
+        Self::with_extensions(st, Extensions::new(), con)
+    }
+
+    pub(crate) fn with_extensions(st: St, ext: Extensions, con: &Connection) -> Self {
+        State(Rc::new(StateInner {
+            st,
+            ext: RefCell::new(ext),
+            spawner: con.spawner(),
+        }))
     }
 
     pub fn get_ref(&self) -> &St {
-        self.0.as_ref()
+        &self.0.st
+    }
+
+    /// Connection extensions
+    ///
+    /// Typed per-connection data, populated during handshake
+    /// and shared by all services of the connection.
+    pub fn extensions(&self) -> Ref<'_, Extensions> {
+        self.0.ext.borrow()
+    }
+
+    /// Mutable connection extensions
+    pub fn extensions_mut(&self) -> RefMut<'_, Extensions> {
+        self.0.ext.borrow_mut()
+    }
+
+    /// Connection scoped task spawner
+    pub fn spawner(&self) -> &Spawner {
+        &self.0.spawner
     }
 }
 
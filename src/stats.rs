@@ -0,0 +1,99 @@
+use std::{cell::Cell, cell::RefCell, rc::Rc};
+
+use ntex::util::{ByteString, HashMap};
+
+/// Per-address router statistics
+///
+/// Counters are keyed by link target address. Statistics handle is
+/// shared between clones and is local to server worker.
+#[derive(Clone, Default)]
+pub struct RouterStats(Rc<RefCell<HashMap<ByteString, Rc<Counters>>>>);
+
+/// Counters of single address
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct AddressStats {
+    /// Number of attached links
+    pub links: usize,
+    /// Number of received transfers
+    pub transfers: u64,
+    /// Number of received payload bytes
+    pub bytes: u64,
+    /// Number of rejected deliveries
+    pub rejected: u64,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    links: Cell<usize>,
+    transfers: Cell<u64>,
+    bytes: Cell<u64>,
+    rejected: Cell<u64>,
+}
+
+impl RouterStats {
+    /// Create empty statistics
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Statistics of address
+    pub fn get(&self, address: &str) -> Option<AddressStats> {
+        self.0.borrow().get(address).map(|c| c.snapshot())
+    }
+
+    /// Statistics of all known addresses
+    pub fn addresses(&self) -> Vec<(ByteString, AddressStats)> {
+        self.0
+            .borrow()
+            .iter()
+            .map(|(addr, c)| (addr.clone(), c.snapshot()))
+            .collect()
+    }
+
+    /// Remove addresses without attached links
+    pub fn cleanup(&self) {
+        self.0.borrow_mut().retain(|_, c| c.links.get() != 0);
+    }
+
+    /// Counters for newly attached link
+    pub(crate) fn attach(&self, address: &ByteString) -> Rc<Counters> {
+        let counters = self
+            .0
+            .borrow_mut()
+            .entry(address.clone())
+            .or_default()
+            .clone();
+        counters.links.set(counters.links.get() + 1);
+        counters
+    }
+}
+
+impl Counters {
+    pub(crate) fn detach(&self) {
+        self.links.set(self.links.get().saturating_sub(1));
+    }
+
+    pub(crate) fn transfer(&self, size: usize) {
+        self.transfers.set(self.transfers.get() + 1);
+        self.bytes.set(self.bytes.get() + size as u64);
+    }
+
+    pub(crate) fn rejected(&self) {
+        self.rejected.set(self.rejected.get() + 1);
+    }
+
+    fn snapshot(&self) -> AddressStats {
+        AddressStats {
+            links: self.links.get(),
+            transfers: self.transfers.get(),
+            bytes: self.bytes.get(),
+            rejected: self.rejected.get(),
+        }
+    }
+}
+
+impl std::fmt::Debug for RouterStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.addresses()).finish()
+    }
+}
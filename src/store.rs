@@ -0,0 +1,176 @@
+use std::{cell::RefCell, collections::VecDeque};
+
+use ntex::util::{ByteString, Bytes, BytesMut, HashMap};
+
+use crate::codec::protocol::DeliveryState;
+use crate::codec::{types::Variant, Decode, Encode};
+
+/// Link settlement state storage
+///
+/// Store keeps unsettled deliveries and terminal outcomes of sender link
+/// deliveries, so link state could survive link re-attach or process restart.
+/// Unsettled deliveries are reported to the peer in `Attach` frame's
+/// `unsettled` map on link attach. Deliveries that peer reports with
+/// terminal outcome in its attach are settled, other deliveries are
+/// removed from the store and are available with `SenderLink::unresolved()`.
+pub trait LinkStateStore {
+    /// Load unsettled deliveries for the link
+    fn unsettled(&self, link: &ByteString) -> Vec<(Bytes, Option<DeliveryState>)>;
+
+    /// Delivery is sent to the peer
+    fn delivery_sent(&self, link: &ByteString, tag: &Bytes);
+
+    /// Delivery is settled, `state` is terminal outcome if known
+    fn delivery_settled(&self, link: &ByteString, tag: &Bytes, state: Option<&DeliveryState>);
+
+    /// Remove link state
+    fn remove(&self, link: &ByteString);
+}
+
+const DEFAULT_OUTCOMES: usize = 1024;
+
+/// In-memory link state store
+///
+/// Store keeps limited number of terminal outcomes per link,
+/// oldest outcomes are evicted first.
+#[derive(Debug)]
+pub struct MemoryLinkStateStore {
+    max_outcomes: usize,
+    links: RefCell<HashMap<ByteString, LinkState>>,
+}
+
+#[derive(Debug, Default)]
+struct LinkState {
+    unsettled: HashMap<Bytes, Option<DeliveryState>>,
+    outcomes: HashMap<Bytes, DeliveryState>,
+    order: VecDeque<Bytes>,
+}
+
+impl Default for MemoryLinkStateStore {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_OUTCOMES)
+    }
+}
+
+impl MemoryLinkStateStore {
+    /// Create new in-memory store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create in-memory store that keeps up to `max_outcomes` terminal
+    /// outcomes per link, default is 1024
+    pub fn with_capacity(max_outcomes: usize) -> Self {
+        MemoryLinkStateStore {
+            max_outcomes,
+            links: RefCell::new(HashMap::default()),
+        }
+    }
+
+    /// Terminal outcome of settled delivery
+    pub fn outcome(&self, link: &ByteString, tag: &Bytes) -> Option<DeliveryState> {
+        self.links
+            .borrow()
+            .get(link)
+            .and_then(|link| link.outcomes.get(tag).cloned())
+    }
+}
+
+impl LinkStateStore for MemoryLinkStateStore {
+    fn unsettled(&self, link: &ByteString) -> Vec<(Bytes, Option<DeliveryState>)> {
+        self.links
+            .borrow()
+            .get(link)
+            .map(|link| {
+                link.unsettled
+                    .iter()
+                    .map(|(tag, state)| (tag.clone(), state.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn delivery_sent(&self, link: &ByteString, tag: &Bytes) {
+        self.links
+            .borrow_mut()
+            .entry(link.clone())
+            .or_default()
+            .unsettled
+            .insert(tag.clone(), None);
+    }
+
+    fn delivery_settled(&self, link: &ByteString, tag: &Bytes, state: Option<&DeliveryState>) {
+        if let Some(link) = self.links.borrow_mut().get_mut(link) {
+            if link.unsettled.remove(tag).is_none() {
+                return;
+            }
+            if let Some(state) = state {
+                if self.max_outcomes == 0 {
+                    return;
+                }
+                if link.outcomes.insert(tag.clone(), state.clone()).is_none() {
+                    link.order.push_back(tag.clone());
+                }
+                while link.order.len() > self.max_outcomes {
+                    if let Some(tag) = link.order.pop_front() {
+                        link.outcomes.remove(&tag);
+                    }
+                }
+            }
+        }
+    }
+
+    fn remove(&self, link: &ByteString) {
+        self.links.borrow_mut().remove(link);
+    }
+}
+
+/// Delivery state as value of attach `unsettled` map
+pub(crate) fn state_to_variant(state: &DeliveryState) -> Variant {
+    let mut buf = BytesMut::with_capacity(state.encoded_size());
+    state.encode(&mut buf);
+    Variant::decode(&buf)
+        .map(|(_, value)| value)
+        .unwrap_or(Variant::Null)
+}
+
+/// Delivery state from value of attach `unsettled` map
+pub(crate) fn variant_to_state(value: &Variant) -> Option<DeliveryState> {
+    if let Variant::Null = value {
+        return None;
+    }
+    let mut buf = BytesMut::with_capacity(value.encoded_size());
+    value.encode(&mut buf);
+    DeliveryState::decode(&buf).ok().map(|(_, state)| state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::protocol::{Accepted, Released};
+
+    #[test]
+    fn test_evict_settled() {
+        let store = MemoryLinkStateStore::with_capacity(2);
+        let link = ByteString::from_static("link");
+        for idx in 0..4u8 {
+            let tag = Bytes::from(vec![idx]);
+            store.delivery_sent(&link, &tag);
+            store.delivery_settled(&link, &tag, Some(&DeliveryState::Accepted(Accepted {})));
+        }
+        store.delivery_sent(&link, &Bytes::from_static(b"x"));
+        store.delivery_settled(&link, &Bytes::from_static(b"x"), None);
+
+        assert!(store.unsettled(&link).is_empty());
+        assert!(store.outcome(&link, &Bytes::from(vec![1])).is_none());
+        assert!(store.outcome(&link, &Bytes::from(vec![3])).is_some());
+        assert_eq!(store.links.borrow().get(&link).unwrap().outcomes.len(), 2);
+    }
+
+    #[test]
+    fn test_state_variant() {
+        let state = DeliveryState::Released(Released {});
+        assert_eq!(variant_to_state(&state_to_variant(&state)), Some(state));
+        assert_eq!(variant_to_state(&Variant::Null), None);
+    }
+}
@@ -0,0 +1,246 @@
+use ntex::util::ByteString;
+
+use crate::codec::protocol::{
+    FilterSet, NodeProperties, Source, Target, TerminusDurability, TerminusExpiryPolicy,
+};
+use crate::codec::types::{Multiple, Symbol};
+
+/// Link source or target terms
+#[derive(Debug, Clone)]
+pub struct Terminus<'a> {
+    address: Option<&'a ByteString>,
+    durable: TerminusDurability,
+    expiry_policy: TerminusExpiryPolicy,
+    timeout: u32,
+    dynamic: bool,
+    capabilities: &'a [Symbol],
+    filter: Option<&'a FilterSet>,
+}
+
+impl<'a> Terminus<'a> {
+    pub(crate) fn from_source(source: &'a Source) -> Self {
+        Terminus {
+            address: source.address.as_ref(),
+            durable: source.durable,
+            expiry_policy: source.expiry_policy,
+            timeout: source.timeout,
+            dynamic: source.dynamic,
+            capabilities: symbols(&source.capabilities),
+            filter: source.filter.as_ref(),
+        }
+    }
+
+    pub(crate) fn from_target(target: &'a Target) -> Self {
+        Terminus {
+            address: target.address.as_ref(),
+            durable: target.durable,
+            expiry_policy: target.expiry_policy,
+            timeout: target.timeout,
+            dynamic: target.dynamic,
+            capabilities: symbols(&target.capabilities),
+            filter: None,
+        }
+    }
+
+    /// Node address
+    pub fn address(&self) -> Option<&'a str> {
+        self.address.map(|addr| addr.as_ref())
+    }
+
+    /// Terminus durability
+    pub fn durable(&self) -> TerminusDurability {
+        self.durable
+    }
+
+    /// Terminus expiry policy
+    pub fn expiry_policy(&self) -> TerminusExpiryPolicy {
+        self.expiry_policy
+    }
+
+    /// Terminus expiry timeout in seconds
+    pub fn timeout(&self) -> u32 {
+        self.timeout
+    }
+
+    /// Peer requests dynamically created node
+    pub fn is_dynamic(&self) -> bool {
+        self.dynamic
+    }
+
+    /// Terminus capabilities
+    pub fn capabilities(&self) -> &'a [Symbol] {
+        self.capabilities
+    }
+
+    /// Check if terminus has capability
+    pub fn has_capability(&self, cap: &str) -> bool {
+        self.capabilities.iter().any(|c| c == cap)
+    }
+
+    /// Source filter, always empty for target
+    pub fn filter(&self, name: &str) -> Option<Option<&'a ByteString>> {
+        self.filter
+            .and_then(|filter| filter.get(name))
+            .map(|val| val.as_ref())
+    }
+
+    /// Names of source filters
+    pub fn filters(&self) -> impl Iterator<Item = &'a str> {
+        self.filter
+            .into_iter()
+            .flat_map(|filter| filter.keys().map(|key| key.as_str()))
+    }
+
+    /// Create builder with these terms
+    pub fn to_builder(&self) -> TerminusBuilder {
+        TerminusBuilder {
+            address: self.address.cloned(),
+            durable: self.durable,
+            expiry_policy: self.expiry_policy,
+            timeout: self.timeout,
+            dynamic: self.dynamic,
+            dynamic_node_properties: None,
+            capabilities: self.capabilities.to_vec(),
+            filter: self.filter.cloned(),
+        }
+    }
+}
+
+/// Builder for terms granted in attach response
+#[derive(Debug, Clone)]
+pub struct TerminusBuilder {
+    address: Option<ByteString>,
+    durable: TerminusDurability,
+    expiry_policy: TerminusExpiryPolicy,
+    timeout: u32,
+    dynamic: bool,
+    dynamic_node_properties: Option<NodeProperties>,
+    capabilities: Vec<Symbol>,
+    filter: Option<FilterSet>,
+}
+
+impl Default for TerminusBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TerminusBuilder {
+    /// Create terminus builder with default terms
+    pub fn new() -> Self {
+        TerminusBuilder {
+            address: None,
+            durable: TerminusDurability::None,
+            expiry_policy: TerminusExpiryPolicy::SessionEnd,
+            timeout: 0,
+            dynamic: false,
+            dynamic_node_properties: None,
+            capabilities: Vec::new(),
+            filter: None,
+        }
+    }
+
+    /// Set node address, for dynamic nodes it is address of created node
+    pub fn address<T: Into<ByteString>>(mut self, address: T) -> Self {
+        self.address = Some(address.into());
+        self
+    }
+
+    /// Set terminus durability
+    pub fn durable(mut self, durable: TerminusDurability) -> Self {
+        self.durable = durable;
+        self
+    }
+
+    /// Set terminus expiry policy
+    pub fn expiry_policy(mut self, policy: TerminusExpiryPolicy) -> Self {
+        self.expiry_policy = policy;
+        self
+    }
+
+    /// Set terminus expiry timeout in seconds
+    pub fn timeout(mut self, timeout: u32) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set dynamic flag
+    pub fn dynamic(mut self, dynamic: bool) -> Self {
+        self.dynamic = dynamic;
+        self
+    }
+
+    /// Set properties of dynamically created node
+    pub fn dynamic_node_properties(mut self, props: NodeProperties) -> Self {
+        self.dynamic_node_properties = Some(props);
+        self
+    }
+
+    /// Add terminus capability
+    pub fn capability<T: Into<Symbol>>(mut self, cap: T) -> Self {
+        self.capabilities.push(cap.into());
+        self
+    }
+
+    /// Remove all capabilities
+    pub fn clear_capabilities(mut self) -> Self {
+        self.capabilities.clear();
+        self
+    }
+
+    /// Add source filter, ignored for target
+    pub fn filter<T: Into<Symbol>>(mut self, name: T, value: Option<ByteString>) -> Self {
+        self.filter
+            .get_or_insert_with(FilterSet::default)
+            .insert(name.into(), value);
+        self
+    }
+
+    /// Remove filter, filters not supported by node must not be granted
+    pub fn remove_filter(mut self, name: &str) -> Self {
+        if let Some(ref mut filter) = self.filter {
+            filter.retain(|key, _| key.as_str() != name);
+        }
+        self
+    }
+
+    pub(crate) fn into_source(self, source: Option<&Source>) -> Source {
+        Source {
+            address: self.address,
+            durable: self.durable,
+            expiry_policy: self.expiry_policy,
+            timeout: self.timeout,
+            dynamic: self.dynamic,
+            dynamic_node_properties: self.dynamic_node_properties,
+            distribution_mode: source.and_then(|s| s.distribution_mode.clone()),
+            filter: self.filter,
+            default_outcome: source.and_then(|s| s.default_outcome.clone()),
+            outcomes: source.and_then(|s| s.outcomes.clone()),
+            capabilities: multiple(self.capabilities),
+        }
+    }
+
+    pub(crate) fn into_target(self) -> Target {
+        Target {
+            address: self.address,
+            durable: self.durable,
+            expiry_policy: self.expiry_policy,
+            timeout: self.timeout,
+            dynamic: self.dynamic,
+            dynamic_node_properties: self.dynamic_node_properties,
+            capabilities: multiple(self.capabilities),
+        }
+    }
+}
+
+fn symbols(caps: &Option<Multiple<Symbol>>) -> &[Symbol] {
+    caps.as_ref().map(|caps| caps.0.as_slice()).unwrap_or(&[])
+}
+
+fn multiple(caps: Vec<Symbol>) -> Option<Multiple<Symbol>> {
+    if caps.is_empty() {
+        None
+    } else {
+        Some(Multiple(caps))
+    }
+}
@@ -1,13 +1,17 @@
-use std::fmt;
+use std::{fmt, time::Duration, time::Instant, time::SystemTime, time::UNIX_EPOCH};
 
 use ntex::router::Path;
 use ntex::util::{ByteString, Bytes};
 
 use crate::codec::protocol::{
-    self, Accepted, Attach, DeliveryState, Error, Rejected, TransferBody,
+    self, Accepted, Attach, Declared, DeliveryNumber, DeliveryState, DeliveryTag, Error, Fields,
+    Header, MessageFormat, Modified, Properties, Rejected, Released, Section, TransactionalState,
+    TransferBody,
 };
-use crate::codec::{AmqpParseError, Decode};
-use crate::{rcvlink::ReceiverLink, session::Session, Handle, State};
+use crate::codec::{AmqpParseError, Decode, Message};
+use crate::terminus::{Terminus, TerminusBuilder};
+use crate::{rcvlink::ReceiverLink, session::Session, sndlink::SenderLink, Handle};
+use crate::{Spawner, State};
 
 pub struct Link<S> {
     pub(crate) state: State<S>,
@@ -40,6 +44,27 @@ impl<S> Link<S> {
         self.state.get_ref()
     }
 
+    /// Source terms requested by remote sender
+    pub fn source(&self) -> Option<Terminus<'_>> {
+        self.link.frame().source.as_ref().map(Terminus::from_source)
+    }
+
+    /// Target terms requested by remote sender
+    pub fn target(&self) -> Option<Terminus<'_>> {
+        self.link.frame().target.as_ref().map(Terminus::from_target)
+    }
+
+    /// Set source terms granted in attach response
+    pub fn grant_source(&self, terms: TerminusBuilder) {
+        let frame = self.link.frame_mut();
+        frame.source = Some(terms.into_source(frame.source.as_ref()));
+    }
+
+    /// Set target terms granted in attach response
+    pub fn grant_target(&self, terms: TerminusBuilder) {
+        self.link.frame_mut().target = Some(terms.into_target());
+    }
+
     pub fn handle(&self) -> Handle {
         self.link.handle()
     }
@@ -63,6 +88,21 @@ impl<S> Link<S> {
     pub fn link_credit(&self, credit: u32) {
         self.link.set_link_credit(credit);
     }
+
+    /// Connection scoped task spawner
+    pub fn spawner(&self) -> &Spawner {
+        self.state.spawner()
+    }
+
+    /// Set credit granted in attach response.
+    ///
+    /// By default router grants 50 credits and replenishes credit once
+    /// it is exhausted. If initial credit is set by link handler, credit
+    /// is not replenished, handler grants credit with `ReceiverLink` methods,
+    /// so link could be attached with zero credit.
+    pub fn initial_credit(&self, credit: u32) {
+        self.link.set_initial_credit(credit);
+    }
 }
 
 impl<S> Clone for Link<S> {
@@ -83,32 +123,285 @@ impl<S> fmt::Debug for Link<S> {
     }
 }
 
+/// Link attached by remote receiver
+///
+/// Server pushes messages to remote receiver with `SenderLink::send()`,
+/// remote credit is available with `SenderLink::credit()` and
+/// `SenderLink::credit_events()`.
+pub struct OutgoingLink<S> {
+    state: State<S>,
+    frame: Box<Attach>,
+    link: SenderLink,
+}
+
+impl<S> OutgoingLink<S> {
+    pub(crate) fn new(link: SenderLink, frame: Box<Attach>, state: State<S>) -> Self {
+        OutgoingLink { state, frame, link }
+    }
+
+    /// Remote attach frame
+    pub fn frame(&self) -> &Attach {
+        &self.frame
+    }
+
+    pub fn state(&self) -> &S {
+        self.state.get_ref()
+    }
+
+    /// Source terms requested by remote receiver
+    pub fn source(&self) -> Option<Terminus<'_>> {
+        self.frame.source.as_ref().map(Terminus::from_source)
+    }
+
+    /// Source address requested by remote receiver
+    pub fn address(&self) -> Option<&ByteString> {
+        self.frame
+            .source
+            .as_ref()
+            .and_then(|source| source.address.as_ref())
+    }
+
+    /// Current link credit
+    pub fn credit(&self) -> u32 {
+        self.link.credit()
+    }
+
+    pub fn session(&self) -> &Session {
+        self.link.session()
+    }
+
+    pub fn sender(&self) -> &SenderLink {
+        &self.link
+    }
+}
+
+impl<S> Clone for OutgoingLink<S> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            frame: self.frame.clone(),
+            link: self.link.clone(),
+        }
+    }
+}
+
+impl<S> fmt::Debug for OutgoingLink<S> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("OutgoingLink<S>")
+            .field("frame", &self.frame)
+            .finish()
+    }
+}
+
 pub struct Transfer<S> {
     state: State<S>,
     frame: protocol::Transfer,
     link: ReceiverLink,
+    received: Instant,
 }
 
-#[derive(Debug)]
+/// Delivery outcome
+///
+/// Outcome is returned by send futures and is used for
+/// settlement of received deliveries.
+#[derive(Clone, Debug)]
 pub enum Outcome {
-    Accept,
-    Reject,
-    Error(Error),
+    /// Delivery is processed by receiver
+    Accepted,
+    /// Delivery is invalid and could not be processed
+    Rejected { error: Option<Error> },
+    /// Delivery is not processed and could be redelivered
+    Released,
+    /// Delivery is not processed and is modified by receiver
+    Modified {
+        delivery_failed: bool,
+        undeliverable_here: bool,
+        message_annotations: Option<Fields>,
+    },
+    /// Transaction is declared, contains transaction id
+    Declared { txn_id: Bytes },
+    /// Delivery is part of transaction, contains provisional outcome
+    TransactionalState {
+        txn_id: Bytes,
+        outcome: Option<Box<Outcome>>,
+    },
+    /// Delivery is settled without terminal outcome, e.g. pre-settled delivery
+    Settled,
 }
 
 impl Outcome {
-    pub(crate) fn into_delivery_state(self) -> DeliveryState {
+    /// Check if delivery is accepted
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, Outcome::Accepted)
+    }
+
+    /// Check if receiver considers delivery attempt as failed
+    ///
+    /// Rejected deliveries and deliveries modified with `delivery-failed`
+    /// flag count towards message `delivery-count`.
+    pub fn is_delivery_failed(&self) -> bool {
         match self {
-            Outcome::Accept => DeliveryState::Accepted(Accepted {}),
-            Outcome::Reject => DeliveryState::Rejected(Rejected { error: None }),
-            Outcome::Error(e) => DeliveryState::Rejected(Rejected { error: Some(e) }),
+            Outcome::Rejected { .. } => true,
+            Outcome::Modified {
+                delivery_failed, ..
+            } => *delivery_failed,
+            Outcome::TransactionalState {
+                outcome: Some(outcome),
+                ..
+            } => outcome.is_delivery_failed(),
+            Outcome::Accepted
+            | Outcome::Released
+            | Outcome::Declared { .. }
+            | Outcome::TransactionalState { .. }
+            | Outcome::Settled => false,
+        }
+    }
+
+    /// Update message for redelivery after this outcome
+    ///
+    /// Returns `false` if message must not be redelivered (accepted,
+    /// rejected, settled without outcome or transaction outcomes without
+    /// provisional outcome).
+    /// Otherwise clears message `first-acquirer` flag, increments
+    /// `delivery-count` for failed deliveries and merges annotations
+    /// provided with `modified` outcome.
+    pub fn redeliver(&self, msg: &mut Message) -> bool {
+        match self {
+            Outcome::Accepted
+            | Outcome::Rejected { .. }
+            | Outcome::Declared { .. }
+            | Outcome::Settled => false,
+            Outcome::TransactionalState { outcome, .. } => outcome
+                .as_ref()
+                .map(|outcome| outcome.redeliver(msg))
+                .unwrap_or(false),
+            Outcome::Released => {
+                msg.redelivered(false);
+                true
+            }
+            Outcome::Modified {
+                delivery_failed,
+                message_annotations,
+                ..
+            } => {
+                msg.redelivered(*delivery_failed);
+                if let Some(annotations) = message_annotations {
+                    for (key, value) in annotations {
+                        if let Some(ref mut anns) = msg.message_annotations {
+                            anns.retain(|item| item.0 != *key);
+                        }
+                        msg.add_message_annotation(key.clone(), value.clone());
+                    }
+                }
+                true
+            }
+        }
+    }
+
+    /// Outcome of settled delivery
+    ///
+    /// Delivery that is settled without terminal state, e.g. pre-settled
+    /// delivery, resolves to `Outcome::Settled`.
+    pub(crate) fn from_state(state: Option<DeliveryState>) -> Outcome {
+        match state {
+            Some(DeliveryState::Accepted(_)) => Outcome::Accepted,
+            Some(DeliveryState::Rejected(rejected)) => Outcome::Rejected {
+                error: rejected.error,
+            },
+            Some(DeliveryState::Modified(modified)) => Outcome::Modified {
+                delivery_failed: modified.delivery_failed.unwrap_or(false),
+                undeliverable_here: modified.undeliverable_here.unwrap_or(false),
+                message_annotations: modified.message_annotations,
+            },
+            Some(DeliveryState::Released(_)) => Outcome::Released,
+            Some(DeliveryState::Declared(declared)) => Outcome::Declared {
+                txn_id: declared.txn_id,
+            },
+            Some(DeliveryState::TransactionalState(state)) => Outcome::TransactionalState {
+                txn_id: state.txn_id,
+                outcome: state
+                    .outcome
+                    .map(|outcome| Box::new(Outcome::from_outcome(outcome))),
+            },
+            Some(DeliveryState::Received(_)) | None => Outcome::Settled,
+        }
+    }
+
+    fn from_outcome(outcome: protocol::Outcome) -> Outcome {
+        match outcome {
+            protocol::Outcome::Accepted(accepted) => {
+                Outcome::from_state(Some(DeliveryState::Accepted(accepted)))
+            }
+            protocol::Outcome::Rejected(rejected) => {
+                Outcome::from_state(Some(DeliveryState::Rejected(rejected)))
+            }
+            protocol::Outcome::Released(released) => {
+                Outcome::from_state(Some(DeliveryState::Released(released)))
+            }
+            protocol::Outcome::Modified(modified) => {
+                Outcome::from_state(Some(DeliveryState::Modified(modified)))
+            }
+            protocol::Outcome::Declared(declared) => {
+                Outcome::from_state(Some(DeliveryState::Declared(declared)))
+            }
+        }
+    }
+
+    /// Terminal outcome, transactional state is not an outcome
+    fn into_outcome(self) -> Option<protocol::Outcome> {
+        match self.into_delivery_state()? {
+            DeliveryState::Accepted(accepted) => Some(protocol::Outcome::Accepted(accepted)),
+            DeliveryState::Rejected(rejected) => Some(protocol::Outcome::Rejected(rejected)),
+            DeliveryState::Released(released) => Some(protocol::Outcome::Released(released)),
+            DeliveryState::Modified(modified) => Some(protocol::Outcome::Modified(modified)),
+            DeliveryState::Declared(declared) => Some(protocol::Outcome::Declared(declared)),
+            DeliveryState::TransactionalState(state) => state.outcome,
+            DeliveryState::Received(_) => None,
         }
     }
+
+    /// Delivery state of disposition, settled outcome has no state
+    pub(crate) fn into_delivery_state(self) -> Option<DeliveryState> {
+        let state = match self {
+            Outcome::Accepted => DeliveryState::Accepted(Accepted {}),
+            Outcome::Rejected { error } => DeliveryState::Rejected(Rejected { error }),
+            Outcome::Released => DeliveryState::Released(Released {}),
+            Outcome::Modified {
+                delivery_failed,
+                undeliverable_here,
+                message_annotations,
+            } => DeliveryState::Modified(Modified {
+                delivery_failed: Some(delivery_failed),
+                undeliverable_here: Some(undeliverable_here),
+                message_annotations,
+            }),
+            Outcome::Declared { txn_id } => DeliveryState::Declared(Declared { txn_id }),
+            Outcome::TransactionalState { txn_id, outcome } => {
+                DeliveryState::TransactionalState(TransactionalState {
+                    txn_id,
+                    outcome: outcome.and_then(|outcome| outcome.into_outcome()),
+                })
+            }
+            Outcome::Settled => return None,
+        };
+        Some(state)
+    }
+}
+
+impl From<Error> for Outcome {
+    fn from(error: Error) -> Self {
+        Outcome::Rejected { error: Some(error) }
+    }
 }
 
 impl<S> Transfer<S> {
     pub(crate) fn new(state: State<S>, frame: protocol::Transfer, link: ReceiverLink) -> Self {
-        Transfer { state, frame, link }
+        Transfer {
+            state,
+            frame,
+            link,
+            received: Instant::now(),
+        }
     }
 
     pub fn state(&self) -> &S {
@@ -127,6 +420,47 @@ impl<S> Transfer<S> {
         &self.frame
     }
 
+    /// Delivery id, set for first transfer of the delivery
+    pub fn delivery_id(&self) -> Option<DeliveryNumber> {
+        self.frame.delivery_id
+    }
+
+    /// Delivery tag, set for first transfer of the delivery
+    pub fn delivery_tag(&self) -> Option<&DeliveryTag> {
+        self.frame.delivery_tag.as_ref()
+    }
+
+    /// Message format
+    pub fn message_format(&self) -> Option<MessageFormat> {
+        self.frame.message_format
+    }
+
+    /// Check if delivery is settled by sender
+    pub fn settled(&self) -> Option<bool> {
+        self.frame.settled
+    }
+
+    /// Check if delivery is pre-settled by sender, pre-settled
+    /// deliveries must not be acknowledged
+    pub fn is_settled(&self) -> bool {
+        self.frame.is_settled()
+    }
+
+    /// Check if delivery is resumed
+    pub fn resume(&self) -> bool {
+        self.frame.resume
+    }
+
+    /// Check if delivery is aborted
+    pub fn aborted(&self) -> bool {
+        self.frame.aborted
+    }
+
+    /// Check if delivery is batchable
+    pub fn batchable(&self) -> bool {
+        self.frame.batchable
+    }
+
     pub fn body(&self) -> Option<&Bytes> {
         match self.frame.body {
             Some(TransferBody::Data(ref b)) => Some(b),
@@ -134,6 +468,26 @@ impl<S> Transfer<S> {
         }
     }
 
+    /// Processing deadline of the message
+    ///
+    /// Deadline is derived from header `ttl`, counted from dispatch of
+    /// the transfer, and from `absolute-expiry-time` property. If both
+    /// are set, earliest one is used.
+    pub fn deadline(&self) -> Option<Instant> {
+        if let Some(TransferBody::Data(ref b)) = self.frame.body {
+            deadline(b, self.received)
+        } else {
+            None
+        }
+    }
+
+    /// Check if message deadline has passed
+    pub fn is_expired(&self) -> bool {
+        self.deadline()
+            .map(|deadline| deadline <= Instant::now())
+            .unwrap_or(false)
+    }
+
     pub fn load_message<T: Decode>(&self) -> Result<T, AmqpParseError> {
         if let Some(TransferBody::Data(ref b)) = self.frame.body {
             Ok(T::decode(b)?.1)
@@ -143,6 +497,58 @@ impl<S> Transfer<S> {
     }
 }
 
+/// Decode leading message sections up to properties
+fn deadline(mut input: &[u8], received: Instant) -> Option<Instant> {
+    let mut header = None;
+    while !input.is_empty() {
+        let section = match Section::decode(input) {
+            Ok((rest, section)) => {
+                input = rest;
+                section
+            }
+            Err(_) => break,
+        };
+        match section {
+            Section::Header(hdr) => header = Some(hdr),
+            Section::DeliveryAnnotations(_) | Section::MessageAnnotations(_) => (),
+            Section::Properties(props) => {
+                return message_deadline(received, header.as_ref(), Some(&props))
+            }
+            _ => break,
+        }
+    }
+    message_deadline(received, header.as_ref(), None)
+}
+
+/// Message deadline, header `ttl` is counted from `start`
+///
+/// If both `ttl` and `absolute-expiry-time` are set, earliest one is used.
+pub(crate) fn message_deadline(
+    start: Instant,
+    header: Option<&Header>,
+    properties: Option<&Properties>,
+) -> Option<Instant> {
+    let ttl = header
+        .and_then(|hdr| hdr.ttl)
+        .map(|ttl| start + Duration::from_millis(ttl as u64));
+    let absolute = properties
+        .and_then(|props| props.absolute_expiry_time)
+        .map(|time| {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as i64)
+                .unwrap_or(0);
+            let left = time.timestamp_millis().saturating_sub(now).max(0) as u64;
+            Instant::now() + Duration::from_millis(left)
+        });
+
+    match (ttl, absolute) {
+        (Some(ttl), Some(abs)) => Some(ttl.min(abs)),
+        (Some(deadline), None) | (None, Some(deadline)) => Some(deadline),
+        (None, None) => None,
+    }
+}
+
 impl<S> fmt::Debug for Transfer<S> {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt.debug_struct("Transfer<S>")
@@ -1,9 +1,14 @@
-use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::{convert::TryFrom, pin::Pin, time::Duration};
 
 use ntex::codec::{AsyncRead, AsyncWrite};
+use ntex::http::Uri;
+use ntex::rt::time::delay_for;
 use ntex::server::test_server;
-use ntex::service::{fn_factory_with_config, Service};
-use ntex::{http::Uri, util::Ready};
+use ntex::service::{fn_factory_with_config, fn_service, Service};
+use ntex::util::{poll_fn, Bytes, Ready, Stream};
+use ntex_amqp::codec::protocol::{Error, TransferBody};
 use ntex_amqp::{client, error::LinkError, server, types};
 
 async fn server(
@@ -23,6 +28,24 @@ async fn server(
     Err(LinkError::force_detach().description("unimplemented"))
 }
 
+async fn accept(
+    _: types::Link<()>,
+) -> Result<
+    Box<
+        dyn Service<
+                Request = types::Transfer<()>,
+                Response = types::Outcome,
+                Error = LinkError,
+                Future = Ready<types::Outcome, LinkError>,
+            > + 'static,
+    >,
+    LinkError,
+> {
+    Ok(Box::new(fn_service(|_| {
+        Ready::Ok(types::Outcome::Accepted)
+    })))
+}
+
 #[ntex::test]
 async fn test_simple() -> std::io::Result<()> {
     std::env::set_var("RUST_LOG", "ntex=trace,ntex_amqp=trace");
@@ -126,3 +149,890 @@ async fn test_sasl() -> std::io::Result<()> {
 
     Ok(())
 }
+
+#[ntex::test]
+async fn test_receiver_start() -> std::io::Result<()> {
+    let credits = Arc::new(Mutex::new(Vec::new()));
+    let credits2 = credits.clone();
+
+    let srv = test_server(move || {
+        let credits = credits2.clone();
+        server::Server::new(|con: server::Handshake<_>| async move {
+            match con {
+                server::Handshake::Amqp(con) => {
+                    let con = con.open().await.unwrap();
+                    Ok(con.ack(()))
+                }
+                server::Handshake::Sasl(_) => Err(()),
+            }
+        })
+        .outgoing(move |link: types::OutgoingLink<()>| {
+            let credits = credits.clone();
+            async move {
+                while link.credit() == 0 {
+                    delay_for(Duration::from_millis(10)).await;
+                }
+                for _ in 0..6 {
+                    let outcome = link.sender().send(Bytes::from_static(b"test")).await;
+                    assert!(matches!(outcome, Ok(types::Outcome::Accepted)));
+                    delay_for(Duration::from_millis(50)).await;
+                    credits.lock().unwrap().push(link.credit());
+                }
+                Ok(())
+            }
+        })
+        .finish(
+            server::Router::<()>::new()
+                .service("test", fn_factory_with_config(server))
+                .finish(),
+        )
+    });
+
+    let uri = Uri::try_from(format!("amqp://{}:{}", srv.addr().ip(), srv.addr().port())).unwrap();
+    let client = client::Connector::new().connect(uri).await.unwrap();
+    let sink = client.sink();
+    ntex::rt::spawn(async move {
+        let _ = client.start_default().await;
+    });
+
+    let mut session = sink.open_session().await.unwrap();
+    let link = session
+        .build_receiver_link("test", "test")
+        .open()
+        .await
+        .unwrap();
+    ntex::rt::spawn(link.start(3, |_| async { Ok::<_, Error>(types::Outcome::Accepted) }));
+
+    for _ in 0..100 {
+        if credits.lock().unwrap().len() == 6 {
+            break;
+        }
+        delay_for(Duration::from_millis(20)).await;
+    }
+    // each completed handler replenishes one credit
+    assert_eq!(*credits.lock().unwrap(), vec![3; 6]);
+
+    Ok(())
+}
+
+#[cfg(feature = "openssl")]
+#[ntex::test]
+async fn test_tls_handshake_timeout() -> std::io::Result<()> {
+    use ntex::connect::openssl::{SslConnector, SslMethod};
+
+    // peer accepts tcp connection but never responds to tls handshake
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    std::thread::spawn(move || {
+        let _io = listener.accept();
+        std::thread::sleep(Duration::from_secs(1));
+    });
+
+    let uri = Uri::try_from(format!("amqp://{}:{}", addr.ip(), addr.port())).unwrap();
+    let ssl = SslConnector::builder(SslMethod::tls()).unwrap().build();
+    let res = client::Connector::new()
+        .openssl(ssl)
+        .connect_timeout(50)
+        .tls_handshake_timeout(200)
+        .connect(uri)
+        .await;
+    assert!(matches!(
+        res,
+        Err(client::ConnectError::TlsHandshakeTimeout)
+    ));
+
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_failover_fail_back() -> std::io::Result<()> {
+    let primary_up = Arc::new(AtomicBool::new(false));
+    let up = primary_up.clone();
+    let primary = test_server(move || {
+        let up = up.clone();
+        server::Server::new(move |con: server::Handshake<_>| {
+            let up = up.load(Ordering::Relaxed);
+            async move {
+                match con {
+                    server::Handshake::Amqp(con) if up => {
+                        let con = con.open().await.unwrap();
+                        Ok(con.ack(()))
+                    }
+                    _ => Err(()),
+                }
+            }
+        })
+        .finish(
+            server::Router::<()>::new()
+                .service("test", fn_factory_with_config(accept))
+                .finish(),
+        )
+    });
+    let secondary = test_server(|| {
+        server::Server::new(|con: server::Handshake<_>| async move {
+            match con {
+                server::Handshake::Amqp(con) => {
+                    let con = con.open().await.unwrap();
+                    Ok(con.ack(()))
+                }
+                server::Handshake::Sasl(_) => Err(()),
+            }
+        })
+        .finish(
+            server::Router::<()>::new()
+                .service("test", fn_factory_with_config(accept))
+                .finish(),
+        )
+    });
+
+    let uri = |addr: std::net::SocketAddr| {
+        Uri::try_from(format!("amqp://{}:{}", addr.ip(), addr.port())).unwrap()
+    };
+    let failover = client::Failover::new()
+        .primary(uri(primary.addr()))
+        .secondary(uri(secondary.addr()))
+        .retry_after(Duration::from_millis(10))
+        .fail_back_interval(Duration::from_millis(100));
+
+    let client = client::Connector::new()
+        .connect_failover_reconnecting(failover)
+        .await
+        .unwrap();
+    assert_eq!(client.tier(), client::EndpointTier::Secondary);
+    let mut events = client.events();
+    let link = client.open_sender_link("test", "test").await.unwrap();
+    let outcome = link.send(Bytes::from_static(b"test")).await;
+    assert!(matches!(outcome, Ok(types::Outcome::Accepted)));
+
+    // client stays in secondary region while primary is down
+    delay_for(Duration::from_millis(250)).await;
+    assert_eq!(client.tier(), client::EndpointTier::Secondary);
+
+    primary_up.store(true, Ordering::Relaxed);
+    let event = poll_fn(|cx| Pin::new(&mut events).poll_next(cx)).await;
+    assert!(matches!(event, Some(client::ReconnectEvent::FailedBack)));
+    assert_eq!(client.tier(), client::EndpointTier::Primary);
+
+    // sender link is reattached to primary endpoint
+    let outcome = link.send(Bytes::from_static(b"test")).await;
+    assert!(matches!(outcome, Ok(types::Outcome::Accepted)));
+
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_mux() -> std::io::Result<()> {
+    use ntex::rt::net::TcpStream;
+
+    let srv = test_server(|| {
+        let amqp = server::Server::new(|con: server::Handshake<_>| async move {
+            match con {
+                server::Handshake::Amqp(con) => {
+                    let con = con.open().await.unwrap();
+                    Ok(con.ack(()))
+                }
+                server::Handshake::Sasl(_) => Err(()),
+            }
+        })
+        .finish(
+            server::Router::<()>::new()
+                .service("test", fn_factory_with_config(accept))
+                .finish(),
+        );
+        let health = fn_service(|io: TcpStream| async move {
+            let mut written = 0;
+            let resp = b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n";
+            while written < resp.len() {
+                io.writable().await?;
+                match io.try_write(&resp[written..]) {
+                    Ok(n) => written += n,
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok::<_, std::io::Error>(())
+        });
+        server::Multiplexer::new(amqp, health).detect_timeout(Duration::from_millis(500))
+    });
+
+    // amqp protocol header is routed to amqp server
+    let uri = Uri::try_from(format!("amqp://{}:{}", srv.addr().ip(), srv.addr().port())).unwrap();
+    let client = client::Connector::new().connect(uri).await.unwrap();
+    let sink = client.sink();
+    ntex::rt::spawn(async move {
+        let _ = client.start_default().await;
+    });
+    let mut session = sink.open_session().await.unwrap();
+    let link = session
+        .build_sender_link("test", "test")
+        .open()
+        .await
+        .unwrap();
+    let outcome = link.send(Bytes::from_static(b"test")).await;
+    assert!(matches!(outcome, Ok(types::Outcome::Accepted)));
+
+    // other traffic is routed to fallback service
+    let io = TcpStream::connect(srv.addr()).await?;
+    io.writable().await?;
+    io.try_write(b"GET /health HTTP/1.1\r\n\r\n")?;
+    let mut buf = [0; 64];
+    let n = loop {
+        io.readable().await?;
+        match io.try_read(&mut buf) {
+            Ok(n) => break n,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
+            Err(e) => return Err(e),
+        }
+    };
+    assert!(buf[..n].starts_with(b"HTTP/1.1 200 OK"));
+
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_sender_inflight_limit() -> std::io::Result<()> {
+    let srv = test_server(|| {
+        server::Server::new(|con: server::Handshake<_>| async move {
+            match con {
+                server::Handshake::Amqp(con) => {
+                    let con = con.open().await.unwrap();
+                    Ok(con.ack(()))
+                }
+                server::Handshake::Sasl(_) => Err(()),
+            }
+        })
+        .finish(
+            server::Router::<()>::new()
+                .service("test", fn_factory_with_config(accept))
+                .finish(),
+        )
+    });
+
+    let uri = Uri::try_from(format!("amqp://{}:{}", srv.addr().ip(), srv.addr().port())).unwrap();
+    let client = client::Connector::new().connect(uri).await.unwrap();
+    let sink = client.sink();
+    ntex::rt::spawn(async move {
+        let _ = client.start_default().await;
+    });
+
+    let mut session = sink.open_session().await.unwrap();
+    let link = session
+        .build_sender_link("test", "test")
+        .open()
+        .await
+        .unwrap();
+    link.max_inflight(1, ntex_amqp::InflightPolicy::Error);
+
+    let first = link.send(Bytes::from_static(b"first"));
+    assert_eq!(link.inflight(), 1);
+    let second = link.send(Bytes::from_static(b"second")).await;
+    assert!(matches!(
+        second,
+        Err(ntex_amqp::error::AmqpProtocolError::InflightLimit)
+    ));
+
+    // settled delivery frees in-flight slot
+    assert!(matches!(first.await, Ok(types::Outcome::Accepted)));
+    assert_eq!(link.inflight(), 0);
+    let third = link.send(Bytes::from_static(b"third")).await;
+    assert!(matches!(third, Ok(types::Outcome::Accepted)));
+
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_streaming_abort() -> std::io::Result<()> {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received2 = received.clone();
+
+    let srv = test_server(move || {
+        let received = received2.clone();
+        server::Server::new(|con: server::Handshake<_>| async move {
+            match con {
+                server::Handshake::Amqp(con) => {
+                    let con = con.open().await.unwrap();
+                    Ok(con.ack(()))
+                }
+                server::Handshake::Sasl(_) => Err(()),
+            }
+        })
+        .finish(
+            server::Router::<()>::new()
+                .service(
+                    "test",
+                    fn_factory_with_config(move |_: types::Link<()>| {
+                        let received = received.clone();
+                        async move {
+                            Ok::<_, LinkError>(fn_service(move |tr: types::Transfer<()>| {
+                                received.lock().unwrap().push(tr.body().cloned());
+                                Ready::<_, LinkError>::Ok(types::Outcome::Accepted)
+                            }))
+                        }
+                    }),
+                )
+                .finish(),
+        )
+    });
+
+    let uri = Uri::try_from(format!("amqp://{}:{}", srv.addr().ip(), srv.addr().port())).unwrap();
+    let client = client::Connector::new().connect(uri).await.unwrap();
+    let sink = client.sink();
+    ntex::rt::spawn(async move {
+        let _ = client.start_default().await;
+    });
+
+    let mut session = sink.open_session().await.unwrap();
+    let link = session
+        .build_sender_link("test", "test")
+        .open()
+        .await
+        .unwrap();
+
+    let mut delivery = link.start_delivery();
+    delivery.send(Bytes::from_static(b"partial")).unwrap();
+    delivery.abort();
+
+    // partial state is discarded, link stays usable
+    let mut delivery = link.start_delivery();
+    delivery.send(Bytes::from_static(b"comp")).unwrap();
+    let outcome = delivery.finish(Bytes::from_static(b"lete")).await;
+    assert!(matches!(outcome, Ok(types::Outcome::Accepted)));
+    assert_eq!(link.inflight(), 0);
+    assert_eq!(
+        *received.lock().unwrap(),
+        vec![Some(Bytes::from_static(b"complete"))]
+    );
+
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_link_priority() -> std::io::Result<()> {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received2 = received.clone();
+
+    let srv = test_server(move || {
+        let received = received2.clone();
+        // peer window allows one transfer at a time
+        let mut config = ntex_amqp::Configuration::new();
+        config.session_window(1, 1);
+
+        server::Server::new(|con: server::Handshake<_>| async move {
+            match con {
+                server::Handshake::Amqp(con) => {
+                    let con = con.open().await.unwrap();
+                    Ok(con.ack(()))
+                }
+                server::Handshake::Sasl(_) => Err(()),
+            }
+        })
+        .config(config)
+        .finish(
+            server::Router::<()>::new()
+                .service(
+                    "test",
+                    fn_factory_with_config(move |_: types::Link<()>| {
+                        let received = received.clone();
+                        async move {
+                            Ok::<_, LinkError>(fn_service(move |tr: types::Transfer<()>| {
+                                received.lock().unwrap().push(tr.body().cloned().unwrap());
+                                Ready::<_, LinkError>::Ok(types::Outcome::Accepted)
+                            }))
+                        }
+                    }),
+                )
+                .finish(),
+        )
+    });
+
+    let uri = Uri::try_from(format!("amqp://{}:{}", srv.addr().ip(), srv.addr().port())).unwrap();
+    let client = client::Connector::new().connect(uri).await.unwrap();
+    let sink = client.sink();
+    ntex::rt::spawn(async move {
+        let _ = client.start_default().await;
+    });
+
+    let mut session = sink.open_session().await.unwrap();
+    let bulk = session
+        .build_sender_link("bulk", "test")
+        .open()
+        .await
+        .unwrap();
+    let control = session
+        .build_sender_link("control", "test")
+        .priority(10)
+        .open()
+        .await
+        .unwrap();
+    while bulk.credit() == 0 || control.credit() == 0 {
+        delay_for(Duration::from_millis(10)).await;
+    }
+
+    let mut sends = Vec::new();
+    for _ in 0..5 {
+        sends.push(bulk.send(Bytes::from_static(b"bulk")));
+    }
+    for _ in 0..2 {
+        sends.push(control.send(Bytes::from_static(b"control")));
+    }
+    for outcome in ntex::util::join_all(sends).await {
+        assert!(matches!(outcome, Ok(types::Outcome::Accepted)));
+    }
+
+    // queued control transfers overtake queued bulk transfers
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 7);
+    assert!(received[5..].iter().all(|b| b == "bulk"));
+    assert_eq!(received.iter().filter(|b| *b == "control").count(), 2);
+
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_session_scheduling() -> std::io::Result<()> {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received2 = received.clone();
+
+    let srv = test_server(move || {
+        let received = received2.clone();
+        let mut config = ntex_amqp::Configuration::new();
+        config.session_window(1, 1);
+
+        server::Server::new(|con: server::Handshake<_>| async move {
+            match con {
+                server::Handshake::Amqp(con) => {
+                    let con = con.open().await.unwrap();
+                    Ok(con.ack(()))
+                }
+                server::Handshake::Sasl(_) => Err(()),
+            }
+        })
+        .config(config)
+        .finish(
+            server::Router::<()>::new()
+                .service(
+                    "test",
+                    fn_factory_with_config(move |_: types::Link<()>| {
+                        let received = received.clone();
+                        async move {
+                            Ok::<_, LinkError>(fn_service(move |tr: types::Transfer<()>| {
+                                received.lock().unwrap().push(tr.body().cloned().unwrap());
+                                Ready::<_, LinkError>::Ok(types::Outcome::Accepted)
+                            }))
+                        }
+                    }),
+                )
+                .finish(),
+        )
+    });
+
+    let uri = Uri::try_from(format!("amqp://{}:{}", srv.addr().ip(), srv.addr().port())).unwrap();
+    let mut connector = client::Connector::new();
+    connector.session_quantum(1);
+    let client = connector.connect(uri).await.unwrap();
+    let sink = client.sink();
+    ntex::rt::spawn(async move {
+        let _ = client.start_default().await;
+    });
+
+    let mut session1 = sink.open_session().await.unwrap();
+    let mut session2 = sink.open_session().await.unwrap();
+    session2.set_weight(2);
+    let link1 = session1
+        .build_sender_link("link1", "test")
+        .open()
+        .await
+        .unwrap();
+    let link2 = session2
+        .build_sender_link("link2", "test")
+        .open()
+        .await
+        .unwrap();
+    while link1.credit() == 0 || link2.credit() == 0 {
+        delay_for(Duration::from_millis(10)).await;
+    }
+
+    let mut sends = Vec::new();
+    for i in 0..6u8 {
+        sends.push(link1.send(Bytes::from(vec![1, i])));
+        sends.push(link2.send(Bytes::from(vec![2, i])));
+    }
+    for outcome in ntex::util::join_all(sends).await {
+        assert!(matches!(outcome, Ok(types::Outcome::Accepted)));
+    }
+
+    // queued transfers of both sessions are delivered in per-link order
+    let received = received.lock().unwrap();
+    for link in 1..3u8 {
+        let seq: Vec<_> = received
+            .iter()
+            .filter(|b| b[0] == link)
+            .map(|b| b[1])
+            .collect();
+        assert_eq!(seq, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_disposition_batch() -> std::io::Result<()> {
+    let srv = test_server(|| {
+        // dispositions are flushed once 3 settlements are collected
+        let mut config = ntex_amqp::Configuration::new();
+        config.disposition_batch(Duration::from_secs(10), 3);
+
+        server::Server::new(|con: server::Handshake<_>| async move {
+            match con {
+                server::Handshake::Amqp(con) => {
+                    let con = con.open().await.unwrap();
+                    Ok(con.ack(()))
+                }
+                server::Handshake::Sasl(_) => Err(()),
+            }
+        })
+        .config(config)
+        .finish(
+            server::Router::<()>::new()
+                .service("test", fn_factory_with_config(accept))
+                .finish(),
+        )
+    });
+
+    let uri = Uri::try_from(format!("amqp://{}:{}", srv.addr().ip(), srv.addr().port())).unwrap();
+    let client = client::Connector::new().connect(uri).await.unwrap();
+    let sink = client.sink();
+    ntex::rt::spawn(async move {
+        let _ = client.start_default().await;
+    });
+
+    let mut session = sink.open_session().await.unwrap();
+    let link = session
+        .build_sender_link("test", "test")
+        .open()
+        .await
+        .unwrap();
+
+    let mut first = Box::pin(link.send(Bytes::from_static(b"1")));
+    let res = ntex::util::select(delay_for(Duration::from_millis(200)), &mut first).await;
+    assert!(res.is_left(), "disposition is sent before batch is full");
+
+    let second = link.send(Bytes::from_static(b"2"));
+    let third = link.send(Bytes::from_static(b"3"));
+    for outcome in ntex::util::join_all(vec![Box::pin(second), Box::pin(third), first]).await {
+        assert!(matches!(outcome, Ok(types::Outcome::Accepted)));
+    }
+
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_ordered_shutdown() -> std::io::Result<()> {
+    let srv = test_server(|| {
+        server::Server::new(|con: server::Handshake<_>| async move {
+            match con {
+                server::Handshake::Amqp(con) => {
+                    let con = con.open().await.unwrap();
+                    Ok(con.ack(()))
+                }
+                server::Handshake::Sasl(_) => Err(()),
+            }
+        })
+        .finish(
+            server::Router::<()>::new()
+                .service("test", fn_factory_with_config(accept))
+                .finish(),
+        )
+    });
+
+    let uri = Uri::try_from(format!("amqp://{}:{}", srv.addr().ip(), srv.addr().port())).unwrap();
+    let client = client::Connector::new().connect(uri).await.unwrap();
+    let sink = client.sink();
+    ntex::rt::spawn(async move {
+        let _ = client.start_default().await;
+    });
+
+    let mut session1 = sink.open_session().await.unwrap();
+    let mut session2 = sink.open_session().await.unwrap();
+    let link1 = session1
+        .build_sender_link("link1", "test")
+        .open()
+        .await
+        .unwrap();
+    let link2 = session2
+        .build_sender_link("link2", "test")
+        .open()
+        .await
+        .unwrap();
+    let outcome = link1.send(Bytes::from_static(b"test")).await;
+    assert!(matches!(outcome, Ok(types::Outcome::Accepted)));
+
+    let report = sink.shutdown(ntex_amqp::ShutdownTimeouts::default()).await;
+    assert_eq!(
+        report,
+        ntex_amqp::ShutdownReport {
+            links_detached: 2,
+            links_failed: 0,
+            sessions_ended: 2,
+            sessions_failed: 0,
+            closed: true,
+        }
+    );
+    // detached links do not accept new deliveries
+    assert!(link1.send(Bytes::from_static(b"test")).await.is_err());
+    assert!(link2.send(Bytes::from_static(b"test")).await.is_err());
+
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_reconnect() -> std::io::Result<()> {
+    let connections = Arc::new(AtomicUsize::new(0));
+    let connections2 = connections.clone();
+
+    let srv = test_server(move || {
+        let connections = connections2.clone();
+        server::Server::new(move |con: server::Handshake<_>| {
+            let connections = connections.clone();
+            async move {
+                match con {
+                    server::Handshake::Amqp(con) => {
+                        let con = con.open().await.unwrap();
+                        // drop first connection
+                        if connections.fetch_add(1, Ordering::Relaxed) == 0 {
+                            let sink = con.sink().clone();
+                            ntex::rt::spawn(async move {
+                                delay_for(Duration::from_millis(200)).await;
+                                sink.force_close();
+                            });
+                        }
+                        Ok(con.ack(()))
+                    }
+                    server::Handshake::Sasl(_) => Err(()),
+                }
+            }
+        })
+        .finish(
+            server::Router::<()>::new()
+                .service("test", fn_factory_with_config(accept))
+                .finish(),
+        )
+    });
+
+    let uri = Uri::try_from(format!("amqp://{}:{}", srv.addr().ip(), srv.addr().port())).unwrap();
+    let client = client::Connector::new()
+        .reconnect_policy(
+            ntex_amqp::ExponentialBackoff::new(5).initial_delay(Duration::from_millis(10)),
+        )
+        .connect_reconnecting(uri)
+        .await
+        .unwrap();
+    let mut events = client.events();
+    let link = client.open_sender_link("test", "test").await.unwrap();
+    let outcome = link.send(Bytes::from_static(b"test")).await;
+    assert!(matches!(outcome, Ok(types::Outcome::Accepted)));
+
+    let event = poll_fn(|cx| Pin::new(&mut events).poll_next(cx)).await;
+    assert!(matches!(
+        event,
+        Some(client::ReconnectEvent::Disconnected(_))
+    ));
+    let event = poll_fn(|cx| Pin::new(&mut events).poll_next(cx)).await;
+    assert!(matches!(
+        event,
+        Some(client::ReconnectEvent::Reconnected(0))
+    ));
+    assert_eq!(client.reconnects(), 1);
+    assert_eq!(connections.load(Ordering::Relaxed), 2);
+
+    // sender link is reattached to new connection
+    let outcome = link.send(Bytes::from_static(b"test")).await;
+    assert!(matches!(outcome, Ok(types::Outcome::Accepted)));
+
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_refreshing_credentials() -> std::io::Result<()> {
+    let srv = test_server(|| {
+        server::Server::new(|conn: server::Handshake<_>| async move {
+            match conn {
+                server::Handshake::Amqp(_) => Err(()),
+                server::Handshake::Sasl(auth) => sasl_auth(auth).await.map_err(|_| ()),
+            }
+        })
+        .finish(
+            server::Router::<()>::new()
+                .service("test", fn_factory_with_config(accept))
+                .finish(),
+        )
+    });
+
+    let issued = std::rc::Rc::new(std::cell::Cell::new(0));
+    let issued2 = issued.clone();
+    let provider = move || {
+        issued2.set(issued2.get() + 1);
+        Ready::Ok(client::Credentials {
+            auth: client::SaslAuth {
+                authz_id: "".into(),
+                authn_id: "user1".into(),
+                password: "password1".into(),
+            },
+            expires: Some(std::time::Instant::now() + Duration::from_millis(300)),
+        })
+    };
+
+    let uri = Uri::try_from(format!("amqp://{}:{}", srv.addr().ip(), srv.addr().port())).unwrap();
+    let client = client::Connector::new()
+        .connect_refreshing(uri, provider, Duration::from_millis(200))
+        .await
+        .unwrap();
+    let mut events = client.events();
+    let link = client.open_sender_link("test", "test").await.unwrap();
+
+    // connection is replaced before credentials expire
+    let event = poll_fn(|cx| Pin::new(&mut events).poll_next(cx)).await;
+    assert!(matches!(event, Some(client::ReconnectEvent::Refreshed)));
+    assert_eq!(issued.get(), 2);
+
+    let outcome = link.send(Bytes::from_static(b"test")).await;
+    assert!(matches!(outcome, Ok(types::Outcome::Accepted)));
+
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_resolver() -> std::io::Result<()> {
+    let srv = test_server(|| {
+        server::Server::new(|con: server::Handshake<_>| async move {
+            match con {
+                server::Handshake::Amqp(con) => {
+                    let con = con.open().await.unwrap();
+                    Ok(con.ack(()))
+                }
+                server::Handshake::Sasl(_) => Err(()),
+            }
+        })
+        .finish(
+            server::Router::<()>::new()
+                .service("test", fn_factory_with_config(accept))
+                .finish(),
+        )
+    });
+
+    // first resolved address refuses connections
+    let closed = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+    let addr = srv.addr();
+    let lookups = Arc::new(Mutex::new(Vec::new()));
+    let lookups2 = lookups.clone();
+    let resolver = move |host: &str, port: u16| {
+        lookups2.lock().unwrap().push((host.to_string(), port));
+        Ready::<_, std::io::Error>::Ok(vec![closed, addr])
+    };
+
+    let uri = Uri::try_from(format!("amqp://amqp.test:{}", addr.port())).unwrap();
+    let client = client::Connector::new()
+        .resolver(resolver)
+        .connect(uri)
+        .await
+        .unwrap();
+    let sink = client.sink();
+    ntex::rt::spawn(async move {
+        let _ = client.start_default().await;
+    });
+    assert_eq!(
+        *lookups.lock().unwrap(),
+        vec![("amqp.test".to_string(), addr.port())]
+    );
+
+    let mut session = sink.open_session().await.unwrap();
+    let link = session
+        .build_sender_link("test", "test")
+        .open()
+        .await
+        .unwrap();
+    let outcome = link.send(Bytes::from_static(b"test")).await;
+    assert!(matches!(outcome, Ok(types::Outcome::Accepted)));
+
+    Ok(())
+}
+
+#[ntex::test]
+async fn test_subscriptions() -> std::io::Result<()> {
+    let srv = test_server(|| {
+        server::Server::new(|con: server::Handshake<_>| async move {
+            match con {
+                server::Handshake::Amqp(con) => {
+                    let con = con.open().await.unwrap();
+                    Ok(con.ack(()))
+                }
+                server::Handshake::Sasl(_) => Err(()),
+            }
+        })
+        .outgoing(|link: types::OutgoingLink<()>| async move {
+            // each subscriber gets two messages with link address
+            let address = link.address().cloned().unwrap_or_default();
+            for _ in 0..2 {
+                while link.credit() == 0 {
+                    delay_for(Duration::from_millis(10)).await;
+                }
+                let body = Bytes::copy_from_slice(address.as_bytes());
+                let outcome = link.sender().send(body).await;
+                assert!(matches!(outcome, Ok(types::Outcome::Accepted)));
+            }
+            Ok(())
+        })
+        .finish(
+            server::Router::<()>::new()
+                .service("test", fn_factory_with_config(server))
+                .finish(),
+        )
+    });
+
+    let uri = Uri::try_from(format!("amqp://{}:{}", srv.addr().ip(), srv.addr().port())).unwrap();
+    let client = client::Connector::new().connect(uri).await.unwrap();
+    let sink = client.sink();
+    ntex::rt::spawn(async move {
+        let _ = client.start_default().await;
+    });
+    let session = sink.open_session().await.unwrap();
+
+    let received = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let manager = client::SubscriptionManager::new();
+    for address in &["queue1", "queue2"] {
+        let received = received.clone();
+        manager
+            .subscribe(*address, client::CreditPolicy::Concurrent(1), move |tr| {
+                if let Some(TransferBody::Data(body)) = tr.body {
+                    received.borrow_mut().push(body);
+                }
+                Ready::<_, Error>::Ok(types::Outcome::Accepted)
+            })
+            .await
+            .unwrap();
+    }
+    manager.attach(&session).await.unwrap();
+
+    for _ in 0..100 {
+        if received.borrow().len() == 4 {
+            break;
+        }
+        delay_for(Duration::from_millis(20)).await;
+    }
+    let mut bodies = received.borrow().clone();
+    bodies.sort();
+    assert_eq!(
+        bodies,
+        vec![
+            Bytes::from_static(b"queue1"),
+            Bytes::from_static(b"queue1"),
+            Bytes::from_static(b"queue2"),
+            Bytes::from_static(b"queue2"),
+        ]
+    );
+
+    manager.unsubscribe("queue1").await.unwrap();
+    assert_eq!(manager.addresses(), vec!["queue2"]);
+
+    Ok(())
+}